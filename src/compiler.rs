@@ -4,6 +4,12 @@
 use crate::parser::{self};
 use crate::prelude::*;
 use crate::vval::VVal;
+use crate::vval::Symbol;
+use crate::vval::LookupCache;
+use crate::decimal::Decimal;
+use crate::numarray::{ArrayOp, ArrayError};
+use crate::nvec::NVec;
+use crate::vval_convert::TypedFn;
 use crate::vval::SynPos;
 use crate::vval::Syntax;
 use crate::vval::Env;
@@ -11,10 +17,13 @@ use crate::vval::VValFun;
 use crate::vval::EvalNode;
 use crate::vval::StackAction;
 use crate::vval::CompileError;
+use crate::vval::dec_partial_cmp;
+use crate::formatter::FormatSpec;
 use std::rc::Rc;
 use std::cell::RefCell;
 use std::time::Instant;
 use std::fmt::{Display, Formatter};
+use std::cmp::Ordering;
 
 use fnv::FnvHashMap;
 
@@ -91,12 +100,14 @@ impl LocalFileModuleResolver {
 #[derive(Default, Debug, Clone)]
 pub struct SymbolTable {
     symbols: FnvHashMap<String, VVal>,
+    macros:  FnvHashMap<String, VVal>,
 }
 
 impl SymbolTable {
     pub fn new() -> Self {
         SymbolTable {
             symbols: FnvHashMap::with_capacity_and_hasher(10, Default::default()),
+            macros:  FnvHashMap::default(),
         }
     }
 
@@ -118,6 +129,14 @@ impl SymbolTable {
         self.symbols.insert(String::from(name), value);
     }
 
+    /// Sets the macro `name` to the function `fun`, which is
+    /// called at compile time with the argument ASTs as data
+    /// and returns the replacement AST. See also `std:wlambda:parse`
+    /// for the data format.
+    pub fn set_macro(&mut self, name: &str, fun: VVal) {
+        self.macros.insert(String::from(name), fun);
+    }
+
     /// Helper function for building symbol tables with functions in them.
     ///
     /// See also `VValFun::new_fun` for more details.
@@ -139,6 +158,23 @@ impl SymbolTable {
         self.symbols.insert(
             String::from(fnname), VValFun::new_fun(fun, min_args, max_args, err_arg_ok));
     }
+
+    /// Helper function for building symbol tables with Rust functions
+    /// with typed parameters in them.
+    ///
+    /// See also `GlobalEnv::add_typed_func` for more details.
+    ///
+    ///```
+    /// let mut st = wlambda::compiler::SymbolTable::new();
+    /// st.typed_fun("len", |s: String| s.len());
+    ///```
+    #[allow(dead_code)]
+    pub fn typed_fun<F, Args>(&mut self, fnname: &str, fun: F)
+        where F: TypedFn<Args> {
+
+        self.symbols.insert(
+            String::from(fnname), VValFun::new_typed_fun(fnname, fun));
+    }
 }
 
 impl ModuleResolver for LocalFileModuleResolver {
@@ -207,6 +243,10 @@ pub struct GlobalEnv {
     mem_modules:
         std::rc::Rc<std::cell::RefCell<std::collections::HashMap<String, SymbolTable>>>,
     resolver: Option<Rc<RefCell<dyn ModuleResolver>>>,
+    macros: FnvHashMap<String, VVal>,
+    macro_exports: FnvHashMap<String, VVal>,
+    macro_expansions: usize,
+    macro_depth: usize,
 }
 
 impl std::fmt::Debug for GlobalEnv {
@@ -249,6 +289,27 @@ impl GlobalEnv {
             VValFun::new_fun(fun, min_args, max_args, false));
     }
 
+    /// Adds a Rust function or closure with typed parameters to a
+    /// `GlobalEnv`. The arguments are converted with
+    /// [FromVVal](../vval_convert/trait.FromVVal.html) and the return
+    /// value with [IntoVVal](../vval_convert/trait.IntoVVal.html).
+    /// Trailing `Option` parameters are optional, mistyped arguments
+    /// return an error value.
+    ///
+    /// ```
+    /// use wlambda::compiler::GlobalEnv;
+    ///
+    /// let g = GlobalEnv::new();
+    /// g.borrow_mut().add_typed_func("add", |a: i64, b: i64| a + b);
+    /// ```
+    #[allow(dead_code)]
+    pub fn add_typed_func<F, Args>(&mut self, fnname: &str, fun: F)
+        where F: TypedFn<Args> {
+        self.env.insert(
+            String::from(fnname),
+            VValFun::new_typed_fun(fnname, fun));
+    }
+
     /// Sets a global variable to a value.
     ///
     /// See also [EvalContext::set_global_var()](struct.EvalContext.html#method.set_global_var)
@@ -295,9 +356,25 @@ impl GlobalEnv {
             for (k, v) in &st.symbols {
                 self.env.insert(prefix.clone() + &k, v.clone());
             }
+            for (k, v) in &st.macros {
+                self.macros.insert(prefix.clone() + k, v.clone());
+            }
         }
     }
 
+    /// Defines a macro for the code compiled with this `GlobalEnv`.
+    /// Macros defined this way are also exported, if the code is
+    /// loaded as module by `!@import`.
+    pub fn set_macro(&mut self, name: &str, fun: &VVal) {
+        self.macros.insert(String::from(name), fun.clone());
+        self.macro_exports.insert(String::from(name), fun.clone());
+    }
+
+    /// Returns the macro function with the given name.
+    pub fn get_macro(&self, name: &str) -> Option<VVal> {
+        self.macros.get(name).cloned()
+    }
+
     /// Sets the module resolver. There is a LocalFileModuleResolver available
     /// which loads the modules relative to the current working directory.
     ///
@@ -330,6 +407,10 @@ impl GlobalEnv {
                 std::rc::Rc::new(std::cell::RefCell::new(
                     std::collections::HashMap::new())),
             resolver: None,
+            macros: FnvHashMap::default(),
+            macro_exports: FnvHashMap::default(),
+            macro_expansions: 0,
+            macro_depth: 0,
         }))
     }
 
@@ -508,11 +589,40 @@ impl EvalContext {
             }
         }, Some(1), Some(2));
 
+        let ctx_clone =
+            Self::new_with_user_impl(
+                self.global.clone(),
+                self.local.borrow().get_user());
+
+        self.global.borrow_mut().add_func("std:wlambda:eval_ast", move |env: &mut Env, _argc: usize| {
+            let ast =
+                match parser::data_to_ast(&env.arg(0)) {
+                    Ok(ast) => ast,
+                    Err(e)  => {
+                        return Ok(env.new_err(
+                            format!("wlambda:eval_ast: {}", e)));
+                    },
+                };
+            let ctx     = ctx_clone.clone();
+            let mut ctx = ctx.register_self_eval();
+            match ctx.eval_ast(&ast) {
+                Ok(v)  => Ok(v),
+                Err(e) => Ok(env.new_err(format!("{}", e))),
+            }
+        }, Some(1), Some(1));
+
         self
     }
 
     pub fn get_exports(&self) -> SymbolTable {
-        SymbolTable { symbols: self.local.borrow_mut().exports.clone() }
+        let mut st = SymbolTable {
+            symbols: self.local.borrow_mut().exports.clone(),
+            macros:  FnvHashMap::default(),
+        };
+        for (name, fun) in self.global.borrow().macro_exports.iter() {
+            st.set_macro(name, fun.clone());
+        }
+        st
     }
 
     #[allow(dead_code)]
@@ -1033,8 +1143,11 @@ fn compile_def(ast: &VVal, ce: &mut Rc<RefCell<CompileEnv>>, is_global: bool) ->
                 },
                 VVal::Map(m) => {
                     for (i, vi) in poses.iter().enumerate() {
-                        let vname = vars.at(i).unwrap().s_raw();
-                        let val = m.borrow().get(&vname).cloned().unwrap_or(VVal::Nul);
+                        let val =
+                            match vars.at(i).unwrap() {
+                                VVal::Sym(vname) => m.borrow().get(&vname).cloned(),
+                                _                => None,
+                            }.unwrap_or(VVal::Nul);
 
                         match vi {
                             VarPos::Local(vip) => e.set_consume(*vip, val),
@@ -1128,6 +1241,673 @@ fn set_env_at_varpos(e: &mut Env, pos: &VarPos, v: &VVal) -> Option<String> {
     }
 }
 
+type PatternMatcher = Box<dyn Fn(&VVal, &mut Env) -> Result<bool, StackAction>>;
+
+/// The variables bound by the pattern of a `$match` arm. They shadow
+/// variables of the same name until the arm is compiled.
+struct PatternBindings {
+    names: Vec<String>,
+    saved: Vec<(String, Option<VarPos>)>,
+}
+
+impl PatternBindings {
+    fn new() -> Self {
+        Self { names: vec![], saved: vec![] }
+    }
+
+    fn bind(&mut self, name: &str, spos: &SynPos, ce: &mut Rc<RefCell<CompileEnv>>)
+        -> Result<usize, CompileError>
+    {
+        if self.names.iter().any(|n| n == name) {
+            return Err(CompileError {
+                msg: format!("Variable '{}' bound twice in pattern", name),
+                pos: spos.clone(),
+            });
+        }
+        self.names.push(name.to_string());
+
+        let old = ce.borrow_mut().local_map.remove(name);
+        self.saved.push((name.to_string(), old));
+
+        match ce.borrow_mut().def(name, false) {
+            VarPos::Local(idx) => Ok(idx),
+            _ => Err(CompileError {
+                msg: format!("Can't bind pattern variable '{}'", name),
+                pos: spos.clone(),
+            }),
+        }
+    }
+
+    /// Makes the shadowed variables visible again.
+    fn restore(self, ce: &mut Rc<RefCell<CompileEnv>>) {
+        for (name, old) in self.saved.into_iter().rev() {
+            match old {
+                Some(pos) => { ce.borrow_mut().local_map.insert(name, pos); },
+                None      => { ce.borrow_mut().local_map.remove(&name); },
+            }
+        }
+    }
+}
+
+fn in_pattern_range(v: &VVal, lo: &VVal, hi: &VVal, inclusive: bool) -> bool {
+    match (v, lo, hi) {
+        (VVal::Int(i), VVal::Int(lo), VVal::Int(hi)) => {
+            *i >= *lo && if inclusive { *i <= *hi } else { *i < *hi }
+        },
+        (VVal::Int(_), _, _) | (VVal::Flt(_), _, _) => {
+            let f = v.f();
+            f >= lo.f() && if inclusive { f <= hi.f() } else { f < hi.f() }
+        },
+        _ => false,
+    }
+}
+
+/// Compiles a pattern as produced by `parser::parse_pattern` into
+/// a function, that checks a value against the pattern and sets the
+/// local variables of the bindings.
+fn compile_pattern(pat: &VVal, spos: &SynPos, binds: &mut PatternBindings,
+                   ce: &mut Rc<RefCell<CompileEnv>>)
+    -> Result<PatternMatcher, CompileError>
+{
+    match &pat.v_s_raw(0)[..] {
+        "any" => Ok(Box::new(|_v: &VVal, _e: &mut Env| Ok(true))),
+        "bind" => {
+            let idx = binds.bind(&pat.v_s_raw(1), spos, ce)?;
+            Ok(Box::new(move |v: &VVal, e: &mut Env| {
+                e.set_consume(idx, v.clone());
+                Ok(true)
+            }))
+        },
+        "value" => {
+            let val = compile(&pat.v_(1), ce)?;
+            Ok(Box::new(move |v: &VVal, e: &mut Env| Ok(val(e)?.eqv(&v.deref()))))
+        },
+        "range" => {
+            let lo        = pat.v_(1);
+            let hi        = pat.v_(2);
+            let inclusive = pat.v_(3).b();
+            Ok(Box::new(move |v: &VVal, _e: &mut Env| {
+                Ok(in_pattern_range(&v.deref(), &lo, &hi, inclusive))
+            }))
+        },
+        #[cfg(feature="regex")]
+        "regex" => {
+            let rx =
+                match regex::Regex::new(&pat.v_s_raw(1)) {
+                    Ok(rx) => rx,
+                    Err(e) => {
+                        return Err(CompileError {
+                            msg: format!("Bad regex pattern: {}", e),
+                            pos: spos.clone(),
+                        });
+                    },
+                };
+
+            let mut groups = vec![];
+            for name in rx.capture_names().flatten() {
+                groups.push((name.to_string(), binds.bind(name, spos, ce)?));
+            }
+
+            Ok(Box::new(move |v: &VVal, e: &mut Env| {
+                let v = v.deref();
+                let s =
+                    match v {
+                        VVal::Str(_) | VVal::Sym(_) => v.s_raw(),
+                        _ => return Ok(false),
+                    };
+                match rx.captures(&s) {
+                    Some(caps) => {
+                        for (name, idx) in groups.iter() {
+                            e.set_consume(
+                                *idx,
+                                caps.name(name)
+                                    .map(|m| VVal::new_str(m.as_str()))
+                                    .unwrap_or(VVal::Nul));
+                        }
+                        Ok(true)
+                    },
+                    None => Ok(false),
+                }
+            }))
+        },
+        #[cfg(not(feature="regex"))]
+        "regex" => {
+            Err(CompileError {
+                msg: "Regex patterns need the 'regex' feature".to_string(),
+                pos: spos.clone(),
+            })
+        },
+        "vec" => {
+            let before : Vec<PatternMatcher> =
+                pat.v_(1).map_skip(|p| compile_pattern(p, spos, binds, ce), 0)?;
+            let has_rest = pat.v_(2).b();
+            let rest =
+                if pat.v_(3).is_none() { None }
+                else { Some(binds.bind(&pat.v_s_raw(3), spos, ce)?) };
+            let after : Vec<PatternMatcher> =
+                pat.v_(4).map_skip(|p| compile_pattern(p, spos, binds, ce), 0)?;
+
+            Ok(Box::new(move |v: &VVal, e: &mut Env| {
+                let l =
+                    if let VVal::Lst(l) = v.deref() { l }
+                    else { return Ok(false); };
+                let elems : Vec<VVal> = l.borrow().clone();
+
+                let min_len = before.len() + after.len();
+                if elems.len() < min_len || (!has_rest && elems.len() != min_len) {
+                    return Ok(false);
+                }
+
+                for (p, v) in before.iter().zip(elems.iter()) {
+                    if !p(v, e)? { return Ok(false); }
+                }
+                let after_start = elems.len() - after.len();
+                for (p, v) in after.iter().zip(elems[after_start..].iter()) {
+                    if !p(v, e)? { return Ok(false); }
+                }
+                if let Some(idx) = rest {
+                    e.set_consume(
+                        idx, VVal::vec_mv(elems[before.len()..after_start].to_vec()));
+                }
+                Ok(true)
+            }))
+        },
+        "map" => {
+            let mut entries : Vec<(Symbol, PatternMatcher)> = vec![];
+            for (entry, _) in pat.v_(1).iter() {
+                entries.push(
+                    (Symbol::new(&entry.v_s_raw(0)),
+                     compile_pattern(&entry.v_(1), spos, binds, ce)?));
+            }
+            let has_rest = pat.v_(2).b();
+            let rest =
+                if pat.v_(3).is_none() { None }
+                else { Some(binds.bind(&pat.v_s_raw(3), spos, ce)?) };
+
+            Ok(Box::new(move |v: &VVal, e: &mut Env| {
+                let m =
+                    if let VVal::Map(m) = v.deref() { m }
+                    else { return Ok(false); };
+                if !has_rest && m.borrow().len() != entries.len() {
+                    return Ok(false);
+                }
+
+                for (key, p) in entries.iter() {
+                    let ev = m.borrow().get(key).cloned();
+                    match ev {
+                        Some(ev) => { if !p(&ev, e)? { return Ok(false); } },
+                        None     => { return Ok(false); },
+                    }
+                }
+                if let Some(idx) = rest {
+                    let rest_map = VVal::map();
+                    for (k, v) in m.borrow().iter() {
+                        if !entries.iter().any(|(key, _)| key == k) {
+                            rest_map.set_map_key(k.to_string(), v.clone());
+                        }
+                    }
+                    e.set_consume(idx, rest_map);
+                }
+                Ok(true)
+            }))
+        },
+        p => Err(CompileError {
+            msg: format!("Unknown pattern type '{}'", p),
+            pos: spos.clone(),
+        }),
+    }
+}
+
+/// Compiles `$match value { pattern ? guard => body; ... }`.
+/// Each arm is tried in order, the first one where the pattern matches and
+/// the guard is true evaluates it's body. The variables bound by the
+/// pattern are locals of the enclosing function, but are only visible
+/// in the guard and body of their arm.
+fn compile_match(ast: &VVal, spos: &SynPos, ce: &mut Rc<RefCell<CompileEnv>>)
+    -> Result<EvalNode, CompileError>
+{
+    let value = compile(&ast.v_(1), ce)?;
+
+    let mut arms : Vec<(PatternMatcher, Option<EvalNode>, EvalNode)> = vec![];
+    let mut i = 2;
+    while i + 2 < ast.len() {
+        let mut binds = PatternBindings::new();
+        let pat       = compile_pattern(&ast.v_(i), spos, &mut binds, ce)?;
+        let guard =
+            if ast.v_(i + 1).is_none() { None }
+            else { Some(compile(&ast.v_(i + 1), ce)?) };
+        let body = compile(&ast.v_(i + 2), ce)?;
+        binds.restore(ce);
+
+        arms.push((pat, guard, body));
+        i += 3;
+    }
+
+    Ok(Box::new(move |e: &mut Env| {
+        let v = value(e)?;
+        for (pat, guard, body) in arms.iter() {
+            if !pat(&v, e)? { continue; }
+            if let Some(guard) = guard {
+                if !guard(e)?.b() { continue; }
+            }
+            return body(e);
+        }
+        Ok(VVal::Nul)
+    }))
+}
+
+/// Returns true if the name is a local variable or upvalue in the
+/// function that is currently compiled or one of it's parents.
+fn is_local_var(ce: &Rc<RefCell<CompileEnv>>, name: &str) -> bool {
+    let ce = ce.borrow();
+    if ce.local_map.contains_key(name) {
+        return true;
+    }
+    match &ce.parent {
+        Some(p) => is_local_var(p, name),
+        None    => false,
+    }
+}
+
+/// Returns the syntax name of an AST node in data form, as produced
+/// by `parser::ast_to_data`.
+fn macro_node_syn(data: &VVal) -> Option<String> {
+    match data.at(0) {
+        Some(head) if head.is_map() => head.get_key("syn").map(|s| s.s_raw()),
+        _ => None,
+    }
+}
+
+/// Collects the variables defined by the macro itself, leaving out
+/// the parts of the AST that were passed in as arguments. These are
+/// renamed to keep them from capturing or shadowing variables
+/// of the code that called the macro.
+fn collect_macro_defs(data: &VVal, args: &std::collections::HashSet<i64>,
+                      suffix: &str, renames: &mut FnvHashMap<String, String>)
+{
+    if let VVal::Lst(l) = data {
+        if args.contains(&data.ref_id().unwrap_or(0)) {
+            return;
+        }
+
+        if let Some("Def") = macro_node_syn(data).as_deref() {
+            let ids = data.v_(1);
+            for i in 0..ids.len() {
+                let name = ids.v_s_raw(i);
+                let new_name = format!("{}~{}", name, suffix);
+                renames.insert(name, new_name);
+            }
+        }
+
+        for v in l.borrow().iter() {
+            collect_macro_defs(v, args, suffix, renames);
+        }
+    }
+}
+
+/// Converts the data returned by a macro back to an AST and
+/// applies the renames found by `collect_macro_defs`.
+fn macro_data_to_ast(data: &VVal, args: &std::collections::HashSet<i64>,
+                     renames: &FnvHashMap<String, String>)
+    -> Result<VVal, String>
+{
+    let l =
+        match data {
+            VVal::Lst(l) => l,
+            _ => return parser::data_to_ast(data),
+        };
+
+    if args.contains(&data.ref_id().unwrap_or(0)) {
+        return parser::data_to_ast(data);
+    }
+
+    let mut out = vec![];
+    for v in l.borrow().iter() {
+        out.push(macro_data_to_ast(v, args, renames)?);
+    }
+    let ast = VVal::vec_mv(out);
+    parser::check_ast_node(&ast)?;
+
+    let rename = |name: VVal| {
+        match renames.get(&name.s_raw()) {
+            Some(new_name) => VVal::new_sym(new_name),
+            None           => name,
+        }
+    };
+
+    match macro_node_syn(data).as_deref() {
+        Some("Var") => {
+            ast.set_at(1, rename(ast.v_(1)));
+        },
+        Some("Def") | Some("Assign") | Some("AssignRef") => {
+            let ids = ast.v_(1);
+            for i in 0..ids.len() {
+                ids.set_at(i, rename(ids.v_(i)));
+            }
+            ast.set_at(1, ids);
+        },
+        _ => (),
+    }
+
+    Ok(ast)
+}
+
+/// How deep macro expansions may be nested, before compiling
+/// fails with an error.
+const MAX_MACRO_DEPTH : usize = 64;
+
+/// Expands a call to a macro. Returns `None` if the called
+/// function is not a macro.
+fn expand_macro(ast: &VVal, spos: &SynPos, ce: &mut Rc<RefCell<CompileEnv>>)
+    -> Result<Option<VVal>, CompileError>
+{
+    let fun = ast.v_(1);
+    if fun.v_(0).get_syn() != Syntax::Var {
+        return Ok(None);
+    }
+
+    let name = fun.v_s_raw(1);
+    if is_local_var(ce, &name) {
+        return Ok(None);
+    }
+
+    let global = ce.borrow().global.clone();
+    let mac =
+        match global.borrow().get_macro(&name) {
+            Some(mac) => mac,
+            None      => return Ok(None),
+        };
+
+    let mut args = std::collections::HashSet::new();
+    let mut arg_data = vec![];
+    for i in 2..ast.len() {
+        let data = parser::ast_to_data(&ast.v_(i));
+        if let VVal::Lst(_) = data {
+            args.insert(data.ref_id().unwrap_or(0));
+        }
+        arg_data.push(data);
+    }
+
+    let macro_err = |msg: String| CompileError {
+        msg: format!("Error in macro '{}': {}", name, msg),
+        pos: spos.clone(),
+    };
+
+    let mut ctx =
+        EvalContext::new_with_user_impl(
+            global.clone(), Rc::new(RefCell::new(VVal::vec())));
+    let data =
+        match ctx.call(&mac, &arg_data) {
+            Ok(v) if v.is_err() => return Err(macro_err(v.s())),
+            Ok(v)  => v,
+            Err(e) => return Err(macro_err(format!("{:?}", e))),
+        };
+
+    let suffix = {
+        let mut g = global.borrow_mut();
+        g.macro_expansions += 1;
+        g.macro_expansions.to_string()
+    };
+
+    let mut renames = FnvHashMap::default();
+    collect_macro_defs(&data, &args, &suffix, &mut renames);
+
+    match macro_data_to_ast(&data, &args, &renames) {
+        Ok(ast) => Ok(Some(ast)),
+        Err(e)  => Err(macro_err(e)),
+    }
+}
+
+/// Evaluates the expression of a `!@macro` definition at compile time
+/// and stores the resulting function as macro in the global environment.
+fn compile_def_macro(ast: &VVal, spos: &SynPos, ce: &mut Rc<RefCell<CompileEnv>>)
+    -> Result<EvalNode, CompileError>
+{
+    let name   = ast.v_s_raw(1);
+    let global = ce.borrow().global.clone();
+
+    let mut ctx =
+        EvalContext::new_with_user_impl(
+            global.clone(), Rc::new(RefCell::new(VVal::vec())));
+
+    let fun =
+        match ctx.eval_ast(&ast.v_(2)) {
+            Ok(fun) => fun,
+            Err(EvalError::CompileError(e)) => return Err(e),
+            Err(e) => {
+                return Err(CompileError {
+                    msg: format!("Error in macro '{}': {}", name, e),
+                    pos: spos.clone(),
+                });
+            },
+        };
+
+    if !fun.is_fun() {
+        return ast.to_compile_err(
+            format!("Macro '{}' is not a function: {}", name, fun.s()));
+    }
+
+    global.borrow_mut().set_macro(&name, &fun);
+
+    Ok(Box::new(move |_e: &mut Env| Ok(VVal::Nul)))
+}
+
+type AssignOpFn = Box<dyn Fn(&mut Env, VVal, VVal) -> Result<VVal, StackAction>>;
+
+/// Applies the decimal arithmetic operation `op` (implemented by `f`)
+/// to `a` and `b`. An overflow returns an error value and a
+/// division by 0 panics, like the integer operations.
+fn dec_arith(a: Decimal, op: &str, b: &VVal,
+             f: fn(Decimal, Decimal) -> Option<Decimal>, spos: &SynPos)
+    -> Result<VVal, StackAction>
+{
+    let b =
+        match b.try_d() {
+            Some(b) => b,
+            None =>
+                return Ok(VVal::err(
+                    VVal::new_str_mv(format!("Decimal overflow: {} {} {}", a, op, b.s())),
+                    spos.clone())),
+        };
+    if let Some(r) = f(a, b) {
+        return Ok(VVal::new_dec(r));
+    }
+
+    if b.is_zero() && (op == "/" || op == "%") {
+        Err(StackAction::panic_str(
+            format!("Division by 0: {}{}{}", a, op, b), Some(spos.clone())))
+    } else {
+        Ok(VVal::err(
+            VVal::new_str_mv(format!("Decimal overflow: {} {} {}", a, op, b)),
+            spos.clone()))
+    }
+}
+
+/// Turns the result of an element wise numeric array operation into
+/// a value. A division by 0 panics, the other errors are returned
+/// as error values.
+fn elem_result(r: Result<VVal, ArrayError>, spos: &SynPos)
+    -> Result<VVal, StackAction>
+{
+    match r {
+        Ok(v) => Ok(v),
+        Err(e @ ArrayError::DivisionByZero(..)) =>
+            Err(StackAction::panic_str(e.to_string(), Some(spos.clone()))),
+        Err(e) =>
+            Ok(VVal::err(VVal::new_str_mv(e.to_string()), spos.clone())),
+    }
+}
+
+/// Returns the error value for an integer arithmetic operation
+/// that overflowed.
+fn int_overflow_err(a: i64, op: &str, b: i64, spos: &SynPos) -> VVal {
+    VVal::err(
+        VVal::new_str_mv(format!("Integer overflow: {} {} {}", a, op, b)),
+        spos.clone())
+}
+
+type ArithFn = fn(&mut Env, VVal, VVal, &SynPos) -> Result<VVal, StackAction>;
+
+/// Implements `+` for the `BinOpAdd` node and compound assignments.
+/// Like the other arithmetic operators, overloaded operators of
+/// objects and user data are tried first.
+fn op_add(e: &mut Env, le: VVal, re: VVal, spos: &SynPos) -> Result<VVal, StackAction> {
+    if let Some(ret) = VVal::call_binop(e, "_add_", Some("_radd_"), &le, &re) {
+        return ret;
+    }
+    if let VVal::Flt(f) = le {
+        return Ok(VVal::Flt(f + re.f()));
+    }
+    if let VVal::Dec(d) = le {
+        return dec_arith(*d, "+", &re, Decimal::checked_add, spos);
+    }
+    if let Some(r) = le.elem_binop(ArrayOp::Add, &re) {
+        return elem_result(r, spos);
+    }
+    let (a, b) = (le.i(), re.i());
+    Ok(a.checked_add(b).map(VVal::Int)
+        .unwrap_or_else(|| int_overflow_err(a, "+", b, spos)))
+}
+
+fn op_sub(e: &mut Env, le: VVal, re: VVal, spos: &SynPos) -> Result<VVal, StackAction> {
+    if let Some(ret) = VVal::call_binop(e, "_sub_", Some("_rsub_"), &le, &re) {
+        return ret;
+    }
+    if let VVal::Flt(f) = le {
+        return Ok(VVal::Flt(f - re.f()));
+    }
+    if let VVal::Dec(d) = le {
+        return dec_arith(*d, "-", &re, Decimal::checked_sub, spos);
+    }
+    if let Some(r) = le.elem_binop(ArrayOp::Sub, &re) {
+        return elem_result(r, spos);
+    }
+    let (a, b) = (le.i(), re.i());
+    Ok(a.checked_sub(b).map(VVal::Int)
+        .unwrap_or_else(|| int_overflow_err(a, "-", b, spos)))
+}
+
+fn op_mul(e: &mut Env, le: VVal, re: VVal, spos: &SynPos) -> Result<VVal, StackAction> {
+    if let Some(ret) = VVal::call_binop(e, "_mul_", Some("_rmul_"), &le, &re) {
+        return ret;
+    }
+    if let VVal::Flt(f) = le {
+        return Ok(VVal::Flt(f * re.f()));
+    }
+    if let VVal::Dec(d) = le {
+        return dec_arith(*d, "*", &re, Decimal::checked_mul, spos);
+    }
+    if let Some(r) = le.elem_binop(ArrayOp::Mul, &re) {
+        return elem_result(r, spos);
+    }
+    let (a, b) = (le.i(), re.i());
+    Ok(a.checked_mul(b).map(VVal::Int)
+        .unwrap_or_else(|| int_overflow_err(a, "*", b, spos)))
+}
+
+fn op_div(e: &mut Env, le: VVal, re: VVal, spos: &SynPos) -> Result<VVal, StackAction> {
+    if let Some(ret) = VVal::call_binop(e, "_div_", Some("_rdiv_"), &le, &re) {
+        return ret;
+    }
+    if let VVal::Flt(f) = le {
+        Ok(VVal::Flt(f / re.f()))
+
+    } else if let VVal::Dec(d) = le {
+        dec_arith(*d, "/", &re, Decimal::checked_div, spos)
+
+    } else if let Some(r) = le.elem_binop(ArrayOp::Div, &re) {
+        elem_result(r, spos)
+
+    } else if re.i() == 0 {
+        Err(StackAction::panic_str(
+            format!("Division by 0: {}/{}", le.i(), re.i()),
+            Some(spos.clone())))
+
+    } else {
+        let (a, b) = (le.i(), re.i());
+        Ok(a.checked_div(b).map(VVal::Int)
+            .unwrap_or_else(|| int_overflow_err(a, "/", b, spos)))
+    }
+}
+
+fn op_mod(e: &mut Env, le: VVal, re: VVal, spos: &SynPos) -> Result<VVal, StackAction> {
+    if let Some(ret) = VVal::call_binop(e, "_mod_", Some("_rmod_"), &le, &re) {
+        return ret;
+    }
+    if let VVal::Flt(f) = le {
+        Ok(VVal::Flt(f % re.f()))
+
+    } else if let VVal::Dec(d) = le {
+        dec_arith(*d, "%", &re, Decimal::checked_rem, spos)
+
+    } else if let Some(r) = le.elem_binop(ArrayOp::Rem, &re) {
+        elem_result(r, spos)
+
+    } else if re.i() == 0 {
+        Err(StackAction::panic_str(
+            format!("Division by 0: {}%{}", le.i(), re.i()),
+            Some(spos.clone())))
+
+    } else {
+        let (a, b) = (le.i(), re.i());
+        Ok(a.checked_rem(b).map(VVal::Int)
+            .unwrap_or_else(|| int_overflow_err(a, "%", b, spos)))
+    }
+}
+
+fn compile_arith_binop(ast: &VVal, op: ArithFn, spos: SynPos,
+                       ce: &mut Rc<RefCell<CompileEnv>>)
+    -> Result<EvalNode, CompileError>
+{
+    let left  = compile(&ast.at(1).unwrap(), ce)?;
+    let right = compile(&ast.at(2).unwrap(), ce)?;
+
+    Ok(Box::new(move |e: &mut Env| {
+        let le = check_error_value(left(e)?, "operand")?;
+        let re = check_error_value(right(e)?, "operand")?;
+        op(e, le, re, &spos)
+    }))
+}
+
+/// Compiles the binary operation of a compound field assignment
+/// like `obj.x += 10`. The arithmetic operators are the same as
+/// for the `BinOp*` nodes, all others are looked up as function.
+fn compile_assign_op(op: &str, spos: &SynPos, ce: &mut Rc<RefCell<CompileEnv>>)
+    -> Result<AssignOpFn, CompileError>
+{
+    let arith : Option<ArithFn> =
+        match op {
+            "+" => Some(op_add),
+            "-" => Some(op_sub),
+            "*" => Some(op_mul),
+            "/" => Some(op_div),
+            "%" => Some(op_mod),
+            _   => None,
+        };
+
+    let spos = spos.clone();
+    if let Some(arith) = arith {
+        return Ok(Box::new(move |e: &mut Env, le: VVal, re: VVal| {
+            let le = check_error_value(le, "operand")?;
+            let re = check_error_value(re, "operand")?;
+            arith(e, le, re, &spos)
+        }));
+    }
+
+    let mut var_pos = spos.clone();
+    var_pos.syn = Syntax::Var;
+    let var = VVal::vec();
+    var.push(VVal::Syn(var_pos));
+    var.push(VVal::new_sym(op));
+
+    let fun = compile(&var, ce)?;
+    Ok(Box::new(move |e: &mut Env, le: VVal, re: VVal| {
+        let f = fun(e)?;
+        f.call(e, &[le, re])
+         .map_err(|sa| sa.wrap_panic(Some(spos.clone())))
+    }))
+}
+
 fn compile_assign(ast: &VVal, ce: &mut Rc<RefCell<CompileEnv>>, is_ref: bool) -> Result<EvalNode, CompileError> {
     let prev_max_arity = ce.borrow().implicit_arity.clone();
 
@@ -1168,8 +1948,11 @@ fn compile_assign(ast: &VVal, ce: &mut Rc<RefCell<CompileEnv>>, is_ref: bool) ->
                     },
                     VVal::Map(m) => {
                         for (i, pos) in poses.iter().enumerate() {
-                            let vname = vars.at(i).unwrap().s_raw();
-                            let val = m.borrow().get(&vname).cloned().unwrap_or(VVal::Nul);
+                            let val =
+                                match vars.at(i).unwrap() {
+                                    VVal::Sym(vname) => m.borrow().get(&vname).cloned(),
+                                    _                => None,
+                                }.unwrap_or(VVal::Nul);
                             if let Some(err) = set_ref_at_varpos(e, pos, val) {
                                 return Err(
                                     StackAction::panic_str(err, Some(spos.clone())));
@@ -1204,8 +1987,11 @@ fn compile_assign(ast: &VVal, ce: &mut Rc<RefCell<CompileEnv>>, is_ref: bool) ->
                     },
                     VVal::Map(m) => {
                         for (i, pos) in poses.iter().enumerate() {
-                            let vname = vars.at(i).unwrap().s_raw();
-                            let val = m.borrow().get(&vname).cloned().unwrap_or(VVal::Nul);
+                            let val =
+                                match vars.at(i).unwrap() {
+                                    VVal::Sym(vname) => m.borrow().get(&vname).cloned(),
+                                    _                => None,
+                                }.unwrap_or(VVal::Nul);
 
                             if let Some(err) = set_env_at_varpos(e, pos, &val) {
                                 return Err(
@@ -1315,6 +2101,8 @@ fn check_error_value(v: VVal, at: &str) -> Result<VVal, StackAction> {
 fn generate_get_key(map: EvalNode, idx: EvalNode, spos: SynPos, method: bool)
     -> EvalNode
 {
+    let cache = LookupCache::new();
+
     if method {
         Box::new(move |e: &mut Env| {
             let m = map(e)?;
@@ -1322,7 +2110,7 @@ fn generate_get_key(map: EvalNode, idx: EvalNode, spos: SynPos, method: bool)
             match s {
                 VVal::Int(i)  => Ok(m.at(i as usize).unwrap_or(VVal::Nul)),
                 VVal::Bol(b)  => Ok(m.at(b as usize).unwrap_or(VVal::Nul)),
-                VVal::Sym(sy) => Ok(m.proto_lookup(&sy.borrow()).unwrap_or(VVal::Nul)),
+                VVal::Sym(sy) => Ok(m.proto_lookup_cached(&sy, &cache).unwrap_or(VVal::Nul)),
                 VVal::Str(sy) => Ok(m.proto_lookup(&sy.borrow()).unwrap_or(VVal::Nul)),
                 _ => {
                     e.with_pushed_sp(1, |e: &mut Env| {
@@ -1344,7 +2132,7 @@ fn generate_get_key(map: EvalNode, idx: EvalNode, spos: SynPos, method: bool)
             match s {
                 VVal::Int(i)  => Ok(m.at(i as usize).unwrap_or(VVal::Nul)),
                 VVal::Bol(b)  => Ok(m.at(b as usize).unwrap_or(VVal::Nul)),
-                VVal::Sym(sy) => Ok(m.get_key(&sy.borrow()).unwrap_or(VVal::Nul)),
+                VVal::Sym(sy) => Ok(m.get_key_cached(&sy, &cache).unwrap_or(VVal::Nul)),
                 VVal::Str(sy) => Ok(m.get_key(&sy.borrow()).unwrap_or(VVal::Nul)),
                 _ => {
                     e.with_pushed_sp(1, |e: &mut Env| {
@@ -1362,8 +2150,26 @@ fn generate_get_key(map: EvalNode, idx: EvalNode, spos: SynPos, method: bool)
     }
 }
 
+// Calls `f` with the `argc` arguments on the stack. Method calls on
+// user data are passed to VValUserData::call_method instead of `f`.
+fn call_fun_or_method(f: &VVal, method: &Option<Symbol>, e: &mut Env, argc: usize)
+    -> Result<VVal, StackAction>
+{
+    if let Some(name) = method {
+        if let VVal::Usr(u) = e.self_object().deref() {
+            return e.with_local_call_info(argc, |e: &mut Env| {
+                let args : Vec<VVal> = (0..argc).map(|i| e.arg(i)).collect();
+                u.call_method(name, &args, e)
+            });
+        }
+    }
+    f.call_internal(e, argc)
+}
+
+// `method` is the name of the method for calls like `obj.method args`.
 fn generate_call(func: EvalNode,
                  call_args: Vec<EvalNode>,
+                 method: Option<Symbol>,
                  spos: SynPos)
     -> EvalNode
 {
@@ -1372,7 +2178,7 @@ fn generate_call(func: EvalNode,
     match argc {
         0 => (Box::new(move |e: &mut Env| {
             let f = func(e)?;
-            let ret = f.call_no_args(e);
+            let ret = call_fun_or_method(&f, &method, e, 0);
             if let Err(sa) = ret {
                 Err(sa.wrap_panic(Some(spos.clone())))
             } else {
@@ -1385,7 +2191,7 @@ fn generate_call(func: EvalNode,
             e.with_pushed_sp(1, |e: &mut Env| {
                 let v = call_args[0](e)?;
                 e.set_arg(0, v);
-                let ret = f.call_internal(e, 1);
+                let ret = call_fun_or_method(&f, &method, e, 1);
                 if let Err(sa) = ret {
                     Err(sa.wrap_panic(Some(spos.clone())))
                 } else {
@@ -1402,7 +2208,7 @@ fn generate_call(func: EvalNode,
                 e.set_arg(0, v);
                 e.set_arg(1, v1);
 
-                let ret = f.call_internal(e, 2);
+                let ret = call_fun_or_method(&f, &method, e, 2);
                 if let Err(sa) = ret {
                     Err(sa.wrap_panic(Some(spos.clone())))
                 } else {
@@ -1421,7 +2227,7 @@ fn generate_call(func: EvalNode,
                 e.set_arg(1, v1);
                 e.set_arg(2, v2);
 
-                let ret = f.call_internal(e, 3);
+                let ret = call_fun_or_method(&f, &method, e, 3);
                 if let Err(sa) = ret {
                     Err(sa.wrap_panic(Some(spos.clone())))
                 } else {
@@ -1442,7 +2248,7 @@ fn generate_call(func: EvalNode,
                 e.set_arg(2, v2);
                 e.set_arg(3, v3);
 
-                let ret = f.call_internal(e, 4);
+                let ret = call_fun_or_method(&f, &method, e, 4);
                 if let Err(sa) = ret {
                     Err(sa.wrap_panic(Some(spos.clone())))
                 } else {
@@ -1465,7 +2271,7 @@ fn generate_call(func: EvalNode,
                 e.set_arg(3, v3);
                 e.set_arg(4, v4);
 
-                let ret = f.call_internal(e, 5);
+                let ret = call_fun_or_method(&f, &method, e, 5);
                 if let Err(sa) = ret {
                     Err(sa.wrap_panic(Some(spos.clone())))
                 } else {
@@ -1490,7 +2296,7 @@ fn generate_call(func: EvalNode,
                 e.set_arg(4, v4);
                 e.set_arg(5, v5);
 
-                let ret = f.call_internal(e, 6);
+                let ret = call_fun_or_method(&f, &method, e, 6);
                 if let Err(sa) = ret {
                     Err(sa.wrap_panic(Some(spos.clone())))
                 } else {
@@ -1506,7 +2312,7 @@ fn generate_call(func: EvalNode,
                     let v = x(e)?;
                     e.set_arg(i, v);
                 }
-                let ret = f.call_internal(e, argc);
+                let ret = call_fun_or_method(&f, &method, e, argc);
                 if let Err(sa) = ret {
                     Err(sa.wrap_panic(Some(spos.clone())))
                 } else {
@@ -1665,6 +2471,9 @@ fn compile(ast: &VVal, ce: &mut Rc<RefCell<CompileEnv>>) -> Result<EvalNode, Com
 //                                println!("IMPORT: {}", k);
                                 e.insert(s_prefix.clone() + &k, v.clone());
                             }
+                            for (k, v) in &stbl.macros {
+                                gr.macros.insert(s_prefix.clone() + k, v.clone());
+                            }
                             return Ok(Box::new(move |_e: &mut Env| { Ok(VVal::Nul) }));
                         }
                     }
@@ -1701,6 +2510,10 @@ fn compile(ast: &VVal, ce: &mut Rc<RefCell<CompileEnv>>) -> Result<EvalNode, Com
                                     glob_ref.borrow_mut().env.insert(
                                         s_prefix.clone() + &k, v.clone());
                                 }
+                                for (k, v) in symtbl.macros {
+                                    glob_ref.borrow_mut().macros.insert(
+                                        s_prefix.clone() + &k, v.clone());
+                                }
 
                                 Ok(Box::new(move |_e: &mut Env| { Ok(VVal::Nul) }))
                             },
@@ -1727,6 +2540,16 @@ fn compile(ast: &VVal, ce: &mut Rc<RefCell<CompileEnv>>) -> Result<EvalNode, Com
                         Ok(value)
                     }))
                 },
+                Syntax::Match => { compile_match(ast, &spos, ce) },
+                Syntax::DefMacro => { compile_def_macro(ast, &spos, ce) },
+                Syntax::Code => {
+                    let code = ast.v_(1);
+                    Ok(Box::new(move |_e: &mut Env| Ok(parser::ast_to_data(&code))))
+                },
+                Syntax::Iter => {
+                    let iterable = compile(&ast.at(1).unwrap(), ce)?;
+                    Ok(Box::new(move |e: &mut Env| Ok(iterable(e)?.to_iter())))
+                },
                 Syntax::Err => {
                     let err_val = compile(&ast.at(1).unwrap(), ce)?;
                     Ok(Box::new(move |e: &mut Env|
@@ -1777,35 +2600,41 @@ fn compile(ast: &VVal, ce: &mut Rc<RefCell<CompileEnv>>) -> Result<EvalNode, Com
                 },
                 Syntax::GetSym => {
                     let map = compile(&ast.at(1).unwrap(), ce)?;
-                    let sym = ast.at(2).unwrap().s_raw();
+                    let sym = Symbol::new(&ast.at(2).unwrap().s_raw());
+                    let cache = LookupCache::new();
 
                     Ok(Box::new(move |e: &mut Env| {
                         let m = map(e)?;
-                        Ok(m.get_key(&sym).unwrap_or(VVal::Nul))
+                        Ok(m.get_key_cached(&sym, &cache).unwrap_or(VVal::Nul))
                     }))
                 },
                 Syntax::GetSym2 => {
                     let map = compile(&ast.at(1).unwrap(), ce)?;
-                    let sym = ast.at(2).unwrap().s_raw();
-                    let sym2 = ast.at(3).unwrap().s_raw();
+                    let sym = Symbol::new(&ast.at(2).unwrap().s_raw());
+                    let sym2 = Symbol::new(&ast.at(3).unwrap().s_raw());
+                    let cache = LookupCache::new();
+                    let cache2 = LookupCache::new();
 
                     Ok(Box::new(move |e: &mut Env| {
                         let m = map(e)?;
-                        Ok(m.get_key(&sym).unwrap_or(VVal::Nul)
-                            .get_key(&sym2).unwrap_or(VVal::Nul))
+                        Ok(m.get_key_cached(&sym, &cache).unwrap_or(VVal::Nul)
+                            .get_key_cached(&sym2, &cache2).unwrap_or(VVal::Nul))
                     }))
                 },
                 Syntax::GetSym3 => {
                     let map = compile(&ast.at(1).unwrap(), ce)?;
-                    let sym = ast.at(2).unwrap().s_raw();
-                    let sym2 = ast.at(3).unwrap().s_raw();
-                    let sym3 = ast.at(4).unwrap().s_raw();
+                    let sym = Symbol::new(&ast.at(2).unwrap().s_raw());
+                    let sym2 = Symbol::new(&ast.at(3).unwrap().s_raw());
+                    let sym3 = Symbol::new(&ast.at(4).unwrap().s_raw());
+                    let cache = LookupCache::new();
+                    let cache2 = LookupCache::new();
+                    let cache3 = LookupCache::new();
 
                     Ok(Box::new(move |e: &mut Env| {
                         let m = map(e)?;
-                        Ok(m.get_key(&sym).unwrap_or(VVal::Nul)
-                            .get_key(&sym2).unwrap_or(VVal::Nul)
-                            .get_key(&sym3).unwrap_or(VVal::Nul))
+                        Ok(m.get_key_cached(&sym, &cache).unwrap_or(VVal::Nul)
+                            .get_key_cached(&sym2, &cache2).unwrap_or(VVal::Nul)
+                            .get_key_cached(&sym3, &cache3).unwrap_or(VVal::Nul))
                     }))
                 },
                 Syntax::GetKey => {
@@ -1824,7 +2653,7 @@ fn compile(ast: &VVal, ce: &mut Rc<RefCell<CompileEnv>>) -> Result<EvalNode, Com
                         let s2 = check_error_value(idx2(e)?, "field idx/key")?;
                         let m = match s {
                             VVal::Int(i)  => Ok(m.at(i as usize).unwrap_or(VVal::Nul)),
-                            VVal::Sym(sy) => Ok(m.get_key(&sy.borrow()).unwrap_or(VVal::Nul)),
+                            VVal::Sym(sy) => Ok(m.get_key_sym(&sy).unwrap_or(VVal::Nul)),
                             VVal::Str(sy) => Ok(m.get_key(&sy.borrow()).unwrap_or(VVal::Nul)),
                             _ => {
                                 e.with_pushed_sp(1, |e: &mut Env| {
@@ -1840,7 +2669,7 @@ fn compile(ast: &VVal, ce: &mut Rc<RefCell<CompileEnv>>) -> Result<EvalNode, Com
                         }?;
                         match s2 {
                             VVal::Int(i)  => Ok(m.at(i as usize).unwrap_or(VVal::Nul)),
-                            VVal::Sym(sy) => Ok(m.get_key(&sy.borrow()).unwrap_or(VVal::Nul)),
+                            VVal::Sym(sy) => Ok(m.get_key_sym(&sy).unwrap_or(VVal::Nul)),
                             VVal::Str(sy) => Ok(m.get_key(&sy.borrow()).unwrap_or(VVal::Nul)),
                             _ => {
                                 e.with_pushed_sp(1, |e: &mut Env| {
@@ -1869,7 +2698,7 @@ fn compile(ast: &VVal, ce: &mut Rc<RefCell<CompileEnv>>) -> Result<EvalNode, Com
                         let s3 = check_error_value(idx3(e)?, "field idx/key")?;
                         let m = match s {
                             VVal::Int(i)  => Ok(m.at(i as usize).unwrap_or(VVal::Nul)),
-                            VVal::Sym(sy) => Ok(m.get_key(&sy.borrow()).unwrap_or(VVal::Nul)),
+                            VVal::Sym(sy) => Ok(m.get_key_sym(&sy).unwrap_or(VVal::Nul)),
                             VVal::Str(sy) => Ok(m.get_key(&sy.borrow()).unwrap_or(VVal::Nul)),
                             _ => {
                                 e.with_pushed_sp(1, |e: &mut Env| {
@@ -1885,7 +2714,7 @@ fn compile(ast: &VVal, ce: &mut Rc<RefCell<CompileEnv>>) -> Result<EvalNode, Com
                         }?;
                         let m = match s2 {
                             VVal::Int(i)  => Ok(m.at(i as usize).unwrap_or(VVal::Nul)),
-                            VVal::Sym(sy) => Ok(m.get_key(&sy.borrow()).unwrap_or(VVal::Nul)),
+                            VVal::Sym(sy) => Ok(m.get_key_sym(&sy).unwrap_or(VVal::Nul)),
                             VVal::Str(sy) => Ok(m.get_key(&sy.borrow()).unwrap_or(VVal::Nul)),
                             _ => {
                                 e.with_pushed_sp(1, |e: &mut Env| {
@@ -1901,7 +2730,7 @@ fn compile(ast: &VVal, ce: &mut Rc<RefCell<CompileEnv>>) -> Result<EvalNode, Com
                         }?;
                         match s3 {
                             VVal::Int(i)  => Ok(m.at(i as usize).unwrap_or(VVal::Nul)),
-                            VVal::Sym(sy) => Ok(m.get_key(&sy.borrow()).unwrap_or(VVal::Nul)),
+                            VVal::Sym(sy) => Ok(m.get_key_sym(&sy).unwrap_or(VVal::Nul)),
                             VVal::Str(sy) => Ok(m.get_key(&sy.borrow()).unwrap_or(VVal::Nul)),
                             _ => {
                                 e.with_pushed_sp(1, |e: &mut Env| {
@@ -1923,6 +2752,28 @@ fn compile(ast: &VVal, ce: &mut Rc<RefCell<CompileEnv>>) -> Result<EvalNode, Com
                     let recent_sym = ce.borrow().recent_sym.clone();
                     ce.borrow_mut().recent_var = recent_sym;
                     let val = compile(&ast.at(3).unwrap(), ce)?;
+
+                    if let Some(op) = ast.at(4) {
+                        let op = compile_assign_op(&op.s_raw(), &spos, ce)?;
+                        return Ok(Box::new(move |e: &mut Env| {
+                            let m = map(e)?;
+                            let s = check_error_value(sym(e)?,
+                                                      "field assignment key")?;
+                            let v = check_error_value(val(e)?,
+                                                      "field assignment value")?;
+                            let cur =
+                                match s {
+                                    VVal::Int(i) => m.at(i as usize),
+                                    _            => m.get_key(&s.s_raw()),
+                                }.unwrap_or(VVal::Nul);
+                            let v = op(e, cur, v)?;
+                            match m.set_key(&s, v.clone()) {
+                                Ok(()) => Ok(v),
+                                Err(sa) => Err(sa.wrap_panic(Some(spos.clone()))),
+                            }
+                        }));
+                    }
+
                     Ok(Box::new(move |e: &mut Env| {
                         let m = map(e)?;
                         let s = check_error_value(sym(e)?,
@@ -1935,6 +2786,36 @@ fn compile(ast: &VVal, ce: &mut Rc<RefCell<CompileEnv>>) -> Result<EvalNode, Com
                         }
                     }))
                 },
+                Syntax::Fmt => {
+                    let mut pieces : Vec<(Option<FormatSpec>, EvalNode)> = vec![];
+                    let mut i = 1;
+                    while i + 1 < ast.len() {
+                        let spec =
+                            match ast.v_(i) {
+                                VVal::Nul => None,
+                                spec => {
+                                    match FormatSpec::parse(&spec.s_raw()) {
+                                        Ok(fs) => Some(fs),
+                                        Err(e) => return ast.to_compile_err(e),
+                                    }
+                                },
+                            };
+                        pieces.push((spec, compile(&ast.v_(i + 1), ce)?));
+                        i += 2;
+                    }
+
+                    Ok(Box::new(move |e: &mut Env| {
+                        let mut s = String::new();
+                        for (spec, val) in pieces.iter() {
+                            let v = val(e)?;
+                            match spec {
+                                Some(fs) => s.push_str(&fs.format(&v)),
+                                None     => s.push_str(&v.s_raw()),
+                            }
+                        }
+                        Ok(VVal::new_str_mv(s))
+                    }))
+                },
                 Syntax::CaptureRef => { compile_var(ast, ce, true) },
                 Syntax::Ref => {
                     let val = compile(&ast.at(1).unwrap(), ce)?;
@@ -1954,6 +2835,28 @@ fn compile(ast: &VVal, ce: &mut Rc<RefCell<CompileEnv>>) -> Result<EvalNode, Com
                         Ok(val(e)?.deref())
                     }))
                 },
+                Syntax::IVec => {
+                    let comps : Vec<EvalNode> = ast.map_skip(|e| compile(e, ce), 1)?;
+
+                    Ok(Box::new(move |e: &mut Env| {
+                        let mut c = [0; 4];
+                        for (i, x) in comps.iter().enumerate() {
+                            c[i] = check_error_value(x(e)?, "vector")?.i();
+                        }
+                        Ok(VVal::new_ivec(NVec::from_slice(&c[..comps.len()]).unwrap()))
+                    }))
+                },
+                Syntax::FVec => {
+                    let comps : Vec<EvalNode> = ast.map_skip(|e| compile(e, ce), 1)?;
+
+                    Ok(Box::new(move |e: &mut Env| {
+                        let mut c = [0.0; 4];
+                        for (i, x) in comps.iter().enumerate() {
+                            c[i] = check_error_value(x(e)?, "vector")?.f();
+                        }
+                        Ok(VVal::new_fvec(NVec::from_slice(&c[..comps.len()]).unwrap()))
+                    }))
+                },
                 Syntax::Lst => {
                     let list_elems : Vec<(bool, EvalNode)> =
                         ast.map_skip(|e| {
@@ -1991,7 +2894,7 @@ fn compile(ast: &VVal, ce: &mut Rc<RefCell<CompileEnv>>) -> Result<EvalNode, Com
                                 } else {
                                     let kc = compile(&k, ce)?;
                                     if let VVal::Sym(y) = k {
-                                        ce.borrow_mut().recent_var = y.borrow().clone();
+                                        ce.borrow_mut().recent_var = y.to_string();
                                     } else {
                                         let recent_sym = ce.borrow().recent_sym.clone();
                                         ce.borrow_mut().recent_var = recent_sym;
@@ -2034,129 +2937,58 @@ fn compile(ast: &VVal, ce: &mut Rc<RefCell<CompileEnv>>) -> Result<EvalNode, Com
                         Ok(VVal::Bol(false))
                     }))
                 },
-                Syntax::And => {
-                    let exprs : Vec<EvalNode> =
-                        ast.map_skip(|e| compile(e, ce), 1)?;
-
-                    Ok(Box::new(move |e: &mut Env| {
-                        let mut ret = VVal::Nul;
-                        for x in exprs.iter() {
-                            ret = x(e)?;
-                            if !ret.b() {
-                                return Ok(VVal::Bol(false));
-                            }
-                        }
-                        Ok(ret)
-                    }))
-                },
-                Syntax::Apply => {
-                    let call_argv = compile(&ast.at(2).unwrap(), ce)?;
-                    let func      = compile(&ast.at(1).unwrap(), ce)?;
-
-                    Ok(Box::new(move |e: &mut Env| {
-                        let f = func(e)?;
-                        let mut argv = call_argv(e)?;
-                        let argc =
-                            if let VVal::Lst(l) = &argv {
-                                l.borrow().len()
-                            } else {
-                                let a = VVal::vec();
-                                a.push(argv);
-                                argv = a;
-                                1
-                            };
-
-                        e.with_pushed_sp(argc, |e: &mut Env| {
-                            for i in 0..argc {
-                                let v = argv.at(i).unwrap_or(VVal::Nul);
-                                e.set_arg(i, v);
-                            }
-
-                            let ret = f.call_internal(e, argc);
-                            if let Err(sa) = ret {
-                                Err(sa.wrap_panic(Some(spos.clone())))
-                            } else {
-                                ret
-                            }
-                        })
-                    }))
-                },
-                Syntax::BinOpAdd => {
-                    let left  = compile(&ast.at(1).unwrap(), ce)?;
-                    let right = compile(&ast.at(2).unwrap(), ce)?;
-
-                    Ok(Box::new(move |e: &mut Env| {
-                        let le = left(e)?;
-                        let re = right(e)?;
-                        if let VVal::Flt(f) = le {
-                            Ok(VVal::Flt(f + re.f()))
-                        } else {
-                            Ok(VVal::Int(le.i().wrapping_add(re.i())))
-                        }
-                    }))
-                },
-                Syntax::BinOpSub => {
-                    let left  = compile(&ast.at(1).unwrap(), ce)?;
-                    let right = compile(&ast.at(2).unwrap(), ce)?;
-
-                    Ok(Box::new(move |e: &mut Env| {
-                        let le = left(e)?;
-                        let re = right(e)?;
-                        if let VVal::Flt(f) = le {
-                            Ok(VVal::Flt(f - re.f()))
-                        } else {
-                            Ok(VVal::Int(le.i().wrapping_sub(re.i())))
-                        }
-                    }))
-                },
-                Syntax::BinOpMul => {
-                    let left  = compile(&ast.at(1).unwrap(), ce)?;
-                    let right = compile(&ast.at(2).unwrap(), ce)?;
-
-                    Ok(Box::new(move |e: &mut Env| {
-                        let le = left(e)?;
-                        let re = right(e)?;
-                        if let VVal::Flt(f) = le {
-                            Ok(VVal::Flt(f * re.f()))
-                        } else {
-                            Ok(VVal::Int(le.i().wrapping_mul(re.i())))
-                        }
-                    }))
-                },
-                Syntax::BinOpDiv => {
-                    let left  = compile(&ast.at(1).unwrap(), ce)?;
-                    let right = compile(&ast.at(2).unwrap(), ce)?;
+                Syntax::And => {
+                    let exprs : Vec<EvalNode> =
+                        ast.map_skip(|e| compile(e, ce), 1)?;
 
                     Ok(Box::new(move |e: &mut Env| {
-                        let le = left(e)?;
-                        let re = right(e)?;
-                        if let VVal::Flt(f) = le {
-                            Ok(VVal::Flt(f / re.f()))
-
-                        } else if re.i() == 0 {
-                            Err(StackAction::panic_str(
-                                format!("Division by 0: {}/{}", le.i(), re.i()),
-                                Some(spos.clone())))
-
-                        } else {
-                            Ok(VVal::Int(le.i().wrapping_div(re.i())))
+                        let mut ret = VVal::Nul;
+                        for x in exprs.iter() {
+                            ret = x(e)?;
+                            if !ret.b() {
+                                return Ok(VVal::Bol(false));
+                            }
                         }
+                        Ok(ret)
                     }))
                 },
-                Syntax::BinOpMod => {
-                    let left  = compile(&ast.at(1).unwrap(), ce)?;
-                    let right = compile(&ast.at(2).unwrap(), ce)?;
+                Syntax::Apply => {
+                    let call_argv = compile(&ast.at(2).unwrap(), ce)?;
+                    let func      = compile(&ast.at(1).unwrap(), ce)?;
 
                     Ok(Box::new(move |e: &mut Env| {
-                        let le = left(e)?;
-                        let re = right(e)?;
-                        if let VVal::Flt(f) = le {
-                            Ok(VVal::Flt(f % re.f()))
-                        } else {
-                            Ok(VVal::Int(le.i().wrapping_rem(re.i())))
-                        }
+                        let f = func(e)?;
+                        let mut argv = call_argv(e)?;
+                        let argc =
+                            if let VVal::Lst(l) = &argv {
+                                l.borrow().len()
+                            } else {
+                                let a = VVal::vec();
+                                a.push(argv);
+                                argv = a;
+                                1
+                            };
+
+                        e.with_pushed_sp(argc, |e: &mut Env| {
+                            for i in 0..argc {
+                                let v = argv.at(i).unwrap_or(VVal::Nul);
+                                e.set_arg(i, v);
+                            }
+
+                            let ret = f.call_internal(e, argc);
+                            if let Err(sa) = ret {
+                                Err(sa.wrap_panic(Some(spos.clone())))
+                            } else {
+                                ret
+                            }
+                        })
                     }))
                 },
+                Syntax::BinOpAdd => compile_arith_binop(ast, op_add, spos, ce),
+                Syntax::BinOpSub => compile_arith_binop(ast, op_sub, spos, ce),
+                Syntax::BinOpMul => compile_arith_binop(ast, op_mul, spos, ce),
+                Syntax::BinOpDiv => compile_arith_binop(ast, op_div, spos, ce),
+                Syntax::BinOpMod => compile_arith_binop(ast, op_mod, spos, ce),
                 Syntax::BinOpLe => {
                     let left  = compile(&ast.at(1).unwrap(), ce)?;
                     let right = compile(&ast.at(2).unwrap(), ce)?;
@@ -2164,8 +2996,14 @@ fn compile(ast: &VVal, ce: &mut Rc<RefCell<CompileEnv>>) -> Result<EvalNode, Com
                     Ok(Box::new(move |e: &mut Env| {
                         let le = left(e)?;
                         let re = right(e)?;
+                        if let Some(ret) = VVal::call_binop(e, "_le_", None, &le, &re) {
+                            return ret;
+                        }
                         if let VVal::Flt(af) = le {
                             Ok(VVal::Bol(af <= re.f()))
+                        } else if let VVal::Dec(ad) = le {
+                            Ok(VVal::Bol(dec_partial_cmp(&ad, &re)
+                                         .is_some_and(|o| o <= Ordering::Equal)))
                         } else {
                             Ok(VVal::Bol(le.i() <= re.i()))
                         }
@@ -2178,8 +3016,14 @@ fn compile(ast: &VVal, ce: &mut Rc<RefCell<CompileEnv>>) -> Result<EvalNode, Com
                     Ok(Box::new(move |e: &mut Env| {
                         let le = left(e)?;
                         let re = right(e)?;
+                        if let Some(ret) = VVal::call_binop(e, "_ge_", None, &le, &re) {
+                            return ret;
+                        }
                         if let VVal::Flt(af) = le {
                             Ok(VVal::Bol(af >= re.f()))
+                        } else if let VVal::Dec(ad) = le {
+                            Ok(VVal::Bol(dec_partial_cmp(&ad, &re)
+                                         .is_some_and(|o| o >= Ordering::Equal)))
                         } else {
                             Ok(VVal::Bol(le.i() >= re.i()))
                         }
@@ -2192,8 +3036,14 @@ fn compile(ast: &VVal, ce: &mut Rc<RefCell<CompileEnv>>) -> Result<EvalNode, Com
                     Ok(Box::new(move |e: &mut Env| {
                         let le = left(e)?;
                         let re = right(e)?;
+                        if let Some(ret) = VVal::call_binop(e, "_lt_", None, &le, &re) {
+                            return ret;
+                        }
                         if let VVal::Flt(af) = le {
                             Ok(VVal::Bol(af < re.f()))
+                        } else if let VVal::Dec(ad) = le {
+                            Ok(VVal::Bol(dec_partial_cmp(&ad, &re)
+                                         .is_some_and(|o| o < Ordering::Equal)))
                         } else {
                             Ok(VVal::Bol(le.i() < re.i()))
                         }
@@ -2206,14 +3056,36 @@ fn compile(ast: &VVal, ce: &mut Rc<RefCell<CompileEnv>>) -> Result<EvalNode, Com
                     Ok(Box::new(move |e: &mut Env| {
                         let le = left(e)?;
                         let re = right(e)?;
+                        if let Some(ret) = VVal::call_binop(e, "_gt_", None, &le, &re) {
+                            return ret;
+                        }
                         if let VVal::Flt(af) = le {
                             Ok(VVal::Bol(af > re.f()))
+                        } else if let VVal::Dec(ad) = le {
+                            Ok(VVal::Bol(dec_partial_cmp(&ad, &re)
+                                         .is_some_and(|o| o > Ordering::Equal)))
                         } else {
                             Ok(VVal::Bol(le.i() > re.i()))
                         }
                     }))
                 },
                 Syntax::Call => {
+                    if let Some(expanded) = expand_macro(ast, &spos, ce)? {
+                        // Macros that expand to calls of themselves would
+                        // otherwise recurse until the stack overflows:
+                        let global = ce.borrow().global.clone();
+                        if global.borrow().macro_depth >= MAX_MACRO_DEPTH {
+                            return ast.to_compile_err(format!(
+                                "Macro expansion nested deeper than {}: {}",
+                                MAX_MACRO_DEPTH, ast.v_(1).v_s_raw(1)));
+                        }
+
+                        global.borrow_mut().macro_depth += 1;
+                        let res = compile(&expanded, ce);
+                        global.borrow_mut().macro_depth -= 1;
+                        return res;
+                    }
+
                     if let Some((syntax, object, key)) =
                         fetch_object_key_access(&ast.at(1).unwrap()) {
 
@@ -2231,7 +3103,7 @@ fn compile(ast: &VVal, ce: &mut Rc<RefCell<CompileEnv>>) -> Result<EvalNode, Com
                                     spos.clone(),
                                     true);
                                 let fun_call =
-                                    generate_call(func, call_args, spos);
+                                    generate_call(func, call_args, None, spos);
                                 Ok(Box::new(move |e: &mut Env| {
                                     let o = obj(e)?;
                                     e.with_object(
@@ -2239,13 +3111,20 @@ fn compile(ast: &VVal, ce: &mut Rc<RefCell<CompileEnv>>) -> Result<EvalNode, Com
                                 }))
                             },
                             Syntax::GetSym => {
-                                let key = key.s_raw();
+                                let key = Symbol::new(&key.s_raw());
+                                let cache = LookupCache::new();
+                                let method = Some(key.clone());
                                 let func = Box::new(move |e: &mut Env| {
                                     let o = e.self_object();
-                                    Ok(o.proto_lookup(&key).unwrap_or(VVal::Nul))
+                                    // User data is dispatched by name
+                                    // in call_fun_or_method.
+                                    if let VVal::Usr(_) = o.deref() {
+                                        return Ok(VVal::Nul);
+                                    }
+                                    Ok(o.proto_lookup_cached(&key, &cache).unwrap_or(VVal::Nul))
                                 });
                                 let fun_call =
-                                    generate_call(func, call_args, spos);
+                                    generate_call(func, call_args, method, spos);
                                 Ok(Box::new(move |e: &mut Env| {
                                     let o = obj(e)?;
                                     e.with_object(
@@ -2266,7 +3145,7 @@ fn compile(ast: &VVal, ce: &mut Rc<RefCell<CompileEnv>>) -> Result<EvalNode, Com
                         let func = call_args.pop().expect("function in evaluation args list");
                         call_args.reverse();
 
-                        Ok(generate_call(func, call_args, spos))
+                        Ok(generate_call(func, call_args, None, spos))
                     }
                 },
                 Syntax::Func => {
@@ -3421,6 +4300,29 @@ mod tests {
         assert_eq!(s_eval("std:str:to_lowercase $q FOO "), "\"foo\"");
         assert_eq!(s_eval("std:str:join \",\" $[1,2,3,${a=:x}]"), "\"1,2,3,${a=:\\\"x\\\"}\"");
         assert_eq!(s_eval("std:str:cat $[1,2,3,${a=:x}]"), "\"123${a=:\\\"x\\\"}\"");
+
+        assert_eq!(s_eval("std:str:split \",\" \"a,b,,c\""),        "$[\"a\",\"b\",\"\",\"c\"]");
+        assert_eq!(s_eval("std:str:split \",\" \"\""),              "$[\"\"]");
+        assert_eq!(s_eval("std:str:split_n \",\" 2 \"a,b,c\""),     "$[\"a\",\"b,c\"]");
+        assert_eq!(s_eval("std:str:find \"ü\" \"äöüäöü\""),        "2");
+        assert_eq!(s_eval("std:str:rfind \"ü\" \"äöüäöü\""),       "5");
+        assert_eq!(s_eval("std:str:find \"x\" \"äöü\""),           "$n");
+        assert_eq!(s_eval("std:str:substr 1 1 \"äöü\""),          "\"ö\"");
+        assert_eq!(s_eval("std:str:substr 1 10 \"äöü\""),         "\"öü\"");
+        assert_eq!(s_eval("std:str:substr 1 \"äöü\""),            "\"öü\"");
+        assert_eq!(s_eval("std:str:starts_with \"äö\" \"äöü\""),   "$true");
+        assert_eq!(s_eval("std:str:ends_with \"äö\" \"äöü\""),     "$false");
+        assert_eq!(s_eval("std:str:contains \"öü\" \"äöü\""),      "$true");
+        assert_eq!(s_eval("std:str:repeat 2 \"äö\""),             "\"äöäö\"");
+        assert_eq!(s_eval("std:str:repeat -1 \"äö\""),            "\"\"");
+        assert_eq!(s_eval("std:str:repeat 9223372036854775807 \"ab\""),
+                   "$e \"str:repeat: result of 9223372036854775807 * 2 bytes is too large\"");
+        assert_eq!(s_eval("std:str:reverse \"äöü\""),             "\"üöä\"");
+        assert_eq!(s_eval("std:str:lines \"a\\nb\\r\\nc\""),        "$[\"a\",\"b\",\"c\"]");
+        assert_eq!(s_eval("std:str:char_to_byte_offset 1 \"äöü\""), "2");
+        assert_eq!(s_eval("std:str:char_to_byte_offset 9 \"äöü\""), "$n");
+        assert_eq!(s_eval("std:str:byte_to_char_offset 6 \"äöü\""), "3");
+        assert_eq!(s_eval("std:str:byte_to_char_offset 1 \"äöü\" | is_err"), "$true");
     }
 
     #[test]
@@ -3477,6 +4379,35 @@ mod tests {
                     $q/foobarbaaaarfoobaararar/
             "),
             "\"foob<ar>b<aaaar>foobaar<ar><ar>\"");
+
+            assert_eq!(s_eval("
+                !rx = std:re:compile $q/(?P<k>\\w+)=(?P<v>\\d+)/;
+                $[rx, is_err ~ std:re:compile \"(\", rx \"a=1\", rx \"-\"]"),
+                "$[$<regex:(?P<k>\\w+)=(?P<v>\\d+)>,$true,$[\"a=1\",\"a\",\"1\"],$n]");
+            assert_eq!(s_eval("
+                std:re:named_captures (std:re:compile $q/(?P<k>\\w+)=(?P<v>\\d+)?/) \"x= y=2\""),
+                "${k=\"x\",v=$n}");
+            assert_eq!(s_eval("std:re:named_captures $q/(?P<k>\\d)/ \"x\""),
+                "$n");
+            assert_eq!(s_eval("std:re:find_all $q/a+/ \"äaa.ba\""),
+                "$[$[\"aa\",1,3],$[\"a\",5,6]]");
+            assert_eq!(s_eval("std:re:split (std:re:compile $q/\\s*,\\s*/) \"a , b,c\""),
+                "$[\"a\",\"b\",\"c\"]");
+            assert_eq!(s_eval("
+                std:re:replace_all (std:re:compile $q/(?P<k>\\w+)=(?P<v>\\d+)/)
+                    { std:str:cat _1.v \"=\" _1.k \"/\" _.2 }
+                    \"x=10 y=20\""),
+                "\"10=x/10 20=y/20\"");
+            assert_eq!(s_eval("
+                !r = $&$n;
+                std:re:match (std:re:compile $q/(?P<k>\\w+)=(\\d+)/) \"x=10\" {|| .r = @; };
+                $*r"),
+                "$[$[\"x=10\",\"x\",\"10\"],${k=\"x\"}]");
+            assert_eq!(s_eval("
+                $@v std:re:map (std:re:compile $q/(?P<k>\\w+)=\\d+/) { $+ _1.k } \"a=1,b=2\""),
+                "$[\"a\",\"b\"]");
+            assert_eq!(s_eval("std:re:split (std:str:cat \"x\") \"axbxc\""),
+                "$[\"a\",\"b\",\"c\"]");
         }
     }
 
@@ -3499,6 +4430,74 @@ mod tests {
         assert_eq!(s_eval("std:deser:msgpack $b\"\\x93\\x01\\xC0\\xCB@08Q\\xEB\\x85\\x1E\\xB8\""), "$[1,$n,16.22]");
     }
 
+    #[test]
+    fn check_deser_wlambda() {
+        assert_eq!(s_eval(r#"std:deser:wlambda $q|$[1,-2,3.5,"x\ny",$b"\x01",:z,$t,$f,$n,$[]]|"#),
+                   "$[1,-2,3.5,\"x\\ny\",$b\"\\x01\",:\"z\",$true,$false,$n,$[]]");
+        assert_eq!(s_eval(r#"std:deser:wlambda $q|${a=1,:b=$[${}],"c d"=0.10d}|"#),
+                   "${a=1,b=$[${}],\"c d\"=0.10d}");
+        assert_eq!(s_eval(r#"
+            !v = $[1, "\"", ${x=$b"\x00\xFF", y=$[:a, $none]}, 0xFF];
+            (std:write_str v) == (std:write_str ~ std:deser:wlambda ~ std:write_str v)
+        "#), "$true");
+
+        assert_eq!(s_eval("std:deser:wlambda \"$[1, x]\""),
+                   "$e \"from_wlambda_str failed: [1,7:<wlambda:deser>(Var)] only literal values are allowed\"");
+        assert_eq!(s_eval("std:deser:wlambda \"$[1, std:displayln 10]\""),
+                   "$e \"from_wlambda_str failed: [1,20:<wlambda:deser>(Call)] only literal values are allowed\"");
+        assert_eq!(s_eval("std:deser:wlambda \"${a={ 10 }}\""),
+                   "$e \"from_wlambda_str failed: [1,5:<wlambda:deser>(Func)] only literal values are allowed\"");
+        assert_eq!(s_eval("std:deser:wlambda \"1 + 2\""),
+                   "$e \"from_wlambda_str failed: [1,6:<wlambda:deser>(BinOpAdd)] only literal values are allowed\"");
+        assert_eq!(s_eval("std:deser:wlambda \"1; 2\""),
+                   "$e \"from_wlambda_str failed: expected exactly one value\"");
+        assert_eq!(s_eval("std:deser:wlambda \"\""),
+                   "$e \"from_wlambda_str failed: expected exactly one value\"");
+        assert!(s_eval("std:deser:wlambda \"$[1,\"").starts_with("$e \"from_wlambda_str failed: "));
+
+        assert_eq!(s_eval(r#"
+            !s = std:str:repeat 200 "$[\"";
+            (std:deser:wlambda ~ std:write_str $[s]).0 == s
+        "#), "$true");
+    }
+
+    #[test]
+    fn check_deser_wlambda_depth() {
+        // Without optimizations parsing 128 nested values needs
+        // more stack than test threads get by default:
+        std::thread::Builder::new().stack_size(8 * 1024 * 1024).spawn(|| {
+            let nested = format!("{}{}", "$[".repeat(128), "]".repeat(128));
+            assert_eq!(VVal::from_wlambda_str(&nested).unwrap().s(), nested);
+
+            assert!(VVal::from_wlambda_str(&"$[".repeat(100000)).unwrap_err()
+                    .starts_with("from_wlambda_str failed: error[1,257:<wlambda:deser>] \
+                                  Values nested deeper than 128 at code '$[$["));
+            assert!(VVal::from_wlambda_str(&"${a=".repeat(129)).unwrap_err()
+                    .starts_with("from_wlambda_str failed: error[1,513:<wlambda:deser>] \
+                                  Values nested deeper than 128 at code '${a="));
+            assert!(VVal::from_wlambda_str(&"-".repeat(100000)).is_err());
+            assert!(VVal::from_wlambda_str(&"x.a".repeat(100000)).is_err());
+            assert!(VVal::from_wlambda_str(&"x[]".repeat(100000)).is_err());
+            assert!(VVal::from_wlambda_str(&"x|".repeat(100000)).is_err());
+        }).unwrap().join().unwrap();
+    }
+
+    #[test]
+    fn check_macro_depth() {
+        // Without optimizations 64 nested expansions need
+        // more stack than test threads get by default:
+        std::thread::Builder::new().stack_size(8 * 1024 * 1024).spawn(|| {
+            assert_eq!(
+                format!("{}", eval("!@macro r = { _; $code (r 1) }; r 1").unwrap_err()),
+                "Compile error: [1,23:<wlambda::eval>] Compilation Error: \
+                 Macro expansion nested deeper than 64: r");
+            assert_eq!(s_eval(r"
+                !@macro twice = { std:wlambda:subst ${e = _} ~ $code (e + e) };
+                twice (twice (twice 1))
+            "), "8");
+        }).unwrap().join().unwrap();
+    }
+
     #[test]
     fn check_eval() {
         let mut ctx = EvalContext::new_default();
@@ -3569,6 +4568,803 @@ mod tests {
             r.s(), "$[98,$<MyType((14, 84))>,\"foo\",:\"foo2\"]", "Userdata implementation works");
     }
 
+    #[test]
+    fn check_operator_overloading() {
+        let v2 = r"
+            !V = ${};
+            V.new    = { ${ _proto = V, x = _, y = _1 } };
+            V._add_  = { !s = $self; V.new (s.x + _.x) (s.y + _.y) };
+            V._sub_  = { !s = $self; V.new (s.x - _.x) (s.y - _.y) };
+            V._mul_  = { !s = $self; V.new (s.x * _) (s.y * _) };
+            V._rmul_ = { !s = $self; V.new (s.x * _) (s.y * _) };
+            V._rsub_ = { !s = $self; V.new (_ - s.x) (_ - s.y) };
+            V._eq_   = { ($self.x == _.x) &and ($self.y == _.y) };
+            V._lt_   = { $self.x < _.x };
+            V._ge_   = { $self.x >= _.x };
+            !a = V.new 1 2;
+            !b = V.new 3 4;
+        ";
+        let ev = |code: &str| s_eval(&(v2.to_string() + code));
+
+        assert_eq!(ev("!c = a + b; $[c.x, c.y]"),               "$[4,6]");
+        assert_eq!(ev("!c = b - a; $[c.x, c.y]"),               "$[2,2]");
+        assert_eq!(ev("!c = a * 3; $[c.x, c.y]"),               "$[3,6]");
+        assert_eq!(ev("!c = 3 * a; $[c.x, c.y]"),               "$[3,6]");
+        assert_eq!(ev("!c = 10 - a; $[c.x, c.y]"),              "$[9,8]");
+        assert_eq!(ev("!c = `+` a b b; $[c.x, c.y]"),           "$[7,10]");
+        assert_eq!(ev("!c = `*` 2 a; $[c.x, c.y]"),             "$[2,4]");
+        assert_eq!(ev(".a += b; $[a.x, a.y]"),                  "$[4,6]");
+        assert_eq!(ev("!r = $&& a; !c = r + b; c.x"),           "4");
+        assert_eq!(ev("$[a == V.new[1, 2], a == b, a != b]"),   "$[$true,$false,$true]");
+        assert_eq!(ev("$[a < b, b < a, `<` a b, a >= b]"),      "$[$true,$false,$true,$false]");
+        assert_eq!(ev("(${x = 1} == ${x = 1})"),                "$false");
+        assert_eq!(ev("!m = ${pos = a}; .m.pos += b; $[m.pos.x, m.pos.y]"),
+                   "$[4,6]");
+        assert_eq!(ev("!m = ${pos = a}; .m.pos *= 2; $[m.pos.x, m.pos.y]"),
+                   "$[2,4]");
+        assert_eq!(ev("$[V.new 1 2, 3] + 1"),                   "3");
+        assert_eq!(s_eval(r"
+            !L = ${ new = { $[$self, _] }, _add_ = { $self.0.new ($data + _) } };
+            $[(L.new 1 + 2).1, $[L.new 1, 2] + 3]
+        "), "$[3,5]");
+        assert_eq!(s_eval("$[1 + 2, 1.5 * 2, `+` 1 2 3, `-` 1.5 1, 3 < 4]"),
+                   "$[3,3,6,0.5,$true]");
+    }
+
+    #[test]
+    fn check_class() {
+        let classes = r#"
+            !Animal = std:class :Animal ${
+                kind  = "animal",
+                init  = { $data.name = _ },
+                name  = { $data.name },
+                speak = { "..." },
+                describe = { std:str:format "{} says {}" $data.name $self.speak[] },
+            };
+            !Dog = std:class :Dog Animal ${
+                init  = {|2| std:super Animal :init _; $data.tricks = _1 },
+                speak = { "Woof" },
+                describe = { (std:super Animal :describe) "!" },
+            };
+            !Puppy = std:class :Puppy Dog ${ speak = { "Yip" } };
+            !rex = Dog.new "Rex" 3;
+            !gen = Animal.new "Generic";
+        "#;
+        let ev = |code: &str| s_eval(&(classes.to_string() + code));
+
+        assert_eq!(ev("$[rex.name[], rex.speak[], rex._data.tricks]"), "$[\"Rex\",\"Woof\",3]");
+        assert_eq!(ev("$[rex.describe[], gen.describe[]]"),
+                   "$[\"Rex says Woof!\",\"Generic says ...\"]");
+        assert_eq!(ev("(Puppy.new \"Bit\" 1).describe[]"),           "\"Bit says Yip!\"");
+        assert_eq!(ev("$[Dog._name, Animal.kind, Dog.kind]"),        "$[\"Dog\",\"animal\",$n]");
+        assert_eq!(ev("$[std:instanceof Animal rex, std:instanceof Dog rex, std:instanceof Dog gen]"),
+                   "$[$true,$true,$false]");
+        assert_eq!(ev("$[std:instanceof Animal (Puppy.new 1 2), std:instanceof Dog $[Dog], std:instanceof Dog 1]"),
+                   "$[$true,$true,$false]");
+        assert_eq!(s_eval("!a = ${}; !b = ${_proto = a}; a._proto = b; !c = ${};
+                           $[std:instanceof c ${_proto = a}, std:instanceof b ${_proto = a}]"),
+                   "$[$false,$true]");
+        assert_eq!(ev("std:super Dog :nope"),
+                   "$e \"super: no method \\'nope\\' in class \\'Dog\\'\"");
+        assert_eq!(s_eval("!a = (std:class :A ${}).new[]; $[a._data, a._proto._name]"),
+                   "$[${},\"A\"]");
+        assert_eq!(s_eval("std:class :A 10"),
+                   "$e \"class: expected map of class members, got: 10\"");
+        assert_eq!(s_eval("std:class :A 10 ${}"),
+                   "$e \"class: expected parent class, got: 10\"");
+    }
+
+    #[test]
+    fn check_enum() {
+        let shapes = r#"
+            !Shape = std:enum :Shape $[:Circle, :Rect, :Empty];
+            !c = Shape.Circle 10;
+            !r = Shape.Rect 3 4;
+            !e = Shape.Empty[];
+            !area = {
+                match _
+                    :?v :Circle {|| 3 * _ * _ }
+                    :?v :Rect   {|| !(w, h) = _; w * h }
+                    {|| 0 }
+            };
+        "#;
+        let ev = |code: &str| s_eval(&(shapes.to_string() + code));
+
+        assert_eq!(ev("$[c, r, e]"),    "$[$<Shape.Circle 10>,$<Shape.Rect $[3,4]>,$<Shape.Empty>]");
+        assert_eq!(ev("$[type c, Shape._name]"), "$[\"enum\",\"Shape\"]");
+        assert_eq!(ev("$[std:enum:tag r, std:enum:payload r, std:enum:name r]"),
+                   "$[:\"Rect\",$[3,4],\"Shape\"]");
+        assert_eq!(ev("$[std:enum:tag 1, std:enum:payload 1, std:enum:name 1]"),
+                   "$[$n,$n,$n]");
+        assert_eq!(ev("$[c == Shape.Circle[10], c == Shape.Circle[11], c == r, e == Shape.Empty[]]"),
+                   "$[$true,$false,$false,$true]");
+        assert_eq!(ev("$[area c, area r, area e, area 10]"), "$[300,12,0,0]");
+        assert_eq!(ev("match r :?v :Rect {|2| std:enum:tag _1 }"), ":\"Rect\"");
+        assert_eq!(ev("$[(std:enum:payload c) + 1, $[r]]"),
+                   "$[11,$[$<Shape.Rect $[3,4]>]]");
+        assert_eq!(s_eval("std:enum :E 10"),
+                   "$e \"enum: expected vector of variant names, got: 10\"");
+
+        #[cfg(feature="serde_json")]
+        assert_eq!(ev("std:ser:json $[c, r, e] $t"),
+                   "\"[{\\\"Circle\\\":10},{\\\"Rect\\\":[3,4]},\\\"Empty\\\"]\"");
+        #[cfg(feature="rmp-serde")]
+        assert_eq!(ev("std:deser:msgpack ~ std:ser:msgpack $[c, e]"),
+                   "$[${Circle=10},\"Empty\"]");
+    }
+
+    #[test]
+    fn check_set_omap() {
+        assert_eq!(s_eval("std:set:new $[3, 1, :b, 2, 1, \"a\", $[1,2], 1.5, $n]"),
+                   "$<set[$n,1,1.5,2,3,\"a\",:\"b\",$[1,2]]>");
+        assert_eq!(s_eval("!s = std:set:new[]; std:set:add s 2; std:set:add s 1; $[s, len s, type s]"),
+                   "$[$<set[1,2]>,2,\"set\"]");
+        assert_eq!(s_eval("!s = std:set:new $[1,2]; $[std:set:contains s 2, std:set:remove s 2, std:set:contains s 2, std:set:remove s 2]"),
+                   "$[$true,$true,$false,$false]");
+        assert_eq!(s_eval("std:set:contains (std:set:new $[$[1,2]]) $[1,2]"), "$true");
+        assert_eq!(s_eval("!v = $[1]; !s = std:set:new $[v]; std:push v 2; $[s, std:set:contains s $[1]]"),
+                   "$[$<set[$[1]]>,$true]");
+        assert_eq!(s_eval("!k = ${a=1}; !s = std:set:new $[k]; k.a = 5; $[s, std:set:contains s ${a=1}]"),
+                   "$[$<set[${a=1}]>,$true]");
+        assert_eq!(s_eval("!E = std:enum :E $[:X]; !p = $[1]; !s = std:set:new $[E.X p];
+                           std:push p 2; $[s, std:set:contains s (E.X $[1])]"),
+                   "$[$<set[$<E.X $[1]>]>,$true]");
+        assert_eq!(s_eval("!b = $b\"ab\"; !v = $[std:omap:new $[$[1, b]]]; !s = std:set:new $[v];
+                           std:omap:set v.0 1 $b\"x\"; s"),
+                   "$<set[$[$<omap[1=$b\"ab\"]>]]>");
+        assert_eq!(s_eval("!s = std:set:new $[1,2,3,4,5]; $[std:set:range s 2 4, std:set:range s 4 $n, std:set:range s 4 2]"),
+                   "$[$[2,3],$[4,5],$[]]");
+        assert_eq!(s_eval("!a = std:set:new $[1,2,3]; !b = std:set:new $[2,3,4];
+                           $[std:set:union a b, std:set:intersection a b, std:set:difference a b]"),
+                   "$[$<set[1,2,3,4]>,$<set[2,3]>,$<set[1]>]");
+        assert_eq!(s_eval("std:set:difference (std:set:new $[1,2,3]) $[3,1]"), "$<set[2]>");
+        assert_eq!(s_eval("!a = std:set:new $[1,2]; !b = std:copy a; std:set:add b 3; $[a, b, a == a, a == b]"),
+                   "$[$<set[1,2]>,$<set[1,2,3]>,$true,$false]");
+        assert_eq!(s_eval("$@vec for (std:set:new $[3,1,2]) {|| $+ _ * 2 }"), "$[2,4,6]");
+        assert_eq!(s_eval("std:set:add 10 1"),   "$e \"set:add: expected set, got: 10\"");
+        assert_eq!(s_eval("std:set:range 10 1 2"), "$e \"set:range: expected set, got: 10\"");
+
+        assert_eq!(s_eval("!m = std:omap:new $[$[3,:c], $[1,:a]]; m.2 = :b; $[m, type m, len m]"),
+                   "$[$<omap[1=:\"a\",2=:\"b\",3=:\"c\"]>,\"omap\",3]");
+        assert_eq!(s_eval("!m = std:omap:new ${y=2,x=1}; m.z = 3; $[m, m.x, m.1]"),
+                   "$[$<omap[\"x\"=1,\"y\"=2,:\"z\"=3]>,1,$n]");
+        assert_eq!(s_eval("!m = std:omap:new $[$[1,:a]]; !k = \"2\"; m.(k) = :b; $[m, m.(\"1\"), m.(k), m.1]"),
+                   "$[$<omap[1=:\"a\",2=:\"b\"]>,:\"a\",:\"b\",:\"a\"]");
+        assert_eq!(s_eval("std:set:new $[1, 1.0, 1d, 0.1, 0.1d, 0.5, 0.5d, -0.0, 0]"),
+                   "$<set[0,0.1d,0.1,0.5d,1d]>");
+        assert_eq!(s_eval("!m = std:omap:new[]; std:omap:set m $[1,2] 10;
+                           $[std:omap:get m $[1,2], std:omap:contains m $[1,2], std:omap:remove m $[1,2], std:omap:contains m $[1,2]]"),
+                   "$[10,$true,10,$false]");
+        assert_eq!(s_eval("!m = std:omap:new $[$[1,:a], $[2,:b], $[3,:c]];
+                           $[std:omap:keys m, std:omap:range m 2 $n, std:omap:range m 1 3]"),
+                   "$[$[1,2,3],$<omap[2=:\"b\",3=:\"c\"]>,$<omap[1=:\"a\",2=:\"b\"]>]");
+        assert_eq!(s_eval("!m = std:omap:new $[$[2,:b], $[1,:a]]; $@vec for m {|2| $+ $[_1, _] }"),
+                   "$[$[1,:\"a\"],$[2,:\"b\"]]");
+        assert_eq!(s_eval("std:omap:get ${} 1"), "$e \"omap:get: expected omap, got: ${}\"");
+
+        #[cfg(feature="serde_json")]
+        {
+            assert_eq!(s_eval("std:ser:json (std:omap:new $[$[2,:b], $[1,:a]]) $t"),
+                       "\"{\\\"1\\\":\\\"a\\\",\\\"2\\\":\\\"b\\\"}\"");
+            assert_eq!(s_eval("std:ser:json (std:set:new $[3,1,2]) $t"), "\"[1,2,3]\"");
+        }
+    }
+
+    #[test]
+    fn check_map_key_order() {
+        assert_eq!(s_eval("!m = ${z=1,b=2,a=3}; m.c = 4; m.z = 5; $@vec for m {|2| $+ _1 }"),
+                   "$[\"z\",\"b\",\"a\",\"c\"]");
+        assert_eq!(s_eval("$@vec ${y=1,x=2} {|2| $+ _1 }"), "$[\"y\",\"x\"]");
+        assert_eq!(s_eval("!m = ${z=1,b=2}; $@vec for ${*m, a=3, *${q=4}} {|2| $+ _1 }"),
+                   "$[\"z\",\"b\",\"a\",\"q\"]");
+        assert_eq!(s_eval("$@vec for ($@map for $[:q,:e,:w] {|| $+ _ 1 }) {|2| $+ _1 }"),
+                   "$[\"q\",\"e\",\"w\"]");
+        assert_eq!(s_eval("$@vec for (std:copy ${c=1,a=2,b=3}) {|2| $+ _1 }"),
+                   "$[\"c\",\"a\",\"b\"]");
+        assert_eq!(s_eval("str ${z=1,b=2,a=3}"), "\"${a=3,b=2,z=1}\"");
+
+        #[cfg(feature="serde_json")]
+        {
+            assert_eq!(s_eval("std:ser:json ${z=1,b=2,a=3} $t"),
+                       "\"{\\\"z\\\":1,\\\"b\\\":2,\\\"a\\\":3}\"");
+            assert_eq!(s_eval("std:ser:json (std:deser:json $q/{\"x\":1,\"c\":2,\"a\":3}/) $t"),
+                       "\"{\\\"x\\\":1,\\\"c\\\":2,\\\"a\\\":3}\"");
+        }
+        #[cfg(feature="rmp-serde")]
+        assert_eq!(s_eval("$@vec for (std:deser:msgpack ~ std:ser:msgpack ${z=1,b=2,a=3}) {|2| $+ _1 }"),
+                   "$[\"z\",\"b\",\"a\"]");
+    }
+
+    #[test]
+    fn check_symbol_interning() {
+        use crate::vval::Symbol;
+
+        assert_eq!(s_eval("$[:abc == (sym \"abc\"), :abc == :abd, :abc == \"abc\"]"),
+                   "$[$true,$false,$false]");
+        assert_eq!(s_eval("!m = ${}; m.(sym \"k\") = 1; m.(\"l\") = 2; $[m.k, m.(\"k\"), m.l, m.(:l), m.x]"),
+                   "$[1,1,2,2,$n]");
+        assert_eq!(s_eval("!m = ${a=${b=${c=3}}}; $[m.a.b.c, m.(\"a\").b.(\"c\"), m.a.x.c]"),
+                   "$[3,3,$n]");
+        assert_eq!(s_eval("!o = ${ _proto = ${ f = { $data.v } }, _data = ${ v = 7 } }; o.f[]"),
+                   "7");
+        assert_eq!(s_eval("!(a, b) = ${ a = 1, b = 2 }; a + b"), "3");
+        assert_eq!(s_eval("!a = 0; !b = 0; .(a, b) = ${ a = 1, b = 2 }; a + b"), "3");
+        assert_eq!(s_eval("$match ${ a = 1, b = 2 } { ${ a = x, *r } => $[x, r] }"), "$[1,${b=2}]");
+
+        assert!(Symbol::lookup("check_symbol_interning_sym").is_none());
+        let sym  = Symbol::new("check_symbol_interning_sym");
+        let sym2 = Symbol::new("check_symbol_interning_sym");
+        assert!(sym == sym2);
+        assert!(Symbol::lookup("check_symbol_interning_sym").is_some());
+        std::mem::drop(sym);
+        assert!(Symbol::lookup("check_symbol_interning_sym").is_some());
+        std::mem::drop(sym2);
+        assert!(Symbol::lookup("check_symbol_interning_sym").is_none());
+    }
+
+    #[test]
+    fn check_lookup_cache() {
+        // Every closure below has exactly one call site, which gets
+        // hit repeatedly with changing objects and prototypes.
+        let prelude = r#"
+            !call = { _.f[] };
+            !get  = { _.x };
+            !key  = { _.(_1) };
+            !meth = { _.(_1)[] };
+        "#;
+        let ev = |code: &str| s_eval(&format!("{} {}", prelude, code));
+
+        assert_eq!(ev(r#"
+            !c = ${ f = { 1 } };
+            !o = ${ _proto = c };
+            $[call o, call o]
+        "#), "$[1,1]");
+        assert_eq!(ev(r#"
+            !c = ${ f = { 1 } };
+            !o = ${ _proto = c };
+            !r = $[call o];
+            c.f = { 2 };
+            std:push r call[o];
+            r
+        "#), "$[1,2]");
+        assert_eq!(ev(r#"
+            !s = ${ f = { 1 } };
+            !c = ${ _proto = s };
+            !o = ${ _proto = c };
+            !r = $[call o];
+            c.f = { 2 };
+            std:push r call[o];
+            o.f = { 3 };
+            std:push r call[o];
+            r
+        "#), "$[1,2,3]");
+        assert_eq!(ev(r#"
+            !c1 = ${ f = { 1 } };
+            !c2 = ${ f = { 2 } };
+            !o = ${ _proto = c1 };
+            !r = $[call o];
+            o._proto = c2;
+            std:push r call[o];
+            c2._proto = c1;
+            std:push r call[o];
+            r
+        "#), "$[1,2,2]");
+        assert_eq!(ev(r#"
+            !s1 = ${ f = { 1 } };
+            !s2 = ${ f = { 2 } };
+            !c = ${ _proto = s1 };
+            !o = ${ _proto = c };
+            !r = $[call o];
+            c._proto = s2;
+            std:push r call[o];
+            r
+        "#), "$[1,2]");
+        assert_eq!(ev(r#"
+            !c1 = ${ f = { 1 } };
+            !c2 = ${ f = { 2 } };
+            !p = $&& c1;
+            !o = ${ _proto = p };
+            !r = $[call o];
+            .*p = c2;
+            std:push r call[o];
+            r
+        "#), "$[1,2]");
+        assert_eq!(ev(r#"
+            !a = ${ x = 1, y = 10 };
+            !b = ${ y = 20, x = 2 };
+            !r = $[get a, get b, get a, get ${}];
+            a.x = 3;
+            std:push r get[a];
+            r
+        "#), "$[1,2,1,$n,3]");
+        assert_eq!(ev(r#"
+            !o = ${ x = 1, y = 2 };
+            $[key o :x, key o :y, key o "x", key o :z, key $n :x]
+        "#), "$[1,2,1,$n,$n]");
+        assert_eq!(ev(r#"
+            !c = ${ f = { 1 }, g = { 2 } };
+            !o = ${ _proto = c };
+            !r = $[meth o :f, meth o :g];
+            o.g = { 3 };
+            std:push r meth[o, :g];
+            r
+        "#), "$[1,2,3]");
+        assert_eq!(ev(r#"
+            !r = $[];
+            range 0 2 1 {!(i) = @;
+                !c = ${ f = { i } };
+                std:push r call[${ _proto = c }];
+            };
+            r
+        "#), "$[0,1,2]");
+    }
+
+    #[test]
+    #[ignore]
+    fn bench_map_key_lookup() {
+        // Run with: cargo test --release bench_map_key_lookup -- --ignored --nocapture
+        let code = r#"
+            !m = ${ a_rather_long_field_name = 1, x = ${ y = 2 } };
+            !o = ${ _proto = ${ get = { $data.v } }, _data = ${ v = 3 } };
+            !sum = $&0;
+            range 1 100000 1 {||
+                .sum = sum + m.a_rather_long_field_name + m.x.y + o.get[];
+            };
+            $*sum
+        "#;
+        let ast = parser::parse(code, "<bench_map_key_lookup>").unwrap();
+        assert_eq!(bench_eval_ast(ast, GlobalEnv::new_default(), 10).s(), "600000");
+    }
+
+    #[test]
+    #[ignore]
+    fn bench_method_lookup() {
+        // Run with: cargo test --release bench_method_lookup -- --ignored --nocapture
+        let code = r#"
+            !Base = ${ get = { $data.v }, inc = { $data.v = $data.v + 1 } };
+            !Mid  = ${ _proto = Base, twice = { $self.inc[]; $self.inc[] } };
+            !Cls  = ${ _proto = Mid, new = { ${ _proto = $self, _data = ${ v = 0 } } } };
+            !o = Cls.new[];
+            !sum = $&0;
+            range 1 100000 1 {||
+                o.twice[];
+                .sum = sum + o.get[];
+            };
+            $*sum
+        "#;
+        let ast = parser::parse(code, "<bench_method_lookup>").unwrap();
+        assert_eq!(bench_eval_ast(ast, GlobalEnv::new_default(), 10).s(), "10000100000");
+    }
+
+    #[test]
+    fn check_int_overflow() {
+        assert_eq!(s_eval("9223372036854775807 + 1"),
+                   "$e[1,24:<compiler:s_eval>(BinOpAdd)] \"Integer overflow: 9223372036854775807 + 1\"");
+        assert_eq!(s_eval("unwrap_err ~ -9223372036854775807 - 2"),
+                   "\"Integer overflow: -9223372036854775807 - 2\"");
+        assert_eq!(s_eval("unwrap_err ~ 4611686018427387904 * 2"),
+                   "\"Integer overflow: 4611686018427387904 * 2\"");
+        assert_eq!(s_eval("unwrap_err ~ (-9223372036854775807 - 1) / -1"),
+                   "\"Integer overflow: -9223372036854775808 / -1\"");
+        assert_eq!(s_eval("$[9223372036854775806 + 1, 2 * 3, 7 / 2, 7 % 2, 1.5 + 9223372036854775807]"),
+                   "$[9223372036854775807,6,3,1,9223372036854776000]");
+        assert_eq!(s_eval("unwrap_err ~ `+` 9223372036854775800 7 1"),
+                   "\"Integer overflow: 9223372036854775807 + 1\"");
+        assert_eq!(s_eval("unwrap_err ~ `*` 3037000500 3037000500"),
+                   "\"Integer overflow: 3037000500 * 3037000500\"");
+        assert_eq!(s_eval("`+` 1 2 3"), "6");
+        assert_eq!(s_eval("$[2 ^ 62, unwrap_err 2 ^ 63, unwrap_err 2 ^ -1, unwrap_err 2 ^ 4294967296]"),
+                   "$[4611686018427387904,\"Integer overflow: 2 ^ 63\",\"^: negative exponent: -1\",\"^: exponent out of range: 4294967296\"]");
+        assert_eq!(s_eval("!x = 9223372036854775807; .x += 1; unwrap_err x"),
+                   "\"Integer overflow: 9223372036854775807 + 1\"");
+        assert!(s_eval_no_panic("!o = ${ x = 9223372036854775807 }; o.x *= 2; o.x")
+                .contains("Error value dropped: \\\\\\\"Integer overflow: 9223372036854775807 * 2"));
+        assert_eq!(s_eval("unwrap_err ~ -9223372036854775808 % -1"),
+                   "\"Integer overflow: -9223372036854775808 % -1\"");
+        assert_eq!(s_eval("unwrap_err ~ `%` -9223372036854775808 -1"),
+                   "\"Integer overflow: -9223372036854775808 % -1\"");
+        assert_eq!(s_eval("unwrap_err ~ $i(-9223372036854775808, 1) % $i(-1, 1)"),
+                   "\"Integer overflow: -9223372036854775808 % -1\"");
+        // Chained operators don't read the error value as 0:
+        assert!(s_eval_no_panic("9223372036854775807 + 1 + 5")
+                .contains("SA::Panic(\\\"Error value in operand: \\\\\\\"Integer overflow: 9223372036854775807 + 1"));
+        assert!(s_eval_no_panic("(9223372036854775807 * 2) - 3")
+                .contains("Error value in operand: \\\\\\\"Integer overflow: 9223372036854775807 * 2"));
+        assert!(s_eval_no_panic("3 - (9223372036854775807 * 2)")
+                .contains("Error value in operand: \\\\\\\"Integer overflow: 9223372036854775807 * 2"));
+        assert!(s_eval_no_panic("!X = 100000000000000000000000000000000000000d; 1d - X - X - X")
+                .contains("Error value in operand: \\\\\\\"Decimal overflow: "));
+        assert!(s_eval_no_panic("!x = 1d; .x = x - $e 42; x - 1")
+                .contains("Error value in operand: 42"));
+        assert!(s_eval_no_panic("!x = 9223372036854775807 + 1; .x += 1; x")
+                .contains("Error value in operand: \\\\\\\"Integer overflow: 9223372036854775807 + 1"));
+        assert!(s_eval_no_panic("1 / 0").contains("SA::Panic(\\\"Division by 0: 1/0\\\")"));
+        assert!(s_eval_no_panic("1 % 0").contains("SA::Panic(\\\"Division by 0: 1%0\\\")"));
+        assert!(s_eval_no_panic("`/` 1 0").contains("SA::Panic(\\\"Division by 0: 1/0\\\")"));
+        assert!(s_eval_no_panic("`%` 1 0").contains("SA::Panic(\\\"Division by 0: 1%0\\\")"));
+        assert!(s_eval_no_panic("!x = 1; .x %= 0").contains("SA::Panic(\\\"Division by 0: 1%0\\\")"));
+
+        assert_eq!(s_eval("$[std:num:checked_add 1 2, std:num:checked_add 9223372036854775807 1]"),
+                   "$[3,$n]");
+        assert_eq!(s_eval("$[std:num:checked_sub 1 2, std:num:checked_sub -9223372036854775807 2]"),
+                   "$[-1,$n]");
+        assert_eq!(s_eval("$[std:num:checked_mul 2 3, std:num:checked_mul 3037000500 3037000500]"),
+                   "$[6,$n]");
+        assert_eq!(s_eval("$[std:num:checked_div 7 2, std:num:checked_div 1 0, std:num:checked_div -9223372036854775807 - 1 -1]"),
+                   "$[3,$n,$n]");
+        assert_eq!(s_eval("$[std:num:checked_rem 7 2, std:num:checked_rem 1 0]"),
+                   "$[1,$n]");
+        assert_eq!(s_eval("$[std:num:checked_pow 3 3, std:num:checked_pow 2 63, std:num:checked_pow 2 -1]"),
+                   "$[27,$n,$n]");
+        assert_eq!(s_eval("$[std:num:wrapping_add 9223372036854775807 1, std:num:wrapping_sub -9223372036854775808 1]"),
+                   "$[-9223372036854775808,9223372036854775807]");
+        assert_eq!(s_eval("$[std:num:wrapping_mul 4611686018427387904 2, std:num:wrapping_pow 3 41]"),
+                   "$[-9223372036854775808,-420491770248316829]");
+        assert_eq!(s_eval("$[std:num:wrapping_div 7 2, std:num:wrapping_rem 7 2, std:num:wrapping_rem -9223372036854775808 -1]"),
+                   "$[3,1,0]");
+        assert_eq!(s_eval("unwrap_err ~ std:num:wrapping_div 1 0"),
+                   "\"num:wrapping_div: Division by 0\"");
+        assert_eq!(s_eval("unwrap_err ~ std:num:wrapping_pow 2 -2"),
+                   "\"num:wrapping_pow: negative exponent: -2\"");
+        assert_eq!(s_eval("$[std:num:saturating_add 9223372036854775807 1, std:num:saturating_sub -9223372036854775807 10]"),
+                   "$[9223372036854775807,-9223372036854775808]");
+        assert_eq!(s_eval("$[std:num:saturating_mul -4611686018427387904 4, std:num:saturating_pow 10 30, std:num:saturating_pow -10 31]"),
+                   "$[-9223372036854775808,9223372036854775807,-9223372036854775808]");
+        assert_eq!(s_eval("unwrap_err ~ std:num:saturating_pow 2 -1"),
+                   "\"num:saturating_pow: negative exponent: -1\"");
+    }
+
+    #[test]
+    fn check_decimal() {
+        assert_eq!(s_eval("$[0.1d + 0.2d, 1.10d - 0.1d, 19.99d * 3, 10.00d / 4, 7.5d % 2]"),
+                   "$[0.3d,1.00d,59.97d,2.50d,1.5d]");
+        assert_eq!(s_eval("$[1d / 3, 2d / 3, 1.5d + 0.25, 1.5d + \"1.25\", 2 + 1.5d]"),
+                   "$[0.3333333333333333333333333333d,0.6666666666666666666666666667d,1.75d,2.75d,3]");
+        assert_eq!(s_eval("$[`+` 1.5d 2 0.25d, `-` 1d 0.1d 0.1d, `*` 1.5d 2, `/` 1d 8, `%` 7d 4]"),
+                   "$[3.75d,0.8d,3.0d,0.125d,3d]");
+        assert_eq!(s_eval("$[1.1d ^ 2, 2d ^ -2, 1.5d ^ 0]"),
+                   "$[1.21d,0.25d,1d]");
+        assert_eq!(s_eval("!x = 1.25d; .x += 1; .x *= 2; .x -= 0.5d; .x /= 2; x"), "2.00d");
+        assert_eq!(s_eval("!o = ${ v = 1.25d }; o.v += 1; o.v %= 2; o.v"), "0.25d");
+
+        assert_eq!(s_eval("$[1.5d < 2, 1.5d > 1.49d, 1.5d <= 1.50d, 1.5d >= 1.6, 1.50d == 1.5d, 1.5d == 1.5]"),
+                   "$[$true,$true,$true,$false,$true,$false]");
+        assert_eq!(s_eval("$[`<` 1.5d 2, `>` 1.5d 1.49d, `<=` -1d 0, `>=` 1d 1.01d]"),
+                   "$[$true,$true,$true,$false]");
+        assert_eq!(s_eval("$[str 1.50d, float 1.5d, int -2.7d, bool 0.0d, bool 0.1d, type 1d]"),
+                   "$[\"1.50\",1.5,-2,$false,$true,\"decimal\"]");
+        assert_eq!(s_eval("$[decimal \"12.30\", decimal 0.1, decimal 5, decimal \"x\", is_decimal 1d, is_decimal 1.0]"),
+                   "$[12.30d,0.1d,5d,0d,$true,$false]");
+        assert_eq!(s_eval("$[std:num:abs -1.5d, std:dec:scale 1.500d, std:dec:normalize 1.500d]"),
+                   "$[1.5d,3,1.5d]");
+        assert_eq!(s_eval("$[std:dec:round 2.345d 2, std:dec:round 2.345d 2 :half_up, std:dec:round -2.341d 2 :floor, std:dec:round 2.3d 2 :up]"),
+                   "$[2.34d,2.35d,-2.35d,2.3d]");
+        assert_eq!(s_eval("unwrap_err ~ std:dec:round 1d 2 :foo"),
+                   "\"dec:round: unknown rounding mode: foo\"");
+        assert_eq!(s_eval("unwrap_err ~ std:dec:round 1d -1"),
+                   "\"dec:round: negative number of places: -1\"");
+        assert_eq!(s_eval("std:dec:round 1.25d 4294967296"),    "1.25d");
+        assert_eq!(s_eval("unwrap_err ~ 2d * (float \"1e40\")"),
+                   "\"Decimal overflow: 2 * 10000000000000000000000000000000000000000\"");
+        assert_eq!(s_eval("unwrap_err ~ 1.5d + (float \"inf\")"),
+                   "\"Decimal overflow: 1.5 + inf\"");
+        assert_eq!(s_eval("unwrap_err ~ `-` 1.5d (float \"1e40\")"),
+                   "\"Decimal overflow: 1.5 - 10000000000000000000000000000000000000000\"");
+        assert_eq!(s_eval("$[1d < (float \"1e40\"), 1d > (float \"-1e40\"), 0.1d < 0.1, `<=` 1d 1.0]"),
+                   "$[$true,$true,$true,$true]");
+        assert_eq!(s_eval("unwrap_err ~ decimal (float \"1e40\")"),
+                   "\"Decimal overflow: 10000000000000000000000000000000000000000\"");
+        assert_eq!(s_eval("!(a, b, c) = $[1.5d, 2.25d, -0.5d]; $F\"{a:.2}|{b:>6.1}|{c}|{c:+.3}\""),
+                   "\"1.50|   2.2|-0.5|-0.500\"");
+        assert_eq!(s_eval("std:sort { std:cmp:num:asc _ _1 } $[3.5d, 1.25d, 2d]"),
+                   "$[1.25d,2d,3.5d]");
+        assert_eq!(s_eval("!s = std:set:new[]; std:set:add s 2d; std:set:add s 1.5d; std:set:add s 2.0d; s"),
+                   "$<set[1.5d,2d]>");
+
+        assert_eq!(s_eval("unwrap_err ~ 170141183460469231731687303715884105727d + 1"),
+                   "\"Decimal overflow: 170141183460469231731687303715884105727 + 1\"");
+        assert_eq!(s_eval("unwrap_err ~ `*` 170141183460469231731687303715884105727d 2"),
+                   "\"Decimal overflow: 170141183460469231731687303715884105727 * 2\"");
+        assert!(s_eval_no_panic("1.5d / 0").contains("SA::Panic(\\\"Division by 0: 1.5/0\\\")"));
+        assert!(s_eval_no_panic("1.5d % 0.0d").contains("SA::Panic(\\\"Division by 0: 1.5%0.0\\\")"));
+        assert!(s_eval_no_panic("`/` 1.5d 0").contains("SA::Panic(\\\"Division by 0: 1.5/0\\\")"));
+
+        #[cfg(feature="serde_json")]
+        assert_eq!(s_eval("std:ser:json $[1.50d, ${a=-0.1d}] $t"), "\"[\\\"1.50\\\",{\\\"a\\\":\\\"-0.1\\\"}]\"");
+        #[cfg(feature="serde_json")]
+        assert_eq!(s_eval("std:ser:json $[1.50d, ${a=-0.1d}] $t $t"), "\"[1.5,{\\\"a\\\":-0.1}]\"");
+        #[cfg(feature="serde_json")]
+        assert_eq!(s_eval("std:ser:json 1.5d $t"), "\"\\\"1.5\\\"\"");
+        #[cfg(feature="rmp-serde")]
+        assert_eq!(s_eval("decimal ~ std:deser:msgpack ~ std:ser:msgpack 12.340d"), "12.340d");
+    }
+
+    #[test]
+    fn check_numeric_array() {
+        assert_eq!(s_eval("!a = std:arr:f64 $[1, 2.5, 3]; $[a, type a, len a, a.1, a.(2), a.3]"),
+                   "$[$<f64[1,2.5,3]>,\"f64array\",3,2.5,3,$n]");
+        assert_eq!(s_eval("!a = std:arr:i64 $[1, 2.9, -3]; $[a, type a, a.2]"),
+                   "$[$<i64[1,2,-3]>,\"i64array\",-3]");
+        assert_eq!(s_eval("!a = std:arr:f64 $[1, 2]; !b = std:arr:f64 $[3, 4];
+                           $[a + b, a - b, a * b, b / a, b % a, a + 1, a * 0.5]"),
+                   "$[$<f64[4,6]>,$<f64[-2,-2]>,$<f64[3,8]>,$<f64[3,2]>,$<f64[0,0]>,$<f64[2,3]>,$<f64[0.5,1]>]");
+        assert_eq!(s_eval("!a = std:arr:i64 $[7, 8]; $[a / 2, a % 3, a * (std:arr:f64 $[1.5, 2]), `+` a a 1]"),
+                   "$[$<i64[3,4]>,$<i64[1,2]>,$<i64[7,16]>,$<i64[15,17]>]");
+        assert_eq!(s_eval("!o = ${v = std:arr:f64 $[1, 2]}; o.v += 1; o.v *= o.v; o.v"),
+                   "$<f64[4,9]>");
+        assert_eq!(s_eval("(std:arr:f64 $[1, 2]) + (std:arr:f64 $[1, 2, 3])"),
+                   "$e[1,49:<compiler:s_eval>(BinOpAdd)] \"Array length mismatch: 2 != 3\"");
+        assert_eq!(s_eval("(std:arr:i64 $[1, 9223372036854775807]) + 1"),
+                   "$e[1,44:<compiler:s_eval>(BinOpAdd)] \"Integer overflow: 9223372036854775807 + 1\"");
+        assert!(s_eval_no_panic("(std:arr:i64 $[1, 2]) / 0").contains("Division by 0: 1/0"));
+        assert!(s_eval_no_panic("`/` (std:arr:i64 $[1, 2]) 0").contains("Division by 0: 1/0"));
+        assert_eq!(s_eval("!a = std:arr:f64 $[1, 2, 3, 4]; !i = std:arr:i64 $[4, 1, 3];
+                           $[std:arr:sum a, std:arr:sum i, std:arr:min a, std:arr:max i,
+                             std:arr:mean a, std:arr:mean i, std:arr:dot a a, std:arr:dot i i]"),
+                   "$[10,8,1,4,2.5,2.6666666666666665,30,26]");
+        assert_eq!(s_eval("!e = std:arr:f64 $[]; $[std:arr:sum e, std:arr:min e, std:arr:max e, std:arr:mean e]"),
+                   "$[0,$n,$n,$n]");
+        assert_eq!(s_eval("std:arr:sum ~ std:arr:i64 $[9223372036854775807, 1]"),
+                   "$e \"arr:sum: Integer overflow: 9223372036854775807 + 1\"");
+        assert_eq!(s_eval("std:arr:dot (std:arr:f64 $[1]) (std:arr:f64 $[1, 2])"),
+                   "$e \"arr:dot: Array length mismatch: 1 != 2\"");
+        assert_eq!(s_eval("!a = std:arr:i64 $[1, 2, 3, 4];
+                           $[std:arr:slice a 1, std:arr:slice a 1 3, std:arr:slice a 3 1, std:arr:slice a -1 10]"),
+                   "$[$<i64[2,3,4]>,$<i64[2,3]>,$<i64[]>,$<i64[1,2,3,4]>]");
+        assert_eq!(s_eval("!a = std:arr:f64 $[1, 2]; !b = std:copy a; b.0 = 5; b.3 = 1; $[a, b]"),
+                   "$[$<f64[1,2]>,$<f64[5,2,0,1]>]");
+        assert_eq!(s_eval("!a = std:arr:i64 $[1, 2]; $[a == (std:arr:i64 $[1, 2]), a == (std:arr:f64 $[1, 2])]"),
+                   "$[$true,$false]");
+        assert_eq!(s_eval("$@vec for (std:arr:i64 $[1, 2, 3]) {|| $+ _ * 2 }"), "$[2,4,6]");
+        assert_eq!(s_eval("std:arr:to_vec ~ std:arr:f64 $[1, 2.5]"), "$[1,2.5]");
+        assert_eq!(s_eval("std:arr:to_bytes ~ std:arr:i64 $[1, -1]"),
+                   "$b\"\\x01\\0\\0\\0\\0\\0\\0\\0\\xFF\\xFF\\xFF\\xFF\\xFF\\xFF\\xFF\\xFF\"");
+        assert_eq!(s_eval("std:arr:f64 ~ std:arr:to_bytes ~ std:arr:f64 $[1.5, -2]"), "$<f64[1.5,-2]>");
+        assert_eq!(s_eval("std:arr:i64 ~ std:arr:f64 $[1.9, -2.9]"), "$<i64[1,-2]>");
+        assert_eq!(s_eval("std:arr:i64 $b\"abc\""),
+                   "$e \"arr:i64: byte length is not a multiple of 8: 3\"");
+        assert_eq!(s_eval("std:arr:sum $[1, 2]"),
+                   "$e \"arr:sum: expected numeric array, got: $[1,2]\"");
+
+        #[cfg(feature="serde_json")]
+        assert_eq!(s_eval("std:ser:json (std:arr:f64 $[1.5, 2]) $t"), "\"[1.5,2.0]\"");
+    }
+
+    #[test]
+    #[cfg(target_pointer_width = "64")]
+    fn check_vval_size() {
+        // Bigger variants like decimals and 4 component vectors
+        // are boxed to keep copying values cheap:
+        assert_eq!(std::mem::size_of::<VVal>(), 32);
+        assert!(matches!(VVal::new_ivec(NVec::Vec3(1, 2, 3)), VVal::IVec3([1, 2, 3])));
+        assert!(matches!(VVal::new_fvec(NVec::Vec2(1.0, 2.0)), VVal::FVec2(_)));
+        assert!(matches!(VVal::new_fvec(NVec::Vec4(1.0, 2.0, 3.0, 4.0)), VVal::FVec4(_)));
+    }
+
+    #[test]
+    fn check_nvec() {
+        assert_eq!(s_eval("$[$f (:a) :b, $f(1, 2)]"),                 "$[:\"b\",$f(1,2)]");
+        assert_eq!(s_eval("!v = $i(1, 2); !f = $f(1.5, 2, 3); $[v, f, type v, type f, len v, len f]"),
+                   "$[$i(1,2),$f(1.5,2,3),\"ivec\",\"fvec\",2,3]");
+        assert_eq!(s_eval("!x = 3; $f(x, x * 2, 1)"), "$f(3,6,1)");
+        assert_eq!(s_eval("!f = $f(1.5, 2, 3); $[f.x, f.y, f.z, f.w, f.r, f.b, f.0, f.2, f.3]"),
+                   "$[1.5,2,3,$n,1.5,3,1.5,3,$n]");
+        assert_eq!(s_eval("!f = $f(1.5, 2, 3); $[f.xy, f.zyx, f.xxxx, f.rgb, f.xw, f.xyzxy]"),
+                   "$[$f(1.5,2),$f(3,2,1.5),$f(1.5,1.5,1.5,1.5),$f(1.5,2,3),$n,$n]");
+        assert_eq!(s_eval("!v = $i(1, 2); $[v + 1, v * $i(3, 4), v - $[1, 1], v % 2, v + $f(1.5, 1.5)]"),
+                   "$[$i(2,3),$i(3,8),$i(0,1),$i(1,0),$i(2,3)]");
+        assert_eq!(s_eval("!f = $f(1, 2, 3); $[f * 2, f / $f(1, 2, 3), f + $i(1, 1), `+` f f 1]"),
+                   "$[$f(2,4,6),$f(1,1,1),$f(2,3,3),$f(3,5,7)]");
+        assert_eq!(s_eval("!o = ${pos = $f(0, 0)}; o.pos += $f(1, 2); o.pos *= 2; o.pos"),
+                   "$f(2,4)");
+        assert_eq!(s_eval("$i(9223372036854775807, 1) + 1"),
+                   "$e[1,31:<compiler:s_eval>(BinOpAdd)] \"Integer overflow: 9223372036854775807 + 1\"");
+        assert!(s_eval_no_panic("$i(1, 2) / 0").contains("Division by 0: 1/0"));
+        assert_eq!(s_eval("$[ivec $[1, 2, 3], fvec ${x=1, y=2}, fvec ${x=1, z=2}, ivec $f(1.9, 2),
+                             fvec $i(1, 2), ivec 5, fvec $[1], ivec ~ std:arr:i64 $[1, 2, 3, 4, 5]]"),
+                   "$[$i(1,2,3),$f(1,2),$f(1,0,2),$i(1,2),$f(1,2),$i(5,5),$f(1,0),$i(1,2,3,4)]");
+        assert_eq!(s_eval("$[std:v:mag $f(3, 4), std:v:mag2 $i(3, 4), std:v:norm $i(3, 4), std:v:norm $f(0, 0)]"),
+                   "$[5,25,$f(0.6,0.8),$f(0,0)]");
+        assert_eq!(s_eval("$[std:v:dot $i(1, 2) $i(3, 4), std:v:dot $f(1, 2) $[3, 4], std:v:dot $f(1, 2, 3) $f(1, 1)]"),
+                   "$[11,11,3]");
+        assert_eq!(s_eval("$[std:v:cross $f(1, 0, 0) $f(0, 1, 0), std:v:cross $i(1, 2, 3) $i(4, 5, 6),
+                             std:v:cross $f(1, 0) $f(0, 1)]"),
+                   "$[$f(0,0,1),$i(-3,6,-3),$f(0,0,1)]");
+        assert_eq!(s_eval("std:v:dot $i(9223372036854775807, 1) $i(2, 1)"),
+                   "$e \"v:dot: Integer overflow: 9223372036854775807 * 2\"");
+        assert_eq!(s_eval("!v = $i(1, 2); $[v == $i(1, 2), v == $i(1, 3), v == $f(1, 2)]"),
+                   "$[$true,$false,$false]");
+        assert_eq!(s_eval("$@vec for $f(1.5, 2, 3) {|| $+ _ }"), "$[1.5,2,3]");
+        assert_eq!(s_eval("$[int $f(2.5, 1), float $i(3, 1), bool $i(0, 0), bool $i(0, 1)]"),
+                   "$[2,3,$false,$true]");
+        assert_eq!(s_eval("$[$f, $f , $i(1, 2, 3, 4)]"), "$[$false,$false,$i(1,2,3,4)]");
+
+        #[cfg(feature="serde_json")]
+        assert_eq!(s_eval("std:ser:json $[$f(1, 2.5), $i(1, 2)] $t"), "\"[[1.0,2.5],[1,2]]\"");
+    }
+
+    #[test]
+    fn check_typed_func() {
+        use std::collections::HashMap;
+
+        let global_env = GlobalEnv::new_default();
+        {
+            let mut g = global_env.borrow_mut();
+            g.add_typed_func("tadd",   |a: i64, b: i64| a + b);
+            g.add_typed_func("tscale", |v: Vec<f64>, f: Option<f64>| {
+                v.iter().map(|x| x * f.unwrap_or(2.0)).collect::<Vec<f64>>()
+            });
+            g.add_typed_func("tkeys",  |m: HashMap<String, (u8, bool)>| {
+                let mut k : Vec<String> =
+                    m.iter().filter(|(_, v)| v.1).map(|(k, _)| k.clone()).collect();
+                k.sort();
+                k
+            });
+            g.add_typed_func("tdiv",   |a: i64, b: i64| -> Result<i64, String> {
+                if b == 0 { Err(format!("cannot divide {} by 0", a)) }
+                else { Ok(a / b) }
+            });
+            g.add_typed_func("tunwrap", |r: Result<String, String>| {
+                match r { Ok(s) => s, Err(e) => format!("failed: {}", e) }
+            });
+            g.add_typed_func("tnone",  || ());
+            g.add_typed_func("tbig",   |a: u64| a * 2);
+        }
+
+        let mut ctx = crate::compiler::EvalContext::new(global_env);
+        let mut ev = |s: &str| -> String {
+            match ctx.eval(s) {
+                Ok(VVal::Err(e)) => format!("ERR: {}", e.borrow().0.s_raw()),
+                Ok(v)            => v.s(),
+                Err(e)           => format!("{}", e),
+            }
+        };
+
+        assert_eq!(ev("tadd 1 2"),                       "3");
+        assert_eq!(ev("tscale $[1, 2.5]"),                "$[2,5]");
+        assert_eq!(ev("tscale $[1, 2.5] 3"),              "$[3,7.5]");
+        assert_eq!(ev("tkeys ${a=$[1,$t], b=$[2,$f], c=$[3,$t]}"), "$[\"a\",\"c\"]");
+        assert_eq!(ev("tdiv 10 2"),                      "5");
+        assert_eq!(ev("tnone[]"),                        "$n");
+        assert_eq!(ev("tunwrap :ok"),                    "\"ok\"");
+        assert_eq!(ev("tunwrap $e \"x\""),               "\"failed: x\"");
+
+        assert_eq!(ev("tdiv 10 0"), "ERR: tdiv: cannot divide 10 by 0");
+        assert_eq!(ev("tbig 10"),                        "20");
+        assert_eq!(ev("tbig 9223372036854775807"),
+                   "ERR: tbig: integer out of range: 18446744073709551614");
+        assert_eq!(ev("tadd 1 \"x\""),
+                   "ERR: tadd: argument 2: expected integer, got: \"x\"");
+        assert_eq!(ev("tadd 1.5 2"),
+                   "ERR: tadd: argument 1: expected integer, got: 1.5");
+        assert_eq!(ev("tscale $[1, $t]"),
+                   "ERR: tscale: argument 1: element 1: expected float, got: $true");
+        assert_eq!(ev("tkeys ${a=$[300,$t]}"),
+                   "ERR: tkeys: argument 1: key a: element 0: integer out of range for u8: 300");
+        assert_eq!(ev("tkeys ${a=$[1,$t,3]}"),
+                   "ERR: tkeys: argument 1: key a: expected vector of length 2, got: $[1,$true,3]");
+        assert!(ev("tadd 1").contains("function expects at least 2 arguments"));
+        assert!(ev("tscale $[] 1 2").contains("function expects at most 2 arguments"));
+        assert!(ev("tadd ($e 1) 2").contains("Error value"));
+    }
+
+    #[test]
+    fn check_userdata_binop() {
+        use crate::vval::VValUserData;
+
+        #[derive(Clone, Debug)]
+        struct Money(i64);
+
+        impl VValUserData for Money {
+            fn s(&self) -> String { format!("$<Money({})>", self.0) }
+            fn i(&self) -> i64    { self.0 }
+            fn as_any(&mut self) -> &mut dyn std::any::Any { self }
+            fn clone_ud(&self) -> Box<dyn VValUserData> { Box::new(self.clone()) }
+            fn binop(&self, op: &str, other: &VVal) -> Option<Result<VVal, StackAction>> {
+                match op {
+                    "_add_" | "_radd_" =>
+                        Some(Ok(VVal::Usr(Box::new(Money(self.0 + other.i()))))),
+                    "_eq_" => Some(Ok(VVal::Bol(self.0 == other.i()))),
+                    "_lt_" => Some(Ok(VVal::Bol(self.0 < other.i()))),
+                    _ => None,
+                }
+            }
+        }
+
+        let global_env = GlobalEnv::new_default();
+        global_env.borrow_mut().add_func(
+            "money",
+            |env: &mut Env, _argc: usize| {
+                Ok(VVal::Usr(Box::new(Money(env.arg(0).i()))))
+            }, Some(1), Some(1));
+
+        let mut ctx = crate::compiler::EvalContext::new(global_env);
+        assert_eq!(ctx.eval("(money 10) + (money 5)").unwrap().s(),  "$<Money(15)>");
+        assert_eq!(ctx.eval("1 + (money 5)").unwrap().s(),           "$<Money(6)>");
+        assert_eq!(ctx.eval("$[(money 5) == 5, (money 4) < 5, (money 4) != 4]").unwrap().s(),
+                   "$[$true,$true,$false]");
+        assert_eq!(ctx.eval("(money 10) * 2").unwrap().s(),          "20");
+    }
+
+    #[test]
+    fn check_userdata_call_method() {
+        use crate::vval::VValUserData;
+
+        #[derive(Clone, Debug)]
+        struct Stack(Rc<RefCell<Vec<i64>>>);
+
+        impl VValUserData for Stack {
+            fn s(&self) -> String { format!("$<Stack{:?}>", self.0.borrow()) }
+            fn as_any(&mut self) -> &mut dyn std::any::Any { self }
+            fn clone_ud(&self) -> Box<dyn VValUserData> { Box::new(self.clone()) }
+            fn get_key(&self, key: &str) -> Option<VVal> {
+                if key == "len" { Some(VVal::Int(self.0.borrow().len() as i64)) }
+                else { None }
+            }
+            fn call_method(&self, name: &str, args: &[VVal], env: &mut Env)
+                -> Result<VVal, StackAction>
+            {
+                match name {
+                    "push" => {
+                        for a in args { self.0.borrow_mut().push(a.i()); }
+                        Ok(env.self_object())
+                    },
+                    "sum" => Ok(VVal::Int(self.0.borrow().iter().sum())),
+                    _ => Err(StackAction::panic_msg(format!("Stack has no method {}", name))),
+                }
+            }
+            fn iter(&self) -> Option<VVal> {
+                let v = VVal::vec();
+                for i in self.0.borrow().iter() { v.push(VVal::Int(*i)); }
+                Some(v)
+            }
+            fn compare(&self, other: &VVal) -> Option<std::cmp::Ordering> {
+                if let VVal::Usr(mut o) = other.clone() {
+                    let o = o.as_any().downcast_ref::<Stack>()?;
+                    Some(self.0.borrow().len().cmp(&o.0.borrow().len()))
+                } else {
+                    None
+                }
+            }
+        }
+
+        let global_env = GlobalEnv::new_default();
+        global_env.borrow_mut().add_func(
+            "stack",
+            |_env: &mut Env, _argc: usize| {
+                Ok(VVal::Usr(Box::new(Stack(Rc::new(RefCell::new(vec![]))))))
+            }, Some(0), Some(0));
+
+        let mut ctx = crate::compiler::EvalContext::new(global_env);
+        assert_eq!(ctx.eval("!s = stack[]; s.push 1 2; s.push 3; s").unwrap().s(),
+                   "$<Stack[1, 2, 3]>");
+        assert_eq!(ctx.eval("!s = stack[]; s.push 1 2 3 4 5 6 7; s.sum[]").unwrap().s(), "28");
+        assert_eq!(ctx.eval("!s = stack[]; (s.push 4).push 5; s.len").unwrap().s(), "2");
+        assert_eq!(ctx.eval("!s = stack[]; s.push 3 4; s.(\"len\")").unwrap().s(), "2");
+        assert!(ctx.eval("!s = stack[]; s.pop[]").err().unwrap().to_string()
+                   .contains("Stack has no method pop"));
+
+        assert_eq!(ctx.eval(r#"
+            !s = stack[];
+            s.push 10 20;
+            !r = $[];
+            std:push r ~ std:iter:next ~ $iter s;
+            for s { std:push r _ * 2 };
+            !sum = 0;
+            for s { .sum = sum + _ };
+            $[r, sum]
+        "#).unwrap().s(), "$[$[10,20,40],30]");
+
+        assert_eq!(ctx.eval(r#"
+            !a = stack[]; a.push 1 2 3;
+            !b = stack[]; b.push 1;
+            !c = stack[]; c.push 5 5;
+            $[a < b, b < a, b <= c, c >= a, a > c,
+              std:sort $[a, b, c]]
+        "#).unwrap().s(),
+                   "$[$false,$true,$true,$false,$true,$[$<Stack[1]>,$<Stack[5, 5]>,$<Stack[1, 2, 3]>]]");
+    }
+
     #[test]
     fn check_bytes_impl() {
         #[cfg(feature="serde_json")]
@@ -3851,6 +5647,8 @@ mod tests {
             "std:num:abs   -1.2"),
             "1.2");
         assert_eq!(s_eval("(std:num:abs  -1) * 2"), "2");
+        assert_eq!(s_eval("std:num:abs -9223372036854775808"),
+                   "$e \"Integer overflow: abs -9223372036854775808\"");
     }
 
     #[test]
@@ -4052,6 +5850,65 @@ mod tests {
         "), "123");
     }
 
+    #[test]
+    fn check_macros() {
+        assert_eq!(s_eval(r"
+            !@macro twice = { std:wlambda:subst ${e = _} ~ $code ({!v = e; v + v}[]) };
+            !v = 10;
+            $[twice v, twice (v + 1), twice 3]
+        "), "$[20,22,6]");
+        assert_eq!(s_eval(r"
+            !@macro unless = {!(cond, body) = @;
+                std:wlambda:subst ${cond = cond, body = body} ~ $code ((not cond) body) };
+            !x = 0;
+            unless (x > 0) { .x = 5 };
+            unless (x > 0) { .x = 7 };
+            x
+        "), "5");
+        assert_eq!(s_eval(r"
+            !@macro with_tmp = { std:wlambda:subst ${e = _} ~ $code ({!tmp = 100; e + tmp}[]) };
+            !tmp = 1;
+            with_tmp tmp
+        "), "101");
+        assert_eq!(s_eval(r"
+            !@macro dbl = { std:wlambda:subst ${e = _} ~ $code (e * 2) };
+            !f = { !dbl = { _ + 1 }; dbl 4 };
+            $[f[], dbl 4]
+        "), "$[5,8]");
+        assert_eq!(s_eval(r"
+            !@macro one = { 1 };
+            !@macro nest = { std:wlambda:subst ${e = _} ~ $code (e + one[]) };
+            nest 10
+        "), "11");
+        assert_eq!(s_eval(r"
+            !@import m tests:test_mod_macros;
+            m:square 7
+        "), "49");
+
+        assert_eq!(
+            format!("{}", eval("!@macro m = 10;").unwrap_err()),
+            "Compile error: [1,15:<wlambda::eval>] Compilation Error: Macro 'm' is not a function: 10");
+        assert_eq!(
+            format!("{}", eval("!@macro m = { _; $e :bad }; m 1").unwrap_err()),
+            "Compile error: [1,31:<wlambda::eval>] Compilation Error: Error in macro 'm': $e[1,21:<wlambda::eval>(Err)] :\"bad\"");
+        assert_eq!(
+            format!("{}", eval("!@macro m = { $[${syn = :Foo}] }; m[]").unwrap_err()),
+            "Compile error: [1,36:<wlambda::eval>] Compilation Error: Error in macro 'm': Unknown syntax 'Foo'");
+        assert_eq!(
+            format!("{}", eval("!@macro m = { $[${syn = :BinOpAdd}] }; m[]").unwrap_err()),
+            "Compile error: [1,41:<wlambda::eval>] Compilation Error: Error in macro 'm': \
+             BinOpAdd node needs at least 2 elements, got 0: $[&BinOpAdd]");
+
+        let global = GlobalEnv::new_default();
+        let mut st = SymbolTable::new();
+        let mut ctx = EvalContext::new_default();
+        st.set_macro("neg", ctx.eval(
+            "{ std:wlambda:subst ${e = _} ~ $code (0 - e) }").unwrap());
+        global.borrow_mut().set_module("rmac", st);
+        let mut ctx = EvalContext::new(global);
+        assert_eq!(ctx.eval("!@import r rmac; r:neg (3 + 4)").unwrap().s(), "-7");
+    }
+
     #[test]
     fn check_field_access() {
         assert_eq!(s_eval("$[1,$[1,2,$[1,2,3]]].1"),                                 "$[1,2,$[1,2,3]]");
@@ -4072,6 +5929,92 @@ mod tests {
         assert_eq!(s_eval("${a=${b=${c=9}}}.(\"a\" \"\").(\"b\" \"\").(\"c\" \"\")"), "9");
     }
 
+    #[test]
+    fn check_compound_assign() {
+        assert_eq!(s_eval("!x = 10; .x += 5; x"),                      "15");
+        assert_eq!(s_eval("!x = 10; .x -= 5; .x *= 3; x"),             "15");
+        assert_eq!(s_eval("!x = 10; .x /= 3; x"),                      "3");
+        assert_eq!(s_eval("!x = 10; .x %= 3; x"),                      "1");
+        assert_eq!(s_eval("!x = 1.5; .x += 1; x"),                     "2.5");
+        assert_eq!(s_eval("!x = 2; .x ^= 3; x"),                       "8");
+        assert_eq!(s_eval("!x = 1; .x <<= 4; .x &|= 1; x"),            "17");
+        assert_eq!(s_eval("!x = $&& 1; .x += 2; x"),                   "3");
+        assert_eq!(s_eval("!x = $&& 1; !y = x; .*y += 2; $*x"),        "3");
+        assert_eq!(s_eval("!x = 1; { .x += 1 }[]; { .x += 1 }[]; x"),  "3");
+        assert_eq!(s_eval("!m = ${c=1}; .m.c += 1; m.c += 2; m"),      "${c=4}");
+        assert_eq!(s_eval("!m = ${}; m.c += 1; m"),                    "${c=1}");
+        assert_eq!(s_eval("!v = $[1,2]; .v.0 *= 3; v.1 -= 4; v"),      "$[3,-2]");
+        assert_eq!(s_eval("!v = $[1,$[2]]; v.1.0 &= 3; v"),            "$[1,$[2]]");
+        assert_eq!(s_eval("!m = ${a=${b=2}}; .m.a.b /= 2; m"),         "${a=${b=1}}");
+        assert_eq!(s_eval("!m = ${a=1}; !k = \"a\"; m.(k) += 9; m"),   "${a=10}");
+        assert_eq!(s_eval(r"
+            !calls = 0;
+            !m = ${c=1};
+            !get = { .calls += 1; m };
+            get[].c += 10;
+            $[calls, m]
+        "), "$[1,${c=11}]");
+        assert_eq!(s_eval_no_panic("!m = ${c=1}; m.c /= 0"),
+            "$e \"EXEC ERR: Caught [1,21:<compiler:s_eval_no_panic>(SetKey)] SA::Panic(\\\"Division by 0: 1/0\\\")\"");
+    }
+
+    #[test]
+    fn check_format_string_literal() {
+        assert_eq!(s_eval("!x = 10; $F\"x={x}\""),                  "\"x=10\"");
+        assert_eq!(s_eval("!y = 3.14159; $F\"y={y:>8.2}|\""),       "\"y=    3.14|\"");
+        assert_eq!(s_eval("$F\"{\\\"ab\\\":*^6}\""),               "\"**ab**\"");
+        assert_eq!(s_eval("$F\"{255:x} {255:#X} {5:08b} {8:o}\""),  "\"ff 0xFF 00000101 10\"");
+        assert_eq!(s_eval("$F\"{-5:+05} {5:+} {-2.5:.3}\""),        "\"-0005 +5 -2.500\"");
+        assert_eq!(s_eval("$F\"{1234.5:e} {0.00012:.1E}\""),        "\"1.2345e3 1.2E-4\"");
+        assert_eq!(s_eval("$F\"{$[1,\\\"a\\\"]:?} {\\\"a\\\":?}\""),    "\"$[1,\\\"a\\\"] \\\"a\\\"\"");
+        assert_eq!(s_eval("$F\"{\\\"abcdef\\\":.3}|{1:<3}|{1:^4}|\""), "\"abc|1  | 1  |\"");
+        assert_eq!(s_eval("!s = \"abc\"; $F\"{std:str:len s}{{}}\""), "\"3{}\"");
+        assert_eq!(s_eval("!m = ${a=${b=2}}; $F\"{m.a.b + 1:03}\""), "\"003\"");
+        assert_eq!(s_eval("$F\"{ { _ * 2 } 21 }\""),                "\"42\"");
+        assert_eq!(s_eval_no_panic("$F\"{x\""),
+            "EVAL ERROR: error[1,3:<compiler:s_eval_no_panic>] unterminated placeholder at char 0 in format string at code '{x'");
+        assert_eq!(s_eval("$F\"{:e}|{:a:*^5}\""),                   "\"e|**a**\"");
+        assert_eq!(s_eval("!x = 5; !x:b = 7; $F\"{x:b} {(x:b)}\""), "\"101 7\"");
+        assert_eq!(s_eval_no_panic("$F\"ab{ }\""),
+            "EVAL ERROR: error[1,3:<compiler:s_eval_no_panic>] Empty placeholder '{}' in format string at code 'ab{ }'");
+        assert_eq!(format!("{}", eval("!x = 1; $F\"{x:99999999999999}\"").unwrap_err()),
+            "Compile error: [1,31:<wlambda::eval>] Compilation Error: bad format spec '99999999999999': width larger than 65535");
+        assert_eq!(s_eval_no_panic("$F\"ab{1 +}\""),
+            "EVAL ERROR: error[1,6:<compiler:s_eval_no_panic>] EOF while parsing value. at code '{1 +}'");
+    }
+
+    #[test]
+    fn check_str_format() {
+        assert_eq!(s_eval("std:str:format \"{} + {} = {:>5}\" 1 2 3"),           "\"1 + 2 =     3\"");
+        assert_eq!(s_eval("std:str:format \"{1} {0} {1:?}\" :a \"b\""),         "\"b a \\\"b\\\"\"");
+        assert_eq!(s_eval("std:str:format \"{x:.2}|{y:_<4}|\" ${x=1.234,y=1}"), "\"1.23|1___|\"");
+        assert_eq!(s_eval("std:str:format \"{:#06x} {:e}\" 255 1500.0"),        "\"0x00ff 1.5e3\"");
+        assert_eq!(s_eval("std:str:format \"{{}} {}\" $[1]"),                   "\"{} $[1]\"");
+        assert_eq!(s_eval("std:str:format \"none\""),                          "\"none\"");
+        assert_eq!(s_eval("(std:str:format \"{:zz}\" 1) | error_to_str | std:str:len | bool"), "$true");
+        assert_eq!(s_eval("unwrap_err ~ std:str:format \"{:zz}\" 1"),
+                   "\"str:format: bad format spec \\'zz\\': unknown format type \\'z\\'\"");
+        assert_eq!(s_eval("unwrap_err ~ std:str:format \"{:.}\" 1"),
+                   "\"str:format: bad format spec \\'.\\': expected precision after \\'.\\'\"");
+        assert_eq!(s_eval("unwrap_err ~ std:str:format \"{} {}\" 1"),
+                   "\"str:format: missing argument 1 for \\'{}\\'\"");
+        assert_eq!(s_eval("unwrap_err ~ std:str:format \"{3}\" 1"),
+                   "\"str:format: missing argument 3 for \\'{3}\\'\"");
+        assert_eq!(s_eval("unwrap_err ~ std:str:format \"{18446744073709551615}\" 1"),
+                   "\"str:format: missing argument 18446744073709551615 for \\'{18446744073709551615}\\'\"");
+        assert_eq!(s_eval("unwrap_err ~ std:str:format \"{:99999999999999}\" 1"),
+                   "\"str:format: bad format spec \\'99999999999999\\': width larger than 65535\"");
+        assert_eq!(s_eval("unwrap_err ~ std:str:format \"{:.99999999999999999999999}\" 1"),
+                   "\"str:format: bad format spec \\'.99999999999999999999999\\': precision larger than 65535\"");
+        assert_eq!(s_eval("std:str:len ~ std:str:format \"{:65535.65535}\" 1.5"), "65537");
+        assert_eq!(s_eval("unwrap_err ~ std:str:format \"{foo}\" ${}"),
+                   "\"str:format: no named argument \\'foo\\' for \\'{foo}\\'\"");
+        assert_eq!(s_eval("unwrap_err ~ std:str:format \"{\" 1"),
+                   "\"str:format: unterminated placeholder at char 0 in format string\"");
+        assert_eq!(s_eval("unwrap_err ~ std:str:format \"}\" 1"),
+                   "\"str:format: unmatched \\'}\\' at char 0 in format string\"");
+    }
+
     #[test]
     fn check_method_calls() {
         // Simple vector call table access still works as usual:
@@ -4479,6 +6422,136 @@ mod tests {
         assert_eq!(s_eval("$@b $+\"ABC\""),   "$b\"ABC\"");
     }
 
+    #[test]
+    fn check_match_patterns() {
+        assert_eq!(s_eval("$match 10 { 1 => :a; 10 => :b; _ => :c }"),                ":\"b\"");
+        assert_eq!(s_eval("$match 11 { 1 => :a; 10 => :b }"),                          "$n");
+        assert_eq!(s_eval("$match \"x\" { \"x\" => 1; _ => 2 }"),                      "1");
+        assert_eq!(s_eval("$match :x { :x => 1; _ => 2 }"),                            "1");
+        assert_eq!(s_eval("$match $n { $t => 1; $n => 2 }"),                           "2");
+        assert_eq!(s_eval("!y = 3; $match 3 { (y) => :y; _ => :other }"),              ":\"y\"");
+        assert_eq!(s_eval("$match 3 { 0..3 => :a; 3..=3 => :b }"),                     ":\"b\"");
+        assert_eq!(s_eval("$match 2.5 { 0..2 => :a; 2..3 => :b }"),                    ":\"b\"");
+        assert_eq!(s_eval("$match -5 { -10..0 => :neg; _ => :pos }"),                  ":\"neg\"");
+        assert_eq!(s_eval("$match \"5\" { 0..10 => :num; _ => :other }"),              ":\"other\"");
+        assert_eq!(s_eval("$match $[1, 2] { $[a] => a; $[a, b] => a + b }"),           "3");
+        assert_eq!(s_eval("$match $[1, 2, 3, 4] { $[a, *r] => $[a, r] }"),             "$[1,$[2,3,4]]");
+        assert_eq!(s_eval("$match $[1, 2, 3, 4] { $[a, *, z] => $[a, z] }"),           "$[1,4]");
+        assert_eq!(s_eval("$match $[1] { $[a, *m, z] => $[a, m, z]; _ => :short }"),   ":\"short\"");
+        assert_eq!(s_eval("$match $[$[1, 2], ${k = 3}] { $[$[a, 2], ${k}] => a + k }"), "4");
+        assert_eq!(s_eval("$match ${a = 1, b = 2} { ${a} => :one; ${a = 1, b = x} => x }"), "2");
+        assert_eq!(s_eval("$match ${a = 1, b = 2} { ${a = 1, *r} => r }"),             "${b=2}");
+        assert_eq!(s_eval("$match ${a = 1} { ${\"a\" = v, *} => v }"),                 "1");
+        assert_eq!(s_eval("$match ${a = 1} { ${b, *} => 1; _ => 2 }"),                 "2");
+        assert_eq!(s_eval("$match 20 { x ? x > 10 => :big; x => :small }"),           ":\"big\"");
+        assert_eq!(s_eval("$match 5 { x ? x > 10 => :big; x => :small }"),            ":\"small\"");
+        assert_eq!(s_eval("$match \"12x3\" { $r/^(?P<w>\\d+)x(?P<h>\\d+)$/ => (int w) * (int h) }"), "36");
+        assert_eq!(s_eval("$match \"ab\" { $r/^(?P<a>a)(?P<c>c)?/ => $[a, c] }"),     "$[\"a\",$n]");
+        assert_eq!(s_eval("$match 1 { $r/1/ => :rx; _ => :no }"),                      ":\"no\"");
+        assert_eq!(s_eval("$match $&& $[1] { $[a] => a }"),                            "1");
+        assert_eq!(s_eval("$match 1 { x => { !y = x + 1; y * 2 } }"),                  "4");
+        assert_eq!(s_eval("!x = 10; $match 1 { x => x }; x"),                          "10");
+        assert_eq!(s_eval("!f = $match $[1, 2] { $[a, b] => (\\ a + b) }; f[]"),       "3");
+        assert_eq!(s_eval("!x = 5; $match 1 { 1 ? x > 2 => :guard_sees_outer }"),     ":\"guard_sees_outer\"");
+        assert_eq!(format!("{}", eval("$match 1 { $[a, a] => 1 }").unwrap_err()),
+                   "Compile error: [1,3:<wlambda::eval>] Compilation Error: Variable 'a' bound twice in pattern");
+        assert!(format!("{}", eval("$match 1 { $r/(/ => 1 }").unwrap_err()).contains("Bad regex pattern"));
+        assert!(s_eval_no_panic("$match 1 { 1 2 }").contains("Expected '=>' after pattern"));
+        assert!(s_eval_no_panic("$match 1 { $[*, *] => 1 }").contains("Only one '*'"));
+    }
+
+    #[test]
+    fn check_code_as_data() {
+        assert_eq!(s_eval("($code x).1"),                                     ":\"x\"");
+        assert_eq!(s_eval("($code (1 + 2)).0.syn"),                           ":\"BinOpAdd\"");
+        assert_eq!(s_eval("(std:wlambda:parse \"x\" \"foo.wl\").1.0.file"),  "\"foo.wl\"");
+        assert_eq!(s_eval("unwrap_err ~ std:wlambda:parse \"(\""),
+                   "\"wlambda:parse: error[1,2:<wlambda:parse>] EOF while parsing value. at code \\'\\'\"");
+
+        let mut ctx = EvalContext::new_default();
+
+        assert_eq!(ctx.eval("std:wlambda:eval_ast ~ std:wlambda:parse \"3 * 4\"").unwrap().s(), "12");
+        assert_eq!(ctx.eval("!c = $code (1 + 2); c.1 = 40; std:wlambda:eval_ast c").unwrap().s(), "42");
+        assert_eq!(ctx.eval("std:wlambda:eval_ast $[${syn=:BinOpMul}, 2, 3]").unwrap().s(), "6");
+
+        ctx.set_global_var("XXX", &VVal::Int(1337));
+        assert_eq!(ctx.eval("std:wlambda:eval_ast $code (XXX + 2)").unwrap().s(), "1339");
+        assert_eq!(ctx.eval("unwrap_err ~ std:wlambda:eval_ast $[${syn=:Foo}]").unwrap().s(),
+                   "\"wlambda:eval_ast: Unknown syntax \\'Foo\\'\"");
+
+        assert_eq!(ctx.eval("unwrap_err ~ std:wlambda:eval_ast $[${syn=:BinOpAdd}]").unwrap().s(),
+                   "\"wlambda:eval_ast: BinOpAdd node needs at least 2 elements, got 0: $[&BinOpAdd]\"");
+        assert!(ctx.eval("unwrap_err ~ std:wlambda:eval_ast $[${syn=:Var}]").unwrap().s()
+                .contains("Var node needs at least 1 elements, got 0"));
+        assert!(ctx.eval("unwrap_err ~ std:wlambda:eval_ast $[${syn=:Block}, $[${syn=:Assign}]]").unwrap().s()
+                .contains("Assign node needs at least 2 elements, got 0"));
+        assert!(ctx.eval("unwrap_err ~ std:wlambda:eval_ast $[${syn=:Def}, :x, 10]").unwrap().s()
+                .contains("Def node needs a vector of variable names"));
+        assert!(ctx.eval("unwrap_err ~ std:wlambda:eval_ast $[${syn=:Map}, 1]").unwrap().s()
+                .contains("Map node needs key/value pairs"));
+        assert!(ctx.eval("unwrap_err ~ std:wlambda:eval_ast $[${syn=:IVec}, 1, 2, 3, 4, 5]").unwrap().s()
+                .contains("IVec node needs 2 to 4 elements, got 5"));
+        assert!(ctx.eval("unwrap_err ~ std:wlambda:eval_ast $[${syn=:Accum}, :foo, 1]").unwrap().s()
+                .contains("Bad Accum node"));
+
+        assert_eq!(ctx.eval(r#"
+            std:wlambda:eval_ast ~ std:wlambda:parse $q/
+                !(a, b) = $[1, 2];
+                !m = ${x = $i(a, b), *${y = $[*$[3], b]}};
+                .a = m.x.y + m.y.0;
+                !f = \:ret { !r = $@vec for $[1, 2] { $+ _ }; return :ret r.(1) };
+                !t = $match $[a, b] { $[x, 2] => x + 10; _ => 0 };
+                !c = (a > b) &and $t;
+                $F"{a},{f[]},{m.y.1},{c},{t}"
+            /
+        "#).unwrap().s(), "\"5,2,2,$true,15\"");
+    }
+
+    #[test]
+    fn check_iter() {
+        assert_eq!(s_eval("type $iter $[1,2]"),                               "\"iter\"");
+        assert_eq!(s_eval("$@vec $iter $[1,2,3]"),                            "$[1,2,3]");
+        assert_eq!(s_eval("$@vec $iter \"abc\""),                             "$[\"a\",\"b\",\"c\"]");
+        assert_eq!(s_eval("$@vec $iter $b\"ab\""),                            "$[$b\"a\",$b\"b\"]");
+        assert_eq!(s_eval("$@map $iter ${a=1}"),                              "${a=1}");
+        assert_eq!(s_eval("$@int std:iter:range 1 10"),                       "55");
+        assert_eq!(s_eval("$@vec std:iter:range 10 1 -3"),                    "$[10,7,4,1]");
+        assert_eq!(s_eval("$@vec std:iter:range 0.0 1.0 0.5"),                "$[0,0.5,1]");
+        assert_eq!(s_eval("unwrap_err ~ std:iter:range 1 2 0"),               "\"iter:range: step must not be 0\"");
+        assert_eq!(s_eval("unwrap_err ~ std:iter:range 0.0 1.0 0.0 / 0.0"),   "\"iter:range: bad float range: 0 1 NaN\"");
+        assert_eq!(s_eval("unwrap_err ~ std:iter:range 0.0 1.0 1.0 / 0.0"),   "\"iter:range: bad float range: 0 1 inf\"");
+        assert_eq!(s_eval("unwrap_err ~ std:iter:range 0.0 / 0.0 1.0 0.5"),   "\"iter:range: bad float range: NaN 1 0.5\"");
+        assert_eq!(s_eval("$@vec $iter $[1,2,3,4] | std:iter:map { _ * 2 } | std:iter:filter { _ > 2 }"),
+                   "$[4,6,8]");
+        assert_eq!(s_eval("$@vec std:iter:map { _ + 1 } $[1,2]"),            "$[2,3]");
+        assert_eq!(s_eval("$@map std:iter:map {|| _ * 2 } ${a=1}"),           "${a=2}");
+        assert_eq!(s_eval("$@vec std:iter:take 2 ~ std:iter:skip 3 ~ std:iter:range 1 100"),
+                   "$[4,5]");
+        assert_eq!(s_eval("$@vec std:iter:skip 5 $[1,2]"),                    "$[]");
+        assert_eq!(s_eval("$@vec std:iter:chain $[1,2] $iter \"a\" $[]"),     "$[1,2,\"a\"]");
+        assert_eq!(s_eval("$@vec std:iter:zip $[1,2,3] \"ab\""),              "$[$[1,\"a\"],$[2,\"b\"]]");
+        assert_eq!(s_eval("$@map std:iter:zip $[:a,:b] $[1,2]"),              "${a=1,b=2}");
+        assert_eq!(s_eval("$@vec for (std:iter:enumerate $[:a,:b]) {|| $+ $[_1, _] }"),
+                   "$[$[0,:\"a\"],$[1,:\"b\"]]");
+        assert_eq!(s_eval("!it = $iter $[1,2,3]; $[std:iter:next it, $@vec it, std:iter:next it]"),
+                   "$[1,$[2,3],$n]");
+        assert_eq!(s_eval("!i = $&& 0; $@vec $iter { .i += 1; (i < 4) { i } { $n } }"),
+                   "$[1,2,3]");
+        assert_eq!(s_eval("!calls = $&& 0; !it = std:iter:map { .calls += 1; _ } $[1,2,3]; \
+                           !a = $*calls; std:iter:next it; $[a, $*calls]"),
+                   "$[0,1]");
+        assert_eq!(s_eval("$@vec for (std:iter:range 1 10) { (_ > 3) { break[] }; $+ _ }"),
+                   "$[1,2,3]");
+        assert_eq!(s_eval("$@vec std:iter:range 9223372036854775806 9223372036854775807"),
+                   "$[9223372036854775806,9223372036854775807]");
+        assert_eq!(s_eval("$@vec std:iter:range -9223372036854775807 -9223372036854775808 -1"),
+                   "$[-9223372036854775807,-9223372036854775808]");
+        assert_eq!(s_eval("!i = $&& 0; $@vec for { .i += 1; (i < 4) { i } { $n } } { $+ _ }"),
+                   "$[1,2,3]");
+        assert_eq!(s_eval("$@vec std:iter:map { (_ == 2) { next[] }; (_ > 3) { break[] }; _ } $[1,2,3,4,5]"),
+                   "$[1,3]");
+    }
+
     #[test]
     fn check_accum() {
         assert_eq!(s_eval("std:accum $b\"a\" 1"),           "$b\"a\\x01\"");
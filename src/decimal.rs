@@ -0,0 +1,469 @@
+// Copyright (c) 2020 Weird Constructor <weirdconstructor@gmail.com>
+// This is a part of WLambda. See README.md and COPYING for details.
+
+/*!
+Exact base 10 decimal numbers, used for the `VVal::Dec` values.
+
+A [Decimal](struct.Decimal.html) is an integer mantissa together with
+the number of digits after the decimal point (the scale). Unlike floats
+it represents numbers like `0.1` exactly and keeps the trailing zeros
+of the number it was written with:
+
+```
+use wlambda::decimal::{Decimal, RoundingMode};
+
+let a = Decimal::parse("0.10").unwrap();
+let b = Decimal::parse("0.2").unwrap();
+let s = a.checked_add(b).unwrap();
+assert_eq!(s.to_string(), "0.30");
+assert_eq!(Decimal::parse("2.345").unwrap()
+           .round(2, RoundingMode::HalfEven).to_string(), "2.34");
+```
+*/
+
+use std::cmp::Ordering;
+use std::fmt;
+
+/// The maximum number of digits after the decimal point.
+/// Results with more digits are rounded half to even.
+pub const MAX_SCALE : u32 = 28;
+
+/// An exact decimal number with up to 38 significant digits.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Decimal {
+    mant:  i128,
+    scale: u32,
+}
+
+/// The ways a [Decimal](struct.Decimal.html) can be rounded
+/// to fewer digits.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RoundingMode {
+    /// Round to the nearest, ties to the even neighbour (banker's rounding).
+    HalfEven,
+    /// Round to the nearest, ties away from zero.
+    HalfUp,
+    /// Round to the nearest, ties towards zero.
+    HalfDown,
+    /// Round away from zero.
+    Up,
+    /// Round towards zero (truncate).
+    Down,
+    /// Round towards positive infinity.
+    Ceiling,
+    /// Round towards negative infinity.
+    Floor,
+}
+
+impl RoundingMode {
+    /// Returns the rounding mode for the names `half_even`, `half_up`,
+    /// `half_down`, `up`, `down`, `ceil` and `floor`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "half_even" => Some(RoundingMode::HalfEven),
+            "half_up"   => Some(RoundingMode::HalfUp),
+            "half_down" => Some(RoundingMode::HalfDown),
+            "up"        => Some(RoundingMode::Up),
+            "down"      => Some(RoundingMode::Down),
+            "ceil"      => Some(RoundingMode::Ceiling),
+            "floor"     => Some(RoundingMode::Floor),
+            _           => None,
+        }
+    }
+}
+
+fn pow10(n: u32) -> Option<i128> { 10_i128.checked_pow(n) }
+
+/// Rounds the quotient `q` of a division with the remainder `r`
+/// by the divisor `den` according to `mode`.
+fn round_quotient(q: i128, r: i128, den: i128, mode: RoundingMode) -> Option<i128> {
+    if r == 0 { return Some(q); }
+
+    let neg = (r < 0) != (den < 0);
+    let away =
+        match mode {
+            RoundingMode::Down    => false,
+            RoundingMode::Up      => true,
+            RoundingMode::Floor   => neg,
+            RoundingMode::Ceiling => !neg,
+            _ => {
+                // |r| < |den|, so doubling it can't overflow an u128:
+                match (r.unsigned_abs() * 2).cmp(&den.unsigned_abs()) {
+                    Ordering::Greater => true,
+                    Ordering::Less    => false,
+                    Ordering::Equal   =>
+                        match mode {
+                            RoundingMode::HalfUp   => true,
+                            RoundingMode::HalfDown => false,
+                            _                      => q % 2 != 0,
+                        },
+                }
+            },
+        };
+
+    if !away       { Some(q) }
+    else if neg    { q.checked_sub(1) }
+    else           { q.checked_add(1) }
+}
+
+impl Decimal {
+    /// Creates the decimal `mant * 10^-scale`. A scale above
+    /// [MAX_SCALE](constant.MAX_SCALE.html) is rounded half to even.
+    pub fn new(mant: i128, scale: u32) -> Self {
+        let d = Decimal { mant, scale: 0 };
+        if scale <= MAX_SCALE {
+            Decimal { mant, scale }
+        } else {
+            let den = pow10(scale - MAX_SCALE).unwrap_or(i128::MAX);
+            let q = round_quotient(mant / den, mant % den, den, RoundingMode::HalfEven)
+                    .unwrap_or(d.mant);
+            Decimal { mant: q, scale: MAX_SCALE }
+        }
+    }
+
+    pub fn from_i64(i: i64) -> Self { Decimal { mant: i as i128, scale: 0 } }
+
+    /// Converts a float to the decimal with the shortest representation
+    /// that converts back to the same float. Returns `None` for NaN,
+    /// infinity and floats beyond the range of decimals.
+    pub fn from_f64(f: f64) -> Option<Self> {
+        if !f.is_finite() { return None; }
+        Self::parse(&f.to_string())
+    }
+
+    /// Parses a decimal like `-12.340` or `.5`. Digits after the
+    /// [MAX_SCALE](constant.MAX_SCALE.html)th fractional digit are rounded
+    /// half to even. Returns `None` for invalid or too big numbers.
+    pub fn parse(s: &str) -> Option<Self> {
+        let s = s.trim();
+        let (neg, s) =
+            if let Some(s) = s.strip_prefix('-')      { (true, s) }
+            else if let Some(s) = s.strip_prefix('+') { (false, s) }
+            else                                      { (false, s) };
+
+        let (int, frac) =
+            match s.find('.') {
+                Some(i) => (&s[..i], &s[(i + 1)..]),
+                None    => (s, ""),
+            };
+        if (int.is_empty() && frac.is_empty())
+           || !int.bytes().chain(frac.bytes()).all(|b| b.is_ascii_digit())
+        {
+            return None;
+        }
+
+        let (frac, rest) = frac.split_at(frac.len().min(MAX_SCALE as usize));
+        let mut mant : i128 = 0;
+        for b in int.bytes().chain(frac.bytes()) {
+            mant = mant.checked_mul(10)?.checked_add(i128::from(b - b'0'))?;
+        }
+
+        if let Some(first) = rest.bytes().next() {
+            let sticky = rest.bytes().skip(1).any(|b| b != b'0');
+            if first > b'5' || (first == b'5' && (sticky || mant % 2 != 0)) {
+                mant = mant.checked_add(1)?;
+            }
+        }
+
+        Some(Decimal {
+            mant:  if neg { -mant } else { mant },
+            scale: frac.len() as u32,
+        })
+    }
+
+    /// The integer mantissa, the value is `mantissa * 10^-scale`.
+    pub fn mantissa(&self) -> i128 { self.mant }
+
+    /// The number of digits after the decimal point.
+    pub fn scale(&self) -> u32 { self.scale }
+
+    pub fn is_zero(&self) -> bool { self.mant == 0 }
+
+    pub fn is_negative(&self) -> bool { self.mant < 0 }
+
+    pub fn abs(&self) -> Self {
+        Decimal { mant: self.mant.checked_abs().unwrap_or(i128::MAX), scale: self.scale }
+    }
+
+    /// Returns the nearest float.
+    pub fn to_f64(self) -> f64 {
+        self.to_string().parse().unwrap_or(0.0)
+    }
+
+    /// Returns the integer part, saturated to the range of an `i64`.
+    pub fn to_i64(self) -> i64 {
+        let int = self.mant / pow10(self.scale).unwrap_or(1);
+        if int > i64::MAX as i128      { i64::MAX }
+        else if int < i64::MIN as i128 { i64::MIN }
+        else                           { int as i64 }
+    }
+
+    /// Compares the decimal exactly with the value of the float `f`.
+    /// Returns `None` if `f` is NaN.
+    pub fn cmp_f64(&self, f: f64) -> Option<Ordering> {
+        if f.is_nan() { return None; }
+        if f.is_infinite() {
+            return Some(if f > 0.0 { Ordering::Less } else { Ordering::Greater });
+        }
+
+        // Rounding to the nearest float keeps the order, only a
+        // decimal that rounds to `f` itself needs to be looked at closer:
+        let near = self.to_f64();
+        if near != f { return near.partial_cmp(&f); }
+
+        // All digits of `f`, cut off at the scale of this decimal:
+        let exact = format!("{:.1074}", f);
+        let (int, frac) = exact.split_at(exact.find('.')?);
+        let (frac, rest) = frac[1..].split_at(self.scale as usize);
+        let cut = Decimal::parse(&format!("{}.{}", int, frac))?;
+
+        match self.cmp(&cut) {
+            Ordering::Equal if rest.bytes().any(|b| b != b'0') =>
+                Some(if f > 0.0 { Ordering::Less } else { Ordering::Greater }),
+            ord => Some(ord),
+        }
+    }
+
+    /// Removes the trailing zeros after the decimal point.
+    pub fn normalize(&self) -> Self {
+        let mut d = *self;
+        while d.scale > 0 && d.mant % 10 == 0 {
+            d.mant  /= 10;
+            d.scale -= 1;
+        }
+        d
+    }
+
+    /// Returns the mantissa of this decimal at the larger `scale`.
+    fn mant_at(&self, scale: u32) -> Option<i128> {
+        self.mant.checked_mul(pow10(scale - self.scale)?)
+    }
+
+    /// Returns both mantissas at the scale of the more precise decimal.
+    fn align(&self, o: &Decimal) -> Option<(i128, i128, u32)> {
+        let scale = self.scale.max(o.scale);
+        Some((self.mant_at(scale)?, o.mant_at(scale)?, scale))
+    }
+
+    pub fn checked_add(self, o: Decimal) -> Option<Self> {
+        let (a, b, scale) = self.align(&o)?;
+        Some(Decimal { mant: a.checked_add(b)?, scale })
+    }
+
+    pub fn checked_sub(self, o: Decimal) -> Option<Self> {
+        let (a, b, scale) = self.align(&o)?;
+        Some(Decimal { mant: a.checked_sub(b)?, scale })
+    }
+
+    pub fn checked_mul(self, o: Decimal) -> Option<Self> {
+        Some(Decimal::new(self.mant.checked_mul(o.mant)?, self.scale + o.scale))
+    }
+
+    /// Divides this decimal by `o`. The result is exact, if it fits into
+    /// [MAX_SCALE](constant.MAX_SCALE.html) digits after the decimal point.
+    /// Otherwise it is rounded half to even. Returns `None` for a
+    /// division by zero or if the result overflows.
+    pub fn checked_div(self, o: Decimal) -> Option<Self> {
+        if o.mant == 0 { return None; }
+
+        // The quotient of the mantissas has the scale `self.scale - o.scale`,
+        // further digits are appended until the remainder is 0.
+        let den       = o.mant;
+        let mut scale = i64::from(self.scale) - i64::from(o.scale);
+        let mut q     = self.mant.checked_div(den)?;
+        let mut r     = self.mant % den;
+        while r != 0 && scale < i64::from(MAX_SCALE) {
+            let r10 = match r.checked_mul(10) { Some(r10) => r10, None => break };
+            let q10 =
+                match q.checked_mul(10).and_then(|q| q.checked_add(r10 / den)) {
+                    Some(q10) => q10,
+                    None      => break,
+                };
+            q      = q10;
+            r      = r10 % den;
+            scale += 1;
+        }
+
+        let mut q = round_quotient(q, r, den, RoundingMode::HalfEven)?;
+        while scale < 0 {
+            q      = q.checked_mul(10)?;
+            scale += 1;
+        }
+
+        Some(Decimal { mant: q, scale: scale as u32 })
+    }
+
+    /// Returns the remainder of the division by `o`, with the sign of
+    /// this decimal. Returns `None` for a division by zero.
+    pub fn checked_rem(self, o: Decimal) -> Option<Self> {
+        let (a, b, scale) = self.align(&o)?;
+        Some(Decimal { mant: a.checked_rem(b)?, scale })
+    }
+
+    /// Raises this decimal to the integer power `exp`.
+    pub fn checked_pow(self, exp: i64) -> Option<Self> {
+        let mut result = Decimal::from_i64(1);
+        let mut base   = self;
+        let mut n      = exp.unsigned_abs();
+        while n > 0 {
+            if n & 1 == 1 { result = result.checked_mul(base)?; }
+            n >>= 1;
+            if n > 0 { base = base.checked_mul(base)?; }
+        }
+
+        if exp < 0 { Decimal::from_i64(1).checked_div(result) }
+        else       { Some(result) }
+    }
+
+    /// Rounds this decimal to `places` digits after the decimal point.
+    /// Decimals with fewer digits are returned unchanged.
+    pub fn round(&self, places: u32, mode: RoundingMode) -> Self {
+        if places >= self.scale { return *self; }
+
+        let den = pow10(self.scale - places).unwrap_or(i128::MAX);
+        match round_quotient(self.mant / den, self.mant % den, den, mode) {
+            Some(mant) => Decimal { mant, scale: places },
+            None       => *self,
+        }
+    }
+
+    /// Returns this decimal with exactly `places` digits after the decimal
+    /// point, rounding with `mode` or appending zeros. Returns `None` if
+    /// `places` exceeds [MAX_SCALE](constant.MAX_SCALE.html) or the
+    /// result overflows.
+    pub fn with_scale(&self, places: u32, mode: RoundingMode) -> Option<Self> {
+        if places > MAX_SCALE { return None; }
+        if places <= self.scale { return Some(self.round(places, mode)); }
+        Some(Decimal { mant: self.mant_at(places)?, scale: places })
+    }
+}
+
+impl PartialEq for Decimal {
+    fn eq(&self, o: &Decimal) -> bool { self.cmp(o) == Ordering::Equal }
+}
+
+impl Eq for Decimal { }
+
+impl PartialOrd for Decimal {
+    fn partial_cmp(&self, o: &Decimal) -> Option<Ordering> { Some(self.cmp(o)) }
+}
+
+impl Ord for Decimal {
+    fn cmp(&self, o: &Decimal) -> Ordering {
+        if self.scale == o.scale { return self.mant.cmp(&o.mant); }
+
+        let (lo, hi, flip) =
+            if self.scale < o.scale { (self, o, false) }
+            else                    { (o, self, true) };
+
+        let ord =
+            match lo.mant_at(hi.scale) {
+                Some(m) => m.cmp(&hi.mant),
+                // Too big to be rescaled, so it's further
+                // from zero than `hi` can be:
+                None    => lo.mant.cmp(&0),
+            };
+
+        if flip { ord.reverse() } else { ord }
+    }
+}
+
+impl fmt::Display for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let digits = self.mant.unsigned_abs().to_string();
+        let sign   = if self.mant < 0 { "-" } else { "" };
+        if self.scale == 0 {
+            return write!(f, "{}{}", sign, digits);
+        }
+
+        let scale  = self.scale as usize;
+        let digits =
+            if digits.len() <= scale { "0".repeat(scale + 1 - digits.len()) + &digits }
+            else                     { digits };
+        let (int, frac) = digits.split_at(digits.len() - scale);
+        write!(f, "{}{}.{}", sign, int, frac)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn d(s: &str) -> Decimal { Decimal::parse(s).unwrap() }
+
+    #[test]
+    fn check_decimal_parse_display() {
+        assert_eq!(d("1.50").to_string(),   "1.50");
+        assert_eq!(d("-0.05").to_string(),  "-0.05");
+        assert_eq!(d(".5").to_string(),     "0.5");
+        assert_eq!(d("12.").to_string(),    "12");
+        assert_eq!(d("+7").to_string(),     "7");
+        assert_eq!(d("0.12345678901234567890123456785").to_string(),
+                   "0.1234567890123456789012345678");
+        assert_eq!(d("0.12345678901234567890123456775").to_string(),
+                   "0.1234567890123456789012345678");
+        assert!(Decimal::parse("").is_none());
+        assert!(Decimal::parse(".").is_none());
+        assert!(Decimal::parse("1e5").is_none());
+        assert!(Decimal::parse("1.2.3").is_none());
+        assert!(Decimal::parse("999999999999999999999999999999999999999").is_none());
+        assert_eq!(Decimal::from_f64(0.1).unwrap().to_string(), "0.1");
+        assert!(Decimal::from_f64(f64::NAN).is_none());
+    }
+
+    #[test]
+    fn check_decimal_arith() {
+        assert_eq!(d("0.1").checked_add(d("0.2")).unwrap().to_string(),  "0.3");
+        assert_eq!(d("1.10").checked_sub(d("0.1")).unwrap().to_string(), "1.00");
+        assert_eq!(d("1.5").checked_mul(d("1.5")).unwrap().to_string(),  "2.25");
+        assert_eq!(d("1").checked_div(d("3")).unwrap().to_string(),
+                   "0.3333333333333333333333333333");
+        assert_eq!(d("2").checked_div(d("3")).unwrap().to_string(),
+                   "0.6666666666666666666666666667");
+        assert_eq!(d("1.00").checked_div(d("4")).unwrap().to_string(), "0.25");
+        assert_eq!(d("100").checked_div(d("0.01")).unwrap().to_string(), "10000");
+        assert_eq!(d("-7.5").checked_rem(d("2")).unwrap().to_string(),  "-1.5");
+        assert_eq!(d("1.1").checked_pow(2).unwrap().to_string(),        "1.21");
+        assert_eq!(d("2").checked_pow(-2).unwrap().to_string(),         "0.25");
+        assert!(d("1").checked_div(d("0")).is_none());
+        assert!(d("1").checked_rem(d("0.0")).is_none());
+        assert!(d("10").checked_pow(39).is_none());
+    }
+
+    #[test]
+    fn check_decimal_round_cmp() {
+        let r = |s: &str, p: u32, m: RoundingMode| d(s).round(p, m).to_string();
+        assert_eq!(r("2.345",  2, RoundingMode::HalfEven), "2.34");
+        assert_eq!(r("2.355",  2, RoundingMode::HalfEven), "2.36");
+        assert_eq!(r("2.345",  2, RoundingMode::HalfUp),   "2.35");
+        assert_eq!(r("2.345",  2, RoundingMode::HalfDown), "2.34");
+        assert_eq!(r("-2.341", 2, RoundingMode::Up),       "-2.35");
+        assert_eq!(r("-2.349", 2, RoundingMode::Down),     "-2.34");
+        assert_eq!(r("-2.341", 2, RoundingMode::Floor),    "-2.35");
+        assert_eq!(r("-2.349", 2, RoundingMode::Ceiling),  "-2.34");
+        assert_eq!(r("2.3",    2, RoundingMode::Up),       "2.3");
+        assert_eq!(d("2.3").with_scale(3, RoundingMode::Up).unwrap().to_string(), "2.300");
+        assert_eq!(d("2.35").with_scale(1, RoundingMode::Up).unwrap().to_string(), "2.4");
+        assert!(d("2.3").with_scale(29, RoundingMode::Up).is_none());
+
+        assert!(d("1.50") == d("1.5"));
+        assert!(d("1.49") <  d("1.5"));
+        assert!(d("-2")   <  d("-1.99"));
+        assert!(d("170141183460469231731687303715884105727") > d("0.5"));
+        assert!(d("-170141183460469231731687303715884105727") < d("0.5"));
+
+        assert_eq!(d("0.5").cmp_f64(0.5),                    Some(Ordering::Equal));
+        assert_eq!(d("0.1").cmp_f64(0.1),                    Some(Ordering::Less));
+        assert_eq!(d("-0.1").cmp_f64(-0.1),                  Some(Ordering::Greater));
+        assert_eq!(d("0.1000000000000000055511151231").cmp_f64(0.1),
+                   Some(Ordering::Less));
+        assert_eq!(d("0.1000000000000000055511151232").cmp_f64(0.1),
+                   Some(Ordering::Greater));
+        assert_eq!(d("9007199254740993").cmp_f64(9007199254740992.0),
+                   Some(Ordering::Greater));
+        assert_eq!(d("0").cmp_f64(-0.0),                     Some(Ordering::Equal));
+        assert_eq!(d("1").cmp_f64(f64::INFINITY),            Some(Ordering::Less));
+        assert_eq!(d("1").cmp_f64(1e300),                    Some(Ordering::Less));
+        assert_eq!(d("1").cmp_f64(f64::NAN),                 None);
+    }
+}
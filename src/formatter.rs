@@ -0,0 +1,432 @@
+// Copyright (c) 2020 Weird Constructor <weirdconstructor@gmail.com>
+// This is a part of WLambda. See README.md and COPYING for details.
+
+/*!
+Implements the Rust like format specifications that are used by
+the `$F"..."` string interpolation literal and by `std:str:format`.
+
+A format string consists of literal text and placeholders in curly
+braces. `{{` and `}}` are escapes for literal braces:
+
+```txt
+    placeholder = "{", [ argument ], [ ":", spec ], "}"
+    spec        = [ [ fill ], align ], [ "+" ], [ "#" ], [ "0" ],
+                  [ width ], [ ".", precision ], [ type ]
+    fill        = ?any character?
+    align       = "<" | "^" | ">"
+    width       = integer
+    precision   = integer
+    type        = "?" | "x" | "X" | "b" | "o" | "e" | "E"
+```
+*/
+
+use crate::vval::VVal;
+use crate::decimal::RoundingMode;
+
+/// The alignment of a formatted value inside it's field width.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FmtAlign {
+    Left,
+    Center,
+    Right,
+}
+
+/// The representation a value is formatted in.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FmtType {
+    /// `s_raw()` of the value, floats respect the precision.
+    Display,
+    /// `s()` of the value, selected by `?`.
+    Debug,
+    /// Lower case hexadecimal integer, selected by `x`.
+    HexLower,
+    /// Upper case hexadecimal integer, selected by `X`.
+    HexUpper,
+    /// Binary integer, selected by `b`.
+    Binary,
+    /// Octal integer, selected by `o`.
+    Octal,
+    /// Lower case exponent form of a float, selected by `e`.
+    ExpLower,
+    /// Upper case exponent form of a float, selected by `E`.
+    ExpUpper,
+}
+
+/// A parsed format specification, the part after the `:` in
+/// a placeholder like `{x:>8.2}`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FormatSpec {
+    pub fill:      char,
+    pub align:     Option<FmtAlign>,
+    pub sign:      bool,
+    pub alternate: bool,
+    pub zero_pad:  bool,
+    pub width:     Option<usize>,
+    pub precision: Option<usize>,
+    pub typ:       FmtType,
+}
+
+impl Default for FormatSpec {
+    fn default() -> Self {
+        Self {
+            fill:      ' ',
+            align:     None,
+            sign:      false,
+            alternate: false,
+            zero_pad:  false,
+            width:     None,
+            precision: None,
+            typ:       FmtType::Display,
+        }
+    }
+}
+
+fn char_to_align(c: char) -> Option<FmtAlign> {
+    match c {
+        '<' => Some(FmtAlign::Left),
+        '^' => Some(FmtAlign::Center),
+        '>' => Some(FmtAlign::Right),
+        _   => None,
+    }
+}
+
+/// The largest width and precision a format spec may have, so
+/// that a typo in a format string can't allocate huge strings.
+pub const MAX_FMT_WIDTH: usize = 0xFFFF;
+
+fn take_number(chars: &[char], pos: &mut usize) -> Option<usize> {
+    let start = *pos;
+    while *pos < chars.len() && chars[*pos].is_ascii_digit() {
+        *pos += 1;
+    }
+    if start == *pos { return None; }
+    // Numbers too large for usize are caught by the limit check:
+    Some(chars[start..*pos].iter().collect::<String>()
+            .parse::<usize>().unwrap_or(usize::MAX))
+}
+
+impl FormatSpec {
+    /// Parses a format specification like `>8.2` or `#010x`.
+    /// A width or precision above `MAX_FMT_WIDTH` is an error.
+    pub fn parse(spec: &str) -> Result<FormatSpec, String> {
+        let fs = FormatSpec::parse_syntax(spec)?;
+        for (what, n) in [("width", fs.width), ("precision", fs.precision)] {
+            if n.unwrap_or(0) > MAX_FMT_WIDTH {
+                return Err(format!(
+                    "bad format spec '{}': {} larger than {}",
+                    spec, what, MAX_FMT_WIDTH));
+            }
+        }
+        Ok(fs)
+    }
+
+    /// Parses the syntax of a format specification, without checking
+    /// the limits of the width and precision.
+    fn parse_syntax(spec: &str) -> Result<FormatSpec, String> {
+        let chars : Vec<char> = spec.chars().collect();
+        let mut fs  = FormatSpec::default();
+        let mut pos = 0;
+
+        if chars.len() > 1 && char_to_align(chars[1]).is_some() {
+            fs.fill  = chars[0];
+            fs.align = char_to_align(chars[1]);
+            pos = 2;
+        } else if !chars.is_empty() && char_to_align(chars[0]).is_some() {
+            fs.align = char_to_align(chars[0]);
+            pos = 1;
+        }
+
+        if pos < chars.len() && chars[pos] == '+' {
+            fs.sign = true;
+            pos += 1;
+        }
+        if pos < chars.len() && chars[pos] == '#' {
+            fs.alternate = true;
+            pos += 1;
+        }
+        if pos < chars.len() && chars[pos] == '0' {
+            fs.zero_pad = true;
+            pos += 1;
+        }
+
+        fs.width = take_number(&chars, &mut pos);
+
+        if pos < chars.len() && chars[pos] == '.' {
+            pos += 1;
+            fs.precision = take_number(&chars, &mut pos);
+            if fs.precision.is_none() {
+                return Err(format!(
+                    "bad format spec '{}': expected precision after '.'", spec));
+            }
+        }
+
+        if pos < chars.len() {
+            fs.typ =
+                match chars[pos] {
+                    '?' => FmtType::Debug,
+                    'x' => FmtType::HexLower,
+                    'X' => FmtType::HexUpper,
+                    'b' => FmtType::Binary,
+                    'o' => FmtType::Octal,
+                    'e' => FmtType::ExpLower,
+                    'E' => FmtType::ExpUpper,
+                    c => {
+                        return Err(format!(
+                            "bad format spec '{}': unknown format type '{}'",
+                            spec, c));
+                    },
+                };
+            pos += 1;
+        }
+
+        if pos < chars.len() {
+            return Err(format!(
+                "bad format spec '{}': unexpected '{}'",
+                spec, chars[pos..].iter().collect::<String>()));
+        }
+
+        Ok(fs)
+    }
+
+    /// Formats the value `v` according to this specification.
+    pub fn format(&self, v: &VVal) -> String {
+        let (prefix, body, numeric) =
+            match self.typ {
+                FmtType::Debug => (String::new(), v.s(), false),
+                FmtType::HexLower => self.int_parts(v, "0x", |i| format!("{:x}", i)),
+                FmtType::HexUpper => self.int_parts(v, "0x", |i| format!("{:X}", i)),
+                FmtType::Binary   => self.int_parts(v, "0b", |i| format!("{:b}", i)),
+                FmtType::Octal    => self.int_parts(v, "0o", |i| format!("{:o}", i)),
+                FmtType::ExpLower | FmtType::ExpUpper => {
+                    let f = v.f();
+                    let s =
+                        match self.precision {
+                            Some(p) => format!("{:.*e}", p, f.abs()),
+                            None    => format!("{:e}", f.abs()),
+                        };
+                    let s =
+                        if self.typ == FmtType::ExpUpper { s.to_uppercase() }
+                        else { s };
+                    (self.sign_prefix(f < 0.0), s, true)
+                },
+                FmtType::Display => {
+                    match v {
+                        VVal::Int(i) if self.precision.is_none() => {
+                            (self.sign_prefix(*i < 0),
+                             i.unsigned_abs().to_string(), true)
+                        },
+                        VVal::Dec(d) => {
+                            let d =
+                                match self.precision {
+                                    Some(p) =>
+                                        d.with_scale(p as u32, RoundingMode::HalfEven)
+                                         .unwrap_or_else(|| d.round(p as u32, RoundingMode::HalfEven)),
+                                    None    => **d,
+                                };
+                            (self.sign_prefix(d.is_negative()), d.abs().to_string(), true)
+                        },
+                        VVal::Int(_) | VVal::Flt(_) => {
+                            let f = v.f();
+                            let s =
+                                match self.precision {
+                                    Some(p) => format!("{:.*}", p, f.abs()),
+                                    None    => f.abs().to_string(),
+                                };
+                            (self.sign_prefix(f < 0.0), s, true)
+                        },
+                        _ => {
+                            let s = v.s_raw();
+                            let s =
+                                match self.precision {
+                                    Some(p) => s.chars().take(p).collect(),
+                                    None    => s,
+                                };
+                            (String::new(), s, false)
+                        },
+                    }
+                },
+            };
+
+        let width = self.width.unwrap_or(0);
+        let len   = prefix.chars().count() + body.chars().count();
+        if len >= width {
+            return prefix + &body;
+        }
+        let pad = width - len;
+
+        if numeric && self.zero_pad {
+            return prefix + &"0".repeat(pad) + &body;
+        }
+
+        let align =
+            self.align.unwrap_or(
+                if numeric { FmtAlign::Right } else { FmtAlign::Left });
+        let fill = self.fill.to_string();
+
+        match align {
+            FmtAlign::Left   => prefix + &body + &fill.repeat(pad),
+            FmtAlign::Right  => fill.repeat(pad) + &prefix + &body,
+            FmtAlign::Center =>
+                fill.repeat(pad / 2) + &prefix + &body
+                + &fill.repeat(pad - pad / 2),
+        }
+    }
+
+    fn sign_prefix(&self, negative: bool) -> String {
+        if negative       { String::from("-") }
+        else if self.sign { String::from("+") }
+        else              { String::new() }
+    }
+
+    fn int_parts<F>(&self, v: &VVal, radix_prefix: &str, fmt: F) -> (String, String, bool)
+        where F: Fn(i64) -> String
+    {
+        let mut prefix = self.sign_prefix(false);
+        if self.alternate { prefix += radix_prefix; }
+        (prefix, fmt(v.i()), true)
+    }
+}
+
+/// A piece of a parsed format string.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FmtPiece {
+    /// Literal text, with `{{` and `}}` already unescaped.
+    Lit(String),
+    /// A placeholder with it's contents, that still need to be split
+    /// into the argument and format specification.
+    Arg(String),
+}
+
+/// Returns the ways to split the contents of a placeholder into the
+/// argument and the format specification, in the order they should be
+/// tried. Every `:` that is not nested inside parenthesis, brackets,
+/// braces or strings, that has a non empty argument before it and
+/// a valid format specification after it is a candidate, the last one
+/// first. The last candidate is always the whole contents without a
+/// format specification.
+///
+/// The parser takes the first candidate whose argument parses as a
+/// complete expression. So `{x:>8}` formats `x` right aligned, while
+/// `{:e}` is the symbol `:e` and `{std:str:len s}` is a call. A variable
+/// like `x:b`, that ends in a valid format specification, has to be put
+/// in parenthesis: `{(x:b)}`.
+pub fn arg_spec_candidates(content: &str) -> Vec<(String, Option<String>)> {
+    let chars : Vec<char> = content.chars().collect();
+    let mut depth     = 0;
+    let mut in_string = false;
+    let mut colons    = vec![];
+
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if in_string {
+            if c == '\\' { i += 1; }
+            else if c == '"' { in_string = false; }
+        } else {
+            match c {
+                '"'             => { in_string = true; },
+                '(' | '[' | '{' => { depth += 1; },
+                ')' | ']' | '}' => { depth -= 1; },
+                ':' if depth == 0 => { colons.push(i); },
+                _ => (),
+            }
+        }
+        i += 1;
+    }
+
+    let mut candidates = vec![];
+    for colon in colons.iter().rev() {
+        let arg  : String = chars[0..*colon].iter().collect();
+        let spec : String = chars[(colon + 1)..].iter().collect();
+        if !arg.trim().is_empty() && FormatSpec::parse_syntax(&spec).is_ok() {
+            candidates.push((arg, Some(spec)));
+        }
+    }
+    candidates.push((content.to_string(), None));
+
+    candidates
+}
+
+/// Splits the contents of a placeholder like `name:>8.2` at the first `:`
+/// into the argument and the format specification. In contrast to
+/// `arg_spec_candidates` a malformed format specification is an error.
+pub fn split_arg_spec_strict(content: &str) -> Result<(String, Option<FormatSpec>), String> {
+    match content.find(':') {
+        Some(idx) => {
+            let spec = FormatSpec::parse(&content[(idx + 1)..])?;
+            Ok((content[0..idx].trim().to_string(), Some(spec)))
+        },
+        None => Ok((content.trim().to_string(), None)),
+    }
+}
+
+/// Parses a format string into literal text and placeholders.
+/// Braces inside placeholders must be balanced, so that placeholders
+/// can contain WLambda expressions with blocks and map literals.
+pub fn parse_format_string(fmt: &str) -> Result<Vec<FmtPiece>, String> {
+    let chars : Vec<char> = fmt.chars().collect();
+    let mut pieces = vec![];
+    let mut lit    = String::new();
+
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '{' if i + 1 < chars.len() && chars[i + 1] == '{' => {
+                lit.push('{');
+                i += 2;
+            },
+            '}' if i + 1 < chars.len() && chars[i + 1] == '}' => {
+                lit.push('}');
+                i += 2;
+            },
+            '}' => {
+                return Err(format!(
+                    "unmatched '}}' at char {} in format string", i));
+            },
+            '{' => {
+                let start     = i + 1;
+                let mut depth = 1;
+                let mut in_string = false;
+                i += 1;
+                while i < chars.len() {
+                    let c = chars[i];
+                    if in_string {
+                        if c == '\\' { i += 1; }
+                        else if c == '"' { in_string = false; }
+                    } else if c == '"' {
+                        in_string = true;
+                    } else if c == '{' {
+                        depth += 1;
+                    } else if c == '}' {
+                        depth -= 1;
+                        if depth == 0 { break; }
+                    }
+                    i += 1;
+                }
+
+                if i >= chars.len() {
+                    return Err(format!(
+                        "unterminated placeholder at char {} in format string",
+                        start - 1));
+                }
+
+                if !lit.is_empty() {
+                    pieces.push(FmtPiece::Lit(std::mem::take(&mut lit)));
+                }
+
+                pieces.push(FmtPiece::Arg(chars[start..i].iter().collect()));
+                i += 1;
+            },
+            c => {
+                lit.push(c);
+                i += 1;
+            },
+        }
+    }
+
+    if !lit.is_empty() {
+        pieces.push(FmtPiece::Lit(lit));
+    }
+
+    Ok(pieces)
+}
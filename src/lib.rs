@@ -500,6 +500,57 @@ let res_mul : VVal = ctx.eval("my_crazy_mul 2 4").unwrap();
 assert_eq!(res_mul.i(), 221);
 ```
 
+## Functions with typed parameters
+
+`GlobalEnv::add_typed_func` registers ordinary Rust functions and closures.
+The arguments are converted with the `FromVVal` trait and the return value
+with `IntoVVal`, which are implemented for numbers, strings, `Vec`, `HashMap`,
+`Option`, `Result` and tuples. Mistyped arguments return an error value:
+
+```
+use wlambda::*;
+
+let mut ctx = EvalContext::new_default();
+
+ctx.global.borrow_mut().add_typed_func("avg", |l: Vec<f64>| -> Result<f64, String> {
+    if l.is_empty() { return Err("empty list".to_string()); }
+    Ok(l.iter().sum::<f64>() / l.len() as f64)
+});
+
+assert_eq!(ctx.eval("avg $[1, 2, 4.5]").unwrap().f(), 2.5);
+assert!(ctx.eval("avg $[]").unwrap().is_err());
+assert!(ctx.eval("avg $[1, \"x\"]").unwrap().is_err());
+```
+
+## Exposing Rust structs
+
+The companion crate `wlambda_derive` (also re-exported with the `derive`
+feature) provides `#[derive(WLambdaUserData)]` for structs and the
+`#[wlambda_methods]` attribute for their impl blocks. The struct is then
+passed to WLambda in a shared `UserDataRef`, where its fields can be read
+and assigned and its methods called:
+
+```
+# #[cfg(feature="derive")] {
+use wlambda::*;
+
+#[derive(WLambdaUserData)]
+#[wlambda(methods)]
+struct Point { x: i64, y: i64 }
+
+#[wlambda_methods]
+impl Point {
+    fn len2(&self) -> i64 { self.x * self.x + self.y * self.y }
+}
+
+let mut ctx = EvalContext::new_default();
+ctx.set_global_var("p", &Point { x: 3, y: 4 }.into());
+
+assert_eq!(ctx.eval("p.x = 6; p.len2[]").unwrap().i(), 52);
+assert_eq!(ctx.eval("p").unwrap().s(), "$<Point{x=6,y=4}>");
+# }
+```
+
 ## Maintaining state
 
 ```
@@ -516,6 +567,31 @@ let r = ctx.eval("x + y").unwrap();
 assert_eq!(r.s(), "42");
 ```
 
+## Converting Rust values with serde
+
+With the `serde` feature `to_vval` and `from_vval` convert any Rust value,
+that implements `Serialize` or `Deserialize`, directly to and from a VVal.
+Errors name the key path of the offending value:
+
+```
+use wlambda::*;
+use std::collections::HashMap;
+
+let mut ctx = EvalContext::new_default();
+
+let mut limits : HashMap<String, u8> = HashMap::new();
+limits.insert("retries".to_string(), 3);
+ctx.set_global_var("limits", &to_vval(&limits).unwrap());
+
+let r = ctx.eval("${ retries = limits.retries * 2 }").unwrap();
+let r : HashMap<String, u8> = from_vval(&r).unwrap();
+assert_eq!(r["retries"], 6);
+
+let r = ctx.eval("${ retries = limits.retries * 100 }").unwrap();
+assert_eq!(from_vval::<HashMap<String, u8>>(&r).unwrap_err().to_string(),
+           "retries: invalid value: integer `300`, expected u8");
+```
+
 # Possible Roadmap
 
 There are several things that can be added more or less easily to
@@ -622,6 +698,13 @@ pub mod threads;
 pub mod util;
 pub mod vval_user_obj;
 pub mod csv;
+pub mod formatter;
+pub mod decimal;
+pub mod numarray;
+pub mod nvec;
+pub mod vval_convert;
+#[cfg(feature="serde")]
+pub mod serde_vval;
 
 pub use vval::VVal;
 pub use vval::Env;
@@ -631,3 +714,8 @@ pub use compiler::GlobalEnv;
 pub use compiler::EvalContext;
 pub use compiler::eval;
 pub use compiler::SymbolTable;
+pub use vval_convert::{FromVVal, IntoVVal};
+#[cfg(feature="derive")]
+pub use wlambda_derive::{WLambdaUserData, wlambda_methods};
+#[cfg(feature="serde")]
+pub use serde_vval::{to_vval, from_vval};
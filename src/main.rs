@@ -8,6 +8,11 @@ mod prelude;
 mod util;
 mod vval_user_obj;
 mod csv;
+mod formatter;
+mod decimal;
+mod numarray;
+mod nvec;
+mod vval_convert;
 
 use vval::Env;
 use vval::VVal;
@@ -0,0 +1,362 @@
+// Copyright (c) 2020 Weird Constructor <weirdconstructor@gmail.com>
+// This is a part of WLambda. See README.md and COPYING for details.
+
+/*!
+Typed arrays of numbers, used for the `VVal::NArr` values.
+
+A [NumArray](enum.NumArray.html) stores its elements unboxed in a
+`Vec<f64>` or `Vec<i64>`. The arithmetic operations work element wise,
+either with another array of the same length or with a single number:
+
+```
+use wlambda::numarray::{NumArray, ArrayOp};
+
+let a = NumArray::Flt(vec![1.0, 2.0, 3.0]);
+let b = NumArray::Flt(vec![0.5, 0.5, 0.5]);
+assert_eq!(a.binop(ArrayOp::Mul, &b).unwrap().to_string(), "$<f64[0.5,1,1.5]>");
+assert_eq!(a.dot(&b).unwrap().to_string(), "3");
+```
+*/
+
+use std::fmt;
+
+/// A single element of a [NumArray](enum.NumArray.html).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Num {
+    Flt(f64),
+    Int(i64),
+}
+
+impl Num {
+    pub fn f(self) -> f64 {
+        match self { Num::Flt(f) => f, Num::Int(i) => i as f64 }
+    }
+
+    pub fn i(self) -> i64 {
+        match self { Num::Flt(f) => f as i64, Num::Int(i) => i }
+    }
+}
+
+impl fmt::Display for Num {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Num::Flt(v) => write!(f, "{}", v),
+            Num::Int(v) => write!(f, "{}", v),
+        }
+    }
+}
+
+/// An array of floats or integers.
+#[derive(Debug, Clone, PartialEq)]
+pub enum NumArray {
+    Flt(Vec<f64>),
+    Int(Vec<i64>),
+}
+
+/// The element wise arithmetic operations of
+/// [NumArray::binop](enum.NumArray.html#method.binop).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ArrayOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+}
+
+impl ArrayOp {
+    /// Returns the WLambda operator for this operation.
+    pub fn symbol(self) -> &'static str {
+        match self {
+            ArrayOp::Add => "+",
+            ArrayOp::Sub => "-",
+            ArrayOp::Mul => "*",
+            ArrayOp::Div => "/",
+            ArrayOp::Rem => "%",
+        }
+    }
+
+    /// Applies this operation to two floats.
+    pub fn apply_f64(self, a: f64, b: f64) -> f64 {
+        match self {
+            ArrayOp::Add => a + b,
+            ArrayOp::Sub => a - b,
+            ArrayOp::Mul => a * b,
+            ArrayOp::Div => a / b,
+            ArrayOp::Rem => a % b,
+        }
+    }
+
+    /// Applies this operation to two integers. An overflow or
+    /// a division by 0 returns an error.
+    pub fn apply_i64(self, a: i64, b: i64) -> Result<i64, ArrayError> {
+        let r =
+            match self {
+                ArrayOp::Add => a.checked_add(b),
+                ArrayOp::Sub => a.checked_sub(b),
+                ArrayOp::Mul => a.checked_mul(b),
+                ArrayOp::Div => a.checked_div(b),
+                ArrayOp::Rem => a.checked_rem(b),
+            };
+        match r {
+            Some(r) => Ok(r),
+            None if b == 0 && (self == ArrayOp::Div || self == ArrayOp::Rem)
+                    => Err(ArrayError::DivisionByZero(a, self, b)),
+            None    => Err(ArrayError::Overflow(a, self, b)),
+        }
+    }
+}
+
+/// The errors of the arithmetic operations on a [NumArray](enum.NumArray.html).
+#[derive(Debug, Clone, PartialEq)]
+pub enum ArrayError {
+    /// The arrays have a different number of elements.
+    LengthMismatch(usize, usize),
+    /// An integer operation overflowed.
+    Overflow(i64, ArrayOp, i64),
+    /// An integer was divided by 0.
+    DivisionByZero(i64, ArrayOp, i64),
+}
+
+impl fmt::Display for ArrayError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ArrayError::LengthMismatch(a, b) =>
+                write!(f, "Array length mismatch: {} != {}", a, b),
+            ArrayError::Overflow(a, op, b) =>
+                write!(f, "Integer overflow: {} {} {}", a, op.symbol(), b),
+            ArrayError::DivisionByZero(a, op, b) =>
+                write!(f, "Division by 0: {}{}{}", a, op.symbol(), b),
+        }
+    }
+}
+
+impl NumArray {
+    pub fn len(&self) -> usize {
+        match self {
+            NumArray::Flt(v) => v.len(),
+            NumArray::Int(v) => v.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool { self.len() == 0 }
+
+    pub fn is_float(&self) -> bool { matches!(self, NumArray::Flt(_)) }
+
+    /// Returns the element type name, `"f64"` or `"i64"`.
+    pub fn elem_type(&self) -> &'static str {
+        if self.is_float() { "f64" } else { "i64" }
+    }
+
+    pub fn get(&self, idx: usize) -> Option<Num> {
+        match self {
+            NumArray::Flt(v) => v.get(idx).map(|f| Num::Flt(*f)),
+            NumArray::Int(v) => v.get(idx).map(|i| Num::Int(*i)),
+        }
+    }
+
+    /// Sets the element at `idx` to `n`, converted to the element type.
+    /// The array is filled up with zeros if `idx` is out of range.
+    pub fn set(&mut self, idx: usize, n: Num) {
+        match self {
+            NumArray::Flt(v) => {
+                if v.len() <= idx { v.resize(idx + 1, 0.0); }
+                v[idx] = n.f();
+            },
+            NumArray::Int(v) => {
+                if v.len() <= idx { v.resize(idx + 1, 0); }
+                v[idx] = n.i();
+            },
+        }
+    }
+
+    /// Returns a copy of this array with float elements.
+    pub fn to_float(&self) -> NumArray {
+        match self {
+            NumArray::Flt(v) => NumArray::Flt(v.clone()),
+            NumArray::Int(v) => NumArray::Flt(v.iter().map(|i| *i as f64).collect()),
+        }
+    }
+
+    /// Returns a copy of this array with integer elements.
+    /// Floats are truncated.
+    pub fn to_int(&self) -> NumArray {
+        match self {
+            NumArray::Flt(v) => NumArray::Int(v.iter().map(|f| *f as i64).collect()),
+            NumArray::Int(v) => NumArray::Int(v.clone()),
+        }
+    }
+
+    /// Creates a float array from the little endian encoded 8 byte
+    /// floats in `b`. Returns `None` if the length of `b` is not
+    /// a multiple of 8.
+    pub fn from_le_bytes_f64(b: &[u8]) -> Option<NumArray> {
+        let chunks = b.chunks_exact(8);
+        if !chunks.remainder().is_empty() { return None; }
+        Some(NumArray::Flt(chunks.map(|c| {
+            let mut buf = [0; 8];
+            buf.copy_from_slice(c);
+            f64::from_le_bytes(buf)
+        }).collect()))
+    }
+
+    /// Creates an integer array from the little endian encoded 8 byte
+    /// integers in `b`. Returns `None` if the length of `b` is not
+    /// a multiple of 8.
+    pub fn from_le_bytes_i64(b: &[u8]) -> Option<NumArray> {
+        let chunks = b.chunks_exact(8);
+        if !chunks.remainder().is_empty() { return None; }
+        Some(NumArray::Int(chunks.map(|c| {
+            let mut buf = [0; 8];
+            buf.copy_from_slice(c);
+            i64::from_le_bytes(buf)
+        }).collect()))
+    }
+
+    /// Encodes the elements as little endian 8 byte numbers.
+    pub fn to_le_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(self.len() * 8);
+        match self {
+            NumArray::Flt(v) => for f in v.iter() { out.extend_from_slice(&f.to_le_bytes()); },
+            NumArray::Int(v) => for i in v.iter() { out.extend_from_slice(&i.to_le_bytes()); },
+        }
+        out
+    }
+
+    /// Returns a copy of the elements from `start` up to (excluding)
+    /// `end`. Both are clamped to the length of the array.
+    pub fn slice(&self, start: usize, end: usize) -> NumArray {
+        let end   = end.min(self.len());
+        let start = start.min(end);
+        match self {
+            NumArray::Flt(v) => NumArray::Flt(v[start..end].to_vec()),
+            NumArray::Int(v) => NumArray::Int(v[start..end].to_vec()),
+        }
+    }
+
+    /// Applies `op` element wise to this array and `b`. The result has
+    /// the element type of this array. If `b` has only one element it
+    /// is used for all elements of this array, otherwise both arrays
+    /// need the same length.
+    pub fn binop(&self, op: ArrayOp, b: &NumArray) -> Result<NumArray, ArrayError> {
+        let bl = b.len();
+        if bl != 1 && bl != self.len() {
+            return Err(ArrayError::LengthMismatch(self.len(), bl));
+        }
+
+        match (self, b) {
+            (NumArray::Flt(a), NumArray::Flt(b)) => Ok(NumArray::Flt(
+                a.iter().enumerate()
+                 .map(|(i, x)| op.apply_f64(*x, b[if bl == 1 { 0 } else { i }]))
+                 .collect())),
+            (NumArray::Flt(a), b) => Ok(NumArray::Flt(
+                a.iter().enumerate()
+                 .map(|(i, x)| op.apply_f64(*x, b.elem_f(if bl == 1 { 0 } else { i })))
+                 .collect())),
+            (NumArray::Int(a), b) => {
+                let mut out = Vec::with_capacity(a.len());
+                for (i, x) in a.iter().enumerate() {
+                    out.push(op.apply_i64(*x, b.elem_i(if bl == 1 { 0 } else { i }))?);
+                }
+                Ok(NumArray::Int(out))
+            },
+        }
+    }
+
+    fn elem_f(&self, idx: usize) -> f64 {
+        match self {
+            NumArray::Flt(v) => v[idx],
+            NumArray::Int(v) => v[idx] as f64,
+        }
+    }
+
+    fn elem_i(&self, idx: usize) -> i64 {
+        match self {
+            NumArray::Flt(v) => v[idx] as i64,
+            NumArray::Int(v) => v[idx],
+        }
+    }
+
+    /// Returns the sum of all elements, 0 for an empty array.
+    pub fn sum(&self) -> Result<Num, ArrayError> {
+        match self {
+            NumArray::Flt(v) => Ok(Num::Flt(v.iter().fold(0.0, |s, f| s + f))),
+            NumArray::Int(v) => {
+                let mut sum : i64 = 0;
+                for i in v.iter() {
+                    sum = ArrayOp::Add.apply_i64(sum, *i)?;
+                }
+                Ok(Num::Int(sum))
+            },
+        }
+    }
+
+    /// Returns the smallest element, `None` for an empty array.
+    /// NaN elements of float arrays are ignored.
+    pub fn min(&self) -> Option<Num> {
+        match self {
+            NumArray::Flt(v) =>
+                v.iter().copied().reduce(f64::min).map(Num::Flt),
+            NumArray::Int(v) => v.iter().min().map(|i| Num::Int(*i)),
+        }
+    }
+
+    /// Returns the largest element, `None` for an empty array.
+    /// NaN elements of float arrays are ignored.
+    pub fn max(&self) -> Option<Num> {
+        match self {
+            NumArray::Flt(v) =>
+                v.iter().copied().reduce(f64::max).map(Num::Flt),
+            NumArray::Int(v) => v.iter().max().map(|i| Num::Int(*i)),
+        }
+    }
+
+    /// Returns the arithmetic mean of the elements as float,
+    /// `None` for an empty array.
+    pub fn mean(&self) -> Option<f64> {
+        if self.is_empty() { return None; }
+        let sum : f64 =
+            match self {
+                NumArray::Flt(v) => v.iter().sum(),
+                NumArray::Int(v) => v.iter().map(|i| *i as f64).sum(),
+            };
+        Some(sum / (self.len() as f64))
+    }
+
+    /// Returns the dot product of this array and `b`, which need the
+    /// same length. The result has the element type of this array.
+    pub fn dot(&self, b: &NumArray) -> Result<Num, ArrayError> {
+        if self.len() != b.len() {
+            return Err(ArrayError::LengthMismatch(self.len(), b.len()));
+        }
+
+        match self {
+            NumArray::Flt(a) =>
+                Ok(Num::Flt(a.iter().enumerate()
+                             .map(|(i, x)| x * b.elem_f(i))
+                             .sum())),
+            NumArray::Int(a) => {
+                let mut sum : i64 = 0;
+                for (i, x) in a.iter().enumerate() {
+                    let p = ArrayOp::Mul.apply_i64(*x, b.elem_i(i))?;
+                    sum = ArrayOp::Add.apply_i64(sum, p)?;
+                }
+                Ok(Num::Int(sum))
+            },
+        }
+    }
+}
+
+impl fmt::Display for NumArray {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "$<{}[", self.elem_type())?;
+        for i in 0..self.len() {
+            if i > 0 { write!(f, ",")?; }
+            match self {
+                NumArray::Flt(v) => write!(f, "{}", v[i])?,
+                NumArray::Int(v) => write!(f, "{}", v[i])?,
+            }
+        }
+        write!(f, "]>")
+    }
+}
@@ -0,0 +1,226 @@
+// Copyright (c) 2020 Weird Constructor <weirdconstructor@gmail.com>
+// This is a part of WLambda. See README.md and COPYING for details.
+
+/*!
+Small vectors with 2, 3 or 4 integer or float components, used for the
+integer and float vector values. The components are stored inline, and
+values with 2 or 3 components keep them directly in the `VVal`:
+
+```
+use wlambda::nvec::NVec;
+use wlambda::numarray::ArrayOp;
+
+let a : NVec<f64> = NVec::Vec2(3.0, 4.0);
+assert_eq!(a.mag(), 5.0);
+assert_eq!(a.binop(ArrayOp::Mul, &NVec::Vec2(2.0, 0.5)).unwrap().to_string(), "$f(6,2)");
+assert_eq!(a.swizzle("yx").unwrap().to_string(), "$f(4,3)");
+```
+*/
+
+use std::fmt;
+use crate::numarray::{ArrayOp, ArrayError};
+
+/// The component type of an [NVec](enum.NVec.html), `i64` or `f64`.
+pub trait NVecNum: Copy + PartialEq + Default + fmt::Display {
+    /// The letter of the vector literal, `i` for `$i(...)`
+    /// or `f` for `$f(...)`.
+    const SIGIL: char;
+
+    fn from_f64(f: f64) -> Self;
+    fn from_i64(i: i64) -> Self;
+    fn to_f64(self) -> f64;
+
+    /// Converts this number to the component type `M`.
+    fn convert<M: NVecNum>(self) -> M;
+
+    /// Applies the arithmetic operation `op` to `a` and `b`.
+    fn binop(op: ArrayOp, a: Self, b: Self) -> Result<Self, ArrayError>;
+}
+
+impl NVecNum for i64 {
+    const SIGIL : char = 'i';
+
+    fn from_f64(f: f64) -> Self { f as i64 }
+    fn from_i64(i: i64) -> Self { i }
+    fn to_f64(self) -> f64 { self as f64 }
+    fn convert<M: NVecNum>(self) -> M { M::from_i64(self) }
+
+    fn binop(op: ArrayOp, a: Self, b: Self) -> Result<Self, ArrayError> {
+        op.apply_i64(a, b)
+    }
+}
+
+impl NVecNum for f64 {
+    const SIGIL : char = 'f';
+
+    fn from_f64(f: f64) -> Self { f }
+    fn from_i64(i: i64) -> Self { i as f64 }
+    fn to_f64(self) -> f64 { self }
+    fn convert<M: NVecNum>(self) -> M { M::from_f64(self) }
+
+    fn binop(op: ArrayOp, a: Self, b: Self) -> Result<Self, ArrayError> {
+        Ok(op.apply_f64(a, b))
+    }
+}
+
+/// A vector with 2, 3 or 4 components.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NVec<N: NVecNum> {
+    Vec2(N, N),
+    Vec3(N, N, N),
+    Vec4(N, N, N, N),
+}
+
+/// Returns the component index for the swizzle letters
+/// `x`, `y`, `z`, `w` and `r`, `g`, `b`, `a`.
+fn swizzle_index(c: char) -> Option<usize> {
+    match c {
+        'x' | 'r' => Some(0),
+        'y' | 'g' => Some(1),
+        'z' | 'b' => Some(2),
+        'w' | 'a' => Some(3),
+        _         => None,
+    }
+}
+
+impl<N: NVecNum> NVec<N> {
+    /// Creates a vector from 2 to 4 components. Returns `None`
+    /// for any other number of components.
+    pub fn from_slice(c: &[N]) -> Option<Self> {
+        match *c {
+            [x, y]       => Some(NVec::Vec2(x, y)),
+            [x, y, z]    => Some(NVec::Vec3(x, y, z)),
+            [x, y, z, w] => Some(NVec::Vec4(x, y, z, w)),
+            _            => None,
+        }
+    }
+
+    /// Creates a vector with `dims` components, which are all `n`.
+    pub fn splat(n: N, dims: usize) -> Self {
+        Self::from_slice(&[n; 4][..dims.clamp(2, 4)]).unwrap()
+    }
+
+    /// Returns the number of components.
+    pub fn dims(&self) -> usize {
+        match self {
+            NVec::Vec2(..) => 2,
+            NVec::Vec3(..) => 3,
+            NVec::Vec4(..) => 4,
+        }
+    }
+
+    /// Returns all 4 components, the missing ones are 0.
+    pub fn components(&self) -> [N; 4] {
+        let o = N::default();
+        match *self {
+            NVec::Vec2(x, y)       => [x, y, o, o],
+            NVec::Vec3(x, y, z)    => [x, y, z, o],
+            NVec::Vec4(x, y, z, w) => [x, y, z, w],
+        }
+    }
+
+    /// Returns the component at `idx`, or `None` if the
+    /// vector has fewer components.
+    pub fn get(&self, idx: usize) -> Option<N> {
+        if idx < self.dims() { Some(self.components()[idx]) } else { None }
+    }
+
+    pub fn x(&self) -> N { self.components()[0] }
+
+    /// Converts the components to the component type `M`.
+    pub fn convert<M: NVecNum>(&self) -> NVec<M> {
+        let c = self.components();
+        let c = [c[0].convert(), c[1].convert(), c[2].convert(), c[3].convert()];
+        NVec::from_slice(&c[..self.dims()]).unwrap()
+    }
+
+    /// Returns the component named by the letter `c`, see also
+    /// [swizzle](#method.swizzle).
+    pub fn component(&self, c: char) -> Option<N> {
+        self.get(swizzle_index(c)?)
+    }
+
+    /// Returns a new vector with the components named by the 2 to 4
+    /// letters of `s` in that order. The letters `x`, `y`, `z`, `w`
+    /// and `r`, `g`, `b`, `a` name the first to fourth component.
+    /// Returns `None` if a letter names no component of this vector.
+    pub fn swizzle(&self, s: &str) -> Option<Self> {
+        let mut c   = [N::default(); 4];
+        let mut len = 0;
+        for chr in s.chars() {
+            if len >= 4 { return None; }
+            c[len] = self.component(chr)?;
+            len += 1;
+        }
+        Self::from_slice(&c[..len])
+    }
+
+    /// Applies `op` component wise to this vector and `b`. The
+    /// result has the number of components of this vector, missing
+    /// components of `b` are 0.
+    pub fn binop(&self, op: ArrayOp, b: &Self) -> Result<Self, ArrayError> {
+        let a = self.components();
+        let b = b.components();
+        let mut r = [N::default(); 4];
+        for i in 0..self.dims() {
+            r[i] = N::binop(op, a[i], b[i])?;
+        }
+        Ok(Self::from_slice(&r[..self.dims()]).unwrap())
+    }
+
+    /// Returns the dot product of this vector and `b`,
+    /// missing components of `b` are 0.
+    pub fn dot(&self, b: &Self) -> Result<N, ArrayError> {
+        let a = self.components();
+        let b = b.components();
+        let mut sum = N::default();
+        for i in 0..self.dims() {
+            sum = N::binop(ArrayOp::Add, sum, N::binop(ArrayOp::Mul, a[i], b[i])?)?;
+        }
+        Ok(sum)
+    }
+
+    /// Returns the cross product of this vector and `b` as 3 component
+    /// vector. Only the first 3 components are used, 2 component vectors
+    /// have a z component of 0.
+    pub fn cross(&self, b: &Self) -> Result<Self, ArrayError> {
+        let a = self.components();
+        let b = b.components();
+        let m = |i: usize, j: usize| -> Result<N, ArrayError> {
+            N::binop(ArrayOp::Sub,
+                N::binop(ArrayOp::Mul, a[i], b[j])?,
+                N::binop(ArrayOp::Mul, a[j], b[i])?)
+        };
+        Ok(NVec::Vec3(m(1, 2)?, m(2, 0)?, m(0, 1)?))
+    }
+
+    /// Returns the squared length of this vector.
+    pub fn mag2(&self) -> f64 {
+        self.components()[..self.dims()].iter()
+            .map(|c| c.to_f64() * c.to_f64())
+            .sum()
+    }
+
+    /// Returns the length of this vector.
+    pub fn mag(&self) -> f64 { self.mag2().sqrt() }
+
+    /// Returns this vector scaled to length 1 as float vector.
+    /// A vector of length 0 is returned unchanged.
+    pub fn norm(&self) -> NVec<f64> {
+        let v : NVec<f64> = self.convert();
+        let m = v.mag();
+        if m == 0.0 { return v; }
+        v.binop(ArrayOp::Div, &NVec::splat(m, v.dims())).unwrap()
+    }
+}
+
+impl<N: NVecNum> fmt::Display for NVec<N> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "${}(", N::SIGIL)?;
+        for (i, c) in self.components()[..self.dims()].iter().enumerate() {
+            if i > 0 { write!(f, ",")?; }
+            write!(f, "{}", c)?;
+        }
+        write!(f, ")")
+    }
+}
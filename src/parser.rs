@@ -89,6 +89,9 @@ In the following grammar, white space and comments are omitted:
                   | "Q", ?any character as quote?, { ?any character? }, ?any character as quote?
                     (* but Q generates a byte string instead! *)
                   ;
+    format_string = "F", string (* "{" expr [ ":" format_spec ] "}" placeholders
+                                   are interpolated, see `formatter` module *)
+                  ;
     list_expr     = "*", expr   (* splices the vector result of 'expr'
                                    into the currently parsed list *)
                   | expr
@@ -128,10 +131,40 @@ In the following grammar, white space and comments are omitted:
                   ;
     capture_ref   = ":", var
                   ;
+    iter          = "iter", value
+                    (* creates a lazy iterator over the value *)
+                  ;
+    code          = "code", value
+                    (* returns the AST of the value as data,
+                       see also `ast_to_data` *)
+                  ;
+    pattern       = "_"                          (* matches anything *)
+                  | ident                        (* binds the value *)
+                  | number, ( ".." | "..=" ), number
+                  | number | string | symbol
+                  | "$", special_value           (* a literal like $t or $q/x/ *)
+                  | "(", expr, ")"               (* compared with eqv *)
+                  | "$r", quote_string           (* regex, named groups are bound *)
+                  | "$[", [ vec_pat_elem, { ",", vec_pat_elem } ], "]"
+                  | "${", [ map_pat_elem, { ",", map_pat_elem } ], "}"
+                  ;
+    vec_pat_elem  = pattern
+                  | "*", [ ident ]               (* the remaining elements *)
+                  ;
+    map_pat_elem  = ( ident | symbol | string ), "=", pattern
+                  | ident                        (* binds the key's value *)
+                  | "*", [ ident ]               (* the remaining keys *)
+                  ;
+    match_arm     = pattern, [ "?", expr ], "=>", ( "{", block, "}" | expr )
+                  ;
+    match         = "match", call_no_ops, "{", [ match_arm, { ";", match_arm } ], [ ";" ], "}"
+                    (* the block of an arm is evaluated in place *)
+                  ;
     deref         = "*", value
                   ;
     special_value = byte_string
                   | quote_string
+                  | format_string
                   | list
                   | map
                   | none
@@ -144,6 +177,9 @@ In the following grammar, white space and comments are omitted:
                   | deref
                   | capture_ref
                   | accumulator
+                  | iter
+                  | match
+                  | code
                   ;
     arity_def     = "|", number, "<", number, "|" (* set min/max *)
                   | "|", number, "|"              (* set min and max *)
@@ -187,12 +223,18 @@ In the following grammar, white space and comments are omitted:
     bin_op        = call_no_ops, { op, bin_op } (* precedence parsing is done
                                                    in a Pratt parser style *)
                   ;
+    assign_op     = ( "^" | "*" | "/" | "%" | "-" | "+" | "<<" | ">>"
+                    | "&" | "&^" | "&|" ), "="
+                    (* compound assignment: `x += 1` is `x = x + 1`,
+                       but the target is only evaluated once *)
+                  ;
     arg_list      = "[", [ expr, { ",", expr }, [ "," ] ], "]"
                   | "[[", expr, "]]"  (* apply result vector of expr as argument list *)
                   ;
     field         = ".", ( integer | ident | value ), [ field ]
                   ;
     field_access  = field, "=", expr
+                  | field, assign_op, expr
                   | field, arg_list
                   | field
                   (* please note, that a field access like:
@@ -216,6 +258,11 @@ In the following grammar, white space and comments are omitted:
                   ;
     simple_assign = qident, "=", expr
                   ;
+    op_assign     = [ "*" ], qident, assign_op, expr (* "*" assigns to the
+                                                        referenced value *)
+                  ;
+    field_assign  = qident, field_access (* must end in a field assignment *)
+                  ;
     destr_assign  = "(", [ qident, { ",", qident } ], ")", "=" expr
                   ;
     definition    = [ ref_specifier ], ( simple_assign | destr_assign )
@@ -225,11 +272,18 @@ In the following grammar, white space and comments are omitted:
                   ;
     export        = "!", "@export", symbol, [ "=" ], expr
                   ;
+    macro_def     = "!", "@macro", symbol, "=", expr
+                    (* expr is evaluated at compile time to a function,
+                       that maps the argument ASTs to a new AST *)
+                  ;
     statement     = "!" definition
                   | "." simple_assign
                   | "." destr_assign
+                  | "." op_assign
+                  | "." field_assign
                   | import
                   | export
+                  | macro_def
                   | expr
                   ;
     block         = "{", { statement, ";", {";"}}, [ statement, {";"} ], "}"
@@ -244,6 +298,11 @@ In the following grammar, white space and comments are omitted:
 
 use crate::vval::VVal;
 use crate::vval::Syntax;
+use crate::vval::SynPos;
+use crate::vval::FileRef;
+use crate::decimal::Decimal;
+use crate::formatter;
+use crate::formatter::FmtPiece;
 
 pub mod state;
 
@@ -539,6 +598,60 @@ fn parse_string(ps: &mut State, bytes: bool) -> Result<VVal, ParseError> {
     Ok(vec)
 }
 
+fn parse_format_string(ps: &mut State) -> Result<VVal, ParseError> {
+    if ps.peek().unwrap_or(' ') != '"' {
+        return ps.err_unexpected_token(
+            ps.peek().unwrap_or(' '), "Expected '\"' after '$F'");
+    }
+
+    // Errors in the format string are reported at it's start:
+    let fmt_pos = ps.pos();
+    let fmt     = parse_string(ps, false)?.v_s_raw(1);
+
+    let pieces =
+        match formatter::parse_format_string(&fmt) {
+            Ok(pieces) => pieces,
+            Err(e)     => return ps.sub_state_at(&fmt, fmt_pos).err_bad_value(&e[..]),
+        };
+
+    let fmt_node = ps.syn(Syntax::Fmt);
+    for piece in pieces {
+        match piece {
+            FmtPiece::Lit(s) => {
+                fmt_node.push(VVal::Nul);
+                fmt_node.push(VVal::new_str_mv(s));
+            },
+            FmtPiece::Arg(content) => {
+                if content.trim().is_empty() {
+                    return ps.sub_state_at(&fmt, fmt_pos).err_bad_value(
+                        "Empty placeholder '{}' in format string");
+                }
+
+                // The last candidate is the whole placeholder, so it's
+                // error is reported if no candidate parses.
+                let mut res = Ok(VVal::Nul);
+                for (arg, spec) in formatter::arg_spec_candidates(&content) {
+                    let mut sub_ps = ps.sub_state_at(&arg, fmt_pos);
+                    res = parse_expr(&mut sub_ps);
+                    if res.is_ok() && !sub_ps.at_eof {
+                        res = sub_ps.err_bad_value(
+                            "Unparsed code in format string placeholder");
+                    }
+
+                    if let Ok(expr) = &res {
+                        fmt_node.push(spec.map_or(VVal::Nul, VVal::new_str_mv));
+                        fmt_node.push(expr.clone());
+                        break;
+                    }
+                }
+                res.map_err(|e| e.with_code(&format!("{{{}}}", content)))?;
+            },
+        }
+    }
+
+    Ok(fmt_node)
+}
+
 #[allow(clippy::cast_lossless)]
 fn parse_num(ps: &mut State) -> Result<VVal, ParseError> {
     if ps.at_eof { return ps.err_eof("number"); }
@@ -600,8 +713,27 @@ fn parse_num(ps: &mut State) -> Result<VVal, ParseError> {
         (10, radix_or_num)
     };
 
-    let (is_float, fract_num) = if ps.consume_if_eq('.') {
-        let fract_digits : String = ps.take_while(|c| c.is_digit(radix as u32)).iter().collect();
+    let fract_digits : Option<String> =
+        if ps.consume_if_eq('.') {
+            Some(ps.take_while(|c| c.is_digit(radix as u32)).iter().collect())
+        } else {
+            None
+        };
+
+    if radix == 10 && ps.consume_if_eq('d') {
+        ps.skip_ws_and_comments();
+
+        let digits =
+            format!("{}{}.{}", if sign == -1 { "-" } else { "" },
+                    num, fract_digits.unwrap_or_default());
+        return match Decimal::parse(&digits) {
+            Some(d) => Ok(VVal::new_dec(d)),
+            None    => ps.err_bad_number(&format!(
+                "Couldn't parse decimal number '{}'", digits)),
+        };
+    }
+
+    let (is_float, fract_num) = if let Some(fract_digits) = fract_digits {
         if let Ok(fract_num) = u64::from_str_radix(&fract_digits, radix as u32) {
             (true, (fract_num as f64) / (radix as f64).powf(fract_digits.len() as f64))
         } else {
@@ -659,6 +791,31 @@ fn parse_list(ps: &mut State) -> Result<VVal, ParseError> {
     Ok(list)
 }
 
+/// Parses the 2 to 4 components of a `$i(x, y, ...)` or
+/// `$f(x, y, ...)` vector literal.
+fn parse_nvec(ps: &mut State, syn: Syntax) -> Result<VVal, ParseError> {
+    if !ps.consume_if_eq_wsc('(') {
+        return ps.err_unexpected_token('(', "At vector.");
+    }
+
+    let vec = ps.syn(syn);
+
+    while ps.expect_some(ps.peek())? != ')' {
+        vec.push(parse_expr(ps)?);
+        if !ps.consume_if_eq_wsc(',') { break; }
+    }
+
+    if !ps.consume_if_eq_wsc(')') {
+        return ps.err_unexpected_token(')', "At the end of vector");
+    }
+
+    if vec.len() < 3 || vec.len() > 5 {
+        return ps.err_bad_value("Vectors need 2 to 4 components");
+    }
+
+    Ok(vec)
+}
+
 fn parse_map(ps: &mut State) -> Result<VVal, ParseError> {
     //println!("parse_map [{}]", ps.rest());
     if !ps.consume_if_eq_wsc('{') {
@@ -703,12 +860,257 @@ fn parse_map(ps: &mut State) -> Result<VVal, ParseError> {
 }
 
 
+/// Parses a number literal for a pattern. In contrast to `parse_num`
+/// a `..` following the number is not read as decimal point,
+/// so that ranges like `1..10` are possible.
+fn parse_pattern_num(ps: &mut State) -> Result<VVal, ParseError> {
+    let mut num = String::new();
+    if let Some(c) = ps.peek() {
+        if c == '-' || c == '+' {
+            num.push(c);
+            ps.consume();
+        }
+    }
+    num.extend(ps.take_while(|c| c.is_ascii_alphanumeric()));
+    if ps.lookahead(".") && !ps.lookahead("..") {
+        ps.consume();
+        num.push('.');
+        num.extend(ps.take_while(|c| c.is_ascii_alphanumeric()));
+    }
+
+    let n = parse_num(&mut ps.sub_state(&num))?;
+    ps.skip_ws_and_comments();
+
+    match n {
+        VVal::Int(_) | VVal::Flt(_) => Ok(n),
+        _ => ps.err_bad_value(&format!("Expected number in pattern, got '{}'", num)),
+    }
+}
+
+/// Parses the key of a map pattern, an identifier, symbol or string.
+fn parse_pattern_key(ps: &mut State) -> Result<(String, bool), ParseError> {
+    let c = ps.expect_some(ps.peek())?;
+    if is_ident_start(c) {
+        return Ok((parse_identifier(ps)?, true));
+    }
+
+    let key = parse_value(ps)?;
+    match key.v_(0).get_syn() {
+        Syntax::Key | Syntax::Str => Ok((key.v_s_raw(1), false)),
+        _ => Err(ps.err_bad_value(
+                "Expected identifier, symbol or string as map pattern key")
+                .unwrap_err()),
+    }
+}
+
+/// Parses the optional name after a `*` in a vector or map pattern.
+fn parse_pattern_rest(ps: &mut State) -> Result<VVal, ParseError> {
+    let c = ps.expect_some(ps.peek())?;
+    if is_ident_start(c) {
+        Ok(VVal::new_sym_mv(parse_identifier(ps)?))
+    } else {
+        Ok(VVal::Nul)
+    }
+}
+
+fn parse_pattern(ps: &mut State) -> Result<VVal, ParseError> {
+    let c = ps.expect_some(ps.peek())?;
+
+    let pat = VVal::vec();
+    match c {
+        '0' ..= '9' | '+' | '-' => {
+            let lo = parse_pattern_num(ps)?;
+            let inclusive = ps.consume_lookahead("..=");
+            if inclusive || ps.consume_lookahead("..") {
+                ps.skip_ws_and_comments();
+                pat.push(VVal::new_sym("range"));
+                pat.push(lo);
+                pat.push(parse_pattern_num(ps)?);
+                pat.push(VVal::Bol(inclusive));
+            } else {
+                pat.push(VVal::new_sym("value"));
+                pat.push(lo);
+            }
+        },
+        '"' | ':' => {
+            pat.push(VVal::new_sym("value"));
+            pat.push(parse_value(ps)?);
+        },
+        '(' => {
+            ps.consume_wsc();
+            pat.push(VVal::new_sym("value"));
+            pat.push(parse_expr(ps)?);
+            if !ps.consume_if_eq_wsc(')') {
+                return ps.err_unexpected_token(')', "In pattern expression.");
+            }
+        },
+        '$' => {
+            ps.consume_wsc();
+            match ps.expect_some(ps.peek())? {
+                '[' => {
+                    ps.consume_wsc();
+                    let before = VVal::vec();
+                    let after  = VVal::vec();
+                    let mut rest = None;
+
+                    while ps.expect_some(ps.peek())? != ']' {
+                        if ps.consume_if_eq_wsc('*') {
+                            if rest.is_some() {
+                                return ps.err_bad_value(
+                                    "Only one '*' allowed in a vector pattern");
+                            }
+                            rest = Some(parse_pattern_rest(ps)?);
+                        } else if rest.is_some() {
+                            after.push(parse_pattern(ps)?);
+                        } else {
+                            before.push(parse_pattern(ps)?);
+                        }
+                        if !ps.consume_if_eq_wsc(',') { break; }
+                    }
+
+                    if !ps.consume_if_eq_wsc(']') {
+                        return ps.err_unexpected_token(']', "At the end of vector pattern");
+                    }
+
+                    pat.push(VVal::new_sym("vec"));
+                    pat.push(before);
+                    pat.push(VVal::Bol(rest.is_some()));
+                    pat.push(rest.unwrap_or(VVal::Nul));
+                    pat.push(after);
+                },
+                '{' => {
+                    ps.consume_wsc();
+                    let entries  = VVal::vec();
+                    let mut rest = None;
+
+                    while ps.expect_some(ps.peek())? != '}' {
+                        if ps.consume_if_eq_wsc('*') {
+                            if rest.is_some() {
+                                return ps.err_bad_value(
+                                    "Only one '*' allowed in a map pattern");
+                            }
+                            rest = Some(parse_pattern_rest(ps)?);
+                        } else {
+                            let (key, is_ident) = parse_pattern_key(ps)?;
+                            let key_pat =
+                                if ps.consume_if_eq_wsc('=') {
+                                    parse_pattern(ps)?
+                                } else if is_ident {
+                                    let bind = VVal::vec();
+                                    bind.push(VVal::new_sym("bind"));
+                                    bind.push(VVal::new_sym(&key));
+                                    bind
+                                } else {
+                                    return ps.err_unexpected_token(
+                                        '=', "After reading map pattern key");
+                                };
+                            let entry = VVal::vec();
+                            entry.push(VVal::new_str_mv(key));
+                            entry.push(key_pat);
+                            entries.push(entry);
+                        }
+                        if !ps.consume_if_eq_wsc(',') { break; }
+                    }
+
+                    if !ps.consume_if_eq_wsc('}') {
+                        return ps.err_unexpected_token('}', "At the end of map pattern");
+                    }
+
+                    pat.push(VVal::new_sym("map"));
+                    pat.push(entries);
+                    pat.push(VVal::Bol(rest.is_some()));
+                    pat.push(rest.unwrap_or(VVal::Nul));
+                },
+                'r' => {
+                    ps.consume();
+                    let rx = parse_q_string(ps, false)?;
+                    pat.push(VVal::new_sym("regex"));
+                    pat.push(rx.v_(1));
+                },
+                _ => {
+                    pat.push(VVal::new_sym("value"));
+                    pat.push(parse_special_value(ps)?);
+                },
+            }
+        },
+        _ if is_ident_start(c) => {
+            let id = parse_identifier(ps)?;
+            if id == "_" {
+                pat.push(VVal::new_sym("any"));
+            } else {
+                pat.push(VVal::new_sym("bind"));
+                pat.push(VVal::new_sym_mv(id));
+            }
+        },
+        _ => {
+            return ps.err_bad_value(
+                "Expected pattern: literal, (expr), range, $r regex, \
+                 vector, map, '_' or variable name");
+        },
+    }
+
+    Ok(pat)
+}
+
+fn parse_match(ps: &mut State) -> Result<VVal, ParseError> {
+    let m = ps.syn(Syntax::Match);
+    m.push(parse_call(ps, true)?);
+
+    if !ps.consume_if_eq_wsc('{') {
+        return ps.err_unexpected_token('{', "After the value of $match");
+    }
+
+    while ps.expect_some(ps.peek())? != '}' {
+        m.push(parse_pattern(ps)?);
+
+        if ps.consume_if_eq_wsc('?') {
+            m.push(parse_expr(ps)?);
+        } else {
+            m.push(VVal::Nul);
+        }
+
+        if !ps.consume_lookahead("=>") {
+            return ps.err_unexpected_token('=', "Expected '=>' after pattern");
+        }
+        ps.skip_ws_and_comments();
+
+        if ps.lookahead("{") {
+            let syn   = ps.syn_raw(Syntax::Block);
+            let block = parse_block(ps, true)?;
+            if !block.v_(1).is_none() {
+                return ps.err_bad_value(
+                    "No arity definition allowed in $match arm block, \
+                     use (\\ ...) to return a function");
+            }
+            let body = VVal::vec();
+            body.push(syn);
+            for i in 2..block.len() {
+                body.push(block.v_(i));
+            }
+            m.push(body);
+            ps.consume_if_eq_wsc(';');
+        } else {
+            m.push(parse_expr(ps)?);
+            if !ps.consume_if_eq_wsc(';') && !ps.lookahead("}") {
+                return ps.err_unexpected_token(';', "After $match arm");
+            }
+        }
+    }
+
+    if !ps.consume_if_eq_wsc('}') {
+        return ps.err_unexpected_token('}', "At the end of $match");
+    }
+
+    Ok(m)
+}
+
 fn parse_special_value(ps: &mut State) -> Result<VVal, ParseError> {
     if ps.at_eof { return ps.err_eof("literal value"); }
     let c = ps.expect_some(ps.peek())?;
 
     match c {
         'b' => { ps.consume(); parse_string(ps, true) },
+        'F' => { ps.consume(); parse_format_string(ps) },
         'q' => { ps.consume(); parse_q_string(ps, false) },
         'Q' => { ps.consume(); parse_q_string(ps, true) },
         '[' => parse_list(ps),
@@ -721,6 +1123,37 @@ fn parse_special_value(ps: &mut State) -> Result<VVal, ParseError> {
             }
             Ok(VVal::Nul)
         },
+        'c' => {
+            if ps.consume_lookahead("code") {
+                ps.skip_ws_and_comments();
+                let code = ps.syn(Syntax::Code);
+                code.push(parse_value(ps)?);
+                Ok(code)
+            } else {
+                ps.err_bad_value("Expected special value '$code'")
+            }
+        },
+        'm' => {
+            if ps.consume_lookahead("match") {
+                ps.skip_ws_and_comments();
+                parse_match(ps)
+            } else {
+                ps.err_bad_value("Expected special value '$match'")
+            }
+        },
+        'i' => {
+            if ps.lookahead("i(") {
+                ps.consume();
+                parse_nvec(ps, Syntax::IVec)
+            } else if ps.consume_lookahead("iter") {
+                ps.skip_ws_and_comments();
+                let it = ps.syn(Syntax::Iter);
+                it.push(parse_value(ps)?);
+                Ok(it)
+            } else {
+                ps.err_bad_value("Expected special value '$iter'")
+            }
+        },
         'd' => {
             if ps.consume_lookahead("data") {
                 ps.skip_ws_and_comments();
@@ -746,6 +1179,11 @@ fn parse_special_value(ps: &mut State) -> Result<VVal, ParseError> {
             Ok(VVal::Bol(true))
         },
         'f' => {
+            if ps.lookahead("f(") {
+                ps.consume();
+                return parse_nvec(ps, Syntax::FVec);
+            }
+
             if ps.consume_lookahead("false") {
                 ps.skip_ws_and_comments();
             } else {
@@ -1011,75 +1449,82 @@ fn is_ident_start(c: char) -> bool {
 
 fn parse_value(ps: &mut State) -> Result<VVal, ParseError> {
     //println!("parse_value [{}]", ps.rest());
-    if let Some(c) = ps.peek() {
-        match c {
-            '0' ..= '9' | '+' | '-' => parse_num(ps),
-            '"' => parse_string(ps, false),
-            '$' => { ps.consume_wsc(); parse_special_value(ps) },
-            '(' => {
-                ps.consume_wsc();
-                let expr = parse_expr(ps)?;
-                if !ps.consume_if_eq_wsc(')') {
-                    return ps.err_unexpected_token(')', "In sub expression.");
-                }
-                Ok(expr)
-            },
-            '{' => {
-                let syn = ps.syn_raw(Syntax::Func);
-                let block = parse_block(ps, true)?;
-                block.set_at(0, syn);
-                block.insert_at(1, VVal::Nul);
-                Ok(block)
-            },
-            '\\' => {
-                ps.consume_wsc();
+    let depth = ps.nesting();
+    ps.enter_nesting()?;
 
-                if ps.consume_if_eq_wsc(':') {
+    let value =
+        if let Some(c) = ps.peek() {
+            match c {
+                '0' ..= '9' | '+' | '-' => parse_num(ps),
+                '"' => parse_string(ps, false),
+                '$' => { ps.consume_wsc(); parse_special_value(ps) },
+                '(' => {
+                    ps.consume_wsc();
+                    let expr = parse_expr(ps)?;
+                    if !ps.consume_if_eq_wsc(')') {
+                        return ps.err_unexpected_token(')', "In sub expression.");
+                    }
+                    Ok(expr)
+                },
+                '{' => {
                     let syn = ps.syn_raw(Syntax::Func);
-
-                    let block_name = parse_identifier(ps)?;
-                    ps.skip_ws_and_comments();
                     let block = parse_block(ps, true)?;
-
                     block.set_at(0, syn);
-                    block.insert_at(1, VVal::new_sym_mv(block_name));
+                    block.insert_at(1, VVal::Nul);
                     Ok(block)
-                } else {
-                    let block = ps.syn(Syntax::Func);
+                },
+                '\\' => {
+                    ps.consume_wsc();
 
-                    let arity =
-                        if ps.lookahead("|") { parse_arity(ps)? }
-                        else { VVal::Nul };
+                    if ps.consume_if_eq_wsc(':') {
+                        let syn = ps.syn_raw(Syntax::Func);
 
-                    let next_stmt = parse_stmt(ps)?;
-                    block.push(VVal::Nul);
-                    block.push(arity);
-                    block.push(next_stmt);
-                    Ok(block)
-                }
-            },
-            ':' => {
-                ps.consume_wsc();
-                if ps.lookahead("\"") {
-                    let s = parse_string(ps, false)?;
-                    Ok(make_sym(ps, &s.at(1).unwrap().s_raw()))
-                } else {
+                        let block_name = parse_identifier(ps)?;
+                        ps.skip_ws_and_comments();
+                        let block = parse_block(ps, true)?;
+
+                        block.set_at(0, syn);
+                        block.insert_at(1, VVal::new_sym_mv(block_name));
+                        Ok(block)
+                    } else {
+                        let block = ps.syn(Syntax::Func);
+
+                        let arity =
+                            if ps.lookahead("|") { parse_arity(ps)? }
+                            else { VVal::Nul };
+
+                        let next_stmt = parse_stmt(ps)?;
+                        block.push(VVal::Nul);
+                        block.push(arity);
+                        block.push(next_stmt);
+                        Ok(block)
+                    }
+                },
+                ':' => {
+                    ps.consume_wsc();
+                    if ps.lookahead("\"") {
+                        let s = parse_string(ps, false)?;
+                        Ok(make_sym(ps, &s.at(1).unwrap().s_raw()))
+                    } else {
+                        let id = parse_identifier(ps)?;
+                        Ok(make_sym(ps, &id))
+                    }
+                },
+                _ if is_ident_start(c) => {
                     let id = parse_identifier(ps)?;
-                    Ok(make_sym(ps, &id))
+                    Ok(make_var(ps, &id))
+                },
+                _ => {
+                    ps.err_bad_value("Expected literal value, sub \
+                                     expression, block, key or identifier.")
                 }
-            },
-            _ if is_ident_start(c) => {
-                let id = parse_identifier(ps)?;
-                Ok(make_var(ps, &id))
-            },
-            _ => {
-                ps.err_bad_value("Expected literal value, sub \
-                                 expression, block, key or identifier.")
             }
-        }
-    } else {
-        ps.err_eof("value.")
-    }
+        } else {
+            ps.err_eof("value.")
+        };
+
+    ps.set_nesting(depth);
+    value
 }
 
 fn optimize_get_key(ps: &mut State, obj: VVal, value: VVal) -> VVal {
@@ -1167,6 +1612,7 @@ fn parse_field_access(obj_val: VVal, ps: &mut State) -> Result<VVal, ParseError>
     while let Some(c) = ps.peek() {
         if c != '.' { break; }
 
+        ps.enter_nesting()?;
         ps.consume_wsc();
 
         let c = if let Some(c) = ps.peek() {
@@ -1195,6 +1641,16 @@ fn parse_field_access(obj_val: VVal, ps: &mut State) -> Result<VVal, ParseError>
                 parse_value(ps)?
             };
 
+        if let Some(op) = peek_assign_op(ps) {
+            ps.consume_wsc_n(op.len() + 1);
+            let field_set = ps.syn(Syntax::SetKey);
+            field_set.push(obj);
+            field_set.push(value);
+            field_set.push(parse_expr(ps)?);
+            field_set.push(VVal::new_sym_mv(op));
+            return Ok(field_set);
+        }
+
         if let Some(c) = ps.peek() {
             match c {
                 '=' => {
@@ -1271,6 +1727,20 @@ fn parse_arg_list<'a>(call: &'a mut VVal, ps: &mut State) -> Result<&'a mut VVal
     Ok(call)
 }
 
+/// Checks if a compound assignment operator like `+=` or `&|=`
+/// is under the parse head and returns the binary operator part of it.
+fn peek_assign_op(ps: &mut State) -> Option<String> {
+    let op = ps.peek_op()?;
+    match &op[..] {
+          "+" | "-" | "*" | "/" | "%" | "^"
+        | "<<" | ">>" | "&" | "&^" | "&|" => {
+            if ps.lookahead(&(op.clone() + "=")) { Some(op) }
+            else { None }
+        },
+        _ => None,
+    }
+}
+
 fn get_op_prec(op: &str) -> i32 {
     match op {
         "^"                         => 15,
@@ -1289,6 +1759,7 @@ fn get_op_prec(op: &str) -> i32 {
 }
 
 fn parse_binop(mut left: VVal, ps: &mut State, op: &str) -> Result<VVal, ParseError> {
+    ps.enter_nesting()?;
     let prec = get_op_prec(op);
     let mut right = parse_call(ps, true)?;
 
@@ -1311,6 +1782,10 @@ fn parse_call(ps: &mut State, binop_mode: bool) -> Result<VVal, ParseError> {
     //println!("parse_expr [{}] np={}", ps.rest(), no_pipe);
     let mut value = parse_value(ps)?;
 
+    // The call chain and binary operators below nest the value
+    // parsed so far deeper with each step:
+    let depth = ps.nesting();
+
     // look ahead, if we see an expression delimiter.
     // because then, this is not going to be a call!
     // Also exception to parse_expr, we are excluding the '|'.
@@ -1326,6 +1801,7 @@ fn parse_call(ps: &mut State, binop_mode: bool) -> Result<VVal, ParseError> {
         let op = ps.peek_op();
         match c {
             '[' => {
+                ps.enter_nesting()?;
                 let mut call = make_to_call(ps, value);
                 match parse_arg_list(&mut call, ps) {
                     Ok(_)    => { value = call; },
@@ -1343,6 +1819,7 @@ fn parse_call(ps: &mut State, binop_mode: bool) -> Result<VVal, ParseError> {
                 // We don't set value here, because it will not be
                 // used by '(' or '.' cases anymore!
                 // Those will be covered by parse_expr() presumably.
+                ps.set_nesting(depth);
                 return Ok(res_call);
             },
             ';' | ')' | ',' | ']' | '|' | '}' => {
@@ -1371,6 +1848,7 @@ fn parse_call(ps: &mut State, binop_mode: bool) -> Result<VVal, ParseError> {
         res_call.push(value);
     }
 
+    ps.set_nesting(depth);
     Ok(res_call)
 }
 
@@ -1380,9 +1858,11 @@ fn parse_expr(ps: &mut State) -> Result<VVal, ParseError> {
         return Ok(call);
     }
 
+    let depth = ps.nesting();
     while let Some(c) = ps.peek() {
         match c {
             '|' => {
+                ps.enter_nesting()?;
                 if ps.lookahead("|>") {
                     ps.consume();
                     ps.consume_wsc();
@@ -1434,6 +1914,7 @@ fn parse_expr(ps: &mut State) -> Result<VVal, ParseError> {
             }
         }
     }
+    ps.set_nesting(depth);
 
     Ok(call)
 }
@@ -1490,6 +1971,43 @@ fn parse_assignment(ps: &mut State, is_def: bool) -> Result<VVal, ParseError> {
         _ => { ids.push(VVal::new_sym_mv(parse_identifier(ps)?)); }
     }
 
+    if !is_def && !destructuring {
+        let var_name = ids.v_s_raw(0);
+
+        if ps.lookahead(".") {
+            if assign.v_(0).get_syn() == Syntax::AssignRef {
+                return ps.err_bad_value(
+                    "Can't assign to a field of a dereferenced variable");
+            }
+
+            let depth = ps.nesting();
+            let field_set = parse_field_access(make_var(ps, &var_name), ps)?;
+            ps.set_nesting(depth);
+            if field_set.v_(0).get_syn() != Syntax::SetKey {
+                return ps.err_unexpected_token('=', "In field assignment");
+            }
+            return Ok(field_set);
+        }
+
+        if let Some(op) = peek_assign_op(ps) {
+            ps.consume_wsc_n(op.len() + 1);
+
+            let cur_val =
+                if assign.v_(0).get_syn() == Syntax::AssignRef {
+                    let deref = ps.syn(Syntax::Deref);
+                    deref.push(make_var(ps, &var_name));
+                    deref
+                } else {
+                    make_var(ps, &var_name)
+                };
+
+            let value = parse_expr(ps)?;
+            assign.push(ids);
+            assign.push(make_binop(ps, &op, cur_val, value));
+            return Ok(assign);
+        }
+    }
+
     assign.push(ids);
 
     if !ps.consume_if_eq_wsc('=') {
@@ -1551,8 +2069,20 @@ fn parse_stmt(ps: &mut State) -> Result<VVal, ParseError> {
                                 exp.push(expr);
                                 Ok(exp)
                             },
+                            "macro" => {
+                                let name = parse_identifier(ps)?;
+                                ps.skip_ws_and_comments();
+                                if !ps.consume_if_eq_wsc('=') {
+                                    return ps.err_unexpected_token('=', "In macro definition");
+                                }
+                                let expr = parse_expr(ps)?;
+                                let mac = ps.syn(Syntax::DefMacro);
+                                mac.push(VVal::new_sym(&name));
+                                mac.push(expr);
+                                Ok(mac)
+                            },
                             "dump_stack" => { Ok(ps.syn(Syntax::DumpStack)) },
-                            _ => { ps.err_bad_keyword(&id, "import or export") }
+                            _ => { ps.err_bad_keyword(&id, "import, export or macro") }
                         }
                     } else {
                         parse_assignment(ps, true)
@@ -1682,6 +2212,173 @@ pub fn parse(s: &str, filename: &str) -> Result<VVal, ParseError> {
    parse_block(&mut ps, false)
 }
 
+/// Like `parse`, but returns an error if values are nested deeper
+/// than `max_depth`, for reading untrusted input without
+/// overflowing the stack.
+pub fn parse_with_max_depth(s: &str, filename: &str, max_depth: usize)
+    -> Result<VVal, ParseError>
+{
+    let mut ps = State::new(s, filename);
+    ps.set_max_depth(max_depth);
+    parse_block(&mut ps, false)
+}
+
+/// Converts an AST as returned by `parse` into plain data, that
+/// can be inspected and modified by scripts. Every syntax node
+/// head is turned into a map with the name of the syntax as symbol
+/// and it's position:
+///
+/// ```rust
+/// use wlambda::parser::{parse, ast_to_data};
+///
+/// let ast = parse("x", "file").unwrap();
+/// assert_eq!(
+///     ast_to_data(&ast).s(),
+///     "$[${col=1,file=\"file\",line=1,syn=:\"Block\"},\
+///        $[${col=2,file=\"file\",line=1,syn=:\"Var\"},:\"x\"]]");
+/// ```
+pub fn ast_to_data(ast: &VVal) -> VVal {
+    match ast {
+        VVal::Syn(sp) => {
+            let m = VVal::map();
+            m.set_map_key("syn".to_string(), VVal::new_sym_mv(format!("{:?}", sp.syn)));
+            m.set_map_key("line".to_string(), VVal::Int(i64::from(sp.line)));
+            m.set_map_key("col".to_string(), VVal::Int(i64::from(sp.col)));
+            m.set_map_key("file".to_string(), VVal::new_str(sp.file.s()));
+            if let Some(name) = &sp.name {
+                m.set_map_key("name".to_string(), VVal::new_str(name));
+            }
+            m
+        },
+        VVal::Lst(l) => {
+            VVal::vec_mv(l.borrow().iter().map(ast_to_data).collect())
+        },
+        _ => ast.clone(),
+    }
+}
+
+/// The reverse of `ast_to_data`, turns the maps with a `syn` key
+/// back into syntax nodes, that can be compiled. The `line`, `col` and `file`
+/// keys are optional. All other values, including maps without
+/// a `syn` key, are kept as they are and compile to constants.
+pub fn data_to_ast(data: &VVal) -> Result<VVal, String> {
+    match data {
+        VVal::Map(_) => {
+            let syn = match data.get_key("syn") {
+                Some(syn) => syn.s_raw(),
+                None => return Ok(data.clone()),
+            };
+
+            let syn =
+                match Syntax::from_name(&syn) {
+                    Some(syn) => syn,
+                    None => return Err(format!("Unknown syntax '{}'", syn)),
+                };
+
+            let file =
+                data.get_key("file")
+                    .map(|f| f.s_raw())
+                    .unwrap_or_else(|| "<ast>".to_string());
+
+            Ok(VVal::Syn(SynPos {
+                syn,
+                line: data.get_key("line").map(|v| v.i() as u32).unwrap_or(0),
+                col:  data.get_key("col").map(|v| v.i() as u32).unwrap_or(0),
+                file: FileRef::new(&file),
+                name: data.get_key("name").map(|n| std::rc::Rc::new(n.s_raw())),
+            }))
+        },
+        VVal::Lst(l) => {
+            let mut out = vec![];
+            for v in l.borrow().iter() {
+                out.push(data_to_ast(v)?);
+            }
+            let ast = VVal::vec_mv(out);
+            check_ast_node(&ast)?;
+            Ok(ast)
+        },
+        _ => Ok(data.clone()),
+    }
+}
+
+/// Checks if a syntax node has the elements the compiler expects
+/// for its syntax, so that ASTs built from data by scripts or macros
+/// result in an error instead of crashing the compiler.
+/// Vectors that don't start with a syntax head are not checked.
+pub fn check_ast_node(ast: &VVal) -> Result<(), String> {
+    let syn =
+        match ast.v_(0) {
+            VVal::Syn(sp) => sp.syn,
+            _ => return Ok(()),
+        };
+    let args = ast.len() - 1;
+
+    let min_args =
+        match syn {
+            Syntax::Var | Syntax::CaptureRef | Syntax::Key | Syntax::Str
+            | Syntax::Ref | Syntax::WRef | Syntax::Deref | Syntax::Iter
+            | Syntax::Err | Syntax::Call | Syntax::MapSplice
+            | Syntax::VecSplice
+                => 1,
+            Syntax::GetKey | Syntax::GetSym | Syntax::GetIdx
+            | Syntax::BinOpAdd | Syntax::BinOpSub | Syntax::BinOpMul
+            | Syntax::BinOpDiv | Syntax::BinOpMod | Syntax::BinOpLe
+            | Syntax::BinOpLt | Syntax::BinOpGe | Syntax::BinOpGt
+            | Syntax::Apply | Syntax::Import | Syntax::Export
+            | Syntax::Func | Syntax::DefMacro
+            | Syntax::Def | Syntax::DefGlobRef
+            | Syntax::Assign | Syntax::AssignRef
+                => 2,
+            Syntax::SetKey | Syntax::GetKey2 | Syntax::GetSym2
+            | Syntax::GetIdx2
+                => 3,
+            Syntax::GetKey3 | Syntax::GetSym3 | Syntax::GetIdx3
+                => 4,
+            _   => 0,
+        };
+
+    if args < min_args {
+        return Err(format!(
+            "{:?} node needs at least {} elements, got {}: {}",
+            syn, min_args, args, ast.s()));
+    }
+
+    match syn {
+        Syntax::Def | Syntax::DefGlobRef | Syntax::Assign | Syntax::AssignRef
+            if !ast.v_(1).is_vec() || ast.v_(1).is_empty() =>
+        {
+            return Err(format!(
+                "{:?} node needs a vector of variable names: {}",
+                syn, ast.s()));
+        },
+        Syntax::IVec | Syntax::FVec if !(2..=4).contains(&args) => {
+            return Err(format!(
+                "{:?} node needs 2 to 4 elements, got {}: {}",
+                syn, args, ast.s()));
+        },
+        Syntax::Map => {
+            for i in 1..ast.len() {
+                if !ast.v_(i).is_vec() || ast.v_(i).len() < 2 {
+                    return Err(format!(
+                        "Map node needs key/value pairs: {}", ast.s()));
+                }
+            }
+        },
+        Syntax::Accum if args > 0 => {
+            match &ast.v_s_raw(1)[..] {
+                "@" => (),
+                "string" | "bytes" | "float" | "int" | "map" | "vec" if args >= 2 => (),
+                _ => {
+                    return Err(format!("Bad Accum node: {}", ast.s()));
+                },
+            }
+        },
+        _ => (),
+    }
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1714,6 +2411,12 @@ mod tests {
         assert_eq!(parse("-0xFF.9;"),  "$[&Block,-255.5625]");
         assert_eq!(parse("-0xFF.A;"),  "$[&Block,-255.625]");
         assert_eq!(parse("-0xFF.F;"),  "$[&Block,-255.9375]");
+        assert_eq!(parse("10.50d;"),   "$[&Block,10.50d]");
+        assert_eq!(parse("-0.05d;"),   "$[&Block,-0.05d]");
+        assert_eq!(parse("7d;"),       "$[&Block,7d]");
+        assert_eq!(parse("0.12345678901234567890123456789d;"),
+                   "$[&Block,0.1234567890123456789012345679d]");
+        assert_eq!(parse("0xFd;"),     "$[&Block,253]");
     }
 
     #[test]
@@ -1738,6 +2441,19 @@ mod tests {
                    "$[&Block,$[&Lst,10,$[&Lst,1,2,3],11.23,-30,-255]]");
     }
 
+    #[test]
+    fn check_parse_nvec() {
+        assert_eq!(parse("$i(1, 2);"),          "$[&Block,$[&IVec,1,2]]");
+        assert_eq!(parse("$f(1.5, x, 3, 4);"),  "$[&Block,$[&FVec,1.5,$[&Var,:\"x\"],3,4]]");
+        assert_eq!(parse("$i(1, 2,)"),          "$[&Block,$[&IVec,1,2]]");
+        assert_eq!(parse("$f (1)"),             "$[&Block,$[&Call,$false,1]]");
+        assert_eq!(parse("$iter $i(1, 2)"),     "$[&Block,$[&Iter,$[&IVec,1,2]]]");
+        assert_eq!(parse_error("$i(1)"),
+            "Parse error: error[1,6:<parser_test>] Vectors need 2 to 4 components at code \'\'");
+        assert_eq!(parse_error("$f(1, 2, 3, 4, 5)"),
+            "Parse error: error[1,18:<parser_test>] Vectors need 2 to 4 components at code \'\'");
+    }
+
     #[test]
     fn check_parse_last_commas() {
         assert_eq!(parse("$[10,]"),         "$[&Block,$[&Lst,10]]");
@@ -1882,6 +2598,19 @@ mod tests {
         assert_eq!(parse(". (a,b) = 10"),        "$[&Block,$[&Assign,$[:\"a\",:\"b\"],10,$true]]");
     }
 
+    #[test]
+    fn check_compound_assignments() {
+        assert_eq!(parse(".x += 10"),     "$[&Block,$[&Assign,$[:\"x\"],$[&BinOpAdd,$[&Var,:\"x\"],10]]]");
+        assert_eq!(parse(".x -= 10"),     "$[&Block,$[&Assign,$[:\"x\"],$[&BinOpSub,$[&Var,:\"x\"],10]]]");
+        assert_eq!(parse(".x &|= 1"),     "$[&Block,$[&Assign,$[:\"x\"],$[&Call,$[&Var,:\"&|\"],$[&Var,:\"x\"],1]]]");
+        assert_eq!(parse(".*x *= 2"),     "$[&Block,$[&AssignRef,$[:\"x\"],$[&BinOpMul,$[&Deref,$[&Var,:\"x\"]],2]]]");
+        assert_eq!(parse("o.x += 1"),     "$[&Block,$[&SetKey,$[&Var,:\"o\"],$[&Key,:\"x\"],1,:\"+\"]]");
+        assert_eq!(parse("o.1 <<= 1"),    "$[&Block,$[&SetKey,$[&Var,:\"o\"],1,1,:\"<<\"]]");
+        assert_eq!(parse(".o.x = 1"),     "$[&Block,$[&SetKey,$[&Var,:\"o\"],$[&Key,:\"x\"],1]]");
+        assert_eq!(parse(".o.x.y /= 3"),  "$[&Block,$[&SetKey,$[&GetSym,$[&Var,:\"o\"],:\"x\"],$[&Key,:\"y\"],3,:\"/\"]]");
+        assert_eq!(parse("o.x <= 1"),     "$[&Block,$[&BinOpLe,$[&GetSym,$[&Var,:\"o\"],:\"x\"],1]]");
+    }
+
     #[test]
     fn check_func() {
         assert_eq!(parse("{}"),           "$[&Block,$[&Func,$n,$n]]");
@@ -1943,6 +2672,59 @@ mod tests {
         assert_eq!(parse("o.(1 \"x\").(1 \"y\").(1 \"z\")"), "$[&Block,$[&GetKey3,$[&Var,:\"o\"],$[&Call,1,$[&Str,\"x\"]],$[&Call,1,$[&Str,\"y\"]],$[&Call,1,$[&Str,\"z\"]]]]");
     }
 
+    #[test]
+    fn check_iter() {
+        assert_eq!(parse("$iter $[1,2]"),       "$[&Block,$[&Iter,$[&Lst,1,2]]]");
+        assert_eq!(parse("$iter x | f"),        "$[&Block,$[&Call,$[&Var,:\"f\"],$[&Iter,$[&Var,:\"x\"]]]]");
+    }
+
+    #[test]
+    fn check_macro_def() {
+        assert_eq!(parse("!@macro m = { _ }; m 1"),
+                   "$[&Block,$[&DefMacro,:\"m\",$[&Func,$n,$n,$[&Var,:\"_\"]]],$[&Call,$[&Var,:\"m\"],1]]");
+        assert_eq!(parse_error("!@macro m { _ }"),
+                   "Parse error: error[1,11:<parser_test>] Unexpected token '='. In macro definition at code '{ _ }'");
+    }
+
+    #[test]
+    fn check_code() {
+        assert_eq!(parse("$code 1 + 2"),        "$[&Block,$[&BinOpAdd,$[&Code,1],2]]");
+        assert_eq!(parse("$code (x 1)"),        "$[&Block,$[&Code,$[&Call,$[&Var,:\"x\"],1]]]");
+
+        let ast  = super::parse("$[x, ${a=1}]", "<t>").unwrap();
+        let data = ast_to_data(&ast);
+        assert_eq!(data.v_(1).v_(0).v_k("syn").s(),  ":\"Lst\"");
+        assert_eq!(data.v_(1).v_(0).v_k("file").s(), "\"<t>\"");
+        assert_eq!(data_to_ast(&data).unwrap().s(), ast.s());
+
+        let bad = VVal::map();
+        bad.set_map_key("syn".to_string(), VVal::new_sym("Nope"));
+        assert_eq!(data_to_ast(&bad).unwrap_err(), "Unknown syntax 'Nope'");
+    }
+
+    #[test]
+    fn check_match() {
+        assert_eq!(parse("$match x { _ => 1 }"),
+                   "$[&Block,$[&Match,$[&Var,:\"x\"],$[:\"any\"],$n,1]]");
+        assert_eq!(parse("$match x { a ? a > 1 => a; 1..=3 => 2 }"),
+                   "$[&Block,$[&Match,$[&Var,:\"x\"],$[:\"bind\",:\"a\"],$[&BinOpGt,$[&Var,:\"a\"],1],$[&Var,:\"a\"],$[:\"range\",1,3,$true],$n,2]]");
+        assert_eq!(parse("$match x { $[1, *r] => { r } }"),
+                   "$[&Block,$[&Match,$[&Var,:\"x\"],$[:\"vec\",$[$[:\"value\",1]],$true,:\"r\",$[]],$n,$[&Block,$[&Var,:\"r\"]]]]");
+        assert_eq!(parse("$match x { ${a, :b = _, *} => 1 }"),
+                   "$[&Block,$[&Match,$[&Var,:\"x\"],$[:\"map\",$[$[\"a\",$[:\"bind\",:\"a\"]],$[\"b\",$[:\"any\"]]],$true,$n],$n,1]]");
+        assert_eq!(parse("$match x { $r/a/ => 1; (y) => 2; -1.5..2 => 3 }"),
+                   "$[&Block,$[&Match,$[&Var,:\"x\"],$[:\"regex\",\"a\"],$n,1,$[:\"value\",$[&Var,:\"y\"]],$n,2,$[:\"range\",-1.5,2,$false],$n,3]]");
+    }
+
+    #[test]
+    fn check_format_string() {
+        assert_eq!(parse("$F\"x={x}\""),            "$[&Block,$[&Fmt,$n,\"x=\",$n,$[&Var,:\"x\"]]]");
+        assert_eq!(parse("$F\"{x:>8.2}!\""),        "$[&Block,$[&Fmt,\">8.2\",$[&Var,:\"x\"],$n,\"!\"]]");
+        assert_eq!(parse("$F\"{{{x + 1}}}\""),      "$[&Block,$[&Fmt,$n,\"{\",$n,$[&BinOpAdd,$[&Var,:\"x\"],1],$n,\"}\"]]");
+        assert_eq!(parse("$F\"{std:str:len s:x}\""), "$[&Block,$[&Fmt,\"x\",$[&Call,$[&Var,:\"std:str:len\"],$[&Var,:\"s\"]]]]");
+        assert_eq!(parse("$F\"{ ${a=1}.a }\""),     "$[&Block,$[&Fmt,$n,$[&GetSym,$[&Map,$[:\"a\",1]],:\"a\"]]]");
+    }
+
     #[test]
     fn check_err_val() {
         assert_eq!(parse("$e 10 20 30"),        "$[&Block,$[&Err,$[&Call,10,20,30]]]");
@@ -27,6 +27,8 @@ pub struct State {
         line_no:    u32,
         col_no:     u32,
         file:       FileRef,
+        depth:      usize,
+        max_depth:  usize,
     pub at_eof:     bool,
 }
 
@@ -69,6 +71,23 @@ pub fn parse_error_to_string(pe: &ParseError) -> String {
     }
 }
 
+impl ParseError {
+    /// Replaces the snippet of the code, the error occured at, with `code`.
+    pub fn with_code(self, code: &str) -> ParseError {
+        let set = |t: (String, String, u32, u32, FileRef)|
+            (t.0, code.to_string(), t.2, t.3, t.4);
+        match self {
+            ParseError::UnexpectedToken(t) => ParseError::UnexpectedToken(set(t)),
+            ParseError::BadEscape(t)       => ParseError::BadEscape(set(t)),
+            ParseError::BadValue(t)        => ParseError::BadValue(set(t)),
+            ParseError::BadKeyword(t)      => ParseError::BadKeyword(set(t)),
+            ParseError::BadNumber(t)       => ParseError::BadNumber(set(t)),
+            ParseError::BadCall(t)         => ParseError::BadCall(set(t)),
+            ParseError::EOF(t)             => ParseError::EOF(set(t)),
+        }
+    }
+}
+
 impl Display for ParseError {
     fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
         write!(f, "{}", parse_error_to_string(self))
@@ -376,12 +395,69 @@ impl State {
             line_no:   1,
             col_no:    1,
             file:      FileRef::new(filename),
+            depth:     0,
+            max_depth: usize::MAX,
         };
         ps.init();
         ps.skip_ws_and_comments();
         ps
     }
 
+    /// Returns the line and column of the parse head.
+    pub fn pos(&self) -> (u32, u32) {
+        (self.line_no, self.col_no)
+    }
+
+    /// Creates a parser state for a piece of code that is embedded
+    /// in the code parsed by this state, like the expressions inside
+    /// a `$F"..."` format string. The line and column numbers
+    /// start at the current parse head position.
+    pub fn sub_state(&self, code: &str) -> State {
+        self.sub_state_at(code, self.pos())
+    }
+
+    /// Like [sub_state](#method.sub_state), but the line and column
+    /// numbers start at `pos`, as returned by [pos](#method.pos).
+    pub fn sub_state_at(&self, code: &str, pos: (u32, u32)) -> State {
+        let mut ps = State {
+            chars:     code.chars().collect(),
+            peek_char: ' ',
+            at_eof:    false,
+            line_no:   pos.0,
+            col_no:    pos.1,
+            file:      self.file.clone(),
+            depth:     self.depth,
+            max_depth: self.max_depth,
+        };
+        ps.init();
+        ps.skip_ws_and_comments();
+        ps
+    }
+
+    /// Limits how deep values may be nested, deeper nesting results
+    /// in a parse error instead of overflowing the stack.
+    pub fn set_max_depth(&mut self, max_depth: usize) {
+        self.max_depth = max_depth;
+    }
+
+    /// Returns the current nesting depth, to be restored with
+    /// [set_nesting](#method.set_nesting) after parsing a nested value.
+    pub fn nesting(&self) -> usize { self.depth }
+
+    pub fn set_nesting(&mut self, depth: usize) { self.depth = depth; }
+
+    /// Called when the parser descends into a nested value or
+    /// wraps the value parsed so far into another syntax node.
+    pub fn enter_nesting(&mut self) -> Result<(), ParseError> {
+        if self.depth >= self.max_depth {
+            return Err(ParseError::BadValue((
+                format!("Values nested deeper than {}", self.max_depth),
+                self.rest(), self.line_no, self.col_no, self.file.clone())));
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
     pub fn expect_some<T>(&self, o: Option<T>) -> Result<T, ParseError> {
         match o {
             None => {
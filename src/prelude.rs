@@ -34,6 +34,7 @@ Smalltalk, LISP and Perl.
 - [1](#1-syntax) - Syntax
 - [2](#2-variable-definition-and-assignment) - Variable Definition and Assignment
   - [2.1](#21-global-variables) - Global Variables
+  - [2.2](#22-compound-assignment) - Compound Assignment
 - [3](#3-functions-part-12) - Functions (part 1/2)
   - [3.1](#31-closures) - Closures
     - [3.1.1](#311-object-oriented-programming-with-closures) - Object Oriented Programming with Closures
@@ -42,6 +43,7 @@ Smalltalk, LISP and Perl.
     - [3.3.1](#331-stdtonoarity-function) - std:to_no_arity _function_
   - [3.4](#34-calling-fields--method-calling) - Calling fields / Method calling
     - [3.4.1](#341-object-oriented-programming-with-prototypes) - Object Oriented Programming with Prototypes
+    - [3.4.2](#342-classes-with-stdclass) - Classes with std:class
 - [4](#4-data-types) - Data Types
   - [4.1](#41-none-sentinel-value-n-or-none) - None sentinel value: `$n` or `$none`
     - [4.1.1](#411-isnone-value) - is_none _value_
@@ -59,29 +61,72 @@ Smalltalk, LISP and Perl.
   - [4.5](#45-64-bit-floats) - 64-Bit Floats
     - [4.5.1](#451-float-value) - float _value_
     - [4.5.2](#452-isfloat-value) - is_float _value_
-  - [4.6](#46-strings) - Strings
-    - [4.6.1](#461-stdstrcat-a-b-) - std:str:cat _a_ _b_ ...
-    - [4.6.2](#462-stdstrjoin-sep-vector) - std:str:join _sep_ _vector_
-    - [4.6.3](#463-stdstrlen-value) - std:str:len _value_
-    - [4.6.4](#464-stdstrtrim-value) - std:str:trim _value_
-    - [4.6.5](#465-stdstrtrimstart-value) - std:str:trim_start _value_
-    - [4.6.6](#466-stdstrtrimend-value) - std:str:trim_end _value_
-    - [4.6.7](#467-stdstrpadstart-len-pad-str-value) - std:str:pad_start _len_ _pad-str_ _value_
-    - [4.6.8](#468-stdstrpadend-len-pad-str-value) - std:str:pad_end _len_ _pad-str_ _value_
-  - [4.7](#47-bytes-or-byte-vectors) - Bytes (or Byte Vectors)
-    - [4.7.1](#471-call-properties-of-bytes) - Call Properties of Bytes
-    - [4.7.2](#472-byte-conversion-functions) - Byte Conversion Functions
-  - [4.8](#48-symbols) - Symbols
-  - [4.9](#49-vectors-or-lists) - Vectors (or Lists)
-    - [4.9.1](#491-splicing) - Splicing
-    - [4.9.2](#492-stdappend-vec-a-value-or-vec-) - std:append _vec-a_ _value-or-vec_ ...
-  - [4.10](#410-associative-maps-or-string-to-value-mappings) - Associative Maps (or String to Value mappings)
+  - [4.6](#46-decimal-numbers) - Decimal Numbers
+    - [4.6.1](#461-decimal-value) - decimal _value_
+    - [4.6.2](#462-isdecimal-value) - is_decimal _value_
+    - [4.6.3](#463-stddecround-decimal-places-mode) - std:dec:round _decimal_ _places_ \[_mode_]
+    - [4.6.4](#464-stddecscale-decimal) - std:dec:scale _decimal_
+    - [4.6.5](#465-stddecnormalize-decimal) - std:dec:normalize _decimal_
+  - [4.7](#47-strings) - Strings
+    - [4.7.1](#471-stdstrcat-a-b-) - std:str:cat _a_ _b_ ...
+    - [4.7.2](#472-stdstrjoin-sep-vector) - std:str:join _sep_ _vector_
+    - [4.7.3](#473-stdstrlen-value) - std:str:len _value_
+    - [4.7.4](#474-stdstrtrim-value) - std:str:trim _value_
+    - [4.7.5](#475-stdstrtrimstart-value) - std:str:trim_start _value_
+    - [4.7.6](#476-stdstrtrimend-value) - std:str:trim_end _value_
+    - [4.7.7](#477-stdstrpadstart-len-pad-str-value) - std:str:pad_start _len_ _pad-str_ _value_
+    - [4.7.8](#478-stdstrpadend-len-pad-str-value) - std:str:pad_end _len_ _pad-str_ _value_
+    - [4.7.9](#479-string-interpolation-f) - String Interpolation: `$F"..."`
+    - [4.7.10](#4710-stdstrformat-format-string-args-) - std:str:format _format-string_ _args_ ...
+    - [4.7.11](#4711-stdstrsplit-pattern-string) - std:str:split _pattern_ _string_
+    - [4.7.12](#4712-stdstrsplitn-pattern-max-string) - std:str:split_n _pattern_ _max_ _string_
+    - [4.7.13](#4713-stdstrfind-pattern-string) - std:str:find _pattern_ _string_
+    - [4.7.14](#4714-stdstrrfind-pattern-string) - std:str:rfind _pattern_ _string_
+    - [4.7.15](#4715-stdstrsubstr-start-len-string) - std:str:substr _start_ [_len_] _string_
+    - [4.7.16](#4716-stdstrstartswith-prefix-string) - std:str:starts_with _prefix_ _string_
+    - [4.7.17](#4717-stdstrendswith-suffix-string) - std:str:ends_with _suffix_ _string_
+    - [4.7.18](#4718-stdstrcontains-pattern-string) - std:str:contains _pattern_ _string_
+    - [4.7.19](#4719-stdstrrepeat-count-string) - std:str:repeat _count_ _string_
+    - [4.7.20](#4720-stdstrreverse-string) - std:str:reverse _string_
+    - [4.7.21](#4721-stdstrlines-string) - std:str:lines _string_
+    - [4.7.22](#4722-stdstrchartobyteoffset-char-index-string) - std:str:char_to_byte_offset _char-index_ _string_
+    - [4.7.23](#4723-stdstrbytetocharoffset-byte-offset-string) - std:str:byte_to_char_offset _byte-offset_ _string_
+  - [4.8](#48-bytes-or-byte-vectors) - Bytes (or Byte Vectors)
+    - [4.8.1](#481-call-properties-of-bytes) - Call Properties of Bytes
+    - [4.8.2](#482-byte-conversion-functions) - Byte Conversion Functions
+  - [4.9](#49-symbols) - Symbols
+  - [4.10](#410-vectors-or-lists) - Vectors (or Lists)
     - [4.10.1](#4101-splicing) - Splicing
-  - [4.11](#411-references) - References
-    - [4.11.1](#4111-weaken-references) - Weaken References
-    - [4.11.2](#4112-strengthening-references) - Strengthening References
-    - [4.11.3](#4113-stdsetref-ref-value) - std:set_ref _ref_ _value_
-  - [4.12](#412-calling-semantics-of-data-types) - Calling Semantics of Data Types
+    - [4.10.2](#4102-stdappend-vec-a-value-or-vec-) - std:append _vec-a_ _value-or-vec_ ...
+  - [4.11](#411-associative-maps-or-string-to-value-mappings) - Associative Maps (or String to Value mappings)
+    - [4.11.1](#4111-key-order) - Key Order
+    - [4.11.2](#4112-splicing) - Splicing
+  - [4.12](#412-enums-or-tagged-unions) - Enums (or Tagged Unions)
+  - [4.13](#413-sets-and-ordered-maps) - Sets and Ordered Maps
+  - [4.14](#414-numeric-arrays) - Numeric Arrays
+    - [4.14.1](#4141-stdarrf64-data) - std:arr:f64 _data_
+    - [4.14.2](#4142-stdarri64-data) - std:arr:i64 _data_
+    - [4.14.3](#4143-stdarrtovec-array) - std:arr:to_vec _array_
+    - [4.14.4](#4144-stdarrtobytes-array) - std:arr:to_bytes _array_
+    - [4.14.5](#4145-stdarrsum-array) - std:arr:sum _array_
+    - [4.14.6](#4146-stdarrmin-array) - std:arr:min _array_
+    - [4.14.7](#4147-stdarrmax-array) - std:arr:max _array_
+    - [4.14.8](#4148-stdarrmean-array) - std:arr:mean _array_
+    - [4.14.9](#4149-stdarrdot-a-b) - std:arr:dot _a_ _b_
+    - [4.14.10](#41410-stdarrslice-array-start-end) - std:arr:slice _array_ _start_ \[_end_]
+  - [4.15](#415-numeric-vectors) - Numeric Vectors
+    - [4.15.1](#4151-ivec-value) - ivec _value_
+    - [4.15.2](#4152-fvec-value) - fvec _value_
+    - [4.15.3](#4153-stdvmag2-vec) - std:v:mag2 _vec_
+    - [4.15.4](#4154-stdvmag-vec) - std:v:mag _vec_
+    - [4.15.5](#4155-stdvnorm-vec) - std:v:norm _vec_
+    - [4.15.6](#4156-stdvdot-a-b) - std:v:dot _a_ _b_
+    - [4.15.7](#4157-stdvcross-a-b) - std:v:cross _a_ _b_
+  - [4.16](#416-references) - References
+    - [4.16.1](#4161-weaken-references) - Weaken References
+    - [4.16.2](#4162-strengthening-references) - Strengthening References
+    - [4.16.3](#4163-stdsetref-ref-value) - std:set_ref _ref_ _value_
+  - [4.17](#417-calling-semantics-of-data-types) - Calling Semantics of Data Types
 - [5](#5-functions-part-22) - Functions (part 2/2)
   - [5.1](#51-function-call-composition) - Function call composition
     - [5.1.1](#511--tail-argument-function-chaninig) - '|' Tail Argument Function Chaninig
@@ -95,16 +140,28 @@ Smalltalk, LISP and Perl.
     - [7.1.1](#711-while-predicate-fun) - while _predicate_ _fun_
     - [7.1.2](#712-range-start-end-step-fun) - range _start_ _end_ _step_ _fun_
     - [7.1.3](#713-break-value) - break _value_
+    - [7.1.4](#714-pattern-matching-with-match) - Pattern matching with `$match`
   - [7.2](#72-collection-iteration) - Collection Iteration
     - [7.2.1](#721-iteration-over-vectors) - Iteration over vectors
     - [7.2.2](#722-iteration-over-maps) - Iteration over maps
     - [7.2.3](#723-for-iteratable-value-function) - for _iteratable-value_ _function_
+    - [7.2.4](#724-lazy-iterators-iter-value) - Lazy Iterators `$iter` _value_
+    - [7.2.5](#725-stditerrange-start-end-step) - std:iter:range _start_ _end_ [_step_]
+    - [7.2.6](#726-stditernext-iterator) - std:iter:next _iterator_
+    - [7.2.7](#727-stditermap-function-iterator) - std:iter:map _function_ _iterator_
+    - [7.2.8](#728-stditerfilter-function-iterator) - std:iter:filter _function_ _iterator_
+    - [7.2.9](#729-stditertake-count-iterator) - std:iter:take _count_ _iterator_
+    - [7.2.10](#7210-stditerskip-count-iterator) - std:iter:skip _count_ _iterator_
+    - [7.2.11](#7211-stditerchain-iterator-) - std:iter:chain _iterator_ ...
+    - [7.2.12](#7212-stditerzip-iterator-) - std:iter:zip _iterator_ ...
+    - [7.2.13](#7213-stditerenumerate-iterator) - std:iter:enumerate _iterator_
   - [7.3](#73-accumulation-and-collection) - Accumulation and Collection
     - [7.3.1](#731-transforming-a-vector) - Transforming a vector
     - [7.3.2](#732-example-of-) - Example of `$@@`
     - [7.3.3](#733-transforming-a-vector-to-a-map) - Transforming a vector to a map
     - [7.3.4](#734-iteratively-concatenating-strings) - Iteratively concatenating strings
     - [7.3.5](#735-accumulating-sums) - Accumulating sums
+    - [7.3.6](#736-collecting-iterators) - Collecting iterators
   - [7.4](#74-utilities) - Utilities
     - [7.4.1](#741-stdaccum-collection-a-b-) - std:accum _collection_ _a_ _b_ ...
     - [7.4.2](#742-stdzip-vector-map-fn) - std:zip _vector_ _map-fn_
@@ -117,6 +174,23 @@ Smalltalk, LISP and Perl.
     - [8.1.4](#814--op-a-op-b) - / _op-a_ _op-b_
     - [8.1.5](#815--op-a-op-b) - % _op-a_ _op-b_
     - [8.1.6](#816--op-a-op-b) - ^ _op-a_ _op-b_
+    - [8.1.7](#817-integer-overflow) - Integer Overflow
+    - [8.1.8](#818-stdnumcheckedadd-a-b) - std:num:checked_add _a_ _b_
+    - [8.1.9](#819-stdnumcheckedsub-a-b) - std:num:checked_sub _a_ _b_
+    - [8.1.10](#8110-stdnumcheckedmul-a-b) - std:num:checked_mul _a_ _b_
+    - [8.1.11](#8111-stdnumcheckeddiv-a-b) - std:num:checked_div _a_ _b_
+    - [8.1.12](#8112-stdnumcheckedrem-a-b) - std:num:checked_rem _a_ _b_
+    - [8.1.13](#8113-stdnumcheckedpow-a-b) - std:num:checked_pow _a_ _b_
+    - [8.1.14](#8114-stdnumwrappingadd-a-b) - std:num:wrapping_add _a_ _b_
+    - [8.1.15](#8115-stdnumwrappingsub-a-b) - std:num:wrapping_sub _a_ _b_
+    - [8.1.16](#8116-stdnumwrappingmul-a-b) - std:num:wrapping_mul _a_ _b_
+    - [8.1.17](#8117-stdnumwrappingdiv-a-b) - std:num:wrapping_div _a_ _b_
+    - [8.1.18](#8118-stdnumwrappingrem-a-b) - std:num:wrapping_rem _a_ _b_
+    - [8.1.19](#8119-stdnumwrappingpow-a-b) - std:num:wrapping_pow _a_ _b_
+    - [8.1.20](#8120-stdnumsaturatingadd-a-b) - std:num:saturating_add _a_ _b_
+    - [8.1.21](#8121-stdnumsaturatingsub-a-b) - std:num:saturating_sub _a_ _b_
+    - [8.1.22](#8122-stdnumsaturatingmul-a-b) - std:num:saturating_mul _a_ _b_
+    - [8.1.23](#8123-stdnumsaturatingpow-a-b) - std:num:saturating_pow _a_ _b_
   - [8.2](#82-comparison) - Comparison
     - [8.2.1](#821--op-a-op-b) - == _op-a_ _op-b_
     - [8.2.2](#822--op-a-op-b) - != _op-a_ _op-b_
@@ -130,9 +204,11 @@ Smalltalk, LISP and Perl.
     - [8.3.3](#833--op-a-op-b) - &| _op-a_ _op-b_
     - [8.3.4](#834--op-a-op-b) - << _op-a_ _op-b_
     - [8.3.5](#835--op-a-op-b) - >> _op-a_ _op-b_
+  - [8.4](#84-operator-overloading) - Operator Overloading
 - [9](#9-modules) - Modules
   - [9.1](#91-export) - export
   - [9.2](#92-import) - import
+  - [9.3](#93-macros) - macros
 - [10](#10-core-library) - Core Library
 - [11](#11-standard-library) - Standard Library
     - [11.0.1](#1101-stdshuffle-randfunc-vec) - std:shuffle _rand_func_ _vec_
@@ -146,7 +222,32 @@ Smalltalk, LISP and Perl.
     - [11.0.9](#1109-stdeval-code-string) - std:eval _code-string_
     - [11.0.10](#11010-stdassert-bool-message) - std:assert _bool_ \[_message_]
     - [11.0.11](#11011-stdasserteq-actual-expected-message) - std:assert_eq _actual_ _expected_ \[_message_]
-    - [11.0.12](#11012-stdwlambdaversion) - std:wlambda:version
+    - [11.0.12](#11012-stdclass-name-parent-members) - std:class _name_ \[_parent_] _members_
+    - [11.0.13](#11013-stdsuper-class-method-arg1-) - std:super _class_ _method_ \[_arg1_ ...]
+    - [11.0.14](#11014-stdinstanceof-class-value) - std:instanceof _class_ _value_
+    - [11.0.15](#11015-stdenum-name-variants) - std:enum _name_ _variants_
+    - [11.0.16](#11016-stdenumtag-value) - std:enum:tag _value_
+    - [11.0.17](#11017-stdenumpayload-value) - std:enum:payload _value_
+    - [11.0.18](#11018-stdenumname-value) - std:enum:name _value_
+    - [11.0.19](#11019-stdsetnew-items) - std:set:new \[_items_]
+    - [11.0.20](#11020-stdsetadd-set-value) - std:set:add _set_ _value_
+    - [11.0.21](#11021-stdsetremove-set-value) - std:set:remove _set_ _value_
+    - [11.0.22](#11022-stdsetcontains-set-value) - std:set:contains _set_ _value_
+    - [11.0.23](#11023-stdsetunion-a-b) - std:set:union _a_ _b_
+    - [11.0.24](#11024-stdsetintersection-a-b) - std:set:intersection _a_ _b_
+    - [11.0.25](#11025-stdsetdifference-a-b) - std:set:difference _a_ _b_
+    - [11.0.26](#11026-stdsetrange-set-from-to) - std:set:range _set_ _from_ _to_
+    - [11.0.27](#11027-stdomapnew-items) - std:omap:new \[_items_]
+    - [11.0.28](#11028-stdomapget-omap-key) - std:omap:get _omap_ _key_
+    - [11.0.29](#11029-stdomapset-omap-key-value) - std:omap:set _omap_ _key_ _value_
+    - [11.0.30](#11030-stdomapremove-omap-key) - std:omap:remove _omap_ _key_
+    - [11.0.31](#11031-stdomapcontains-omap-key) - std:omap:contains _omap_ _key_
+    - [11.0.32](#11032-stdomapkeys-omap) - std:omap:keys _omap_
+    - [11.0.33](#11033-stdomaprange-omap-from-to) - std:omap:range _omap_ _from_ _to_
+    - [11.0.34](#11034-stdwlambdaversion) - std:wlambda:version
+    - [11.0.35](#11035-stdwlambdaparse-code-string-filename) - std:wlambda:parse _code-string_ \[_filename_]
+    - [11.0.36](#11036-stdwlambdasubst-vars-ast) - std:wlambda:subst _vars_ _ast_
+    - [11.0.37](#11037-stdwlambdaevalast-ast) - std:wlambda:eval_ast _ast_
   - [11.1](#111-io) - I/O
     - [11.1.1](#1111-stdiofilereadtext-filename) - std:io:file:read_text _filename_
     - [11.1.2](#1112-stdiofileread-filename) - std:io:file:read _filename_
@@ -154,13 +255,21 @@ Smalltalk, LISP and Perl.
     - [11.1.4](#1114-stdiofileappend-filename-bytes-or-string) - std:io:file:append _filename_ _bytes-or-string_
 - [12](#12-optional-standard-library) - Optional Standard Library
   - [12.1](#121-serialization) - serialization
-    - [12.1.1](#1211-stdserjson-data-nopretty) - std:ser:json _data_ \[_no_pretty_]
+    - [12.1.1](#1211-stdserjson-data-nopretty-decimalsasnumbers) - std:ser:json _data_ \[_no_pretty_] \[_decimals_as_numbers_]
     - [12.1.2](#1212-stddeserjson-string) - std:deser:json _string_
     - [12.1.3](#1213-stdsercsv-fielddelim-rowseparator-escapeall-table) - std:ser:csv _field_delim_ _row_separator_ _escape_all_ _table_
     - [12.1.4](#1214-stddesercsv-fielddelim-rowseparator-data) - std:deser:csv _field_delim_ _row_separator_ _data_
     - [12.1.5](#1215-stdsermsgpack-data) - std:ser:msgpack _data_
     - [12.1.6](#1216-stddesermsgpack-bytes) - std:deser:msgpack _bytes_
+    - [12.1.7](#1217-stddeserwlambda-string) - std:deser:wlambda _string_
   - [12.2](#122-regex) - regex
+    - [12.2.1](#1221-stdrecompile-regex) - std:re:compile _regex_
+    - [12.2.2](#1222-stdrematch-regex-string-function) - std:re:match _regex_ _string_ _function_
+    - [12.2.3](#1223-stdremap-regex-function-string) - std:re:map _regex_ _function_ _string_
+    - [12.2.4](#1224-stdrereplaceall-regex-replace-fn-string) - std:re:replace_all _regex_ _replace-fn_ _string_
+    - [12.2.5](#1225-stdrenamedcaptures-regex-string) - std:re:named_captures _regex_ _string_
+    - [12.2.6](#1226-stdrefindall-regex-string) - std:re:find_all _regex_ _string_
+    - [12.2.7](#1227-stdresplit-regex-string) - std:re:split _regex_ _string_
   - [12.3](#123-chrono) - chrono
     - [12.3.1](#1231-stdchronotimestamp-format) - std:chrono:timestamp \[_format_]
   - [12.4](#124-hash) - hash
@@ -229,6 +338,63 @@ std:assert_eq a 13;
 
 Global variables however do not live beyond file or module boundaries.
 
+### <a name="22-compound-assignment"></a>2.2 - Compound Assignment
+
+For the common case of updating a variable with the result of a binary
+operation on itself, there are the compound assignment operators
+`+=`, `-=`, `*=`, `/=`, `%=`, `^=`, `<<=`, `>>=`, `&=`, `&^=` and `&|=`.
+`.x += 1` does the same as `.x = x + 1`:
+
+```wlambda
+!x = 10;
+.x += 5;
+.x *= 2;
+std:assert_eq x 30;
+
+# Variables captured by closures are updated as usual:
+!count = 0;
+!inc = { .count += 1 };
+inc[]; inc[];
+std:assert_eq count 2;
+```
+
+Just like `.r = 2` replaces the value of `r`, `.r += 1` also does. To modify
+the value a `$&&` reference points to use `.*r += 1`:
+
+```wlambda
+!r = $&& 1;
+!y = r;
+
+.*y += 1;
+std:assert_eq $*r 2;
+
+.*y <<= 2;
+std:assert_eq $*r 8;
+```
+
+They also work for fields of maps and indices of vectors. The object
+and the key are only evaluated once:
+
+```wlambda
+!m = ${ count = 1 };
+!v = $[1, 2, 3];
+
+.m.count += 1;
+m.count  += 1;
+.v.0     *= 10;
+v.(1 + 1) -= 4;
+
+std:assert_eq m.count 3;
+std:assert_eq (str v) "$[10,2,-1]";
+
+!calls = 0;
+!get_m = { .calls += 1; m };
+get_m[].count += 10;
+
+std:assert_eq m.count 13;
+std:assert_eq calls   1;
+```
+
 ## <a name="3-functions-part-12"></a>3 - Functions (part 1/2)
 
 A function can be defined using the `{ ... }` syntax and the `\ _statement_`
@@ -539,6 +705,64 @@ std:assert_eq inst.gen[3] 30;
 std:assert_eq inst.gen2[4] 40;
 ```
 
+#### <a name="342-classes-with-stdclass"></a>3.4.2 - Classes with std:class
+
+For the common case, `std:class` declares a class on top of the
+prototype mechanism described above. It takes the name of the class,
+an optional parent class and a map of members.
+The class gets a `new` method, which creates an instance with the class as
+`_proto` and an empty map in `_data`. If the class or one of it's parents
+has an `init` method, it's called with `$self` set to the new instance and the
+arguments of `new`:
+
+```wlambda
+!Animal = std:class :Animal ${
+    init  = { $data.name = _ },
+    name  = { $data.name },
+    speak = { "..." },
+    describe = { std:str:format "{} says {}" $data.name $self.speak[] },
+};
+
+!Dog = std:class :Dog Animal ${
+    init  = {|2| std:super Animal :init _; $data.tricks = _1 },
+    speak = { "Woof" },
+    describe = { (std:super Animal :describe) "!" },
+};
+
+!rex = Dog.new "Rex" 3;
+
+std:assert_eq rex.name[]     "Rex";
+std:assert_eq rex.describe[] "Rex says Woof!";
+std:assert_eq rex._data.tricks 3;
+
+std:assert (std:instanceof Animal rex);
+std:assert (std:instanceof Dog rex);
+std:assert (not ~ std:instanceof Dog ~ Animal.new "Generic");
+```
+
+Methods of parent classes are found through the `_proto` chain. To call
+a method of a parent class, that is overridden, use `std:super`
+with the parent class.
+
+Members of the map that are not functions are static members, that are
+stored in the class itself. The class name is stored in `_name`:
+
+```wlambda
+!Counter = std:class :Counter ${
+    created = 0,
+    init    = {
+        !class = $self._proto;
+        class.created = class.created + 1;
+    },
+};
+
+Counter.new[];
+Counter.new[];
+
+std:assert_eq Counter.created 2;
+std:assert_eq Counter._name   "Counter";
+```
+
 ## <a name="4-data-types"></a>4 - Data Types
 
 ### <a name="41-none-sentinel-value-n-or-none"></a>4.1 - None sentinel value: `$n` or `$none`
@@ -907,7 +1131,125 @@ std:assert ~ not ~ is_float 4;
 std:assert ~ not ~ is_float $true;
 ```
 
-### <a name="46-strings"></a>4.6 - Strings
+### <a name="46-decimal-numbers"></a>4.6 - Decimal Numbers
+
+Floats can't represent most decimal fractions like `0.1` exactly. For
+calculations with money or other exact base 10 quantities WLambda has
+decimal numbers. They are written with a `d` suffix and keep the number
+of digits after the decimal point they were written with:
+
+```wlambda
+std:assert_eq (str 0.1d + 0.2d) "0.3";
+std:assert_eq (str 19.99d * 3)  "59.97";
+std:assert_eq (str 1.50d)       "1.50";
+std:assert ~ 1.50d == 1.5d;
+```
+
+Like with floats, the type of the first operand defines the result type
+of the arithmetic operators. The other operand is converted to a decimal,
+floats are converted to their shortest decimal representation:
+
+```wlambda
+std:assert_eq (str 1.5d + 2)    "3.5";
+std:assert_eq (str 1.5d + 0.25) "1.75";
+std:assert_eq (2 + 1.5d)        3;
+std:assert_eq (str 2d ^ -2)     "0.25";
+std:assert ~ 1.25d < 1.3;
+```
+
+Decimals can have up to 28 digits after the decimal point. The results of
+divisions with more digits are rounded half to even (banker's rounding):
+
+```wlambda
+std:assert_eq (str 1d / 3)      "0.3333333333333333333333333333";
+std:assert_eq (str 10.00d / 4)  "2.50";
+```
+
+The mantissa of a decimal is a 128 bit integer. Like with integers, an
+overflow returns an error value and a division by 0 panics.
+
+Decimals are serialized as strings by `std:ser:json` and `std:ser:msgpack`
+to keep them exact, but `std:ser:json` can write them as numbers too:
+
+```wlambda
+std:assert_eq (std:ser:json $[1.50d] $t)     "[\"1.50\"]";
+std:assert_eq (std:ser:json $[1.50d] $t $t)  "[1.5]";
+```
+
+#### <a name="461-decimal-value"></a>4.6.1 - decimal _value_
+
+Converts _value_ to a decimal. Strings are parsed, floats are converted to
+their shortest decimal representation and everything else is converted to
+an integer first. Floats beyond the range of decimals, infinity and NaN
+return an error.
+
+```wlambda
+std:assert_eq (str ~ decimal "12.30") "12.30";
+std:assert_eq (str ~ decimal 0.1)     "0.1";
+std:assert_eq (str ~ decimal 5)       "5";
+std:assert_eq (str ~ decimal "x")     "0";
+std:assert ~ is_err ~ decimal (float "1e40");
+std:assert_eq (int 2.99d)             2;
+std:assert_eq (float 2.5d)            2.5;
+```
+
+#### <a name="462-isdecimal-value"></a>4.6.2 - is_decimal _value_
+
+Returns `$true` if _value_ is a decimal, otherwise `$false` is returned.
+
+```wlambda
+std:assert ~ is_decimal 1.5d;
+std:assert ~ is_decimal 1d + 1;
+std:assert ~ not ~ is_decimal 1.5;
+std:assert_eq (type 1.5d) "decimal";
+```
+
+#### <a name="463-stddecround-decimal-places-mode"></a>4.6.3 - std:dec:round _decimal_ _places_ \[_mode_]
+
+Rounds the _decimal_ to at most _places_ digits after the decimal point.
+The default rounding _mode_ is `:half_even`, the others are `:half_up`,
+`:half_down`, `:up` (away from zero), `:down` (towards zero), `:ceil`
+and `:floor`.
+
+```wlambda
+std:assert_eq (str ~ std:dec:round 2.345d 2)            "2.34";
+std:assert_eq (str ~ std:dec:round 2.355d 2)            "2.36";
+std:assert_eq (str ~ std:dec:round 2.345d 2 :half_up)   "2.35";
+std:assert_eq (str ~ std:dec:round 2.345d 2 :half_down) "2.34";
+std:assert_eq (str ~ std:dec:round -2.341d 2 :up)       "-2.35";
+std:assert_eq (str ~ std:dec:round -2.349d 2 :down)     "-2.34";
+std:assert_eq (str ~ std:dec:round -2.341d 2 :floor)    "-2.35";
+std:assert_eq (str ~ std:dec:round -2.349d 2 :ceil)     "-2.34";
+std:assert_eq (str ~ std:dec:round 2.3d 2)              "2.3";
+std:assert ~ is_err ~ std:dec:round 2.3d 2 :nearest;
+```
+
+To format a decimal with a fixed number of digits use a format string:
+
+```wlambda
+!price = 2.5d;
+std:assert_eq $F"{price:.2}" "2.50";
+```
+
+#### <a name="464-stddecscale-decimal"></a>4.6.4 - std:dec:scale _decimal_
+
+Returns the number of digits after the decimal point of _decimal_.
+
+```wlambda
+std:assert_eq (std:dec:scale 1.500d) 3;
+std:assert_eq (std:dec:scale 15d)    0;
+```
+
+#### <a name="465-stddecnormalize-decimal"></a>4.6.5 - std:dec:normalize _decimal_
+
+Removes the trailing zeros after the decimal point of _decimal_.
+
+```wlambda
+std:assert_eq (str ~ std:dec:normalize 1.500d) "1.5";
+std:assert_eq (str ~ std:dec:normalize 10.0d)  "10";
+```
+
+### <a name="47-strings"></a>4.7 - Strings
 
 Strings in WLambda are like Rust UTF-8 encoded Unicode strings.
 There is no character data type however. There are two types of literal
@@ -921,7 +1263,7 @@ std:assert_eq $q/any delimiter may be used instead of/
 std:assert_eq "\u{2211}" "∑";
 ```
 
-#### <a name="461-stdstrcat-a-b-"></a>4.6.1 - std:str:cat _a_ _b_ ...
+#### <a name="471-stdstrcat-a-b-"></a>4.7.1 - std:str:cat _a_ _b_ ...
 
 Stringifies (like with `str`) and concatenates all it's arguments.
 
@@ -931,7 +1273,7 @@ std:assert_eq
     "a1023.2abcd$[1,2,3]";
 ```
 
-#### <a name="462-stdstrjoin-sep-vector"></a>4.6.2 - std:str:join _sep_ _vector_
+#### <a name="472-stdstrjoin-sep-vector"></a>4.7.2 - std:str:join _sep_ _vector_
 
 Join's the stringified elements of _vector_ with the _sep_ string.
 Will return an error if _vector_ is not a vector.
@@ -942,7 +1284,7 @@ std:assert_eq
     "1::2::3";
 ```
 
-#### <a name="463-stdstrlen-value"></a>4.6.3 - std:str:len _value_
+#### <a name="473-stdstrlen-value"></a>4.7.3 - std:str:len _value_
 
 Returns the length of the stringified _value_ in unicode characters.
 The core function `len` does return the number of bytes in the string
@@ -957,7 +1299,7 @@ std:assert_eq (len         "abcd") 4;
 std:assert_eq (std:str:len "abcd") 4;
 ```
 
-#### <a name="464-stdstrtrim-value"></a>4.6.4 - std:str:trim _value_
+#### <a name="474-stdstrtrim-value"></a>4.7.4 - std:str:trim _value_
 
 Trims off any (unicode) white space from the start and end of the
 stringified _value_.
@@ -968,7 +1310,7 @@ std:assert_eq
     "fooo bar";
 ```
 
-#### <a name="465-stdstrtrimstart-value"></a>4.6.5 - std:str:trim_start _value_
+#### <a name="475-stdstrtrimstart-value"></a>4.7.5 - std:str:trim_start _value_
 
 Trims off any (unicode) white space from the start of the stringified _value_.
 
@@ -978,7 +1320,7 @@ std:assert_eq
     "fooo bar \n";
 ```
 
-#### <a name="466-stdstrtrimend-value"></a>4.6.6 - std:str:trim_end _value_
+#### <a name="476-stdstrtrimend-value"></a>4.7.6 - std:str:trim_end _value_
 
 Trims off any (unicode) white space from the end of the stringified _value_.
 
@@ -988,7 +1330,7 @@ std:assert_eq
     "  \nfooo bar";
 ```
 
-#### <a name="467-stdstrpadstart-len-pad-str-value"></a>4.6.7 - std:str:pad_start _len_ _pad-str_ _value_
+#### <a name="477-stdstrpadstart-len-pad-str-value"></a>4.7.7 - std:str:pad_start _len_ _pad-str_ _value_
 
 Pads the stringified _value_ by _pad-str_ up to _len_ characters, inserting
 at the start of the string.
@@ -1012,7 +1354,7 @@ std:assert_eq
     "∑∑";
 ```
 
-#### <a name="468-stdstrpadend-len-pad-str-value"></a>4.6.8 - std:str:pad_end _len_ _pad-str_ _value_
+#### <a name="478-stdstrpadend-len-pad-str-value"></a>4.7.8 - std:str:pad_end _len_ _pad-str_ _value_
 
 Pads the stringified _value_ by _pad-str_ up to _len_ characters,
 appending at the end.
@@ -1036,7 +1378,232 @@ std:assert_eq
     "∑∑";
 ```
 
-### <a name="47-bytes-or-byte-vectors"></a>4.7 - Bytes (or Byte Vectors)
+#### <a name="479-string-interpolation-f"></a>4.7.9 - String Interpolation: `$F"..."`
+
+Strings can be built by interpolating values with the `$F"..."` literal.
+Every `{expr}` placeholder is replaced by the (raw) string representation
+of the value of the WLambda expression _expr_. Literal braces are
+written as `{{` and `}}`:
+
+```wlambda
+!x    = 10;
+!name = "Bob";
+
+std:assert_eq $F"x={x}, name={name}" "x=10, name=Bob";
+std:assert_eq $F"{x + 1} {std:str:len name}" "11 3";
+std:assert_eq $F"{{x}}" "{x}";
+```
+
+After a `:` a format specification like in Rust can follow.
+The syntax is `[[fill]align][+][#][0][width][.precision][type]`:
+
+- _fill_ is the padding character, a space by default.
+- _align_ is `<` (left), `^` (centered) or `>` (right). Numbers are aligned
+  right and everything else left by default.
+- `+` prints the sign also for positive numbers.
+- `#` adds the `0x`, `0b` or `0o` prefix to integers in another radix.
+- `0` pads numbers with zeros after the sign.
+- _width_ is the minimum width in characters.
+- _precision_ is the number of digits after the decimal point of
+  numbers, or the maximum number of characters of other values.
+- _type_ is `?` for the written representation (like `std:write_str`),
+  `x`/`X` for hexadecimal, `b` for binary, `o` for octal integers and
+  `e`/`E` for the exponent form of floats.
+
+_width_ and _precision_ may be at most 65535.
+
+```wlambda
+!y = 3.14159;
+
+std:assert_eq $F"[{y:>8.2}]"      "[    3.14]";
+std:assert_eq $F"[{y:<8.1}]"      "[3.1     ]";
+std:assert_eq $F"[{:a:*^7}]"      "[***a***]";
+std:assert_eq $F"{255:x} {255:#X}" "ff 0xFF";
+std:assert_eq $F"{5:08b}"          "00000101";
+std:assert_eq $F"{-5:+04} {5:+}"   "-005 +5";
+std:assert_eq $F"{1234.5:.2e}"     "1.23e3";
+std:assert_eq $F"{$[1,2]:?}"       "$[1,2]";
+std:assert_eq $F"{\"abc\":?}"     $q/"abc"/;
+```
+
+A `:` only starts the format specification if a valid specification
+follows and the expression before it is complete. So `{:e}` is the
+symbol `:e` and not an empty placeholder with the exponent format.
+Variables ending in something like a format specification need to be
+put in parenthesis:
+
+```wlambda
+!x   = 5;
+!x:b = 7;
+
+std:assert_eq $F"{:e}"    "e";
+std:assert_eq $F"{x:b}"   "101";
+std:assert_eq $F"{(x:b)}" "7";
+```
+
+#### <a name="4710-stdstrformat-format-string-args-"></a>4.7.10 - std:str:format _format-string_ _args_ ...
+
+Formats the _args_ like the `$F"..."` literal formats it's expressions,
+see above for the syntax of the format specification after the `:`.
+A placeholder `{}` takes the next argument, `{0}` the first argument,
+`{1}` the second and so on. `{name}` looks up the key _name_
+in the map that is passed as last argument:
+
+```wlambda
+std:assert_eq (std:str:format "{} + {} = {:>4}" 1 2 3) "1 + 2 =    3";
+std:assert_eq (std:str:format "{1}{0}{1}" :a :b)      "bab";
+std:assert_eq (std:str:format "{:?} {}" "a" "a")      $q/"a" a/;
+
+std:assert_eq
+    (std:str:format "{name}: {age:03}" ${ name = "Bob", age = 7 })
+    "Bob: 007";
+```
+
+A malformed format string or a missing argument results in an error value:
+
+```wlambda
+std:assert_eq
+    (unwrap_err ~ std:str:format "{:zz}" 1)
+    "str:format: bad format spec 'zz': unknown format type 'z'";
+std:assert_eq
+    (unwrap_err ~ std:str:format "{} {}" 1)
+    "str:format: missing argument 1 for '{}'";
+```
+
+#### <a name="4711-stdstrsplit-pattern-string"></a>4.7.11 - std:str:split _pattern_ _string_
+
+Splits the _string_ at every occurence of the _pattern_ string
+and returns a vector of the parts:
+
+```wlambda
+std:assert_eq (str ~ std:str:split "," "a,b,,c") $q/$["a","b","","c"]/;
+std:assert_eq (str ~ std:str:split ", " "a, b") $q/$["a","b"]/;
+```
+
+#### <a name="4712-stdstrsplitn-pattern-max-string"></a>4.7.12 - std:str:split_n _pattern_ _max_ _string_
+
+Like `std:str:split`, but returns at most _max_ parts.
+The last part contains the rest of the _string_:
+
+```wlambda
+std:assert_eq (str ~ std:str:split_n "=" 2 "x=y=z") $q/$["x","y=z"]/;
+```
+
+#### <a name="4713-stdstrfind-pattern-string"></a>4.7.13 - std:str:find _pattern_ _string_
+
+Returns the position of the first occurence of _pattern_ in _string_
+in unicode characters (like `std:str:len` counts). Returns `$none` if
+the _pattern_ was not found:
+
+```wlambda
+std:assert_eq (std:str:find "b" "äbcb")   1;
+std:assert_eq (std:str:find "x" "äbcb")   $none;
+```
+
+#### <a name="4714-stdstrrfind-pattern-string"></a>4.7.14 - std:str:rfind _pattern_ _string_
+
+Like `std:str:find`, but returns the position of the last occurence:
+
+```wlambda
+std:assert_eq (std:str:rfind "b" "äbcb")  3;
+std:assert_eq (std:str:rfind "x" "äbcb")  $none;
+```
+
+#### <a name="4715-stdstrsubstr-start-len-string"></a>4.7.15 - std:str:substr _start_ [_len_] _string_
+
+Returns the part of _string_ beginning at the character index _start_
+with at most _len_ characters. If _len_ is omitted, the rest of the
+_string_ is returned:
+
+```wlambda
+std:assert_eq (std:str:substr 1 2 "äöüß")  "öü";
+std:assert_eq (std:str:substr 2 "äöüß")    "üß";
+std:assert_eq (std:str:substr 10 "äöüß")   "";
+```
+
+#### <a name="4716-stdstrstartswith-prefix-string"></a>4.7.16 - std:str:starts_with _prefix_ _string_
+
+Returns `$true` if _string_ starts with _prefix_:
+
+```wlambda
+std:assert (std:str:starts_with "foo" "foobar");
+std:assert (not ~ std:str:starts_with "bar" "foobar");
+```
+
+#### <a name="4717-stdstrendswith-suffix-string"></a>4.7.17 - std:str:ends_with _suffix_ _string_
+
+Returns `$true` if _string_ ends with _suffix_:
+
+```wlambda
+std:assert (std:str:ends_with "bar" "foobar");
+std:assert (not ~ std:str:ends_with "foo" "foobar");
+```
+
+#### <a name="4718-stdstrcontains-pattern-string"></a>4.7.18 - std:str:contains _pattern_ _string_
+
+Returns `$true` if _pattern_ occurs in _string_:
+
+```wlambda
+std:assert (std:str:contains "oba" "foobar");
+std:assert (not ~ std:str:contains "x" "foobar");
+```
+
+#### <a name="4719-stdstrrepeat-count-string"></a>4.7.19 - std:str:repeat _count_ _string_
+
+Returns the _string_ repeated _count_ times:
+
+```wlambda
+std:assert_eq (std:str:repeat 3 "ab") "ababab";
+```
+
+If the resulting string would be larger than 1 GiB, an error is returned:
+
+```wlambda
+std:assert ~ is_err ~ std:str:repeat 9223372036854775807 "ab";
+```
+
+#### <a name="4720-stdstrreverse-string"></a>4.7.20 - std:str:reverse _string_
+
+Returns the _string_ with the order of it's unicode characters reversed:
+
+```wlambda
+std:assert_eq (std:str:reverse "äbc") "cbä";
+```
+
+#### <a name="4721-stdstrlines-string"></a>4.7.21 - std:str:lines _string_
+
+Splits the _string_ into a vector of lines. The line endings
+`"\n"` and `"\r\n"` are removed:
+
+```wlambda
+std:assert_eq (str ~ std:str:lines "a\r\nb\n\nc\n") $q/$["a","b","","c"]/;
+```
+
+#### <a name="4722-stdstrchartobyteoffset-char-index-string"></a>4.7.22 - std:str:char_to_byte_offset _char-index_ _string_
+
+Converts a position in unicode characters into a byte offset into the UTF-8
+representation of the _string_. The index after the last character
+returns the length in bytes. Returns `$none` if the index is out of range:
+
+```wlambda
+std:assert_eq (std:str:char_to_byte_offset 2 "äöü") 4;
+std:assert_eq (std:str:char_to_byte_offset 3 "äöü") 6;
+std:assert_eq (std:str:char_to_byte_offset 4 "äöü") $none;
+```
+
+#### <a name="4723-stdstrbytetocharoffset-byte-offset-string"></a>4.7.23 - std:str:byte_to_char_offset _byte-offset_ _string_
+
+Converts a byte offset into the UTF-8 representation of the _string_ into
+the position in unicode characters. Returns `$none` if the offset is out
+of range and an error if the offset is not at a character boundary:
+
+```wlambda
+std:assert_eq (std:str:byte_to_char_offset 4 "äöü") 2;
+std:assert_eq (std:str:byte_to_char_offset 7 "äöü") $none;
+std:assert (is_err ~ std:str:byte_to_char_offset 3 "äöü");
+```
+
+### <a name="48-bytes-or-byte-vectors"></a>4.8 - Bytes (or Byte Vectors)
 
 Bytes are a special kind of strings. Their literal form is:
 
@@ -1046,7 +1613,7 @@ $b"\xFF\xFD\x00";
 $Q/ABCDEF\xFD/;      # \xFD is not an escape sequence here!
 ```
 
-#### <a name="471-call-properties-of-bytes"></a>4.7.1 - Call Properties of Bytes
+#### <a name="481-call-properties-of-bytes"></a>4.8.1 - Call Properties of Bytes
 
 You can index inside a byte array by calling it with an integer:
 
@@ -1073,7 +1640,7 @@ std:assert_eq ($b"b" some_map) 30;
 std:assert_eq some_map.$b"a" 20;   # with method call syntax
 ```
 
-#### <a name="472-byte-conversion-functions"></a>4.7.2 - Byte Conversion Functions
+#### <a name="482-byte-conversion-functions"></a>4.8.2 - Byte Conversion Functions
 
 You can convert bytes to strings in a multitude of ways:
 
@@ -1121,9 +1688,23 @@ There is also an inverse operation to `bytes:to_hex`:
 std:assert_eq (std:bytes:from_hex ~ std:bytes:to_hex $b"ABC") $b"ABC";
 ```
 
-### <a name="48-symbols"></a>4.8 - Symbols
+### <a name="49-symbols"></a>4.9 - Symbols
+
+Symbols are written as `:name` or `:"some name"` and are mostly used as
+keys and tags. They are interned, so all symbols with the same name share
+the same storage and comparing them is cheap. `sym` turns a string into a
+symbol:
+
+```wlambda
+std:assert_eq (type :abc)      "sym";
+std:assert (:abc == (sym "abc"));
+std:assert (:abc != "abc");
+
+!m = ${ abc = 10 };
+std:assert_eq (:abc m) 10;
+```
 
-### <a name="49-vectors-or-lists"></a>4.9 - Vectors (or Lists)
+### <a name="410-vectors-or-lists"></a>4.10 - Vectors (or Lists)
 
 The literal syntax for vectors (or sometimes also called lists in WLambda)
 is `$[...]`. You may write any kind of expression in it and you will get
@@ -1151,7 +1732,7 @@ std:assert_eq some_vec.1 20;
 std:assert_eq some_vec.2 30;
 ```
 
-#### <a name="491-splicing"></a>4.9.1 - Splicing
+#### <a name="4101-splicing"></a>4.10.1 - Splicing
 
 You can splice vectors directly into their literal form with the `$[..., * vec_expr, ...]`
 syntax. Here is an example:
@@ -1171,7 +1752,7 @@ std:assert_eq some_vec.(1 + 1) 3;
 std:assert_eq (str $[1,2,*$[3,4]]) "$[1,2,3,4]";
 ```
 
-#### <a name="492-stdappend-vec-a-value-or-vec-"></a>4.9.2 - std:append _vec-a_ _value-or-vec_ ...
+#### <a name="4102-stdappend-vec-a-value-or-vec-"></a>4.10.2 - std:append _vec-a_ _value-or-vec_ ...
 
 Appends _value-or-vec_ and all following items to _vec-a_.
 If _value-or-vec_ is a vector, all it's items will be appended to _vec-a_.
@@ -1190,7 +1771,7 @@ If _vec-a_ is not a vector, a vector containing it will be created:
 std:assert_eq (str v) "$[1,:\"a\",:\"b\",:\"c\",:\"d\"]";
 ```
 
-### <a name="410-associative-maps-or-string-to-value-mappings"></a>4.10 - Associative Maps (or String to Value mappings)
+### <a name="411-associative-maps-or-string-to-value-mappings"></a>4.11 - Associative Maps (or String to Value mappings)
 
 Aside from vectors there are associative maps in WLambda. Their syntax is
 `${ key = expr, ... }`. The keys of these maps have to be strings,
@@ -1234,7 +1815,28 @@ the field accessing syntax `some_map.a`, the function is passed the map `some_ma
 via the special value `$self`. There is another special variable `$data`
 that allows you to access the `$self._data` field.
 
-#### <a name="4101-splicing"></a>4.10.1 - Splicing
+#### <a name="4111-key-order"></a>4.11.1 - Key Order
+
+Maps remember the order in which their keys were inserted. Iterating over
+a map, calling a map with a function and serializing it with `std:ser:json`
+or `std:ser:msgpack` visit the keys in that order. Assigning to an existing
+key keeps its position. Only the string representation of a map with
+`str` or `std:displayln` sorts the keys:
+
+```wlambda
+!m = ${ z = 1, b = 2 };
+m.a = 3;
+m.z = 4;
+
+!keys = $[];
+for m {|2| std:push keys _1 };
+std:assert_eq (str keys) $q/$["z","b","a"]/;
+
+std:assert_eq (std:ser:json m $t) $q/{"z":4,"b":2,"a":3}/;
+std:assert_eq (str m)             "${a=3,b=2,z=4}";
+```
+
+#### <a name="4112-splicing"></a>4.11.2 - Splicing
 
 Like vectors you can splice map values directly into map literals:
 
@@ -1253,81 +1855,528 @@ std:assert_eq (str ${*${a=10}}) "${a=10}";
 std:assert_eq (str ${*map_gen "y"}) $q/${_y="y"}/;
 ```
 
-### <a name="411-references"></a>4.11 - References
+### <a name="412-enums-or-tagged-unions"></a>4.12 - Enums (or Tagged Unions)
 
-Some data structures already have reference characteristics, such as strings,
-vectors and maps. There are 3 types of references in WLambda that handle
-different usecases. These referential types are neccessary to mutate lexical
-variables from a parent scope. To give a rather natural example:
+An enum value is a value tagged with the name of its enum type and one of
+its named variants. It can carry an arbitrary payload. Enums are defined
+with `std:enum`, which returns a map containing one constructor function
+per variant:
 
 ```wlambda
-!x = 10;
-{ .x = 20; }[];
-std:assert_eq x 20;
-```
-
-The example works rather intuitively. There is however lots of implicit
-referential stuff going on. Once `x` is captured by a closure it ise implicitly
-changed in to a _weakable_ `$&` reference and the closure stores only a weak
-reference to `x`. This is done to maintain lexical scope and prevent accidental
-cyclic references when closures from a scope are leaked.
+!Shape = std:enum :Shape $[:Circle, :Rect, :Empty];
 
-These types of references exist:
+!c = Shape.Circle 10;
+!r = Shape.Rect 3 4;
+!e = Shape.Empty[];
 
-- `$&` - A _weakable_ reference, that is captured weakly by closures.
-- `$(&)` - A _weak_ reference, can't be constructed literally, only indirectly
-as upvalue of a closure or by `std:weaken`.
-- `$&&` - A _strong_ reference, that is captured stongly by closures.
-Inside closures they are also implicitly dereferenced by assignment
-and access by variable name.
+std:assert_eq (type c)  "enum";
+std:assert_eq (str c)   "$<Shape.Circle 10>";
+std:assert_eq (str r)   "$<Shape.Rect $[3,4]>";
+std:assert_eq (str e)   "$<Shape.Empty>";
+```
 
-The weakable reference is captured weakly by closures and does not keep the
-referenced value alive if the value reference count drops to zero.
-The strong references will stay strong and need explicit care to handle in the
-function where they are stored directly in a local variable. But if strong
-references are caught, they are also implicitly handled.
+A constructor called with one argument uses that argument as payload,
+with more arguments the payload is a vector, and without any it's `$none`.
+The variant, payload and enum name can be queried with `std:enum:tag`,
+`std:enum:payload` and `std:enum:name`. Enum values are equal if
+all three are equal:
 
 ```wlambda
-!x = $& 10;
+!Shape = std:enum :Shape $[:Circle, :Rect];
 
-{ .x = 20; }[]; # Closures implicitly handle weak references
+!r = Shape.Rect 3 4;
 
-std:assert_eq x 20;
+std:assert_eq (std:enum:tag r)        :Rect;
+std:assert_eq (str ~ std:enum:payload r) "$[3,4]";
+std:assert_eq (std:enum:name r)       "Shape";
+
+std:assert (Shape.Circle[10] == Shape.Circle[10]);
+std:assert (Shape.Circle[10] != Shape.Circle[11]);
 ```
 
-And the same with strong references:
+For dispatching on the variant use the `:?v` kind of `match`. The function
+receives the payload and the enum value itself:
 
 ```wlambda
-!x = $&& 10;
+!Shape = std:enum :Shape $[:Circle, :Rect, :Empty];
 
-.*x = 11;
-
-{ .x = 20; }[]; # Closures implicitly handle strong references too
+!area = {
+    match _
+        :?v :Circle {|| 3 * _ * _ }
+        :?v :Rect   {|| !(w, h) = _; w * h }
+        {|| 0 }
+};
 
-std:assert_eq $*x 20;
+std:assert_eq (area ~ Shape.Circle 2) 12;
+std:assert_eq (area ~ Shape.Rect 3 4) 12;
+std:assert_eq (area ~ Shape.Empty[])  0;
 ```
 
-Strong references can also be created using the `std:to_ref` function:
+When serialized, a variant without payload becomes its name as string and
+any other variant a map with the variant name as single key:
 
 ```wlambda
-!x = std:to_ref 10;
-std:assert_eq (std:write_str x) "$&&10";
+!Shape = std:enum :Shape $[:Circle, :Empty];
+
+std:assert_eq
+    (std:ser:json $[Shape.Circle 10, Shape.Empty[]] $t)
+    $q/[{"Circle":10},"Empty"]/;
 ```
 
-#### <a name="4111-weaken-references"></a>4.11.1 - Weaken References
+### <a name="413-sets-and-ordered-maps"></a>4.13 - Sets and Ordered Maps
 
-You can weaken any of those two types of references manually using the
-`std:weaken` function.
+Keys of associative maps are always strings, so integer or vector keys
+are converted to strings and the order of the keys is random. Sets and
+ordered maps accept any value as key and keep their keys sorted.
+
+Numbers are ordered by value, strings and symbols by their characters,
+vectors element wise and enums by name, variant and payload. Keys of
+different types are ordered: none, booleans, numbers, strings, bytes,
+vectors, enums and then everything else. A string and a symbol with the
+same characters are the same key, as well as an integer and a float
+with the same value.
+Keys are copied when they are added, so changing a vector or map
+afterwards does not change the key in the set or ordered map.
+
+A set is created with `std:set:new` from the items of a vector (or
+any other iterable value):
 
 ```wlambda
-!drop_check = $& $f;
+!s = std:set:new $[3, 1, 2, 1, $[1, 2], "x"];
 
-# Make a reference to the value 10 and set `drop_check` to $true
-# when all (non weak) references to it are gone.
-!x = $&& (std:to_drop 10 {|| .drop_check = $true });
+std:assert_eq (type s) "set";
+std:assert_eq (len s)  5;
+std:assert_eq (str s)  $q/$<set[1,2,3,"x",$[1,2]]>/;
 
-# Create a weakened reference to the value referred to by x:
-!y = std:weaken x;
+std:set:add s 10;
+std:assert (std:set:contains s 10);
+std:assert (std:set:contains s $[1, 2]);
+
+!sum = $&0;
+for s {!v = _; (is_int v) { .sum = sum + v } };
+std:assert_eq $*sum 16;
+```
+
+Ordered maps are created with `std:omap:new` from a map or a vector
+of key value pairs. Iteration is in key order, just like `std:omap:keys`
+returns the keys. Fields with string keys and integer indices can be
+accessed like for ordinary maps. Field names that are integers, like
+`m.("2")`, access the integer keys. For other keys use `std:omap:get`
+and `std:omap:set`:
+
+```wlambda
+!m = std:omap:new $[$[3, :c], $[1, :a]];
+m.2 = :b;
+
+std:assert_eq (type m) "omap";
+std:assert_eq m.2 :b;
+std:assert_eq m.("2") :b;
+std:assert_eq (str ~ std:omap:keys m) "$[1,2,3]";
+
+std:omap:set m $[1, 2] "vec";
+std:assert_eq (std:omap:get m $[1, 2]) "vec";
+
+!out = $[];
+for m {|2| std:push out _1 };
+std:assert_eq (str out) "$[1,2,3,$[1,2]]";
+```
+
+Both support range queries with `std:set:range` and `std:omap:range`,
+which return the keys greater or equal to the lower bound and less
+than the upper bound. Sets can be combined with `std:set:union`,
+`std:set:intersection` and `std:set:difference`:
+
+```wlambda
+!a = std:set:new $[1, 2, 3, 4];
+!b = std:set:new $[3, 4, 5];
+
+std:assert_eq (str ~ std:set:range a 2 4)         "$[2,3]";
+std:assert_eq (str ~ std:set:union a b)           "$<set[1,2,3,4,5]>";
+std:assert_eq (str ~ std:set:intersection a b)    "$<set[3,4]>";
+std:assert_eq (str ~ std:set:difference a b)      "$<set[1,2]>";
+```
+
+### <a name="414-numeric-arrays"></a>4.14 - Numeric Arrays
+
+The elements of a vector can be any value, so each number in a vector is
+stored as a full WLambda value. For vector math on many numbers, like in
+signal processing, there are numeric arrays. They store their elements
+unboxed as 64 bit floats (`std:arr:f64`) or 64 bit integers (`std:arr:i64`):
+
+```wlambda
+!a = std:arr:f64 $[1, 2.5, 3];
+
+std:assert_eq (type a)  "f64array";
+std:assert_eq (len a)   3;
+std:assert_eq (str a)   "$<f64[1,2.5,3]>";
+std:assert_eq a.1       2.5;
+
+a.0 = 10;
+std:assert_eq (str a)   "$<f64[10,2.5,3]>";
+```
+
+The arithmetic operators `+`, `-`, `*`, `/` and `%` work element wise,
+if the left operand is a numeric array. The right operand is either a
+numeric array of the same length or a number, that is applied to all
+elements. Like with the other numbers, the left operand defines the
+element type of the result:
+
+```wlambda
+!a = std:arr:f64 $[1, 2, 3];
+!b = std:arr:f64 $[0.5, 0.5, 2];
+
+std:assert_eq (str a * b)   "$<f64[0.5,1,6]>";
+std:assert_eq (str a + 1)   "$<f64[2,3,4]>";
+std:assert_eq (str a / 2)   "$<f64[0.5,1,1.5]>";
+
+!i = std:arr:i64 $[4, 5, 6];
+std:assert_eq (str i * b)   "$<i64[0,0,12]>";
+std:assert_eq (str i % 4)   "$<i64[0,1,2]>";
+```
+
+Arrays of different lengths return an error value. For integer arrays an
+overflow returns an error value and a division by 0 panics, like for
+integers:
+
+```wlambda
+!a = std:arr:f64 $[1, 2, 3];
+std:assert_eq (unwrap_err ~ a + (std:arr:f64 $[1, 2]))
+              "Array length mismatch: 3 != 2";
+std:assert ~ is_err ~ (std:arr:i64 $[1, 2]) * 9223372036854775807;
+```
+
+Numeric arrays can be iterated like vectors and are serialized as
+vectors of numbers:
+
+```wlambda
+!sum = $&0;
+for (std:arr:i64 $[1, 2, 3]) { .sum = sum + _ };
+std:assert_eq $*sum 6;
+
+std:assert_eq (std:ser:json (std:arr:i64 $[1, 2]) $t) "[1,2]";
+```
+
+#### <a name="4141-stdarrf64-data"></a>4.14.1 - std:arr:f64 _data_
+
+Creates a float array from the items of _data_, which can be a vector or
+any other iterable value. Bytes are decoded as little endian 8 byte floats,
+numeric arrays are converted.
+
+```wlambda
+std:assert_eq (str ~ std:arr:f64 $[1, 2.5])                 "$<f64[1,2.5]>";
+std:assert_eq (str ~ std:arr:f64 ~ std:arr:i64 $[1, 2])     "$<f64[1,2]>";
+std:assert_eq (str ~ std:arr:f64 $b"\x00\x00\x00\x00\x00\x00\xF0\x3F")
+              "$<f64[1]>";
+std:assert ~ is_err ~ std:arr:f64 $b"\x00\x00";
+```
+
+#### <a name="4142-stdarri64-data"></a>4.14.2 - std:arr:i64 _data_
+
+Creates an integer array from the items of _data_, like `std:arr:f64`.
+Floats are truncated and bytes are decoded as little endian 8 byte integers.
+
+```wlambda
+std:assert_eq (str ~ std:arr:i64 $[1, 2.9, -3.9])           "$<i64[1,2,-3]>";
+std:assert_eq (str ~ std:arr:i64 $b"\x01\x01\x00\x00\x00\x00\x00\x00")
+              "$<i64[257]>";
+```
+
+#### <a name="4143-stdarrtovec-array"></a>4.14.3 - std:arr:to_vec _array_
+
+Returns a vector with the elements of the numeric _array_.
+
+```wlambda
+std:assert_eq (str ~ std:arr:to_vec ~ std:arr:f64 $[1, 2.5]) "$[1,2.5]";
+```
+
+#### <a name="4144-stdarrtobytes-array"></a>4.14.4 - std:arr:to_bytes _array_
+
+Returns the elements of the numeric _array_ encoded as little endian
+8 byte numbers.
+
+```wlambda
+std:assert_eq (std:arr:to_bytes ~ std:arr:i64 $[258])
+              $b"\x02\x01\x00\x00\x00\x00\x00\x00";
+std:assert_eq (str ~ std:arr:f64 ~ std:arr:to_bytes ~ std:arr:f64 $[1.5, -2])
+              "$<f64[1.5,-2]>";
+```
+
+#### <a name="4145-stdarrsum-array"></a>4.14.5 - std:arr:sum _array_
+
+Returns the sum of the elements of the numeric _array_. The sum of an
+integer array returns an error value if it overflows.
+
+```wlambda
+std:assert_eq (std:arr:sum ~ std:arr:f64 $[1, 2.5]) 3.5;
+std:assert_eq (std:arr:sum ~ std:arr:i64 $[1, 2])   3;
+std:assert_eq (std:arr:sum ~ std:arr:i64 $[])       0;
+```
+
+#### <a name="4146-stdarrmin-array"></a>4.14.6 - std:arr:min _array_
+
+Returns the smallest element of the numeric _array_ or `$none` if it
+is empty.
+
+```wlambda
+std:assert_eq (std:arr:min ~ std:arr:f64 $[3, 1.5, 2]) 1.5;
+std:assert_eq (std:arr:min ~ std:arr:i64 $[])          $n;
+```
+
+#### <a name="4147-stdarrmax-array"></a>4.14.7 - std:arr:max _array_
+
+Returns the largest element of the numeric _array_ or `$none` if it
+is empty.
+
+```wlambda
+std:assert_eq (std:arr:max ~ std:arr:i64 $[3, 1, 2]) 3;
+```
+
+#### <a name="4148-stdarrmean-array"></a>4.14.8 - std:arr:mean _array_
+
+Returns the arithmetic mean of the elements of the numeric _array_ as
+float or `$none` if it is empty.
+
+```wlambda
+std:assert_eq (std:arr:mean ~ std:arr:i64 $[1, 2]) 1.5;
+std:assert_eq (std:arr:mean ~ std:arr:f64 $[])     $n;
+```
+
+#### <a name="4149-stdarrdot-a-b"></a>4.14.9 - std:arr:dot _a_ _b_
+
+Returns the dot product of the numeric arrays _a_ and _b_, which need
+the same length. The result has the element type of _a_.
+
+```wlambda
+!a = std:arr:f64 $[1, 2, 3];
+std:assert_eq (std:arr:dot a a) 14.0;
+std:assert ~ is_err ~ std:arr:dot a (std:arr:f64 $[1]);
+```
+
+#### <a name="41410-stdarrslice-array-start-end"></a>4.14.10 - std:arr:slice _array_ _start_ \[_end_]
+
+Returns a new numeric array with the elements of _array_ from index
+_start_ up to, but not including, _end_. Without _end_ the elements up
+to the end of the _array_ are returned.
+
+```wlambda
+!a = std:arr:i64 $[1, 2, 3, 4];
+std:assert_eq (str ~ std:arr:slice a 1)    "$<i64[2,3,4]>";
+std:assert_eq (str ~ std:arr:slice a 1 3)  "$<i64[2,3]>";
+std:assert_eq (str ~ std:arr:slice a 3 10) "$<i64[4]>";
+```
+
+### <a name="415-numeric-vectors"></a>4.15 - Numeric Vectors
+
+For 2D and 3D math, like positions, directions and colors, WLambda has
+vectors with 2 to 4 integer or float components. They are written
+`$i(x, y, z, w)` for integer vectors and `$f(x, y, z, w)` for float
+vectors, the `(` has to follow the `$i` or `$f` directly. Unlike
+lists they can't be changed, operations on them always return
+a new vector. Vectors with 2 or 3 components are stored directly in the
+value, so creating them does not allocate any memory:
+
+```wlambda
+!pos = $f(1.5, 2, 3);
+
+std:assert_eq (type pos)        "fvec";
+std:assert_eq (type $i(1, 2))   "ivec";
+std:assert_eq (len pos)         3;
+std:assert_eq (str pos)         "$f(1.5,2,3)";
+
+!x = 10;
+std:assert_eq (str $i(x, x * 2)) "$i(10,20)";
+```
+
+The components are accessed by the fields `x`, `y`, `z` and `w`, or
+`r`, `g`, `b` and `a`, or by their index. Swizzles are fields with 2 to
+4 of these letters, they return a new vector with the named components:
+
+```wlambda
+!pos = $f(1.5, 2, 3);
+
+std:assert_eq pos.x     1.5;
+std:assert_eq pos.b     3.0;
+std:assert_eq pos.1     2.0;
+std:assert_eq pos.w     $n;
+
+std:assert_eq (str pos.xy)   "$f(1.5,2)";
+std:assert_eq (str pos.zyx)  "$f(3,2,1.5)";
+std:assert_eq (str pos.xxxx) "$f(1.5,1.5,1.5,1.5)";
+```
+
+Vectors are values like numbers, their components can't be changed.
+The arithmetic operators `+`, `-`, `*`, `/` and `%` work component wise.
+The right operand is converted to the type of the left operand, numbers
+are used for all components:
+
+```wlambda
+!a = $f(1, 2);
+
+std:assert_eq (str a + $f(0.5, 0.5))   "$f(1.5,2.5)";
+std:assert_eq (str a * 2)              "$f(2,4)";
+std:assert_eq (str $i(3, 4) + a)       "$i(4,6)";
+std:assert_eq (str a + $i(1, 2, 3))    "$f(2,4)";
+std:assert_eq (str $i(1, 2, 3) + a)    "$i(2,4,3)";
+
+!obj = ${ pos = $f(0, 0) };
+obj.pos += $f(1, 2);
+std:assert_eq (str obj.pos) "$f(1,2)";
+```
+
+Integer vectors return an error value on overflow and panic on a
+division by 0, like integers.
+
+#### <a name="4151-ivec-value"></a>4.15.1 - ivec _value_
+
+Converts _value_ to an integer vector. Float vectors are truncated, lists
+and numeric arrays use their first 2 to 4 elements and maps their keys
+`x`, `y`, `z` and `w`. Missing components are 0. Any other _value_ is used
+for both components of a 2 component vector.
+
+```wlambda
+std:assert_eq (str ~ ivec $f(1.9, -2.9))       "$i(1,-2)";
+std:assert_eq (str ~ ivec $[1, 2, 3])          "$i(1,2,3)";
+std:assert_eq (str ~ ivec $[1])                "$i(1,0)";
+std:assert_eq (str ~ ivec ${x = 1, z = 3})     "$i(1,0,3)";
+std:assert_eq (str ~ ivec 5)                   "$i(5,5)";
+```
+
+#### <a name="4152-fvec-value"></a>4.15.2 - fvec _value_
+
+Converts _value_ to a float vector, like `ivec`.
+
+```wlambda
+std:assert_eq (str ~ fvec $i(1, 2))            "$f(1,2)";
+std:assert_eq (str ~ fvec ${x = 1.5, y = 2})   "$f(1.5,2)";
+```
+
+A vector can be converted to a list by iterating over it:
+
+```wlambda
+std:assert_eq (str ~ $@vec for $f(1.5, 2) {|| $+ _ }) "$[1.5,2]";
+```
+
+#### <a name="4153-stdvmag2-vec"></a>4.15.3 - std:v:mag2 _vec_
+
+Returns the squared length of the vector _vec_ as float.
+
+```wlambda
+std:assert_eq (std:v:mag2 $i(3, 4)) 25.0;
+```
+
+#### <a name="4154-stdvmag-vec"></a>4.15.4 - std:v:mag _vec_
+
+Returns the length of the vector _vec_ as float.
+
+```wlambda
+std:assert_eq (std:v:mag $f(3, 4)) 5.0;
+```
+
+#### <a name="4155-stdvnorm-vec"></a>4.15.5 - std:v:norm _vec_
+
+Returns the vector _vec_ scaled to length 1 as float vector. A vector of
+length 0 is returned as is.
+
+```wlambda
+std:assert_eq (str ~ std:v:norm $i(3, 4))  "$f(0.6,0.8)";
+std:assert_eq (str ~ std:v:norm $f(0, 0))  "$f(0,0)";
+```
+
+#### <a name="4156-stdvdot-a-b"></a>4.15.6 - std:v:dot _a_ _b_
+
+Returns the dot product of the vectors _a_ and _b_. The result is an
+integer if _a_ is an integer vector, otherwise a float.
+
+```wlambda
+std:assert_eq (std:v:dot $i(1, 2) $i(3, 4)) 11;
+std:assert_eq (std:v:dot $f(1, 2) $i(3, 4)) 11.0;
+```
+
+#### <a name="4157-stdvcross-a-b"></a>4.15.7 - std:v:cross _a_ _b_
+
+Returns the cross product of the vectors _a_ and _b_ as 3 component
+vector of the type of _a_. 2 component vectors have a z component of 0.
+
+```wlambda
+std:assert_eq (str ~ std:v:cross $f(1, 0, 0) $f(0, 1, 0)) "$f(0,0,1)";
+std:assert_eq (str ~ std:v:cross $i(1, 2) $i(3, 4))       "$i(0,0,-2)";
+```
+
+### <a name="416-references"></a>4.16 - References
+
+Some data structures already have reference characteristics, such as strings,
+vectors and maps. There are 3 types of references in WLambda that handle
+different usecases. These referential types are neccessary to mutate lexical
+variables from a parent scope. To give a rather natural example:
+
+```wlambda
+!x = 10;
+{ .x = 20; }[];
+std:assert_eq x 20;
+```
+
+The example works rather intuitively. There is however lots of implicit
+referential stuff going on. Once `x` is captured by a closure it ise implicitly
+changed in to a _weakable_ `$&` reference and the closure stores only a weak
+reference to `x`. This is done to maintain lexical scope and prevent accidental
+cyclic references when closures from a scope are leaked.
+
+These types of references exist:
+
+- `$&` - A _weakable_ reference, that is captured weakly by closures.
+- `$(&)` - A _weak_ reference, can't be constructed literally, only indirectly
+as upvalue of a closure or by `std:weaken`.
+- `$&&` - A _strong_ reference, that is captured stongly by closures.
+Inside closures they are also implicitly dereferenced by assignment
+and access by variable name.
+
+The weakable reference is captured weakly by closures and does not keep the
+referenced value alive if the value reference count drops to zero.
+The strong references will stay strong and need explicit care to handle in the
+function where they are stored directly in a local variable. But if strong
+references are caught, they are also implicitly handled.
+
+```wlambda
+!x = $& 10;
+
+{ .x = 20; }[]; # Closures implicitly handle weak references
+
+std:assert_eq x 20;
+```
+
+And the same with strong references:
+
+```wlambda
+!x = $&& 10;
+
+.*x = 11;
+
+{ .x = 20; }[]; # Closures implicitly handle strong references too
+
+std:assert_eq $*x 20;
+```
+
+Strong references can also be created using the `std:to_ref` function:
+
+```wlambda
+!x = std:to_ref 10;
+std:assert_eq (std:write_str x) "$&&10";
+```
+
+#### <a name="4161-weaken-references"></a>4.16.1 - Weaken References
+
+You can weaken any of those two types of references manually using the
+`std:weaken` function.
+
+```wlambda
+!drop_check = $& $f;
+
+# Make a reference to the value 10 and set `drop_check` to $true
+# when all (non weak) references to it are gone.
+!x = $&& (std:to_drop 10 {|| .drop_check = $true });
+
+# Create a weakened reference to the value referred to by x:
+!y = std:weaken x;
 
 # Deref y gives you 10:
 std:assert_eq $*y 10;
@@ -1342,14 +2391,14 @@ std:assert_eq $*y $n;
 std:assert drop_check;
 ```
 
-#### <a name="4112-strengthening-references"></a>4.11.2 - Strengthening References
+#### <a name="4162-strengthening-references"></a>4.16.2 - Strengthening References
 
 You can convert a weak reference (weakened by `std:weaken`) or a captured weak
 reference `$&` to strong with `std:strengthen`.
 
 TODO: Example
 
-#### <a name="4113-stdsetref-ref-value"></a>4.11.3 - std:set_ref _ref_ _value_
+#### <a name="4163-stdsetref-ref-value"></a>4.16.3 - std:set_ref _ref_ _value_
 
 Sets the value of the reference _ref_ to _value_.
 If _ref_ is not a strong, weakable or weak reference nothing happens.
@@ -1374,7 +2423,7 @@ std:set_ref w3 14;      # Set reference via the weak reference in w3 to r3.
 std:assert_eq $*r3 14;
 ```
 
-### <a name="412-calling-semantics-of-data-types"></a>4.12 - Calling Semantics of Data Types
+### <a name="417-calling-semantics-of-data-types"></a>4.17 - Calling Semantics of Data Types
 
 You can call almost all basic data types of WLambda.
 Here is an overview of the data type calling semantics:
@@ -1765,50 +2814,150 @@ An example where the list iteration is stopped:
 std:assert_eq val :XX;
 ```
 
-### <a name="72-collection-iteration"></a>7.2 - Collection Iteration
+#### <a name="714-pattern-matching-with-match"></a>7.1.4 - Pattern matching with `$match`
 
-#### <a name="721-iteration-over-vectors"></a>7.2.1 - Iteration over vectors
+`$match` compares a value against a list of patterns. The first arm
+whose pattern matches the value is evaluated and returned. If no arm
+matches, `$none` is returned:
 
-Iterating over a vector is the most basic iteration supported by WLambda.
-You just call the vector with a function as first argument:
+```wlambda
+!describe = {!(v) = @;
+    $match v {
+        0            => "zero";
+        1..10        => "small";
+        $[x, y]      => $F"pair {x}{y}";
+        ${name, *}   => $F"named {name}";
+        _            => "other";
+    }
+};
+
+std:assert_eq (describe 0)                     "zero";
+std:assert_eq (describe 9)                     "small";
+std:assert_eq (describe $[1, 2])               "pair 12";
+std:assert_eq (describe ${name = "x", a = 1})  "named x";
+std:assert_eq (describe "?")                   "other";
+```
+
+Arms are separated by `;`. The body of an arm is either an expression or
+a block in `{ ... }`, which is evaluated in place and not turned into a
+function. Use `(\ ...)` if an arm should return a function.
+
+Variables in a pattern are bound to the matching parts of the value.
+They are compiled into local variables, that are only visible in the
+guard and body of their arm:
 
 ```wlambda
-!sum = 0;
-$[1, 2, 3] {
-    .sum = sum + _;
+!x = 10;
+!sum = $match $[1, 2] {
+    $[x, y] => {
+        !s = x + y;
+        s * 2
+    };
 };
 
 std:assert_eq sum 6;
+std:assert_eq x   10;
 ```
 
-You can also use `for` if you like.
+These are the available patterns:
 
-#### <a name="722-iteration-over-maps"></a>7.2.2 - Iteration over maps
+| Pattern                  | Matches |
+|--------------------------|---------|
+| `_`                      | Any value. |
+| _name_                   | Any value, which is bound to the variable _name_. |
+| `10`, `"str"`, `:sym`, `$t`, `$n`, ... | A value that is equal (`std:eqv`) to the literal. |
+| `(` _expr_ `)`           | A value that is equal to the value of _expr_. |
+| _a_ `..` _b_             | A number that is >= _a_ and < _b_. |
+| _a_ `..=` _b_            | A number that is >= _a_ and <= _b_. |
+| `$r/` _regex_ `/`        | A string that matches the regex. Named groups are bound as variables. |
+| `$[` _p1_, _p2_ `]`      | A vector with exactly as many elements, each matching it's pattern. |
+| `$[` _p1_, `*` _rest_, _pn_ `]` | A vector with at least as many elements. The elements in between are bound to _rest_, which is optional. |
+| `${` _key_ `=` _p_ `}`   | A map with exactly the given keys, each value matching it's pattern. |
+| `${` _key_, `*` _rest_ `}` | A map with at least the given keys. A _key_ without pattern binds the value to a variable with the same name. The remaining keys are bound to _rest_, which is optional. |
 
-Iterating over a map is as simple as iterating over a vector.
-The map can be called with a function as first argument and it starts
-iterating over it's key/value pairs. The first argument of the
-function is the value, the second argument is the key.
+Patterns can be nested:
 
 ```wlambda
-!sum  = 0;
-!keys = $[];
+!shape = ${ type = :circle, center = $[1, 2], r = 3 };
 
-${a = 10, b = 20, c = 30} {
-    !(v, k) = @;
-    .sum = sum + v;
-    std:push keys k;
+!res = $match shape {
+    ${type = :circle, center = $[x, y], r} => $[x + y, r];
+    ${type = :rect, *}                     => :rect;
 };
 
-std:assert_eq sum 60;
-std:assert_eq (std:str:join "," ~ std:sort keys) "a,b,c";
+std:assert_eq (str res) "$[3,3]";
 ```
 
-You can also use `for` if you like.
+A regex pattern binds it's named groups, groups that did not
+participate in the match are `$none`:
 
-#### <a name="723-for-iteratable-value-function"></a>7.2.3 - for _iteratable-value_ _function_
+```wlambda
+!size = $match "640x480" {
+    $r/^(?P<w>\d+)x(?P<h>\d+)$/ => (int w) * (int h);
+};
 
-Calls _function_ for every element of _iteratable-value_.
+std:assert_eq size 307200;
+```
+
+An arm can have a guard after a `?`. The arm is only taken if the pattern
+matches and the guard evaluates to a true value:
+
+```wlambda
+!classify = {!(v) = @;
+    $match v {
+        $[a, b] ? a == b => :same;
+        $[a, b]          => :different;
+    }
+};
+
+std:assert_eq (classify $[1, 1]) :same;
+std:assert_eq (classify $[1, 2]) :different;
+```
+
+### <a name="72-collection-iteration"></a>7.2 - Collection Iteration
+
+#### <a name="721-iteration-over-vectors"></a>7.2.1 - Iteration over vectors
+
+Iterating over a vector is the most basic iteration supported by WLambda.
+You just call the vector with a function as first argument:
+
+```wlambda
+!sum = 0;
+$[1, 2, 3] {
+    .sum = sum + _;
+};
+
+std:assert_eq sum 6;
+```
+
+You can also use `for` if you like.
+
+#### <a name="722-iteration-over-maps"></a>7.2.2 - Iteration over maps
+
+Iterating over a map is as simple as iterating over a vector.
+The map can be called with a function as first argument and it starts
+iterating over it's key/value pairs. The first argument of the
+function is the value, the second argument is the key.
+
+```wlambda
+!sum  = 0;
+!keys = $[];
+
+${a = 10, b = 20, c = 30} {
+    !(v, k) = @;
+    .sum = sum + v;
+    std:push keys k;
+};
+
+std:assert_eq sum 60;
+std:assert_eq (std:str:join "," ~ std:sort keys) "a,b,c";
+```
+
+You can also use `for` if you like.
+
+#### <a name="723-for-iteratable-value-function"></a>7.2.3 - for _iteratable-value_ _function_
+
+Calls _function_ for every element of _iteratable-value_.
 Iteratable values are:
 
 - Vectors
@@ -1866,6 +3015,189 @@ for :abc {
 
 std:assert_eq (str str_chars) (str $["a", "b", "c"]);
 ```
+- Iterators, see also _Lazy Iterators_ below
+```wlambda
+!sum = 0;
+
+for (std:iter:range 1 4) {
+    .sum += _;
+};
+
+std:assert_eq sum 10;
+```
+- Functions, which are called until they return `$none`
+```wlambda
+!n   = $&& 0;
+!sum = 0;
+
+for { .n += 1; (n <= 4) { n } { $n } } {
+    .sum += _;
+};
+
+std:assert_eq sum 10;
+```
+
+#### <a name="724-lazy-iterators-iter-value"></a>7.2.4 - Lazy Iterators `$iter` _value_
+
+`$iter` creates a lazy iterator value from any iteratable value.
+The items are only produced when they are requested, by `for`, by
+the `$@vec`, `$@map` and other accumulators (see below) or by
+`std:iter:next`. Unlike vectors, an iterator is consumed while it is
+iterated over. Copies of an iterator share that state:
+
+```wlambda
+!it = $iter $[1, 2, 3];
+
+std:assert_eq (std:iter:next it) 1;
+std:assert_eq (str ~ $@vec it)   "$[2,3]";
+std:assert_eq (std:iter:next it) $n;
+```
+
+`$iter` works on vectors, maps, strings, symbols, byte vectors and
+user data implementing the `iter` method of `VValUserData`.
+If the value is a function, it is called without arguments for each item
+until it returns `$none`:
+
+```wlambda
+!n = $&& 0;
+!counter = $iter { .n += 1; (n <= 3) { n } { $n } };
+
+std:assert_eq (str ~ $@vec counter) "$[1,2,3]";
+```
+
+Iterators are combined with the `std:iter:*` functions below. They all
+accept any iteratable value in place of an iterator. Like `for`, they call
+their function with the item and, for items from maps and
+`std:iter:enumerate`, the key as second argument:
+
+```wlambda
+!squares =
+    $iter $[1, 2, 3, 4, 5, 6]
+    | std:iter:filter { (_ % 2) == 0 }
+    | std:iter:map    { _ * _ };
+
+std:assert_eq (str ~ $@vec squares) "$[4,16,36]";
+```
+
+The functions are only called as the items are requested:
+
+```wlambda
+!calls = $&& 0;
+!it = std:iter:map { .calls += 1; _ * 10 } $[1, 2, 3];
+
+std:assert_eq $*calls 0;
+std:assert_eq (std:iter:next it) 10;
+std:assert_eq $*calls 1;
+```
+
+A `break` in any of these functions ends the iteration and `next`
+skips the current item.
+
+#### <a name="725-stditerrange-start-end-step"></a>7.2.5 - std:iter:range _start_ _end_ [_step_]
+
+Returns an iterator that counts from _start_ to _end_, including _end_,
+in steps of _step_, which defaults to 1. If _start_ is a float, the items
+are floats. A negative _step_ counts down. A _step_ of 0 returns an error,
+as well as an infinite float _step_ or a NaN _start_ or _end_.
+
+```wlambda
+std:assert_eq (str ~ $@vec std:iter:range 1 5)        "$[1,2,3,4,5]";
+std:assert_eq (str ~ $@vec std:iter:range 10 0 -5)    "$[10,5,0]";
+std:assert_eq (str ~ $@vec std:iter:range 0.0 1.0 0.5) "$[0,0.5,1]";
+std:assert ~ is_err ~ std:iter:range 0.0 1.0 0.0 / 0.0;
+```
+
+#### <a name="726-stditernext-iterator"></a>7.2.6 - std:iter:next _iterator_
+
+Returns the next item of _iterator_ or `$none` if it is exhausted.
+
+```wlambda
+!it = std:iter:range 1 2;
+
+std:assert_eq (std:iter:next it) 1;
+std:assert_eq (std:iter:next it) 2;
+std:assert_eq (std:iter:next it) $n;
+```
+
+#### <a name="727-stditermap-function-iterator"></a>7.2.7 - std:iter:map _function_ _iterator_
+
+Returns an iterator over the return values of _function_ called
+with each item of _iterator_. The keys of the items are kept.
+
+```wlambda
+std:assert_eq (str ~ $@vec std:iter:map { _ + 1 } $[1, 2]) "$[2,3]";
+
+!m = $@map std:iter:map {|| _ * 2 } ${a = 1, b = 2};
+std:assert_eq m.b 4;
+```
+
+#### <a name="728-stditerfilter-function-iterator"></a>7.2.8 - std:iter:filter _function_ _iterator_
+
+Returns an iterator over the items of _iterator_ for which _function_
+returns a true value.
+
+```wlambda
+std:assert_eq
+    (str ~ $@vec std:iter:filter { _ > 1 } $[1, 2, 3])
+    "$[2,3]";
+```
+
+#### <a name="729-stditertake-count-iterator"></a>7.2.9 - std:iter:take _count_ _iterator_
+
+Returns an iterator over the first _count_ items of _iterator_.
+
+```wlambda
+!n = $&& 0;
+!naturals = $iter { .n += 1; $*n };
+
+std:assert_eq (str ~ $@vec std:iter:take 3 naturals) "$[1,2,3]";
+```
+
+#### <a name="7210-stditerskip-count-iterator"></a>7.2.10 - std:iter:skip _count_ _iterator_
+
+Returns an iterator that skips the first _count_ items of _iterator_.
+
+```wlambda
+std:assert_eq (str ~ $@vec std:iter:skip 2 $[1, 2, 3, 4]) "$[3,4]";
+```
+
+#### <a name="7211-stditerchain-iterator-"></a>7.2.11 - std:iter:chain _iterator_ ...
+
+Returns an iterator over the items of all given iterators, one after
+another.
+
+```wlambda
+std:assert_eq
+    (str ~ $@vec std:iter:chain $[1, 2] "ab")
+    "$[1,2,\"a\",\"b\"]";
+```
+
+#### <a name="7212-stditerzip-iterator-"></a>7.2.12 - std:iter:zip _iterator_ ...
+
+Returns an iterator over vectors, that contain the next item of each
+given iterator. It ends when any of the iterators is exhausted.
+Collecting pairs with `$@map` uses the first element as key:
+
+```wlambda
+std:assert_eq
+    (str ~ $@vec std:iter:zip $[1, 2, 3] "ab")
+    "$[$[1,\"a\"],$[2,\"b\"]]";
+
+!m = $@map std:iter:zip $[:x, :y] $[10, 20];
+std:assert_eq m.y 20;
+```
+
+#### <a name="7213-stditerenumerate-iterator"></a>7.2.13 - std:iter:enumerate _iterator_
+
+Returns an iterator over the items of _iterator_ with their index
+as key. `for` passes the index as second argument:
+
+```wlambda
+!out = $[];
+for (std:iter:enumerate $[:a, :b]) {|| std:push out ~ $[_1, _] };
+
+std:assert_eq (str out) "$[$[0,:\"a\"],$[1,:\"b\"]]";
+```
 
 ### <a name="73-accumulation-and-collection"></a>7.3 - Accumulation and Collection
 
@@ -1997,6 +3329,21 @@ And with floats:
 std:assert_eq (std:num:round 10.0 * sum) 81.0;
 ```
 
+#### <a name="736-collecting-iterators"></a>7.3.6 - Collecting iterators
+
+If the _expr_ of an accumulator evaluates to an iterator, all of it's
+items are accumulated. For `$@map` the key of an item is used, items
+without a key have to be `$[key, value]` pairs:
+
+```wlambda
+std:assert_eq (str ~ $@vec std:iter:map { _ * 2 } $[1, 2]) "$[2,4]";
+std:assert_eq ($@int std:iter:range 1 100)                 5050;
+std:assert_eq ($@string $iter $[:a, :b])                   "ab";
+
+!m = $@map $iter $[$[:a, 1], $[:b, 2]];
+std:assert_eq m.b 2;
+```
+
 
 ### <a name="74-utilities"></a>7.4 - Utilities
 
@@ -2123,6 +3470,190 @@ std:assert_eq std:num:round[(2.0 ^ 2.1) * 1000] 4287.0;
 std:assert_eq 2 ^ 2.1   4; # first arg type matters!
 ```
 
+#### <a name="817-integer-overflow"></a>8.1.7 - Integer Overflow
+
+Integers are 64 bit signed integers. If the result of `+`, `-`, `*`, `/`,
+`%` or `^` on integers does not fit into that range, an error value is returned
+instead of a silently wrapped around result:
+
+```wlambda
+!big = 9223372036854775807; # largest integer
+std:assert_eq (big - 1) + 1 big;
+
+std:assert ~ is_err (big + 1);
+std:assert_eq (unwrap_err big + 1) "Integer overflow: 9223372036854775807 + 1";
+
+std:assert ~ is_err (2 ^ 63);
+std:assert ~ is_err (2 ^ -1);
+std:assert ~ is_err (-9223372036854775808 % -1);
+```
+
+An error value as operand of an arithmetic operator panics, like it does
+in the argument list of a function call. So an overflow can't get lost
+in a longer calculation:
+
+```wlambda
+!res = std:eval $q{ (9223372036854775807 + 1) + 5 };
+std:assert ~ is_err res;
+std:assert ~ is_some ~ std:str:find "Error value in operand" ~ unwrap_err res;
+```
+
+If you need a different behaviour, use the `std:num:checked_*`,
+`std:num:wrapping_*` or `std:num:saturating_*` functions.
+
+#### <a name="818-stdnumcheckedadd-a-b"></a>8.1.8 - std:num:checked_add _a_ _b_
+
+Adds the integers _a_ and _b_ and returns `$none` if the result overflows.
+
+```wlambda
+std:assert_eq (std:num:checked_add 1 2)                   3;
+std:assert_eq (std:num:checked_add 9223372036854775807 1) $n;
+```
+
+#### <a name="819-stdnumcheckedsub-a-b"></a>8.1.9 - std:num:checked_sub _a_ _b_
+
+Subtracts _b_ from _a_ and returns `$none` if the result overflows.
+
+```wlambda
+std:assert_eq (std:num:checked_sub 3 5)                    -2;
+std:assert_eq (std:num:checked_sub -9223372036854775807 2) $n;
+```
+
+#### <a name="8110-stdnumcheckedmul-a-b"></a>8.1.10 - std:num:checked_mul _a_ _b_
+
+Multiplies _a_ with _b_ and returns `$none` if the result overflows.
+
+```wlambda
+std:assert_eq (std:num:checked_mul 3 4)                   12;
+std:assert_eq (std:num:checked_mul 3037000500 3037000500) $n;
+```
+
+#### <a name="8111-stdnumcheckeddiv-a-b"></a>8.1.11 - std:num:checked_div _a_ _b_
+
+Divides _a_ by _b_ and returns `$none` if _b_ is 0 or the result
+overflows.
+
+```wlambda
+std:assert_eq (std:num:checked_div 10 3)                    3;
+std:assert_eq (std:num:checked_div 10 0)                    $n;
+std:assert_eq (std:num:checked_div -9223372036854775808 -1) $n;
+```
+
+#### <a name="8112-stdnumcheckedrem-a-b"></a>8.1.12 - std:num:checked_rem _a_ _b_
+
+Returns the remainder of the division of _a_ by _b_, or `$none` if _b_ is 0
+or the division overflows.
+
+```wlambda
+std:assert_eq (std:num:checked_rem 10 3) 1;
+std:assert_eq (std:num:checked_rem 10 0) $n;
+```
+
+#### <a name="8113-stdnumcheckedpow-a-b"></a>8.1.13 - std:num:checked_pow _a_ _b_
+
+Raises _a_ to the power of _b_ and returns `$none` if the result overflows
+or _b_ is negative.
+
+```wlambda
+std:assert_eq (std:num:checked_pow 2 62) 4611686018427387904;
+std:assert_eq (std:num:checked_pow 2 63) $n;
+std:assert_eq (std:num:checked_pow 2 -1) $n;
+```
+
+#### <a name="8114-stdnumwrappingadd-a-b"></a>8.1.14 - std:num:wrapping_add _a_ _b_
+
+Adds the integers _a_ and _b_ and wraps around at the boundary of the
+integer type.
+
+```wlambda
+std:assert_eq (std:num:wrapping_add 9223372036854775807 1) -9223372036854775808;
+```
+
+#### <a name="8115-stdnumwrappingsub-a-b"></a>8.1.15 - std:num:wrapping_sub _a_ _b_
+
+Subtracts _b_ from _a_ and wraps around at the boundary of the integer type.
+
+```wlambda
+std:assert_eq (std:num:wrapping_sub -9223372036854775808 1) 9223372036854775807;
+```
+
+#### <a name="8116-stdnumwrappingmul-a-b"></a>8.1.16 - std:num:wrapping_mul _a_ _b_
+
+Multiplies _a_ with _b_ and wraps around at the boundary of the integer type.
+
+```wlambda
+std:assert_eq (std:num:wrapping_mul 4611686018427387904 2) -9223372036854775808;
+```
+
+#### <a name="8117-stdnumwrappingdiv-a-b"></a>8.1.17 - std:num:wrapping_div _a_ _b_
+
+Divides _a_ by _b_ and wraps around at the boundary of the integer type,
+which only happens for the smallest integer divided by -1.
+A division by 0 returns an error.
+
+```wlambda
+std:assert_eq (std:num:wrapping_div -9223372036854775808 -1) -9223372036854775808;
+std:assert ~ is_err (std:num:wrapping_div 1 0);
+```
+
+#### <a name="8118-stdnumwrappingrem-a-b"></a>8.1.18 - std:num:wrapping_rem _a_ _b_
+
+Returns the remainder of the division of _a_ by _b_, which is 0 for
+the smallest integer divided by -1. A division by 0 returns an error.
+
+```wlambda
+std:assert_eq (std:num:wrapping_rem 10 3)                    1;
+std:assert_eq (std:num:wrapping_rem -9223372036854775808 -1) 0;
+std:assert ~ is_err (std:num:wrapping_rem 1 0);
+```
+
+#### <a name="8119-stdnumwrappingpow-a-b"></a>8.1.19 - std:num:wrapping_pow _a_ _b_
+
+Raises _a_ to the power of _b_ and wraps around at the boundary of the
+integer type. A negative exponent returns an error.
+
+```wlambda
+std:assert_eq (std:num:wrapping_pow 2 64) 0;
+std:assert ~ is_err (std:num:wrapping_pow 2 -1);
+```
+
+#### <a name="8120-stdnumsaturatingadd-a-b"></a>8.1.20 - std:num:saturating_add _a_ _b_
+
+Adds the integers _a_ and _b_ and returns the largest or smallest integer
+if the result overflows.
+
+```wlambda
+std:assert_eq (std:num:saturating_add 9223372036854775807 1) 9223372036854775807;
+```
+
+#### <a name="8121-stdnumsaturatingsub-a-b"></a>8.1.21 - std:num:saturating_sub _a_ _b_
+
+Subtracts _b_ from _a_ and returns the largest or smallest integer
+if the result overflows.
+
+```wlambda
+std:assert_eq (std:num:saturating_sub -9223372036854775807 10) -9223372036854775808;
+```
+
+#### <a name="8122-stdnumsaturatingmul-a-b"></a>8.1.22 - std:num:saturating_mul _a_ _b_
+
+Multiplies _a_ with _b_ and returns the largest or smallest integer
+if the result overflows.
+
+```wlambda
+std:assert_eq (std:num:saturating_mul -4611686018427387904 4) -9223372036854775808;
+```
+
+#### <a name="8123-stdnumsaturatingpow-a-b"></a>8.1.23 - std:num:saturating_pow _a_ _b_
+
+Raises _a_ to the power of _b_ and returns the largest or smallest integer
+if the result overflows. A negative exponent returns an error.
+
+```wlambda
+std:assert_eq (std:num:saturating_pow 10 30) 9223372036854775807;
+std:assert ~ is_err (std:num:saturating_pow 2 -1);
+```
+
 ### <a name="82-comparison"></a>8.2 - Comparison
 
 #### <a name="821--op-a-op-b"></a>8.2.1 - == _op-a_ _op-b_
@@ -2252,6 +3783,76 @@ std:assert (0b1100 >> 2)      == 0b11;
 std:assert (`>>` 0b1011000 3) == 0b1011
 ```
 
+### <a name="84-operator-overloading"></a>8.4 - Operator Overloading
+
+The arithmetic operators `+`, `-`, `*`, `/`, `%` and the comparison
+operators `==`, `!=`, `<`, `<=`, `>` and `>=` can be overloaded for
+objects with a prototype (see also
+[Object Oriented Programming with Prototypes](#341-object-oriented-programming-with-prototypes)).
+If the left operand is an object, the operator calls the method
+of the object with the right operand as argument:
+
+| Operator | Method   | Reflected Method |
+|----------|----------|------------------|
+| `+`      | `_add_`  | `_radd_`         |
+| `-`      | `_sub_`  | `_rsub_`         |
+| `*`      | `_mul_`  | `_rmul_`         |
+| `/`      | `_div_`  | `_rdiv_`         |
+| `%`      | `_mod_`  | `_rmod_`         |
+| `==`     | `_eq_`   |                  |
+| `!=`     | `_eq_` (negated) |          |
+| `<`      | `_lt_`   |                  |
+| `<=`     | `_le_`   |                  |
+| `>`      | `_gt_`   |                  |
+| `>=`     | `_ge_`   |                  |
+
+If the left operand does not provide the method, the reflected method
+of the right operand is called with the left operand as argument.
+This allows to implement `2 * vec` in addition to `vec * 2`.
+If none of both operands overloads the operator, the usual number
+semantics apply.
+
+```wlambda
+!Vec2 = ${
+    new = { ${ _proto = $self, _data = $[_, _1] } },
+    _add_ = {
+        !(x, y) = $data;
+        $self._proto.new (x + _.x[]) (y + _.y[])
+    },
+    _mul_ = {
+        !(x, y) = $data;
+        $self._proto.new (x * _) (y * _)
+    },
+    _rmul_ = { $self * _ },
+    _eq_   = { !(x, y) = $data; (x == _.x[]) &and (y == _.y[]) },
+    x = { $data.0 },
+    y = { $data.1 },
+};
+
+!a = Vec2.new 1 2;
+!b = Vec2.new 3 4;
+
+!c = a + b;
+std:assert_eq c.x[] 4;
+std:assert_eq c.y[] 6;
+
+std:assert_eq (2 * a).y[] 4;
+std:assert_eq (a * 3).x[] 3;
+
+std:assert (a == Vec2.new[1, 2]);
+std:assert (a != b);
+
+.a += b;
+std:assert_eq a.x[] 4;
+```
+
+Vectors used as objects only overload the operators if they consist of
+exactly the class map and the data, like `$[class, data]`, and the
+class map defines the method itself.
+
+Rust user data can overload the operators by implementing the
+`binop` method of the `VValUserData` trait.
+
 ## <a name="9-modules"></a>9 - Modules
 
 ### <a name="91-export"></a>9.1 - export
@@ -2302,10 +3903,67 @@ push v 10; push v 20;
 std:assert_eq (str v) "$[10,20]";
 ```
 
-## <a name="10-core-library"></a>10 - Core Library
+### <a name="93-macros"></a>9.3 - macros
 
-This library contains all the core functions which belong to the
-core of the WLambda Programming Language. These functions can be seen
+Macros are functions, that are called at compile time with the
+syntax trees (ASTs) of their arguments and return a new syntax tree,
+which is then compiled in place of the macro call.
+They are defined with `!@macro` _name_ `=` _expr_, where _expr_ is evaluated
+at compile time and must return a function.
+The ASTs are passed as data, see `std:wlambda:parse` for
+the format. `$code` and `std:wlambda:subst` make it easy to build them
+from a template:
+
+```wlambda
+!@macro unless = {!(cond, body) = @;
+    std:wlambda:subst ${cond = cond, body = body}
+        ~ $code ((not cond) body)
+};
+
+!x = 10;
+unless (x > 20) { .x = 20 };
+std:assert_eq x 20;
+```
+
+As the macro function is executed at compile time, it can't access
+the variables of the code it is used in. It only sees the global
+functions, like the ones from `std`.
+Macros are only expanded when they are called, and local variables of the
+same name take precedence over the macro.
+A macro may expand to calls of other macros, but these expansions may
+only be nested 64 levels deep, otherwise compiling fails with an error.
+The returned AST is checked like the ones passed to `std:wlambda:eval_ast`.
+
+Macros are hygienic: Variables defined by the template of the macro are
+renamed, so they don't clash with the variables in the arguments
+of the macro:
+
+```wlambda
+!@macro twice = {
+    std:wlambda:subst ${e = _} ~ $code ({ !v = e; v + v }[])
+};
+
+!v = 10;
+std:assert_eq (twice v)         20;
+std:assert_eq (twice (v + 1))   22;
+```
+
+Only the argument ASTs, that are returned unmodified are
+considered to come from the caller. If you take apart or copy
+the argument ASTs, they are treated like a part of the template.
+
+Macros defined in a module are exported, and are imported
+with the prefix like the other exported values:
+
+```wlambda
+!@import m tests:test_mod_macros;
+std:assert_eq (m:square 7) 49;
+```
+
+## <a name="10-core-library"></a>10 - Core Library
+
+This library contains all the core functions which belong to the
+core of the WLambda Programming Language. These functions can be seen
 as keywords of WLambda. Some functions are also available as operators.
 
 ## <a name="11-standard-library"></a>11 - Standard Library
@@ -2350,7 +4008,8 @@ There are four functions that implement numeric and lexicographic ordering:
 
 If no _compare_fun_ is given, the ordering will be ascending and lexicographic
 vs. numeric will be chosen by the type of the `a` value (if it is an integer or
-float it will be numeric, otherwise lexicographic).
+float it will be numeric, otherwise lexicographic). User data, that defines an
+ordering, is sorted by that ordering.
 
 ```wlambda
 !v = $[$[1], $[-1], $[3]];
@@ -2472,10 +4131,339 @@ passed in the panic for reference.
 std:assert_eq x 60 "30 * 2 == 60";
 ```
 
-#### <a name="11012-stdwlambdaversion"></a>11.0.12 - std:wlambda:version
+#### <a name="11012-stdclass-name-parent-members"></a>11.0.12 - std:class _name_ \[_parent_] _members_
+
+Creates a new class with the name _name_, the optional parent class
+_parent_ and the members from the map _members_.
+The returned class is a map with the _members_, a `new` method for
+creating instances, the name in `_name` and the _parent_ in `_proto`.
+See also [Classes with std:class](#342-classes-with-stdclass).
+
+```wlambda
+!Point = std:class :Point ${
+    init = { $data.x = _; $data.y = _1 },
+    sum  = { $data.x + $data.y },
+};
+
+std:assert_eq (Point.new 3 4).sum[] 7;
+```
+
+#### <a name="11013-stdsuper-class-method-arg1-"></a>11.0.13 - std:super _class_ _method_ \[_arg1_ ...]
+
+Calls the method _method_ of _class_ (or it's parents) with the current
+`$self`. This is used for calling methods of the parent class, that are
+overridden in a child class. Returns an error if the method does not exist.
+
+```wlambda
+!A = std:class :A ${ hello = { "A:" _ } };
+!B = std:class :B A ${ hello = { "B+" ~ std:super A :hello _ } };
+
+std:assert_eq (B.new[]).hello["x"] "B+A:x";
+```
+
+#### <a name="11014-stdinstanceof-class-value"></a>11.0.14 - std:instanceof _class_ _value_
+
+Returns `$true` if _class_ is found in the `_proto` chain of the
+object _value_.
+
+```wlambda
+!A = std:class :A ${};
+!B = std:class :B A ${};
+
+std:assert (std:instanceof A (B.new[]));
+std:assert (not ~ std:instanceof B (A.new[]));
+std:assert (not ~ std:instanceof A 10);
+```
+
+#### <a name="11015-stdenum-name-variants"></a>11.0.15 - std:enum _name_ _variants_
+
+Defines an enum type with the given _name_ and the vector of _variants_.
+Returns a map with a constructor function for each variant and the
+key `_name`. See also the section about enums above.
+
+```wlambda
+!Color = std:enum :Color $[:Red, :Rgb];
+
+std:assert_eq Color._name           "Color";
+std:assert_eq (str Color.Red[])     "$<Color.Red>";
+std:assert_eq (str ~ Color.Rgb 1 2 3) "$<Color.Rgb $[1,2,3]>";
+```
+
+#### <a name="11016-stdenumtag-value"></a>11.0.16 - std:enum:tag _value_
+
+Returns the variant of the enum _value_ as symbol, or `$none` if _value_
+is not an enum.
+
+```wlambda
+!Color = std:enum :Color $[:Red, :Rgb];
+
+std:assert_eq (std:enum:tag Color.Red[]) :Red;
+std:assert_eq (std:enum:tag 10)          $n;
+```
+
+#### <a name="11017-stdenumpayload-value"></a>11.0.17 - std:enum:payload _value_
+
+Returns the payload of the enum _value_, or `$none` if _value_ is not an
+enum.
+
+```wlambda
+!Color = std:enum :Color $[:Red, :Gray];
+
+std:assert_eq (std:enum:payload ~ Color.Gray 128) 128;
+std:assert_eq (std:enum:payload Color.Red[])      $n;
+```
+
+#### <a name="11018-stdenumname-value"></a>11.0.18 - std:enum:name _value_
+
+Returns the name of the enum type of _value_, or `$none` if _value_ is
+not an enum.
+
+```wlambda
+!Color = std:enum :Color $[:Red];
+
+std:assert_eq (std:enum:name Color.Red[]) "Color";
+```
+
+#### <a name="11019-stdsetnew-items"></a>11.0.19 - std:set:new \[_items_]
+
+Creates a new set from the items of the vector (or any other
+iterable value) _items_. For maps and ordered maps, the keys are used.
+Without _items_ an empty set is returned.
+
+```wlambda
+std:assert_eq (str ~ std:set:new $[2, 1, 2])   "$<set[1,2]>";
+std:assert_eq (str ~ std:set:new ${b=1, a=2})  $q/$<set["a","b"]>/;
+std:assert_eq (len ~ std:set:new[])            0;
+```
+
+#### <a name="11020-stdsetadd-set-value"></a>11.0.20 - std:set:add _set_ _value_
+
+Adds _value_ to _set_ and returns _set_.
+
+```wlambda
+!s = std:set:new[];
+std:set:add s 2;
+std:set:add s 1;
+std:assert_eq (str s) "$<set[1,2]>";
+```
+
+#### <a name="11021-stdsetremove-set-value"></a>11.0.21 - std:set:remove _set_ _value_
+
+Removes _value_ from _set_. Returns `$true` if _value_ was in _set_.
+
+```wlambda
+!s = std:set:new $[1, 2];
+std:assert       (std:set:remove s 1);
+std:assert (not ~ std:set:remove s 1);
+std:assert_eq (str s) "$<set[2]>";
+```
+
+#### <a name="11022-stdsetcontains-set-value"></a>11.0.22 - std:set:contains _set_ _value_
+
+Returns `$true` if _value_ is in _set_.
+
+```wlambda
+!s = std:set:new $[1, $[2, 3]];
+std:assert       (std:set:contains s $[2, 3]);
+std:assert (not ~ std:set:contains s 2);
+```
+
+#### <a name="11023-stdsetunion-a-b"></a>11.0.23 - std:set:union _a_ _b_
+
+Returns a new set with the items that are in _a_ or _b_. Like for
+`std:set:new`, _a_ and _b_ can also be vectors or other iterable values.
+
+```wlambda
+std:assert_eq (str ~ std:set:union (std:set:new $[1, 2]) $[3, 2])
+              "$<set[1,2,3]>";
+```
+
+#### <a name="11024-stdsetintersection-a-b"></a>11.0.24 - std:set:intersection _a_ _b_
+
+Returns a new set with the items that are in _a_ and _b_.
+
+```wlambda
+std:assert_eq (str ~ std:set:intersection (std:set:new $[1, 2]) $[3, 2])
+              "$<set[2]>";
+```
+
+#### <a name="11025-stdsetdifference-a-b"></a>11.0.25 - std:set:difference _a_ _b_
+
+Returns a new set with the items of _a_ that are not in _b_.
+
+```wlambda
+std:assert_eq (str ~ std:set:difference (std:set:new $[1, 2]) $[3, 2])
+              "$<set[1]>";
+```
+
+#### <a name="11026-stdsetrange-set-from-to"></a>11.0.26 - std:set:range _set_ _from_ _to_
+
+Returns a vector with the items of _set_ that are greater or equal
+to _from_ and less than _to_, in order. If _to_ is `$none`, there is
+no upper bound.
+
+```wlambda
+!s = std:set:new $[1, 2, 3, 4, 5];
+std:assert_eq (str ~ std:set:range s 2 4)  "$[2,3]";
+std:assert_eq (str ~ std:set:range s 4 $n) "$[4,5]";
+```
+
+#### <a name="11027-stdomapnew-items"></a>11.0.27 - std:omap:new \[_items_]
+
+Creates a new ordered map from the map _items_ or the vector of
+`$[key, value]` pairs _items_. Without _items_ an empty ordered map
+is returned.
+
+```wlambda
+std:assert_eq (str ~ std:omap:new $[$[2, :b], $[1, :a]])
+              $q/$<omap[1=:"a",2=:"b"]>/;
+std:assert_eq (str ~ std:omap:new ${y=2, x=1})
+              $q/$<omap["x"=1,"y"=2]>/;
+```
+
+#### <a name="11028-stdomapget-omap-key"></a>11.0.28 - std:omap:get _omap_ _key_
+
+Returns the value for _key_ in _omap_, or `$none` if there is none.
+
+```wlambda
+!m = std:omap:new $[$[$[1, 2], 10]];
+std:assert_eq (std:omap:get m $[1, 2]) 10;
+std:assert_eq (std:omap:get m 1)       $n;
+```
+
+#### <a name="11029-stdomapset-omap-key-value"></a>11.0.29 - std:omap:set _omap_ _key_ _value_
+
+Sets _key_ in _omap_ to _value_ and returns _value_.
+
+```wlambda
+!m = std:omap:new[];
+std:omap:set m $[1, 2] 10;
+std:assert_eq (std:omap:get m $[1, 2]) 10;
+```
+
+#### <a name="11030-stdomapremove-omap-key"></a>11.0.30 - std:omap:remove _omap_ _key_
+
+Removes _key_ from _omap_ and returns its value, or `$none` if _key_
+was not in _omap_.
+
+```wlambda
+!m = std:omap:new $[$[1, :a]];
+std:assert_eq (std:omap:remove m 1) :a;
+std:assert_eq (std:omap:remove m 1) $n;
+```
+
+#### <a name="11031-stdomapcontains-omap-key"></a>11.0.31 - std:omap:contains _omap_ _key_
+
+Returns `$true` if _key_ is in _omap_.
+
+```wlambda
+!m = std:omap:new $[$[1, $n]];
+std:assert       (std:omap:contains m 1);
+std:assert (not ~ std:omap:contains m 2);
+```
+
+#### <a name="11032-stdomapkeys-omap"></a>11.0.32 - std:omap:keys _omap_
+
+Returns a vector with the keys of _omap_ in order.
+
+```wlambda
+!m = std:omap:new $[$[2, :b], $[1, :a]];
+std:assert_eq (str ~ std:omap:keys m) "$[1,2]";
+```
+
+#### <a name="11033-stdomaprange-omap-from-to"></a>11.0.33 - std:omap:range _omap_ _from_ _to_
+
+Returns a new ordered map with the entries of _omap_ whose keys are
+greater or equal to _from_ and less than _to_. If _to_ is `$none`,
+there is no upper bound.
+
+```wlambda
+!m = std:omap:new $[$[1, :a], $[2, :b], $[3, :c]];
+std:assert_eq (str ~ std:omap:range m 2 $n) $q/$<omap[2=:"b",3=:"c"]>/;
+std:assert_eq (str ~ std:omap:range m 1 2)  $q/$<omap[1=:"a"]>/;
+```
+
+#### <a name="11034-stdwlambdaversion"></a>11.0.34 - std:wlambda:version
 
 Returns the version number of the WLambda crate when called.
 
+#### <a name="11035-stdwlambdaparse-code-string-filename"></a>11.0.35 - std:wlambda:parse _code-string_ \[_filename_]
+
+Parses _code-string_ and returns the abstract syntax tree (AST) as plain
+data, instead of executing it. If the code can't be parsed, an error
+object is returned. The optional _filename_ is used for the positions in the
+AST and defaults to `"<wlambda:parse>"`.
+
+Every node of the AST is a vector, that starts with a map describing
+the node. The `syn` key holds the kind of syntax as symbol, the keys
+`line`, `col` and `file` hold the position in the source code.
+The rest of the vector are the arguments of the node, which are
+either other nodes or plain values:
+
+```wlambda
+!ast = std:wlambda:parse "1 + x" "test.wl";
+
+std:assert_eq ast.0.syn   :Block;
+std:assert_eq ast.1.0.syn :BinOpAdd;
+std:assert_eq ast.1.0.line 1;
+std:assert_eq ast.1.1     1;
+std:assert_eq ast.1.2.0.syn :Var;
+std:assert_eq ast.1.2.1   :x;
+
+std:assert (is_err ~ std:wlambda:parse "1 + ");
+```
+
+The `$code` _value_ syntax returns the AST of _value_ as the same kind of data,
+without evaluating it:
+
+```wlambda
+!c = $code (10 * 2);
+std:assert_eq c.0.syn :BinOpMul;
+std:assert_eq c.1 10;
+```
+
+Please note that `$code` only takes a single value, so use parenthesis
+for whole expressions.
+
+#### <a name="11036-stdwlambdasubst-vars-ast"></a>11.0.36 - std:wlambda:subst _vars_ _ast_
+
+Returns a copy of the AST data _ast_, where the variables are replaced
+by the values in the map _vars_. This is useful to fill in the arguments
+of a macro into a template created with `$code`:
+
+```wlambda
+!ast = std:wlambda:subst ${x = $code (1 + 2)} ~ $code (x * 3);
+std:assert_eq (std:wlambda:eval_ast ast) 9;
+```
+
+#### <a name="11037-stdwlambdaevalast-ast"></a>11.0.37 - std:wlambda:eval_ast _ast_
+
+Compiles and evaluates the AST data _ast_ in the current global environment,
+like `std:eval` does for code strings. The data needs to have
+the same structure as the one returned by `std:wlambda:parse` or `$code`.
+Nodes are recognized by the `syn` key of the map at the start
+of a vector, `line`, `col` and `file` are optional.
+Values that are not nodes are treated as constants.
+Nodes that lack elements the compiler needs, like the operands of
+a `BinOpAdd`, are rejected.
+Any compilation or evaluation error is returned as error object.
+
+```wlambda
+!ast = std:wlambda:parse "3 * 4";
+std:assert_eq (std:wlambda:eval_ast ast) 12;
+
+!c = $code (1 + 2);
+c.1 = 40;
+std:assert_eq (std:wlambda:eval_ast c) 42;
+
+std:assert_eq
+    (std:wlambda:eval_ast $[${syn = :BinOpSub}, 10, 3])
+    7;
+
+std:assert (is_err ~ std:wlambda:eval_ast $[${syn = :Foo}]);
+std:assert (is_err ~ std:wlambda:eval_ast $[${syn = :BinOpAdd}, 1]);
+```
+
 ### <a name="111-io"></a>11.1 - I/O
 
 #### <a name="1111-stdiofilereadtext-filename"></a>11.1.1 - std:io:file:read_text _filename_
@@ -2517,14 +4505,20 @@ end of the file.
 
 ### <a name="121-serialization"></a>12.1 - serialization
 
-#### <a name="1211-stdserjson-data-nopretty"></a>12.1.1 - std:ser:json _data_ \[_no_pretty_]
+#### <a name="1211-stdserjson-data-nopretty-decimalsasnumbers"></a>12.1.1 - std:ser:json _data_ \[_no_pretty_] \[_decimals_as_numbers_]
 
 Serializes the _data_ and returns a JSON formatted (and pretty printed) string.
 Optionally not pretty printed if _no_pretty_ is a true value.
 
+Decimals are written as strings, unless _decimals_as_numbers_ is a true
+value. They are then converted to floats, which might lose precision.
+
 ```wlambda
 !str = std:ser:json $[1,2.3,${a=4}] $t;
 std:assert_eq str "[1,2.3,{\"a\":4}]";
+
+std:assert_eq (std:ser:json $[0.10d] $t)    "[\"0.10\"]";
+std:assert_eq (std:ser:json $[0.10d] $t $t) "[0.1]";
 ```
 
 #### <a name="1212-stddeserjson-string"></a>12.1.2 - std:deser:json _string_
@@ -2588,8 +4582,127 @@ Deserializes the msgpack bytes value into a data structure.
 std:assert_eq (std:deser:msgpack $b"\xC4\x03abc") $b"abc";
 ```
 
+#### <a name="1217-stddeserwlambda-string"></a>12.1.7 - std:deser:wlambda _string_
+
+Reads the data in WLambda syntax, as written by `std:write_str`, from the
+_string_. Only literal values are accepted: numbers, strings, byte vectors,
+symbols, vectors, maps, booleans and `$none`. Unlike `std:eval`, nothing is
+executed, anything else like a variable or a function call returns an
+error value. This makes it suitable for configuration files and untrusted data.
+Vectors and maps nested deeper than 128 levels are an error too.
+
+```wlambda
+!data = std:deser:wlambda ~ std:write_str $[1, 2.5, ${a = :x, b = $b"\x01"}, $n];
+std:assert_eq data.0 1;
+std:assert_eq data.1 2.5;
+std:assert_eq data.(2).a :x;
+std:assert_eq data.(2).b $b"\x01";
+
+std:assert ~ is_err ~ std:deser:wlambda "$[1, std:displayln 10]";
+```
+
 ### <a name="122-regex"></a>12.2 - regex
 
+The regex functions are available if WLambda was compiled with the
+`regex` feature (enabled by default). The syntax of the regular expressions
+is the one of the Rust `regex` crate. All functions accept either a
+regex string, which is compiled on every call, or a compiled regex
+returned by `std:re:compile`.
+
+#### <a name="1221-stdrecompile-regex"></a>12.2.1 - std:re:compile _regex_
+
+Compiles the _regex_ string and returns a regex object, that can be
+passed to the other `std:re:*` functions instead of a string.
+If the _regex_ is invalid, an error value is returned.
+Calling the regex object with a string returns the vector of
+captured groups of the first match, or `$none` if nothing matched:
+
+```wlambda
+!rx = std:re:compile $q/(\d+)-(\d+)/;
+
+std:assert_eq (str ~ rx "from 10-20") $q/$["10-20","10","20"]/;
+std:assert_eq (rx "nothing") $none;
+std:assert (is_err ~ std:re:compile "(");
+```
+
+#### <a name="1222-stdrematch-regex-string-function"></a>12.2.2 - std:re:match _regex_ _string_ _function_
+
+Calls _function_ with the vector of all captured groups of the first match
+of _regex_ in _string_ and returns the result. Groups that did not
+participate in the match are `$none`. If _regex_ contains named groups
+and _function_ accepts a second argument, it gets a map of the named groups
+too. Returns `$none` if the _regex_ did not match:
+
+```wlambda
+!res = std:re:match $q/(\w+)@(\w+)/ "mail me: foo@bar" { _.2 };
+std:assert_eq res "bar";
+
+!res = std:re:match $q/(?P<user>\w+)@(?P<host>\w+)/ "foo@bar" {
+    std:str:cat _1.host "/" _1.user
+};
+std:assert_eq res "bar/foo";
+```
+
+#### <a name="1223-stdremap-regex-function-string"></a>12.2.3 - std:re:map _regex_ _function_ _string_
+
+Like `std:re:match`, but calls _function_ for every match of _regex_
+in _string_. Returns the value of the last call:
+
+```wlambda
+!v = $@v std:re:map $q/(\d+)/ { $+ ~ int _.1 } "1, 20, 300";
+std:assert_eq (str v) "$[1,20,300]";
+```
+
+#### <a name="1224-stdrereplaceall-regex-replace-fn-string"></a>12.2.4 - std:re:replace_all _regex_ _replace-fn_ _string_
+
+Replaces all matches of _regex_ in _string_ with the return value of
+_replace-fn_. _replace-fn_ is called with the vector of all captured
+groups and, like with `std:re:match`, a map of the named groups.
+Calling `next[]` keeps the match as is and `break` stops replacing:
+
+```wlambda
+!s = std:re:replace_all $q/(\d+)/ { (int _.1) * 2 } "1, 20, 300";
+std:assert_eq s "2, 40, 600";
+
+!rx = std:re:compile $q/(?P<key>\w+)=(?P<val>\w+)/;
+!s  = std:re:replace_all rx { std:str:cat _1.val "=" _1.key } "a=x b=y";
+std:assert_eq s "x=a y=b";
+```
+
+#### <a name="1225-stdrenamedcaptures-regex-string"></a>12.2.5 - std:re:named_captures _regex_ _string_
+
+Returns a map of the named groups of the first match of _regex_ in
+_string_. Named groups that did not participate in the match are `$none`.
+Returns `$none` if the _regex_ did not match:
+
+```wlambda
+!m = std:re:named_captures $q/(?P<y>\d{4})-(?P<m>\d\d)/ "on 2020-03-12";
+std:assert_eq m.y "2020";
+std:assert_eq m.m "03";
+```
+
+#### <a name="1226-stdrefindall-regex-string"></a>12.2.6 - std:re:find_all _regex_ _string_
+
+Returns a vector with an entry for every match of _regex_ in _string_.
+An entry is a vector of the matched string and the start and end
+position of the match in unicode characters (like `std:str:find`):
+
+```wlambda
+std:assert_eq
+    (str ~ std:re:find_all $q/\d+/ "ä1 23")
+    $q/$[$["1",1,2],$["23",3,5]]/;
+```
+
+#### <a name="1227-stdresplit-regex-string"></a>12.2.7 - std:re:split _regex_ _string_
+
+Splits _string_ at every match of _regex_ and returns the vector of parts:
+
+```wlambda
+std:assert_eq
+    (str ~ std:re:split $q/[,;] ?/ "a, b;c")
+    $q/$["a","b","c"]/;
+```
+
 
 ### <a name="123-chrono"></a>12.3 - chrono
 
@@ -2642,10 +4755,20 @@ generated from the given _sm_state_.
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 
+/// Upper limit for the size of the string created by `std:str:repeat`.
+const MAX_STR_REPEAT_LEN: usize = 1 << 30;
+
 use crate::compiler::*;
 use crate::vval::*;
 use crate::util;
+use crate::parser;
 use std::rc::Rc;
+use std::cell::RefCell;
+use std::collections::BTreeSet;
+use std::ops::Bound;
+use std::convert::TryFrom;
+use crate::decimal::{Decimal, RoundingMode, MAX_SCALE};
+use crate::numarray::{NumArray, ArrayOp, ArrayError};
 
 macro_rules! func {
     ($g: ident, $name: expr, $cb: expr, $min: expr, $max: expr, $err_arg_ok: expr) => {
@@ -2660,56 +4783,105 @@ macro_rules! add_func {
 }
 
 macro_rules! add_multi_op {
-    ($g: ident, $op: tt) => {
+    ($g: ident, $op: tt, $int_op: expr, $dec_op: path, $arr_op: expr, $method: literal, $rmethod: literal) => {
         add_func!($g, $op, env, argc, {
             if argc <= 0 { return Ok(VVal::Nul); }
-            if let VVal::Flt(f) = env.arg(0) {
-                let mut accum = f;
-                for i in 1..argc { accum = accum $op env.arg(i).f() }
-                Ok(VVal::Flt(accum))
-            } else {
-                let mut accum = env.arg(0).i();
-                for i in 1..argc { accum = accum $op env.arg(i).i() }
-                Ok(VVal::Int(accum))
+            let mut accum = env.arg(0);
+            for i in 1..argc {
+                let b = env.arg(i);
+                accum =
+                    match VVal::call_binop(env, $method, Some($rmethod), &accum, &b) {
+                        Some(ret) => ret?,
+                        None => {
+                            if let VVal::Flt(f) = accum { VVal::Flt(f $op b.f()) }
+                            else if let VVal::Dec(a) = accum {
+                                let b =
+                                    match b.try_d() {
+                                        Some(d) => d,
+                                        None => {
+                                            return Ok(env.new_err(
+                                                format!("Decimal overflow: {} {} {}",
+                                                        a, stringify!($op), b.s())));
+                                        },
+                                    };
+                                match $dec_op(*a, b) {
+                                    Some(d) => VVal::new_dec(d),
+                                    None if b.is_zero() => {
+                                        return Err(StackAction::panic_msg(
+                                            format!("Division by 0: {}{}{}", a, stringify!($op), b)));
+                                    },
+                                    None => {
+                                        return Ok(env.new_err(
+                                            format!("Decimal overflow: {} {} {}",
+                                                    a, stringify!($op), b)));
+                                    },
+                                }
+                            } else if let Some(r) = accum.elem_binop($arr_op, &b) {
+                                match r {
+                                    Ok(v) => v,
+                                    Err(e @ ArrayError::DivisionByZero(..)) => {
+                                        return Err(StackAction::panic_msg(e.to_string()));
+                                    },
+                                    Err(e) => { return Ok(env.new_err(e.to_string())); },
+                                }
+                            } else {
+                                let (a, b) = (accum.i(), b.i());
+                                match $int_op(a, b) {
+                                    Some(i) => VVal::Int(i),
+                                    // Only `/` and `%` fail for a 0 operand:
+                                    None if b == 0 => {
+                                        return Err(StackAction::panic_msg(
+                                            format!("Division by 0: {}{}{}", a, stringify!($op), b)));
+                                    },
+                                    None => {
+                                        return Ok(env.new_err(
+                                            format!("Integer overflow: {} {} {}",
+                                                    a, stringify!($op), b)));
+                                    },
+                                }
+                            }
+                        },
+                    };
             }
+            Ok(accum)
         }, Some(2), None, false)
     }
 }
 
 macro_rules! add_bool_bin_op {
-    ($g: ident, $op: tt) => {
+    ($g: ident, $op: tt, $method: literal) => {
         add_func!($g, $op, env, argc, {
             if argc < 2 { return Ok(VVal::Nul); }
             let a = env.arg(0);
-            if let VVal::Flt(af) = a { Ok(VVal::Bol(af $op env.arg(1).f())) }
-            else { Ok(VVal::Bol(a.i() $op env.arg(1).i())) }
+            let b = env.arg(1);
+            if let Some(ret) = VVal::call_binop(env, $method, None, &a, &b) {
+                return ret;
+            }
+            if let VVal::Flt(af) = a { Ok(VVal::Bol(af $op b.f())) }
+            else if let VVal::Dec(ad) = a {
+                Ok(VVal::Bol(dec_partial_cmp(&ad, &b)
+                             .is_some_and(|o| o $op std::cmp::Ordering::Equal)))
+            }
+            else { Ok(VVal::Bol(a.i() $op b.i())) }
         }, Some(2), Some(2), false)
     }
 }
 
 macro_rules! add_fi_bin_op {
-    ($g: ident, $op: tt, $a: ident, $b: ident, $ef: expr, $ei: expr) => {
-        add_func!($g, $op, env, argc, {
+    ($g: ident, $op: tt, $env: ident, $a: ident, $b: ident, $ef: expr, $ed: expr, $ei: expr) => {
+        add_func!($g, $op, $env, argc, {
             if argc < 2 { return Ok(VVal::Nul); }
-            let $a = env.arg(0);
-            let $b = env.arg(1);
-            if let VVal::Flt(_) = $a { $ef }
-            else { $ei }
+            let $a = $env.arg(0);
+            let $b = $env.arg(1);
+            match $a {
+                VVal::Flt(_) => $ef,
+                VVal::Dec(_) => $ed,
+                _            => $ei,
+            }
         }, Some(2), Some(2), false)
     }
 }
 
-macro_rules! add_bin_op_err_ok {
-    ($g: ident, $op: tt, $a: ident, $b: ident, $e: expr) => {
-        add_func!($g, $op, env, argc, {
-            if argc < 2 { return Ok(VVal::Nul); }
-            let $a = env.arg(0);
-            let $b = env.arg(1);
-            $e
-        }, Some(2), Some(2), true)
-    }
-}
-
 macro_rules! add_sbin_op {
     ($g: ident, $op: literal, $a: ident, $b: ident, $e: expr) => {
         func!($g,
@@ -2740,24 +4912,79 @@ macro_rules! add_num_fun_flt2 {
     }
 }
 
-fn match_next(env: &mut Env, val: &VVal, mut arg_idx: usize, argc: usize) -> Result<VVal, StackAction> {
-    while arg_idx < argc {
-        if env.arg(arg_idx).is_fun() {
-            return
-                env.with_restore_sp(|e: &mut Env| {
-                    e.push(val.clone());
-                    e.arg(arg_idx).call_internal(e, 1)
-                });
-        }
-
-        let mut match_vals = vec![arg_idx];
-        arg_idx += 1;
-        while arg_idx < argc && !env.arg(arg_idx).is_fun() {
-            match_vals.push(arg_idx);
-            arg_idx += 1;
-        }
-
-        if arg_idx >= argc { return Ok(val.clone()); }
+/// Raises `base` to the power of `exp` with one of the `pow` variants
+/// of `i64`, which only accept exponents in the range of `u32`.
+fn int_pow<R>(base: i64, exp: i64, pow: fn(i64, u32) -> R) -> Result<R, String> {
+    if exp < 0 {
+        return Err(format!("negative exponent: {}", exp));
+    }
+    match u32::try_from(exp) {
+        Ok(exp) => Ok(pow(base, exp)),
+        Err(_)  => Err(format!("exponent out of range: {}", exp)),
+    }
+}
+
+macro_rules! add_num_fun_checked {
+    ($g: ident, $op: literal, $e: tt) => {
+        func!($g, $op,
+            |env: &mut Env, _argc: usize| {
+                Ok(env.arg(0).i().$e(env.arg(1).i()).map_or(VVal::Nul, VVal::Int))
+            }, Some(2), Some(2), false);
+    }
+}
+
+macro_rules! add_num_fun_int2 {
+    ($g: ident, $op: literal, $e: tt) => {
+        func!($g, $op,
+            |env: &mut Env, _argc: usize| {
+                Ok(VVal::Int(env.arg(0).i().$e(env.arg(1).i())))
+            }, Some(2), Some(2), false);
+    }
+}
+
+macro_rules! add_num_fun_int2_div {
+    ($g: ident, $op: literal, $e: tt) => {
+        func!($g, $op,
+            |env: &mut Env, _argc: usize| {
+                let b = env.arg(1).i();
+                if b == 0 {
+                    return Ok(env.new_err(format!("{}: Division by 0", $op)));
+                }
+                Ok(VVal::Int(env.arg(0).i().$e(b)))
+            }, Some(2), Some(2), false);
+    }
+}
+
+macro_rules! add_num_fun_int_pow {
+    ($g: ident, $op: literal, $e: path) => {
+        func!($g, $op,
+            |env: &mut Env, _argc: usize| {
+                match int_pow(env.arg(0).i(), env.arg(1).i(), $e) {
+                    Ok(i)  => Ok(VVal::Int(i)),
+                    Err(e) => Ok(env.new_err(format!("{}: {}", $op, e))),
+                }
+            }, Some(2), Some(2), false);
+    }
+}
+
+fn match_next(env: &mut Env, val: &VVal, mut arg_idx: usize, argc: usize) -> Result<VVal, StackAction> {
+    while arg_idx < argc {
+        if env.arg(arg_idx).is_fun() {
+            return
+                env.with_restore_sp(|e: &mut Env| {
+                    e.push(val.clone());
+                    e.arg(arg_idx).call_internal(e, 1)
+                });
+        }
+
+        let mut match_vals = vec![arg_idx];
+        arg_idx += 1;
+        while arg_idx < argc && !env.arg(arg_idx).is_fun() {
+            match_vals.push(arg_idx);
+            arg_idx += 1;
+        }
+
+        if arg_idx >= argc { return Ok(val.clone()); }
 
         let fun_idx = arg_idx;
 
@@ -2798,6 +5025,16 @@ fn match_next(env: &mut Env, val: &VVal, mut arg_idx: usize, argc: usize) -> Res
                         }
                     }
                 },
+                "?v" => {
+                    if let VVal::Enum(e) = val.deref() {
+                        for i in match_vals.iter().skip(1) {
+                            if env.arg(*i).s_raw() == e.variant {
+                                return env.arg(fun_idx).call(
+                                    env, &[e.payload.clone(), val.clone()]);
+                            }
+                        }
+                    }
+                },
                 "?p" => {
                     if fun_idx + 1 >= argc { return Ok(VVal::Nul); }
                     let fun_idx = fun_idx + 1;
@@ -2836,19 +5073,35 @@ fn match_next(env: &mut Env, val: &VVal, mut arg_idx: usize, argc: usize) -> Res
 pub fn core_symbol_table() -> SymbolTable {
     let mut st = SymbolTable::new();
 
-    add_multi_op!(st, +);
-    add_multi_op!(st, -);
-    add_multi_op!(st, *);
-    add_multi_op!(st, /);
-    add_multi_op!(st, %);
+    add_multi_op!(st, +, i64::checked_add, Decimal::checked_add, ArrayOp::Add, "_add_", "_radd_");
+    add_multi_op!(st, -, i64::checked_sub, Decimal::checked_sub, ArrayOp::Sub, "_sub_", "_rsub_");
+    add_multi_op!(st, *, i64::checked_mul, Decimal::checked_mul, ArrayOp::Mul, "_mul_", "_rmul_");
+    add_multi_op!(st, /, i64::checked_div, Decimal::checked_div, ArrayOp::Div, "_div_", "_rdiv_");
+    add_multi_op!(st, %, i64::checked_rem, Decimal::checked_rem, ArrayOp::Rem, "_mod_", "_rmod_");
 
-    add_bool_bin_op!(st, <);
-    add_bool_bin_op!(st, >);
-    add_bool_bin_op!(st, <=);
-    add_bool_bin_op!(st, >=);
+    add_bool_bin_op!(st, <,  "_lt_");
+    add_bool_bin_op!(st, >,  "_gt_");
+    add_bool_bin_op!(st, <=, "_le_");
+    add_bool_bin_op!(st, >=, "_ge_");
 
-    add_bin_op_err_ok!(st, ==, a, b, Ok(VVal::Bol(a.eqv(&b))));
-    add_bin_op_err_ok!(st, !=, a, b, Ok(VVal::Bol(!a.eqv(&b))));
+    func!(st, "==",
+        |env: &mut Env, _argc: usize| {
+            let a = env.arg(0);
+            let b = env.arg(1);
+            match VVal::call_binop(env, "_eq_", None, &a, &b) {
+                Some(ret) => Ok(VVal::Bol(ret?.b())),
+                None      => Ok(VVal::Bol(a.eqv(&b))),
+            }
+        }, Some(2), Some(2), true);
+    func!(st, "!=",
+        |env: &mut Env, _argc: usize| {
+            let a = env.arg(0);
+            let b = env.arg(1);
+            match VVal::call_binop(env, "_eq_", None, &a, &b) {
+                Some(ret) => Ok(VVal::Bol(!ret?.b())),
+                None      => Ok(VVal::Bol(!a.eqv(&b))),
+            }
+        }, Some(2), Some(2), true);
 
     add_sbin_op!(st, "&|", a, b,
         Ok(VVal::Int(((a.i() as u32) | (b.i() as u32)) as i64)));
@@ -2861,9 +5114,19 @@ pub fn core_symbol_table() -> SymbolTable {
     add_sbin_op!(st, ">>", a, b,
         Ok(VVal::Int(((a.i() as u32) >> (b.i() as u32)) as i64)));
 
-    add_fi_bin_op!(st, ^, a, b,
+    add_fi_bin_op!(st, ^, env, a, b,
         Ok(VVal::Flt(a.f().powf(b.f()))),
-        Ok(VVal::Int(a.i().pow(b.i() as u32))));
+        match a.d().checked_pow(b.i()) {
+            Some(d) => Ok(VVal::new_dec(d)),
+            None    =>
+                Ok(env.new_err(format!("Decimal overflow: {} ^ {}", a.d(), b.i()))),
+        },
+        match int_pow(a.i(), b.i(), i64::checked_pow) {
+            Ok(Some(i)) => Ok(VVal::Int(i)),
+            Ok(None) =>
+                Ok(env.new_err(format!("Integer overflow: {} ^ {}", a.i(), b.i()))),
+            Err(e) => Ok(env.new_err(format!("^: {}", e))),
+        });
 
     func!(st, "not",
         |env: &mut Env, _argc: usize| {
@@ -2989,6 +5252,21 @@ pub fn core_symbol_table() -> SymbolTable {
     func!(st, "int",
         |env: &mut Env, _argc: usize| { Ok(VVal::Int(env.arg(0).i())) },
         Some(1), Some(1), false);
+    func!(st, "decimal",
+        |env: &mut Env, _argc: usize| {
+            match env.arg(0).try_d() {
+                Some(d) => Ok(VVal::new_dec(d)),
+                None    => Ok(env.new_err(
+                    format!("Decimal overflow: {}", env.arg(0).s()))),
+            }
+        },
+        Some(1), Some(1), false);
+    func!(st, "ivec",
+        |env: &mut Env, _argc: usize| { Ok(VVal::new_ivec(env.arg(0).nvec())) },
+        Some(1), Some(1), false);
+    func!(st, "fvec",
+        |env: &mut Env, _argc: usize| { Ok(VVal::new_fvec(env.arg(0).nvec())) },
+        Some(1), Some(1), false);
     func!(st, "str",
         |env: &mut Env, _argc: usize| { Ok(VVal::new_str_mv(env.arg(0).s_raw())) },
         Some(1), Some(1), false);
@@ -3037,6 +5315,9 @@ pub fn core_symbol_table() -> SymbolTable {
     func!(st, "is_int",
         |env: &mut Env, _argc: usize| { Ok(VVal::Bol(env.arg(0).is_int())) },
         Some(1), Some(1), true);
+    func!(st, "is_decimal",
+        |env: &mut Env, _argc: usize| { Ok(VVal::Bol(env.arg(0).is_decimal())) },
+        Some(1), Some(1), true);
 
     func!(st, "len",
         |env: &mut Env, _argc: usize| { Ok(VVal::Int(env.arg(0).len() as i64)) },
@@ -3082,8 +5363,17 @@ pub fn core_symbol_table() -> SymbolTable {
             let val = env.arg(0);
             let f   = env.arg(1);
 
+            let it = val.to_iter();
             let mut ret = VVal::Nul;
-            for (v, k) in val.iter() {
+            loop {
+                let (v, k) =
+                    match it.iter_next(env) {
+                        Ok(Some(item))             => item,
+                        Ok(None)                   => break,
+                        Err(StackAction::Break(v)) => { return Ok(v); },
+                        Err(StackAction::Next)     => { continue; },
+                        Err(e)                     => { return Err(e); }
+                    };
                 let n =
                     if let Some(k) = k { env.push(k); 2 }
                     else               { 1 };
@@ -3154,6 +5444,184 @@ pub fn core_symbol_table() -> SymbolTable {
     st
 }
 
+/// A compiled regular expression, as returned by `std:re:compile`.
+#[cfg(feature="regex")]
+#[derive(Clone)]
+struct VValRegex {
+    rx: regex::Regex,
+}
+
+#[cfg(feature="regex")]
+impl VValUserData for VValRegex {
+    fn s(&self) -> String { format!("$<regex:{}>", self.rx.as_str()) }
+    fn s_raw(&self) -> String { self.rx.as_str().to_string() }
+    fn eqv(&self, other: &Box<dyn VValUserData>) -> bool {
+        self.s() == other.s()
+    }
+    fn clone_ud(&self) -> Box<dyn VValUserData> { Box::new(self.clone()) }
+    fn call(&self, args: &[VVal]) -> Result<VVal, StackAction> {
+        let text = args.first().map(|v| v.s_raw()).unwrap_or_default();
+        Ok(match self.rx.captures(&text) {
+            Some(c) => captures_to_vec(&c),
+            None    => VVal::Nul,
+        })
+    }
+    fn as_any(&mut self) -> &mut dyn std::any::Any { self }
+}
+
+/// Returns the regex in argument `idx`, which is either a
+/// compiled regex from `std:re:compile` or a string that is compiled.
+/// Returns an error value if compiling the regex failed.
+#[cfg(feature="regex")]
+fn regex_arg(env: &mut Env, idx: usize) -> Result<regex::Regex, VVal> {
+    let re = env.arg(idx);
+    if let VVal::Usr(mut ud) = re {
+        if let Some(rx) = ud.as_any().downcast_ref::<VValRegex>() {
+            return Ok(rx.rx.clone());
+        }
+        return Err(env.new_err(
+            format!("Expected regex or string, got '{}'", ud.s())));
+    }
+
+    let re = re.s_raw();
+    regex::Regex::new(&re).map_err(|e|
+        env.new_err(format!("Regex '{}' did not compile: {}", re, e)))
+}
+
+#[cfg(feature="regex")]
+fn captures_to_vec(capts: &regex::Captures) -> VVal {
+    let captures = VVal::vec();
+    for cap in capts.iter() {
+        match cap {
+            None    => { captures.push(VVal::Nul); },
+            Some(c) => { captures.push(VVal::new_str(c.as_str())); },
+        }
+    }
+    captures
+}
+
+#[cfg(feature="regex")]
+fn named_captures_to_map(rx: &regex::Regex, capts: &regex::Captures) -> VVal {
+    let named = VVal::map();
+    for name in rx.capture_names().flatten() {
+        named.set_map_key(
+            name.to_string(),
+            capts.name(name)
+                 .map(|c| VVal::new_str(c.as_str()))
+                 .unwrap_or(VVal::Nul));
+    }
+    named
+}
+
+/// Returns true if the map of named groups should be passed as second
+/// argument to the callback function `f`. That is the case if the regex
+/// has named groups and `f` accepts 2 arguments.
+#[cfg(feature="regex")]
+fn pass_named_captures(rx: &regex::Regex, f: &VVal) -> bool {
+    if !rx.capture_names().any(|n| n.is_some()) {
+        return false;
+    }
+
+    match f {
+        VVal::Fun(fun) => !matches!(fun.max_args, Some(max) if max < 2),
+        _ => false,
+    }
+}
+
+/// Calls `f` with the value of an iterator item and it's key,
+/// if the item has one. Just like `for` does.
+fn call_with_iter_item(f: &VVal, env: &mut Env, v: VVal, k: Option<VVal>)
+    -> Result<VVal, StackAction>
+{
+    match k {
+        Some(k) => f.call(env, &[v, k]),
+        None    => f.call(env, &[v]),
+    }
+}
+
+/// Replaces the `Var` nodes in the AST data `ast` with the values
+/// in the map `vars`, used by `std:wlambda:subst`.
+fn subst_ast_vars(vars: &VVal, ast: &VVal) -> VVal {
+    if let VVal::Lst(l) = ast {
+        let head = ast.v_(0);
+        if head.is_map() && head.v_k("syn").s_raw() == "Var" {
+            if let Some(v) = vars.get_key(&ast.v_s_raw(1)) {
+                return v;
+            }
+        }
+
+        VVal::vec_mv(l.borrow().iter().map(|v| subst_ast_vars(vars, v)).collect())
+    } else {
+        ast.clone()
+    }
+}
+
+/// Collects the keys of a set or ordered map, or the items of
+/// any other iterable value (the keys for maps), used for the
+/// `std:set:*` functions.
+#[allow(clippy::mutable_key_type)]
+fn key_set_of(v: &VVal) -> BTreeSet<VValKey> {
+    match v.deref() {
+        VVal::Set(s)  => s.borrow().clone(),
+        VVal::OMap(m) => m.borrow().keys().cloned().collect(),
+        v             => v.iter().map(|(v, k)| VValKey::new(&k.unwrap_or(v))).collect(),
+    }
+}
+
+/// Creates the numeric array for `std:arr:f64` and `std:arr:i64`.
+/// Bytes are decoded as little endian 8 byte numbers, numeric arrays
+/// are converted and the items of other values are collected.
+fn num_array_of(env: &mut Env, fname: &str, float: bool) -> Result<VVal, StackAction> {
+    let v = env.arg(0).deref();
+    let a =
+        match &v {
+            VVal::Byt(b) => {
+                let b = b.borrow();
+                let a =
+                    if float { NumArray::from_le_bytes_f64(&b) }
+                    else     { NumArray::from_le_bytes_i64(&b) };
+                match a {
+                    Some(a) => a,
+                    None => return Ok(env.new_err(format!(
+                        "{}: byte length is not a multiple of 8: {}",
+                        fname, b.len()))),
+                }
+            },
+            VVal::NArr(a) =>
+                if float { a.borrow().to_float() } else { a.borrow().to_int() },
+            _ =>
+                if float { NumArray::Flt(v.iter().map(|(x, _)| x.f()).collect()) }
+                else     { NumArray::Int(v.iter().map(|(x, _)| x.i()).collect()) },
+        };
+    Ok(VVal::new_narr(a))
+}
+
+/// Returns the numeric array argument `idx` of the `std:arr:*`
+/// function `fname`, or the error value to return if the argument
+/// is something else.
+fn num_array_arg(env: &mut Env, idx: usize, fname: &str)
+    -> Result<Rc<RefCell<NumArray>>, VVal>
+{
+    match env.arg(idx).deref() {
+        VVal::NArr(a) => Ok(a),
+        v => Err(env.new_err(
+                format!("{}: expected numeric array, got: {}", fname, v.s()))),
+    }
+}
+
+/// Returns the bounds for the range queries of `std:set:range` and
+/// `std:omap:range`. A `$none` as upper bound means no upper bound.
+/// Returns `None` if the range is empty.
+fn key_range(from: &VVal, to: &VVal) -> Option<(Bound<VValKey>, Bound<VValKey>)> {
+    let from = VValKey::new(from);
+    if to.is_none() {
+        return Some((Bound::Included(from), Bound::Unbounded));
+    }
+    let to = VValKey::new(to);
+    if from > to { return None; }
+    Some((Bound::Included(from), Bound::Excluded(to)))
+}
+
 fn print_value(env: &mut Env, argc: usize, raw: bool) -> Result<VVal, StackAction> {
     let mut write = env.stdio.write.borrow_mut();
 
@@ -3442,159 +5910,951 @@ pub fn std_symbol_table() -> SymbolTable {
                 }
                 Ok(s)
 
-            } else {
-                Ok(env.new_err(
-                    format!(
-                        "str:join only works with lists as second argument, got '{}'",
-                        lst.s())))
+            } else {
+                Ok(env.new_err(
+                    format!(
+                        "str:join only works with lists as second argument, got '{}'",
+                        lst.s())))
+            }
+        }, Some(2), Some(2), false);
+    func!(st, "str:format",
+        |env: &mut Env, argc: usize| {
+            use crate::formatter::{parse_format_string, split_arg_spec_strict, FmtPiece};
+
+            let fmt = env.arg(0).s_raw();
+            let pieces =
+                match parse_format_string(&fmt) {
+                    Ok(pieces) => pieces,
+                    Err(e) => {
+                        return Ok(env.new_err(
+                            format!("str:format: {}", e)));
+                    },
+                };
+
+            let named    = env.arg(argc - 1);
+            let mut next = 0;
+            let mut out  = String::with_capacity(fmt.len());
+
+            for piece in pieces {
+                let content =
+                    match piece {
+                        FmtPiece::Lit(s) => { out.push_str(&s); continue; },
+                        FmtPiece::Arg(content) => content,
+                    };
+
+                let (arg, spec) =
+                    match split_arg_spec_strict(&content) {
+                        Ok(arg_spec) => arg_spec,
+                        Err(e) => {
+                            return Ok(env.new_err(
+                                format!("str:format: {}", e)));
+                        },
+                    };
+
+                let v =
+                    if arg.is_empty() {
+                        next += 1;
+                        if next >= argc {
+                            return Ok(env.new_err(
+                                format!(
+                                    "str:format: missing argument {} for '{{{}}}'",
+                                    next - 1, content)));
+                        }
+                        env.arg(next)
+
+                    } else if let Ok(idx) = arg.parse::<usize>() {
+                        if idx >= argc - 1 {
+                            return Ok(env.new_err(
+                                format!(
+                                    "str:format: missing argument {} for '{{{}}}'",
+                                    idx, content)));
+                        }
+                        env.arg(idx + 1)
+
+                    } else if let Some(v) =
+                        if named.is_map() { named.get_key(&arg) } else { None }
+                    {
+                        v
+
+                    } else {
+                        return Ok(env.new_err(
+                            format!(
+                                "str:format: no named argument '{}' for '{{{}}}'",
+                                arg, content)));
+                    };
+
+                match spec {
+                    Some(fs) => out.push_str(&fs.format(&v)),
+                    None     => out.push_str(&v.s_raw()),
+                }
+            }
+
+            Ok(VVal::new_str_mv(out))
+        }, Some(1), None, false);
+    func!(st, "str:split",
+        |env: &mut Env, _argc: usize| {
+            let pat  = env.arg(0).s_raw();
+            let data = env.arg(1).s_raw();
+            Ok(VVal::vec_mv(
+                data.split(&pat[..]).map(VVal::new_str).collect()))
+        }, Some(2), Some(2), false);
+    func!(st, "str:split_n",
+        |env: &mut Env, _argc: usize| {
+            let pat  = env.arg(0).s_raw();
+            let cnt  = env.arg(1).i() as usize;
+            let data = env.arg(2).s_raw();
+            Ok(VVal::vec_mv(
+                data.splitn(cnt, &pat[..]).map(VVal::new_str).collect()))
+        }, Some(3), Some(3), false);
+    func!(st, "str:find",
+        |env: &mut Env, _argc: usize| {
+            let pat  = env.arg(0).s_raw();
+            let data = env.arg(1).s_raw();
+            Ok(match data.find(&pat[..]) {
+                Some(idx) => VVal::Int(data[0..idx].chars().count() as i64),
+                None      => VVal::Nul,
+            })
+        }, Some(2), Some(2), false);
+    func!(st, "str:rfind",
+        |env: &mut Env, _argc: usize| {
+            let pat  = env.arg(0).s_raw();
+            let data = env.arg(1).s_raw();
+            Ok(match data.rfind(&pat[..]) {
+                Some(idx) => VVal::Int(data[0..idx].chars().count() as i64),
+                None      => VVal::Nul,
+            })
+        }, Some(2), Some(2), false);
+    func!(st, "str:substr",
+        |env: &mut Env, argc: usize| {
+            let start = env.arg(0).i().max(0) as usize;
+            let (len, data) =
+                if argc == 2 { (None, env.arg(1).s_raw()) }
+                else { (Some(env.arg(1).i().max(0) as usize), env.arg(2).s_raw()) };
+
+            let chars = data.chars().skip(start);
+            Ok(VVal::new_str_mv(
+                match len {
+                    Some(len) => chars.take(len).collect(),
+                    None      => chars.collect(),
+                }))
+        }, Some(2), Some(3), false);
+    func!(st, "str:starts_with",
+        |env: &mut Env, _argc: usize| {
+            Ok(VVal::Bol(env.arg(1).s_raw().starts_with(&env.arg(0).s_raw()[..])))
+        }, Some(2), Some(2), false);
+    func!(st, "str:ends_with",
+        |env: &mut Env, _argc: usize| {
+            Ok(VVal::Bol(env.arg(1).s_raw().ends_with(&env.arg(0).s_raw()[..])))
+        }, Some(2), Some(2), false);
+    func!(st, "str:contains",
+        |env: &mut Env, _argc: usize| {
+            Ok(VVal::Bol(env.arg(1).s_raw().contains(&env.arg(0).s_raw()[..])))
+        }, Some(2), Some(2), false);
+    func!(st, "str:repeat",
+        |env: &mut Env, _argc: usize| {
+            let cnt = env.arg(0).i().max(0) as usize;
+            let s   = env.arg(1).s_raw();
+            match s.len().checked_mul(cnt) {
+                Some(len) if len <= MAX_STR_REPEAT_LEN =>
+                    Ok(VVal::new_str_mv(s.repeat(cnt))),
+                _ =>
+                    Ok(env.new_err(format!(
+                        "str:repeat: result of {} * {} bytes is too large",
+                        cnt, s.len()))),
+            }
+        }, Some(2), Some(2), false);
+    func!(st, "str:reverse",
+        |env: &mut Env, _argc: usize| {
+            Ok(VVal::new_str_mv(env.arg(0).s_raw().chars().rev().collect()))
+        }, Some(1), Some(1), false);
+    func!(st, "str:lines",
+        |env: &mut Env, _argc: usize| {
+            Ok(VVal::vec_mv(
+                env.arg(0).s_raw().lines().map(VVal::new_str).collect()))
+        }, Some(1), Some(1), false);
+    func!(st, "str:char_to_byte_offset",
+        |env: &mut Env, _argc: usize| {
+            let idx  = env.arg(0).i();
+            let data = env.arg(1).s_raw();
+            if idx < 0 { return Ok(VVal::Nul); }
+
+            let idx = idx as usize;
+            Ok(match data.char_indices().nth(idx) {
+                Some((offs, _)) => VVal::Int(offs as i64),
+                None if idx == data.chars().count() => VVal::Int(data.len() as i64),
+                None => VVal::Nul,
+            })
+        }, Some(2), Some(2), false);
+    func!(st, "str:byte_to_char_offset",
+        |env: &mut Env, _argc: usize| {
+            let offs = env.arg(0).i();
+            let data = env.arg(1).s_raw();
+            if offs < 0 || offs as usize > data.len() { return Ok(VVal::Nul); }
+
+            let offs = offs as usize;
+            if !data.is_char_boundary(offs) {
+                return Ok(env.new_err(
+                    format!(
+                        "str:byte_to_char_offset: byte offset {} is not at a char boundary",
+                        offs)));
+            }
+            Ok(VVal::Int(data[0..offs].chars().count() as i64))
+        }, Some(2), Some(2), false);
+    func!(st, "str:from_utf8_lossy",
+        |env: &mut Env, _argc: usize| {
+            let b = env.arg(0);
+            Ok(
+                if let VVal::Byt(u) = b {
+                    VVal::new_str_mv(String::from_utf8_lossy(&u.borrow()).to_string())
+                } else {
+                    VVal::Nul
+                })
+        }, Some(1), Some(1), false);
+    func!(st, "str:from_utf8",
+        |env: &mut Env, _argc: usize| {
+            let b = env.arg(0);
+            if let VVal::Byt(u) = b {
+                match String::from_utf8(u.borrow().to_vec()) {
+                    Ok(s) => Ok(VVal::new_str_mv(s)),
+                    Err(e) => {
+                        Ok(env.new_err(
+                            format!("str:from_utf8 decoding error: {}", e)))
+                    }
+                }
+            } else {
+                Ok(VVal::Nul)
+            }
+        }, Some(1), Some(1), false);
+
+    func!(st, "str:to_char_vec",
+        |env: &mut Env, _argc: usize| {
+            Ok(VVal::vec_mv(
+                env.arg(0).s_raw()
+                    .chars()
+                    .map(|c| VVal::Int(i64::from(c as u32)))
+                    .collect()))
+        }, Some(1), Some(1), false);
+
+    func!(st, "str:from_char_vec",
+        |env: &mut Env, _argc: usize| {
+            let mut s = String::new();
+            for (vc, _) in env.arg(0).iter() {
+                s.push(std::char::from_u32(vc.i() as u32).unwrap_or('?'));
+            }
+            Ok(VVal::new_str_mv(s))
+        }, Some(1), Some(1), false);
+
+    func!(st, "str:to_bytes",
+        |env: &mut Env, _argc: usize| {
+            Ok(VVal::new_byt(env.arg(0).as_bytes()))
+        }, Some(1), Some(1), false);
+
+    func!(st, "bytes:from_vec",
+        |env: &mut Env, _argc: usize| {
+            if let VVal::Lst(u) = env.arg(0) {
+                Ok(VVal::new_byt(u.borrow().iter().map(|v| v.i() as u8).collect()))
+
+            } else {
+                Ok(VVal::Nul)
+            }
+        }, Some(1), Some(1), false);
+
+    func!(st, "bytes:to_vec",
+        |env: &mut Env, _argc: usize| {
+            if let VVal::Byt(u) = env.arg(0) {
+                Ok(VVal::vec_mv(
+                    u.borrow().iter()
+                        .map(|u| VVal::Int(i64::from(*u)))
+                        .collect()))
+            } else {
+                Ok(VVal::vec_mv(
+                    env.arg(0).as_bytes().iter()
+                        .map(|u| VVal::Int(i64::from(*u)))
+                        .collect()))
+            }
+        }, Some(1), Some(1), false);
+
+    func!(st, "bytes:from_hex",
+        |env: &mut Env, _argc: usize| {
+            let s = env.arg(0).s_raw();
+            let out : Vec<u8> = Vec::with_capacity((s.len() + 1) / 2);
+            Ok(VVal::new_byt(
+                s.chars()
+                 .map(|c|
+                     match c { '0'..='9' => i16::from( 9 - (b'9' - (c as u8))),
+                               'a'..='f' => i16::from(15 - (b'f' - (c as u8))),
+                               'A'..='F' => i16::from(15 - (b'F' - (c as u8))),
+                               _ => -1 })
+                 .fold((256, out), |(last, mut out), c: i16|
+                     if c == -1 { (last, out) }
+                     else if last == 256 { (c, out) }
+                     else {
+                         out.push((((last << 4) | (c & 0x0F)) & 0xFF) as u8);
+                         (256, out)
+                     }).1))
+        }, Some(1), Some(1), false);
+
+    func!(st, "bytes:to_hex",
+        |env: &mut Env, argc: usize| {
+            static HEXCHARS : &[char] =
+                &['0', '1', '2', '3', '4', '5', '6', '7',
+                  '8', '9', 'A', 'B', 'C', 'D', 'E', 'F'];
+
+            if let VVal::Byt(u) = env.arg(0) {
+                let mut out : String = String::with_capacity(u.borrow().len() * 2);
+
+                if argc == 1 {
+                    for (a, b) in u.borrow().iter().map(|u|
+                                        (HEXCHARS[(u >> 4) as usize],
+                                         HEXCHARS[(u & 0x0F) as usize])) {
+                        out.push(a);
+                        out.push(b);
+                    }
+                } else {
+                    let group_len = env.arg(1).i();
+                    let group_sep =
+                        if env.arg(2).is_none() { String::from(" ") }
+                        else { env.arg(2).s_raw() };
+
+                    let mut len_counter = 0;
+                    for (a, b) in u.borrow().iter().map(|u|
+                                        (HEXCHARS[(u >> 4) as usize],
+                                         HEXCHARS[(u & 0x0F) as usize])) {
+                        if len_counter >= group_len { out.push_str(&group_sep); len_counter = 0; }
+                        out.push(a);
+                        len_counter += 1;
+                        if len_counter >= group_len { out.push_str(&group_sep); len_counter = 0; }
+                        out.push(b);
+                        len_counter += 1;
+                    }
+                }
+
+                Ok(VVal::new_str_mv(out))
+
+            } else {
+                Ok(VVal::Nul)
+            }
+        }, Some(1), Some(3), false);
+
+    func!(st, "write_str",
+        |env: &mut Env, _argc: usize| {
+            Ok(VVal::new_str_mv(env.arg(0).s()))
+        }, Some(1), Some(1), true);
+
+    func!(st, "to_no_arity",
+        |env: &mut Env, _argc: usize| {
+            let v = env.arg(0);
+            Ok(v.disable_function_arity())
+        }, Some(1), Some(1), false);
+
+    func!(st, "iter:range",
+        |env: &mut Env, argc: usize| {
+            let from = env.arg(0);
+            let to   = env.arg(1);
+            let step = if argc > 2 { env.arg(2) } else { VVal::Int(1) };
+
+            if let VVal::Flt(_) = from {
+                let mut from = from.f();
+                let to       = to.f();
+                let step     = step.f();
+                if step == 0.0 {
+                    return Ok(env.new_err(
+                        "iter:range: step must not be 0".to_string()));
+                }
+                // NaN never compares as being past the end:
+                if !step.is_finite() || from.is_nan() || to.is_nan() {
+                    return Ok(env.new_err(
+                        format!("iter:range: bad float range: {} {} {}",
+                                from, to, step)));
+                }
+
+                Ok(VVal::new_iter(Box::new(move |_env: &mut Env| {
+                    if (step > 0.0 && from > to) || (step < 0.0 && from < to) {
+                        return Ok(None);
+                    }
+                    let v = VVal::Flt(from);
+                    from += step;
+                    Ok(Some((v, None)))
+                })))
+            } else {
+                let mut from = Some(from.i());
+                let to       = to.i();
+                let step     = step.i();
+                if step == 0 {
+                    return Ok(env.new_err(
+                        "iter:range: step must not be 0".to_string()));
+                }
+
+                Ok(VVal::new_iter(Box::new(move |_env: &mut Env| {
+                    let cur =
+                        match from {
+                            Some(cur) => cur,
+                            None      => return Ok(None),
+                        };
+                    if (step > 0 && cur > to) || (step < 0 && cur < to) {
+                        return Ok(None);
+                    }
+                    // Stop instead of overflowing at the end of the i64 range:
+                    from = cur.checked_add(step);
+                    Ok(Some((VVal::Int(cur), None)))
+                })))
+            }
+        }, Some(2), Some(3), false);
+
+    func!(st, "iter:next",
+        |env: &mut Env, _argc: usize| {
+            let it = env.arg(0);
+            match it.iter_next(env)? {
+                Some((v, _)) => Ok(v),
+                None         => Ok(VVal::Nul),
+            }
+        }, Some(1), Some(1), false);
+
+    func!(st, "iter:map",
+        |env: &mut Env, _argc: usize| {
+            let f  = env.arg(0);
+            let it = env.arg(1).to_iter();
+            Ok(VVal::new_iter(Box::new(move |env: &mut Env| {
+                match it.iter_next(env)? {
+                    Some((v, k)) => {
+                        let v = call_with_iter_item(&f, env, v, k.clone())?;
+                        Ok(Some((v, k)))
+                    },
+                    None => Ok(None),
+                }
+            })))
+        }, Some(2), Some(2), false);
+
+    func!(st, "iter:filter",
+        |env: &mut Env, _argc: usize| {
+            let f  = env.arg(0);
+            let it = env.arg(1).to_iter();
+            Ok(VVal::new_iter(Box::new(move |env: &mut Env| {
+                while let Some((v, k)) = it.iter_next(env)? {
+                    if call_with_iter_item(&f, env, v.clone(), k.clone())?.b() {
+                        return Ok(Some((v, k)));
+                    }
+                }
+                Ok(None)
+            })))
+        }, Some(2), Some(2), false);
+
+    func!(st, "iter:take",
+        |env: &mut Env, _argc: usize| {
+            let mut cnt = env.arg(0).i();
+            let it      = env.arg(1).to_iter();
+            Ok(VVal::new_iter(Box::new(move |env: &mut Env| {
+                if cnt <= 0 { return Ok(None); }
+                cnt -= 1;
+                it.iter_next(env)
+            })))
+        }, Some(2), Some(2), false);
+
+    func!(st, "iter:skip",
+        |env: &mut Env, _argc: usize| {
+            let mut cnt = env.arg(0).i();
+            let it      = env.arg(1).to_iter();
+            Ok(VVal::new_iter(Box::new(move |env: &mut Env| {
+                while cnt > 0 {
+                    cnt -= 1;
+                    if it.iter_next(env)?.is_none() { return Ok(None); }
+                }
+                it.iter_next(env)
+            })))
+        }, Some(2), Some(2), false);
+
+    func!(st, "iter:chain",
+        |env: &mut Env, argc: usize| {
+            let iters : Vec<VVal> = (0..argc).map(|i| env.arg(i).to_iter()).collect();
+            let mut idx = 0;
+            Ok(VVal::new_iter(Box::new(move |env: &mut Env| {
+                while idx < iters.len() {
+                    if let Some(item) = iters[idx].iter_next(env)? {
+                        return Ok(Some(item));
+                    }
+                    idx += 1;
+                }
+                Ok(None)
+            })))
+        }, Some(1), None, false);
+
+    func!(st, "iter:zip",
+        |env: &mut Env, argc: usize| {
+            let iters : Vec<VVal> = (0..argc).map(|i| env.arg(i).to_iter()).collect();
+            Ok(VVal::new_iter(Box::new(move |env: &mut Env| {
+                let tuple = VVal::vec();
+                for it in iters.iter() {
+                    match it.iter_next(env)? {
+                        Some((v, _)) => { tuple.push(v); },
+                        None         => { return Ok(None); },
+                    }
+                }
+                Ok(Some((tuple, None)))
+            })))
+        }, Some(1), None, false);
+
+    func!(st, "iter:enumerate",
+        |env: &mut Env, _argc: usize| {
+            let it      = env.arg(0).to_iter();
+            let mut idx = 0;
+            Ok(VVal::new_iter(Box::new(move |env: &mut Env| {
+                match it.iter_next(env)? {
+                    Some((v, _)) => {
+                        idx += 1;
+                        Ok(Some((v, Some(VVal::Int(idx - 1)))))
+                    },
+                    None => Ok(None),
+                }
+            })))
+        }, Some(1), Some(1), false);
+
+    func!(st, "class",
+        |env: &mut Env, argc: usize| {
+            let name = env.arg(0).s_raw();
+            let (parent, members) =
+                if argc == 3 { (env.arg(1).deref(), env.arg(2).deref()) }
+                else         { (VVal::Nul, env.arg(1).deref()) };
+
+            if !members.is_map() {
+                return Ok(env.new_err(
+                    format!("class: expected map of class members, got: {}",
+                            members.s())));
+            }
+            if !parent.is_none() && !parent.is_map() {
+                return Ok(env.new_err(
+                    format!("class: expected parent class, got: {}",
+                            parent.s())));
+            }
+
+            let class = VVal::map();
+            for (v, k) in members.iter() {
+                if let Some(k) = k {
+                    class.set_key(&k, v)?;
+                }
+            }
+            if !parent.is_none() {
+                class.set_map_key("_proto".to_string(), parent);
+            }
+            class.set_map_key("_name".to_string(), VVal::new_str_mv(name));
+            class.set_map_key("new".to_string(), VValFun::new_fun(
+                |env: &mut Env, argc: usize| {
+                    let class = env.self_object();
+                    let inst  = VVal::map();
+                    inst.set_map_key("_proto".to_string(), class.clone());
+                    inst.set_map_key("_data".to_string(), VVal::map());
+
+                    if let Some(init) = class.proto_lookup("init") {
+                        let args : Vec<VVal> =
+                            (0..argc).map(|i| env.arg(i)).collect();
+                        env.with_object(inst.clone(), |e: &mut Env| {
+                            init.call(e, &args)
+                        })?;
+                    }
+
+                    Ok(inst)
+                }, None, None, false));
+
+            Ok(class)
+        }, Some(2), Some(3), false);
+
+    func!(st, "super",
+        |env: &mut Env, argc: usize| {
+            let class  = env.arg(0).deref();
+            let method = env.arg(1).s_raw();
+
+            let fun =
+                match class.proto_lookup(&method) {
+                    Some(fun) => fun,
+                    None => {
+                        return Ok(env.new_err(
+                            format!("super: no method '{}' in class '{}'",
+                                    method, class.v_k("_name").s_raw())));
+                    },
+                };
+
+            let args : Vec<VVal> = (2..argc).map(|i| env.arg(i)).collect();
+            let obj = env.self_object();
+            env.with_object(obj, |e: &mut Env| fun.call(e, &args))
+        }, Some(2), None, false);
+
+    func!(st, "instanceof",
+        |env: &mut Env, _argc: usize| {
+            let class = env.arg(0).deref();
+            let value = env.arg(1).deref();
+
+            let mut cur =
+                match value {
+                    VVal::Map(_) => value.v_k("_proto"),
+                    VVal::Lst(_) => value.v_(0),
+                    _            => VVal::Nul,
+                };
+
+            // A cyclic prototype chain ends at the first repeated map:
+            let mut seen = std::collections::HashSet::new();
+            while cur.is_map() && seen.insert(cur.deref().ref_id()) {
+                if cur.eqv(&class) {
+                    return Ok(VVal::Bol(true));
+                }
+                cur = cur.v_k("_proto");
+            }
+
+            Ok(VVal::Bol(false))
+        }, Some(2), Some(2), false);
+
+    func!(st, "enum",
+        |env: &mut Env, _argc: usize| {
+            let name     = env.arg(0).s_raw();
+            let variants = env.arg(1).deref();
+
+            if !variants.is_vec() {
+                return Ok(env.new_err(
+                    format!("enum: expected vector of variant names, got: {}",
+                            variants.s())));
+            }
+
+            let enm = VVal::map();
+            enm.set_map_key("_name".to_string(), VVal::new_str(&name));
+            for (variant, _) in variants.iter() {
+                let variant = variant.s_raw();
+                let name    = name.clone();
+                let ctor_variant = variant.clone();
+                enm.set_map_key(variant, VValFun::new_fun(
+                    move |env: &mut Env, argc: usize| {
+                        let payload =
+                            match argc {
+                                0 => VVal::Nul,
+                                1 => env.arg(0),
+                                _ => {
+                                    let v = VVal::vec();
+                                    for i in 0..argc { v.push(env.arg(i)); }
+                                    v
+                                },
+                            };
+                        Ok(VVal::new_enum(&name, &ctor_variant, payload))
+                    }, None, None, false));
+            }
+
+            Ok(enm)
+        }, Some(2), Some(2), false);
+
+    func!(st, "enum:tag",
+        |env: &mut Env, _argc: usize| {
+            match env.arg(0).deref() {
+                VVal::Enum(e) => Ok(VVal::new_sym(&e.variant)),
+                _             => Ok(VVal::Nul),
+            }
+        }, Some(1), Some(1), false);
+
+    func!(st, "enum:payload",
+        |env: &mut Env, _argc: usize| {
+            match env.arg(0).deref() {
+                VVal::Enum(e) => Ok(e.payload.clone()),
+                _             => Ok(VVal::Nul),
+            }
+        }, Some(1), Some(1), false);
+
+    func!(st, "enum:name",
+        |env: &mut Env, _argc: usize| {
+            match env.arg(0).deref() {
+                VVal::Enum(e) => Ok(VVal::new_str(&e.name)),
+                _             => Ok(VVal::Nul),
+            }
+        }, Some(1), Some(1), false);
+
+    func!(st, "set:new",
+        |env: &mut Env, argc: usize| {
+            if argc == 0 { return Ok(VVal::new_set()); }
+            Ok(VVal::Set(Rc::new(RefCell::new(key_set_of(&env.arg(0))))))
+        }, Some(0), Some(1), false);
+
+    func!(st, "set:add",
+        |env: &mut Env, _argc: usize| {
+            match env.arg(0).deref() {
+                VVal::Set(s) => { s.borrow_mut().insert(VValKey::new(&env.arg(1))); },
+                v => return Ok(env.new_err(
+                        format!("set:add: expected set, got: {}", v.s()))),
+            }
+            Ok(env.arg(0))
+        }, Some(2), Some(2), false);
+
+    func!(st, "set:remove",
+        |env: &mut Env, _argc: usize| {
+            match env.arg(0).deref() {
+                VVal::Set(s) =>
+                    Ok(VVal::Bol(s.borrow_mut().remove(&VValKey::new(&env.arg(1))))),
+                v => Ok(env.new_err(
+                        format!("set:remove: expected set, got: {}", v.s()))),
+            }
+        }, Some(2), Some(2), false);
+
+    func!(st, "set:contains",
+        |env: &mut Env, _argc: usize| {
+            match env.arg(0).deref() {
+                VVal::Set(s) =>
+                    Ok(VVal::Bol(s.borrow().contains(&VValKey::new(&env.arg(1))))),
+                v => Ok(env.new_err(
+                        format!("set:contains: expected set, got: {}", v.s()))),
+            }
+        }, Some(2), Some(2), false);
+
+    func!(st, "set:union",
+        |env: &mut Env, _argc: usize| {
+            Ok(VVal::Set(Rc::new(RefCell::new(
+                key_set_of(&env.arg(0))
+                    .union(&key_set_of(&env.arg(1))).cloned().collect()))))
+        }, Some(2), Some(2), false);
+
+    func!(st, "set:intersection",
+        |env: &mut Env, _argc: usize| {
+            Ok(VVal::Set(Rc::new(RefCell::new(
+                key_set_of(&env.arg(0))
+                    .intersection(&key_set_of(&env.arg(1))).cloned().collect()))))
+        }, Some(2), Some(2), false);
+
+    func!(st, "set:difference",
+        |env: &mut Env, _argc: usize| {
+            Ok(VVal::Set(Rc::new(RefCell::new(
+                key_set_of(&env.arg(0))
+                    .difference(&key_set_of(&env.arg(1))).cloned().collect()))))
+        }, Some(2), Some(2), false);
+
+    func!(st, "set:range",
+        |env: &mut Env, _argc: usize| {
+            let out = VVal::vec();
+            match env.arg(0).deref() {
+                VVal::Set(s) => {
+                    if let Some(range) = key_range(&env.arg(1), &env.arg(2)) {
+                        for k in s.borrow().range(range) {
+                            out.push(k.value().clone());
+                        }
+                    }
+                },
+                v => return Ok(env.new_err(
+                        format!("set:range: expected set, got: {}", v.s()))),
+            }
+            Ok(out)
+        }, Some(3), Some(3), false);
+
+    func!(st, "omap:new",
+        |env: &mut Env, argc: usize| {
+            let m = VVal::new_omap();
+            if argc == 0 { return Ok(m); }
+            for (v, k) in env.arg(0).iter() {
+                match k {
+                    Some(k) => m.set_key(&k, v)?,
+                    None    => m.set_key(&v.v_(0), v.v_(1))?,
+                }
+            }
+            Ok(m)
+        }, Some(0), Some(1), false);
+
+    func!(st, "omap:get",
+        |env: &mut Env, _argc: usize| {
+            match env.arg(0).deref() {
+                VVal::OMap(m) =>
+                    Ok(m.borrow().get(&VValKey::new(&env.arg(1)))
+                        .cloned().unwrap_or(VVal::Nul)),
+                v => Ok(env.new_err(
+                        format!("omap:get: expected omap, got: {}", v.s()))),
+            }
+        }, Some(2), Some(2), false);
+
+    func!(st, "omap:set",
+        |env: &mut Env, _argc: usize| {
+            match env.arg(0).deref() {
+                VVal::OMap(m) => {
+                    m.borrow_mut().insert(VValKey::new(&env.arg(1)), env.arg(2));
+                    Ok(env.arg(2))
+                },
+                v => Ok(env.new_err(
+                        format!("omap:set: expected omap, got: {}", v.s()))),
+            }
+        }, Some(3), Some(3), false);
+
+    func!(st, "omap:remove",
+        |env: &mut Env, _argc: usize| {
+            match env.arg(0).deref() {
+                VVal::OMap(m) =>
+                    Ok(m.borrow_mut().remove(&VValKey::new(&env.arg(1)))
+                        .unwrap_or(VVal::Nul)),
+                v => Ok(env.new_err(
+                        format!("omap:remove: expected omap, got: {}", v.s()))),
             }
         }, Some(2), Some(2), false);
-    func!(st, "str:from_utf8_lossy",
+
+    func!(st, "omap:contains",
         |env: &mut Env, _argc: usize| {
-            let b = env.arg(0);
-            Ok(
-                if let VVal::Byt(u) = b {
-                    VVal::new_str_mv(String::from_utf8_lossy(&u.borrow()).to_string())
-                } else {
-                    VVal::Nul
-                })
+            match env.arg(0).deref() {
+                VVal::OMap(m) =>
+                    Ok(VVal::Bol(m.borrow().contains_key(&VValKey::new(&env.arg(1))))),
+                v => Ok(env.new_err(
+                        format!("omap:contains: expected omap, got: {}", v.s()))),
+            }
+        }, Some(2), Some(2), false);
+
+    func!(st, "omap:keys",
+        |env: &mut Env, _argc: usize| {
+            match env.arg(0).deref() {
+                VVal::OMap(m) =>
+                    Ok(VVal::vec_mv(
+                        m.borrow().keys().map(|k| k.value().clone()).collect())),
+                v => Ok(env.new_err(
+                        format!("omap:keys: expected omap, got: {}", v.s()))),
+            }
         }, Some(1), Some(1), false);
-    func!(st, "str:from_utf8",
+
+    func!(st, "omap:range",
         |env: &mut Env, _argc: usize| {
-            let b = env.arg(0);
-            if let VVal::Byt(u) = b {
-                match String::from_utf8(u.borrow().to_vec()) {
-                    Ok(s) => Ok(VVal::new_str_mv(s)),
-                    Err(e) => {
-                        Ok(env.new_err(
-                            format!("str:from_utf8 decoding error: {}", e)))
+            let out = VVal::new_omap();
+            match env.arg(0).deref() {
+                VVal::OMap(m) => {
+                    if let Some(range) = key_range(&env.arg(1), &env.arg(2)) {
+                        for (k, v) in m.borrow().range(range) {
+                            out.set_key(k.value(), v.clone())?;
+                        }
                     }
-                }
-            } else {
-                Ok(VVal::Nul)
+                },
+                v => return Ok(env.new_err(
+                        format!("omap:range: expected omap, got: {}", v.s()))),
             }
-        }, Some(1), Some(1), false);
+            Ok(out)
+        }, Some(3), Some(3), false);
 
-    func!(st, "str:to_char_vec",
+    func!(st, "arr:f64",
         |env: &mut Env, _argc: usize| {
-            Ok(VVal::vec_mv(
-                env.arg(0).s_raw()
-                    .chars()
-                    .map(|c| VVal::Int(i64::from(c as u32)))
-                    .collect()))
+            num_array_of(env, "arr:f64", true)
         }, Some(1), Some(1), false);
 
-    func!(st, "str:from_char_vec",
+    func!(st, "arr:i64",
         |env: &mut Env, _argc: usize| {
-            let mut s = String::new();
-            for (vc, _) in env.arg(0).iter() {
-                s.push(std::char::from_u32(vc.i() as u32).unwrap_or('?'));
-            }
-            Ok(VVal::new_str_mv(s))
+            num_array_of(env, "arr:i64", false)
         }, Some(1), Some(1), false);
 
-    func!(st, "str:to_bytes",
+    func!(st, "arr:to_vec",
         |env: &mut Env, _argc: usize| {
-            Ok(VVal::new_byt(env.arg(0).as_bytes()))
+            if let Err(e) = num_array_arg(env, 0, "arr:to_vec") { return Ok(e); }
+            Ok(VVal::vec_mv(env.arg(0).iter().map(|(v, _)| v).collect()))
         }, Some(1), Some(1), false);
 
-    func!(st, "bytes:from_vec",
+    func!(st, "arr:to_bytes",
         |env: &mut Env, _argc: usize| {
-            if let VVal::Lst(u) = env.arg(0) {
-                Ok(VVal::new_byt(u.borrow().iter().map(|v| v.i() as u8).collect()))
-
-            } else {
-                Ok(VVal::Nul)
+            match num_array_arg(env, 0, "arr:to_bytes") {
+                Ok(a)  => Ok(VVal::new_byt(a.borrow().to_le_bytes())),
+                Err(e) => Ok(e),
             }
         }, Some(1), Some(1), false);
 
-    func!(st, "bytes:to_vec",
+    func!(st, "arr:sum",
         |env: &mut Env, _argc: usize| {
-            if let VVal::Byt(u) = env.arg(0) {
-                Ok(VVal::vec_mv(
-                    u.borrow().iter()
-                        .map(|u| VVal::Int(i64::from(*u)))
-                        .collect()))
-            } else {
-                Ok(VVal::vec_mv(
-                    env.arg(0).as_bytes().iter()
-                        .map(|u| VVal::Int(i64::from(*u)))
-                        .collect()))
+            let a =
+                match num_array_arg(env, 0, "arr:sum") {
+                    Ok(a)  => a,
+                    Err(e) => return Ok(e),
+                };
+            let sum = a.borrow().sum();
+            match sum {
+                Ok(n)  => Ok(VVal::from_num(n)),
+                Err(e) => Ok(env.new_err(format!("arr:sum: {}", e))),
             }
         }, Some(1), Some(1), false);
 
-    func!(st, "bytes:from_hex",
+    func!(st, "arr:min",
         |env: &mut Env, _argc: usize| {
-            let s = env.arg(0).s_raw();
-            let out : Vec<u8> = Vec::with_capacity((s.len() + 1) / 2);
-            Ok(VVal::new_byt(
-                s.chars()
-                 .map(|c|
-                     match c { '0'..='9' => i16::from( 9 - (b'9' - (c as u8))),
-                               'a'..='f' => i16::from(15 - (b'f' - (c as u8))),
-                               'A'..='F' => i16::from(15 - (b'F' - (c as u8))),
-                               _ => -1 })
-                 .fold((256, out), |(last, mut out), c: i16|
-                     if c == -1 { (last, out) }
-                     else if last == 256 { (c, out) }
-                     else {
-                         out.push((((last << 4) | (c & 0x0F)) & 0xFF) as u8);
-                         (256, out)
-                     }).1))
+            match num_array_arg(env, 0, "arr:min") {
+                Ok(a)  => Ok(a.borrow().min().map(VVal::from_num).unwrap_or(VVal::Nul)),
+                Err(e) => Ok(e),
+            }
         }, Some(1), Some(1), false);
 
-    func!(st, "bytes:to_hex",
-        |env: &mut Env, argc: usize| {
-            static HEXCHARS : &[char] =
-                &['0', '1', '2', '3', '4', '5', '6', '7',
-                  '8', '9', 'A', 'B', 'C', 'D', 'E', 'F'];
+    func!(st, "arr:max",
+        |env: &mut Env, _argc: usize| {
+            match num_array_arg(env, 0, "arr:max") {
+                Ok(a)  => Ok(a.borrow().max().map(VVal::from_num).unwrap_or(VVal::Nul)),
+                Err(e) => Ok(e),
+            }
+        }, Some(1), Some(1), false);
 
-            if let VVal::Byt(u) = env.arg(0) {
-                let mut out : String = String::with_capacity(u.borrow().len() * 2);
+    func!(st, "arr:mean",
+        |env: &mut Env, _argc: usize| {
+            match num_array_arg(env, 0, "arr:mean") {
+                Ok(a)  => Ok(a.borrow().mean().map(VVal::Flt).unwrap_or(VVal::Nul)),
+                Err(e) => Ok(e),
+            }
+        }, Some(1), Some(1), false);
 
-                if argc == 1 {
-                    for (a, b) in u.borrow().iter().map(|u|
-                                        (HEXCHARS[(u >> 4) as usize],
-                                         HEXCHARS[(u & 0x0F) as usize])) {
-                        out.push(a);
-                        out.push(b);
-                    }
-                } else {
-                    let group_len = env.arg(1).i();
-                    let group_sep =
-                        if env.arg(2).is_none() { String::from(" ") }
-                        else { env.arg(2).s_raw() };
+    func!(st, "arr:dot",
+        |env: &mut Env, _argc: usize| {
+            let a =
+                match num_array_arg(env, 0, "arr:dot") {
+                    Ok(a)  => a,
+                    Err(e) => return Ok(e),
+                };
+            let b =
+                match num_array_arg(env, 1, "arr:dot") {
+                    Ok(b)  => b,
+                    Err(e) => return Ok(e),
+                };
+            let dot = a.borrow().dot(&b.borrow());
+            match dot {
+                Ok(n)  => Ok(VVal::from_num(n)),
+                Err(e) => Ok(env.new_err(format!("arr:dot: {}", e))),
+            }
+        }, Some(2), Some(2), false);
 
-                    let mut len_counter = 0;
-                    for (a, b) in u.borrow().iter().map(|u|
-                                        (HEXCHARS[(u >> 4) as usize],
-                                         HEXCHARS[(u & 0x0F) as usize])) {
-                        if len_counter >= group_len { out.push_str(&group_sep); len_counter = 0; }
-                        out.push(a);
-                        len_counter += 1;
-                        if len_counter >= group_len { out.push_str(&group_sep); len_counter = 0; }
-                        out.push(b);
-                        len_counter += 1;
-                    }
-                }
+    func!(st, "arr:slice",
+        |env: &mut Env, argc: usize| {
+            let a =
+                match num_array_arg(env, 0, "arr:slice") {
+                    Ok(a)  => a,
+                    Err(e) => return Ok(e),
+                };
+            let len   = a.borrow().len();
+            let start = env.arg(1).i().max(0) as usize;
+            let end   =
+                if argc > 2 { env.arg(2).i().max(0) as usize }
+                else        { len };
+            let slice = a.borrow().slice(start, end);
+            Ok(VVal::new_narr(slice))
+        }, Some(2), Some(3), false);
+
+    func!(st, "v:mag2",
+        |env: &mut Env, _argc: usize| {
+            Ok(VVal::Flt(env.arg(0).nvec::<f64>().mag2()))
+        }, Some(1), Some(1), false);
 
-                Ok(VVal::new_str_mv(out))
+    func!(st, "v:mag",
+        |env: &mut Env, _argc: usize| {
+            Ok(VVal::Flt(env.arg(0).nvec::<f64>().mag()))
+        }, Some(1), Some(1), false);
 
-            } else {
-                Ok(VVal::Nul)
-            }
-        }, Some(1), Some(3), false);
+    func!(st, "v:norm",
+        |env: &mut Env, _argc: usize| {
+            Ok(VVal::new_fvec(env.arg(0).nvec::<f64>().norm()))
+        }, Some(1), Some(1), false);
 
-    func!(st, "write_str",
+    func!(st, "v:dot",
         |env: &mut Env, _argc: usize| {
-            Ok(VVal::new_str_mv(env.arg(0).s()))
-        }, Some(1), Some(1), true);
+            let (a, b) = (env.arg(0), env.arg(1));
+            let r =
+                if let Some(a) = a.deref().as_ivec() { a.dot(&b.nvec()).map(VVal::Int) }
+                else { a.nvec::<f64>().dot(&b.nvec()).map(VVal::Flt) };
+            match r {
+                Ok(v)  => Ok(v),
+                Err(e) => Ok(env.new_err(format!("v:dot: {}", e))),
+            }
+        }, Some(2), Some(2), false);
 
-    func!(st, "to_no_arity",
+    func!(st, "v:cross",
         |env: &mut Env, _argc: usize| {
-            let v = env.arg(0);
-            Ok(v.disable_function_arity())
-        }, Some(1), Some(1), false);
+            let (a, b) = (env.arg(0), env.arg(1));
+            let r =
+                if let Some(a) = a.deref().as_ivec() { a.cross(&b.nvec()).map(VVal::new_ivec) }
+                else { a.nvec::<f64>().cross(&b.nvec()).map(VVal::new_fvec) };
+            match r {
+                Ok(v)  => Ok(v),
+                Err(e) => Ok(env.new_err(format!("v:cross: {}", e))),
+            }
+        }, Some(2), Some(2), false);
 
     func!(st, "to_drop",
         |env: &mut Env, _argc: usize| {
@@ -3700,11 +6960,77 @@ pub fn std_symbol_table() -> SymbolTable {
 
     func!(st, "num:abs",
         |env: &mut Env, _argc: usize| {
-            Ok(match env.arg(0) {
-                VVal::Int(i) => VVal::Int(i.abs()),
-                VVal::Flt(i) => VVal::Flt(i.abs()),
-                _ => VVal::Int(env.arg(0).i().abs())
-            })
+            let i =
+                match env.arg(0) {
+                    VVal::Flt(f) => return Ok(VVal::Flt(f.abs())),
+                    VVal::Dec(d) => return Ok(VVal::new_dec(d.abs())),
+                    v            => v.i(),
+                };
+            match i.checked_abs() {
+                Some(i) => Ok(VVal::Int(i)),
+                None    => Ok(env.new_err(format!("Integer overflow: abs {}", i))),
+            }
+        }, Some(1), Some(1), false);
+
+    add_num_fun_checked!(st, "num:checked_add", checked_add);
+    add_num_fun_checked!(st, "num:checked_sub", checked_sub);
+    add_num_fun_checked!(st, "num:checked_mul", checked_mul);
+    add_num_fun_checked!(st, "num:checked_div", checked_div);
+    add_num_fun_checked!(st, "num:checked_rem", checked_rem);
+    func!(st, "num:checked_pow",
+        |env: &mut Env, _argc: usize| {
+            Ok(int_pow(env.arg(0).i(), env.arg(1).i(), i64::checked_pow)
+               .ok().flatten().map_or(VVal::Nul, VVal::Int))
+        }, Some(2), Some(2), false);
+
+    add_num_fun_int2!(st,     "num:wrapping_add", wrapping_add);
+    add_num_fun_int2!(st,     "num:wrapping_sub", wrapping_sub);
+    add_num_fun_int2!(st,     "num:wrapping_mul", wrapping_mul);
+    add_num_fun_int2_div!(st, "num:wrapping_div", wrapping_div);
+    add_num_fun_int2_div!(st, "num:wrapping_rem", wrapping_rem);
+    add_num_fun_int_pow!(st,  "num:wrapping_pow", i64::wrapping_pow);
+
+    add_num_fun_int2!(st,     "num:saturating_add", saturating_add);
+    add_num_fun_int2!(st,     "num:saturating_sub", saturating_sub);
+    add_num_fun_int2!(st,     "num:saturating_mul", saturating_mul);
+    add_num_fun_int_pow!(st,  "num:saturating_pow", i64::saturating_pow);
+
+    func!(st, "dec:round",
+        |env: &mut Env, argc: usize| {
+            let places = env.arg(1).i();
+            if places < 0 {
+                return Ok(env.new_err(
+                    format!("dec:round: negative number of places: {}", places)));
+            }
+
+            let mode =
+                if argc > 2 {
+                    let name = env.arg(2).s_raw();
+                    match RoundingMode::from_name(&name) {
+                        Some(mode) => mode,
+                        None => {
+                            return Ok(env.new_err(
+                                format!("dec:round: unknown rounding mode: {}", name)));
+                        },
+                    }
+                } else {
+                    RoundingMode::HalfEven
+                };
+
+            // A decimal has no more than MAX_SCALE places,
+            // so more places leave it unchanged:
+            let places = places.min(i64::from(MAX_SCALE)) as u32;
+            Ok(VVal::new_dec(env.arg(0).d().round(places, mode)))
+        }, Some(2), Some(3), false);
+
+    func!(st, "dec:scale",
+        |env: &mut Env, _argc: usize| {
+            Ok(VVal::Int(i64::from(env.arg(0).d().scale())))
+        }, Some(1), Some(1), false);
+
+    func!(st, "dec:normalize",
+        |env: &mut Env, _argc: usize| {
+            Ok(VVal::new_dec(env.arg(0).d().normalize()))
         }, Some(1), Some(1), false);
 
     func!(st, "io:lines",
@@ -3954,6 +7280,24 @@ pub fn std_symbol_table() -> SymbolTable {
             Ok(VVal::new_str(VERSION))
         }, Some(0), Some(0), false);
 
+    func!(st, "wlambda:subst",
+        |env: &mut Env, _argc: usize| {
+            Ok(subst_ast_vars(&env.arg(0), &env.arg(1)))
+        }, Some(2), Some(2), false);
+
+    func!(st, "wlambda:parse",
+        |env: &mut Env, argc: usize| {
+            let code = env.arg(0).s_raw();
+            let file =
+                if argc == 2 { env.arg(1).s_raw() }
+                else { "<wlambda:parse>".to_string() };
+
+            match parser::parse(&code, &file) {
+                Ok(ast) => Ok(parser::ast_to_data(&ast)),
+                Err(e)  => Ok(env.new_err(format!("wlambda:parse: {}", e))),
+            }
+        }, Some(1), Some(2), false);
+
     func!(st, "measure_time",
         |env: &mut Env, _argc: usize| {
             use std::convert::TryFrom;
@@ -4091,41 +7435,43 @@ pub fn std_symbol_table() -> SymbolTable {
             }
         }, Some(3), Some(3), false);
 
+    #[cfg(feature="regex")]
+    func!(st, "re:compile",
+        |env: &mut Env, _argc: usize| {
+            match regex_arg(env, 0) {
+                Ok(rx)   => Ok(VVal::Usr(Box::new(VValRegex { rx }))),
+                Err(err) => Ok(err),
+            }
+        }, Some(1), Some(1), false);
+
     #[cfg(feature="regex")]
     func!(st, "re:replace_all",
         |env: &mut Env, _argc: usize| {
-            use regex::Regex;
-            let re   = env.arg(0).s_raw();
+            let rx =
+                match regex_arg(env, 0) {
+                    Ok(rx)   => rx,
+                    Err(err) => return Ok(err),
+                };
             let f    = env.arg(1);
             let text = env.arg(2).s_raw();
 
-            let rx = Regex::new(&re);
-            if let Err(e) = rx {
-                return Ok(env.new_err(
-                    format!("Regex '{}' did not compile: {}", re, e)));
-            }
-            let rx = rx.unwrap();
-
+            let pass_named = pass_named_captures(&rx, &f);
             let mut finished = false;
             let mut ret = Ok(VVal::Nul);
             let ret_str = VVal::new_str_mv(String::from(rx.replace_all(&text, |capts: &regex::Captures| {
-                let captures = VVal::vec();
-                for cap in capts.iter() {
-                    match cap {
-                        None    => { captures.push(VVal::Nul); },
-                        Some(c) => {
-                            captures.push(VVal::new_str(c.as_str()));
-                        }
-                    }
-                }
+                let captures = captures_to_vec(capts);
 
                 let repl = captures.at(0).unwrap_or(VVal::Nul).s_raw();
                 if finished { return repl; }
 
                 if f.is_fun() {
-                    env.push(captures);
-                    let rv = f.call_internal(env, 1);
-                    env.popn(1);
+                    let rv =
+                        if pass_named {
+                            let named = named_captures_to_map(&rx, capts);
+                            f.call(env, &[captures, named])
+                        } else {
+                            f.call(env, &[captures])
+                        };
 
                     match rv {
                         Ok(v)                      => v.s_raw(),
@@ -4144,33 +7490,23 @@ pub fn std_symbol_table() -> SymbolTable {
     #[cfg(feature="regex")]
     func!(st, "re:match",
         |env: &mut Env, _argc: usize| {
-            use regex::Regex;
-            let re   = env.arg(0).s_raw();
+            let rx =
+                match regex_arg(env, 0) {
+                    Ok(rx)   => rx,
+                    Err(err) => return Ok(err),
+                };
             let text = env.arg(1).s_raw();
             let f    = env.arg(2);
 
-            let rx = Regex::new(&re);
-            if let Err(e) = rx {
-                return Ok(env.new_err(
-                    format!("Regex '{}' did not compile: {}", re, e)));
-            }
-            let rx = rx.unwrap();
-
             match rx.captures(&text) {
                 Some(c) => {
-                    let captures = VVal::vec();
-                    for cap in c.iter() {
-                        match cap {
-                            None    => { captures.push(VVal::Nul); },
-                            Some(c) => {
-                                captures.push(VVal::new_str(c.as_str()));
-                            }
-                        }
+                    let captures = captures_to_vec(&c);
+                    if pass_named_captures(&rx, &f) {
+                        let named = named_captures_to_map(&rx, &c);
+                        f.call(env, &[captures, named])
+                    } else {
+                        f.call(env, &[captures])
                     }
-                    env.push(captures);
-                    let rv = f.call_internal(env, 1);
-                    env.popn(1);
-                    rv
                 },
                 None => {
                     Ok(VVal::Nul)
@@ -4181,32 +7517,25 @@ pub fn std_symbol_table() -> SymbolTable {
     #[cfg(feature="regex")]
     func!(st, "re:map",
         |env: &mut Env, _argc: usize| {
-            use regex::Regex;
-            let re   = env.arg(0).s_raw();
+            let rx =
+                match regex_arg(env, 0) {
+                    Ok(rx)   => rx,
+                    Err(err) => return Ok(err),
+                };
             let f    = env.arg(1);
             let text = env.arg(2).s_raw();
 
-            let rx = Regex::new(&re);
-            if let Err(e) = rx {
-                return Ok(env.new_err(
-                    format!("Regex '{}' did not compile: {}", re, e)));
-            }
-            let rx = rx.unwrap();
-
+            let pass_named = pass_named_captures(&rx, &f);
             let mut ret = VVal::Nul;
             for capts in rx.captures_iter(&text) {
-                let captures = VVal::vec();
-                for cap in capts.iter() {
-                    match cap {
-                        None    => { captures.push(VVal::Nul); },
-                        Some(c) => {
-                            captures.push(VVal::new_str(c.as_str()));
-                        }
-                    }
-                }
-                env.push(captures);
-                let rv = f.call_internal(env, 1);
-                env.popn(1);
+                let captures = captures_to_vec(&capts);
+                let rv =
+                    if pass_named {
+                        let named = named_captures_to_map(&rx, &capts);
+                        f.call(env, &[captures, named])
+                    } else {
+                        f.call(env, &[captures])
+                    };
 
                 match rv {
                     Ok(v)                      => { ret = v; },
@@ -4218,6 +7547,63 @@ pub fn std_symbol_table() -> SymbolTable {
             Ok(ret)
         }, Some(3), Some(3), false);
 
+    #[cfg(feature="regex")]
+    func!(st, "re:named_captures",
+        |env: &mut Env, _argc: usize| {
+            let rx =
+                match regex_arg(env, 0) {
+                    Ok(rx)   => rx,
+                    Err(err) => return Ok(err),
+                };
+            let text = env.arg(1).s_raw();
+
+            match rx.captures(&text) {
+                Some(c) => Ok(named_captures_to_map(&rx, &c)),
+                None    => Ok(VVal::Nul),
+            }
+        }, Some(2), Some(2), false);
+
+    #[cfg(feature="regex")]
+    func!(st, "re:find_all",
+        |env: &mut Env, _argc: usize| {
+            let rx =
+                match regex_arg(env, 0) {
+                    Ok(rx)   => rx,
+                    Err(err) => return Ok(err),
+                };
+            let text = env.arg(1).s_raw();
+
+            let ret = VVal::vec();
+            let mut char_pos  = 0;
+            let mut byte_pos  = 0;
+            for m in rx.find_iter(&text) {
+                char_pos += text[byte_pos..m.start()].chars().count();
+                let start = char_pos;
+                char_pos += m.as_str().chars().count();
+                byte_pos  = m.end();
+
+                let found = VVal::vec();
+                found.push(VVal::new_str(m.as_str()));
+                found.push(VVal::Int(start as i64));
+                found.push(VVal::Int(char_pos as i64));
+                ret.push(found);
+            }
+            Ok(ret)
+        }, Some(2), Some(2), false);
+
+    #[cfg(feature="regex")]
+    func!(st, "re:split",
+        |env: &mut Env, _argc: usize| {
+            let rx =
+                match regex_arg(env, 0) {
+                    Ok(rx)   => rx,
+                    Err(err) => return Ok(err),
+                };
+            let text = env.arg(1).s_raw();
+
+            Ok(VVal::vec_mv(rx.split(&text).map(VVal::new_str).collect()))
+        }, Some(2), Some(2), false);
+
     #[cfg(feature="chrono")]
     func!(st, "chrono:timestamp",
         |env: &mut Env, _argc: usize| {
@@ -4241,11 +7627,11 @@ pub fn std_symbol_table() -> SymbolTable {
             let v = env.arg(0);
             let pp = env.arg(1).b();
 
-            match v.to_json(pp) {
+            match v.to_json_opts(pp, env.arg(2).b()) {
                 Ok(s) => Ok(VVal::new_str_mv(s)),
                 Err(e) => Ok(env.new_err(e)),
             }
-        }, Some(1), Some(2), false);
+        }, Some(1), Some(3), false);
 
     #[cfg(feature="serde_json")]
     func!(st, "deser:json",
@@ -4258,6 +7644,16 @@ pub fn std_symbol_table() -> SymbolTable {
             }
         }, Some(1), Some(1), false);
 
+    func!(st, "deser:wlambda",
+        |env: &mut Env, _argc: usize| {
+            let s = env.arg(0).s_raw();
+
+            match VVal::from_wlambda_str(&s) {
+                Ok(v) => Ok(v),
+                Err(e) => Ok(env.new_err(e)),
+            }
+        }, Some(1), Some(1), false);
+
     #[cfg(feature="rmp-serde")]
     func!(st, "ser:msgpack",
         |env: &mut Env, _argc: usize| {
@@ -4329,6 +7725,8 @@ pub fn std_symbol_table() -> SymbolTable {
                 list.sort(|a: &VVal, b: &VVal| {
                     if a.is_int() || a.is_float() {
                         a.compare_num(b)
+                    } else if let VVal::Usr(_) = a {
+                        a.compare_key(b)
                     } else {
                         a.compare_str(b)
                     }
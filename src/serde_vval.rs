@@ -0,0 +1,747 @@
+// Copyright (c) 2020 Weird Constructor <weirdconstructor@gmail.com>
+// This is a part of WLambda. See README.md and COPYING for details.
+
+/*!
+Serde data format backends, that convert Rust values directly to and from
+[VVal](../vval/enum.VVal.html) without going through JSON strings.
+
+Structs and maps become WLambda maps, sequences and tuples become vectors
+and enum variants become WLambda enum values, see also
+[VValEnum](../vval/struct.VValEnum.html). Errors point to the key path of
+the offending value. Decimals are passed as their digits in a string,
+unless a float is expected:
+
+```
+use wlambda::{VVal, to_vval, from_vval};
+use std::collections::HashMap;
+
+let mut ports : HashMap<String, Vec<u16>> = HashMap::new();
+ports.insert("web".to_string(), vec![80, 443]);
+
+let v = to_vval(&ports).unwrap();
+assert_eq!(v.s(), "${web=$[80,443]}");
+
+let back : HashMap<String, Vec<u16>> = from_vval(&v).unwrap();
+assert_eq!(back, ports);
+
+v.v_k("web").set(1, VVal::Int(70000));
+let err = from_vval::<HashMap<String, Vec<u16>>>(&v).unwrap_err();
+assert_eq!(err.to_string(),
+    "web[1]: invalid value: integer `70000`, expected u16");
+```
+*/
+
+use std::fmt;
+use std::convert::TryFrom;
+use crate::vval::VVal;
+use serde::ser::{self, Serialize};
+use serde::de::{self, DeserializeOwned, DeserializeSeed, IntoDeserializer, Visitor};
+
+/// Converts a Rust value to a VVal.
+pub fn to_vval<T: Serialize + ?Sized>(value: &T) -> Result<VVal, Error> {
+    value.serialize(Serializer)
+}
+
+/// Converts a VVal to a Rust value.
+pub fn from_vval<T: DeserializeOwned>(v: &VVal) -> Result<T, Error> {
+    T::deserialize(Deserializer::new(v))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum PathSegment {
+    Key(String),
+    Index(usize),
+}
+
+/// The error of [to_vval](fn.to_vval.html) and
+/// [from_vval](fn.from_vval.html), which remembers the key path of
+/// the value the error occured at.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Error {
+    msg:  String,
+    /// The key path, innermost segment first.
+    path: Vec<PathSegment>,
+}
+
+impl Error {
+    /// Returns the error message without the key path.
+    pub fn message(&self) -> &str { &self.msg }
+
+    /// Returns the key path of the offending value, like `servers[1].port`.
+    /// The path is empty if the error occured at the top level value.
+    pub fn path(&self) -> String {
+        let mut s = String::new();
+        for seg in self.path.iter().rev() {
+            match seg {
+                PathSegment::Key(k) => {
+                    if !s.is_empty() { s.push('.'); }
+                    s.push_str(k);
+                },
+                PathSegment::Index(i) => s.push_str(&format!("[{}]", i)),
+            }
+        }
+        s
+    }
+
+    fn at_key(mut self, key: &str) -> Self {
+        self.path.push(PathSegment::Key(key.to_string()));
+        self
+    }
+
+    fn at_index(mut self, idx: usize) -> Self {
+        self.path.push(PathSegment::Index(idx));
+        self
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.path.is_empty() {
+            write!(f, "{}", self.msg)
+        } else {
+            write!(f, "{}: {}", self.path(), self.msg)
+        }
+    }
+}
+
+impl std::error::Error for Error { }
+
+impl ser::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error { msg: msg.to_string(), path: vec![] }
+    }
+}
+
+impl de::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error { msg: msg.to_string(), path: vec![] }
+    }
+}
+
+fn int_out_of_range<T: fmt::Display>(v: T) -> Error {
+    ser::Error::custom(format!("integer out of range: {}", v))
+}
+
+/// Serializes Rust values to a VVal, see also [to_vval](fn.to_vval.html).
+pub struct Serializer;
+
+impl ser::Serializer for Serializer {
+    type Ok    = VVal;
+    type Error = Error;
+
+    type SerializeSeq           = SeqSerializer;
+    type SerializeTuple         = SeqSerializer;
+    type SerializeTupleStruct   = SeqSerializer;
+    type SerializeTupleVariant  = SeqSerializer;
+    type SerializeMap           = MapSerializer;
+    type SerializeStruct        = MapSerializer;
+    type SerializeStructVariant = MapSerializer;
+
+    fn serialize_bool(self, v: bool) -> Result<VVal, Error> { Ok(VVal::Bol(v)) }
+    fn serialize_i8(self, v: i8)     -> Result<VVal, Error> { Ok(VVal::Int(i64::from(v))) }
+    fn serialize_i16(self, v: i16)   -> Result<VVal, Error> { Ok(VVal::Int(i64::from(v))) }
+    fn serialize_i32(self, v: i32)   -> Result<VVal, Error> { Ok(VVal::Int(i64::from(v))) }
+    fn serialize_i64(self, v: i64)   -> Result<VVal, Error> { Ok(VVal::Int(v)) }
+    fn serialize_u8(self, v: u8)     -> Result<VVal, Error> { Ok(VVal::Int(i64::from(v))) }
+    fn serialize_u16(self, v: u16)   -> Result<VVal, Error> { Ok(VVal::Int(i64::from(v))) }
+    fn serialize_u32(self, v: u32)   -> Result<VVal, Error> { Ok(VVal::Int(i64::from(v))) }
+
+    fn serialize_u64(self, v: u64) -> Result<VVal, Error> {
+        i64::try_from(v).map(VVal::Int).map_err(|_| int_out_of_range(v))
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<VVal, Error> {
+        i64::try_from(v).map(VVal::Int).map_err(|_| int_out_of_range(v))
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<VVal, Error> {
+        i64::try_from(v).map(VVal::Int).map_err(|_| int_out_of_range(v))
+    }
+
+    fn serialize_f32(self, v: f32)   -> Result<VVal, Error> { Ok(VVal::Flt(f64::from(v))) }
+    fn serialize_f64(self, v: f64)   -> Result<VVal, Error> { Ok(VVal::Flt(v)) }
+    fn serialize_char(self, v: char) -> Result<VVal, Error> { Ok(VVal::new_str(&v.to_string())) }
+    fn serialize_str(self, v: &str)  -> Result<VVal, Error> { Ok(VVal::new_str(v)) }
+    fn serialize_bytes(self, v: &[u8]) -> Result<VVal, Error> { Ok(VVal::new_byt(v.to_vec())) }
+    fn serialize_none(self)          -> Result<VVal, Error> { Ok(VVal::Nul) }
+    fn serialize_unit(self)          -> Result<VVal, Error> { Ok(VVal::Nul) }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<VVal, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<VVal, Error> {
+        Ok(VVal::Nul)
+    }
+
+    fn serialize_unit_variant(self, name: &'static str, _idx: u32, variant: &'static str)
+        -> Result<VVal, Error>
+    {
+        Ok(VVal::new_enum(name, variant, VVal::Nul))
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(self, _name: &'static str, value: &T)
+        -> Result<VVal, Error>
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self, name: &'static str, _idx: u32, variant: &'static str, value: &T)
+        -> Result<VVal, Error>
+    {
+        let payload = value.serialize(self).map_err(|e| e.at_key(variant))?;
+        Ok(VVal::new_enum(name, variant, payload))
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<SeqSerializer, Error> {
+        Ok(SeqSerializer::new(None))
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<SeqSerializer, Error> {
+        Ok(SeqSerializer::new(None))
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, _len: usize)
+        -> Result<SeqSerializer, Error>
+    {
+        Ok(SeqSerializer::new(None))
+    }
+
+    fn serialize_tuple_variant(
+        self, name: &'static str, _idx: u32, variant: &'static str, _len: usize)
+        -> Result<SeqSerializer, Error>
+    {
+        Ok(SeqSerializer::new(Some((name, variant))))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<MapSerializer, Error> {
+        Ok(MapSerializer::new(None))
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize)
+        -> Result<MapSerializer, Error>
+    {
+        Ok(MapSerializer::new(None))
+    }
+
+    fn serialize_struct_variant(
+        self, name: &'static str, _idx: u32, variant: &'static str, _len: usize)
+        -> Result<MapSerializer, Error>
+    {
+        Ok(MapSerializer::new(Some((name, variant))))
+    }
+}
+
+/// Collects the elements of sequences, tuples and tuple variants.
+pub struct SeqSerializer {
+    list:    VVal,
+    variant: Option<(&'static str, &'static str)>,
+}
+
+impl SeqSerializer {
+    fn new(variant: Option<(&'static str, &'static str)>) -> Self {
+        SeqSerializer { list: VVal::vec(), variant }
+    }
+
+    fn push<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        let v =
+            value.serialize(Serializer).map_err(|e| {
+                let e = e.at_index(self.list.len());
+                match self.variant {
+                    Some((_, variant)) => e.at_key(variant),
+                    None               => e,
+                }
+            })?;
+        self.list.push(v);
+        Ok(())
+    }
+
+    fn finish(self) -> Result<VVal, Error> {
+        match self.variant {
+            Some((name, variant)) => Ok(VVal::new_enum(name, variant, self.list)),
+            None                  => Ok(self.list),
+        }
+    }
+}
+
+impl ser::SerializeSeq for SeqSerializer {
+    type Ok    = VVal;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<VVal, Error> { self.finish() }
+}
+
+impl ser::SerializeTuple for SeqSerializer {
+    type Ok    = VVal;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<VVal, Error> { self.finish() }
+}
+
+impl ser::SerializeTupleStruct for SeqSerializer {
+    type Ok    = VVal;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<VVal, Error> { self.finish() }
+}
+
+impl ser::SerializeTupleVariant for SeqSerializer {
+    type Ok    = VVal;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<VVal, Error> { self.finish() }
+}
+
+/// Collects the entries of maps, structs and struct variants.
+pub struct MapSerializer {
+    map:     VVal,
+    key:     Option<String>,
+    variant: Option<(&'static str, &'static str)>,
+}
+
+impl MapSerializer {
+    fn new(variant: Option<(&'static str, &'static str)>) -> Self {
+        MapSerializer { map: VVal::map(), key: None, variant }
+    }
+
+    fn insert<T: Serialize + ?Sized>(&mut self, key: &str, value: &T) -> Result<(), Error> {
+        let v =
+            value.serialize(Serializer).map_err(|e| {
+                let e = e.at_key(key);
+                match self.variant {
+                    Some((_, variant)) => e.at_key(variant),
+                    None               => e,
+                }
+            })?;
+        self.map.set_key(&VVal::new_sym(key), v).expect("unshared map");
+        Ok(())
+    }
+
+    fn finish(self) -> Result<VVal, Error> {
+        match self.variant {
+            Some((name, variant)) => Ok(VVal::new_enum(name, variant, self.map)),
+            None                  => Ok(self.map),
+        }
+    }
+}
+
+/// Map keys are strings, so only strings, numbers, booleans and
+/// variants without payload are accepted as keys.
+fn map_key_string(key: VVal) -> Result<String, Error> {
+    match key {
+        VVal::Str(_) | VVal::Sym(_) | VVal::Int(_) | VVal::Flt(_)
+        | VVal::Dec(_) | VVal::Bol(_)
+            => Ok(key.s_raw()),
+        VVal::Enum(e) if e.payload.is_none()
+            => Ok(e.variant.clone()),
+        _   => Err(ser::Error::custom(
+                format!("map key must be a string or number, got: {}", key.s()))),
+    }
+}
+
+impl ser::SerializeMap for MapSerializer {
+    type Ok    = VVal;
+    type Error = Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), Error> {
+        self.key = Some(map_key_string(key.serialize(Serializer)?)?);
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        let key = self.key.take().expect("serialize_key before serialize_value");
+        self.insert(&key, value)
+    }
+
+    fn end(self) -> Result<VVal, Error> { self.finish() }
+}
+
+impl ser::SerializeStruct for MapSerializer {
+    type Ok    = VVal;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T)
+        -> Result<(), Error>
+    {
+        self.insert(key, value)
+    }
+
+    fn end(self) -> Result<VVal, Error> { self.finish() }
+}
+
+impl ser::SerializeStructVariant for MapSerializer {
+    type Ok    = VVal;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T)
+        -> Result<(), Error>
+    {
+        self.insert(key, value)
+    }
+
+    fn end(self) -> Result<VVal, Error> { self.finish() }
+}
+
+/// Deserializes Rust values from a VVal, see also
+/// [from_vval](fn.from_vval.html). References are dereferenced.
+pub struct Deserializer {
+    v: VVal,
+}
+
+impl Deserializer {
+    pub fn new(v: &VVal) -> Self { Deserializer { v: v.deref() } }
+
+    fn unsupported(&self) -> Error {
+        de::Error::custom(format!("unsupported value: {}", self.v.s()))
+    }
+}
+
+fn visit_elements<'de, V: Visitor<'de>>(items: Vec<VVal>, visitor: V) -> Result<V::Value, Error> {
+    let len     = items.len();
+    let mut seq = SeqDeserializer { items: items.into_iter(), idx: 0 };
+    let value   = visitor.visit_seq(&mut seq)?;
+    if seq.items.len() > 0 {
+        return Err(de::Error::invalid_length(len, &"fewer elements in sequence"));
+    }
+    Ok(value)
+}
+
+fn visit_entries<'de, V: Visitor<'de>>(entries: Vec<(VVal, VVal)>, visitor: V) -> Result<V::Value, Error> {
+    let len     = entries.len();
+    let mut map = MapDeserializer { entries: entries.into_iter(), value: None };
+    let value   = visitor.visit_map(&mut map)?;
+    if map.entries.len() > 0 {
+        return Err(de::Error::invalid_length(len, &"fewer elements in map"));
+    }
+    Ok(value)
+}
+
+impl<'de> de::Deserializer<'de> for Deserializer {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match &self.v {
+            VVal::Nul    => visitor.visit_unit(),
+            VVal::Bol(b) => visitor.visit_bool(*b),
+            VVal::Int(i) => visitor.visit_i64(*i),
+            VVal::Flt(f) => visitor.visit_f64(*f),
+            VVal::Dec(d) => visitor.visit_str(&d.to_string()),
+            VVal::Str(_) | VVal::Sym(_)
+                         => visitor.visit_str(&self.v.s_raw()),
+            VVal::Byt(b) => visitor.visit_bytes(&b.borrow()[..]),
+            VVal::Lst(_) | VVal::NArr(_)
+            | VVal::IVec2(_) | VVal::IVec3(_) | VVal::IVec4(_)
+            | VVal::FVec2(_) | VVal::FVec3(_) | VVal::FVec4(_) => {
+                let items =
+                    (0..self.v.len())
+                    .map(|i| self.v.at(i).unwrap_or(VVal::Nul))
+                    .collect();
+                visit_elements(items, visitor)
+            },
+            VVal::Set(s) => {
+                let items = s.borrow().iter().map(|k| k.value().clone()).collect();
+                visit_elements(items, visitor)
+            },
+            VVal::Map(m) => {
+                let entries =
+                    m.borrow().iter()
+                     .map(|(k, v)| (VVal::Sym(k.clone()), v.clone()))
+                     .collect();
+                visit_entries(entries, visitor)
+            },
+            VVal::OMap(m) => {
+                let entries =
+                    m.borrow().iter()
+                     .map(|(k, v)| (k.value().clone(), v.clone()))
+                     .collect();
+                visit_entries(entries, visitor)
+            },
+            VVal::Enum(e) => {
+                if e.payload.is_none() {
+                    visitor.visit_str(&e.variant)
+                } else {
+                    visit_entries(
+                        vec![(VVal::new_str(&e.variant), e.payload.clone())],
+                        visitor)
+                }
+            },
+            _ => Err(self.unsupported()),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        if self.v.is_none() { visitor.visit_none() }
+        else { visitor.visit_some(self) }
+    }
+
+    fn deserialize_f32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_f64(visitor)
+    }
+
+    fn deserialize_f64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match &self.v {
+            VVal::Dec(d) => visitor.visit_f64(d.to_f64()),
+            _            => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V)
+        -> Result<V::Value, Error>
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self, _name: &'static str, _variants: &'static [&'static str], visitor: V)
+        -> Result<V::Value, Error>
+    {
+        let (variant, payload) =
+            match &self.v {
+                VVal::Enum(e) => {
+                    let payload =
+                        if e.payload.is_none() { None }
+                        else { Some(e.payload.clone()) };
+                    (e.variant.clone(), payload)
+                },
+                VVal::Str(_) | VVal::Sym(_) => (self.v.s_raw(), None),
+                VVal::Map(m) if m.borrow().len() == 1 => {
+                    let m = m.borrow();
+                    let (k, v) = m.iter().next().unwrap();
+                    (k.to_string(), Some(v.clone()))
+                },
+                _ => {
+                    return Err(de::Error::custom(
+                        format!("invalid type: {}, expected enum",
+                                self.v.type_name())));
+                },
+            };
+        visitor.visit_enum(EnumDeserializer { variant, payload })
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map struct
+        identifier
+    }
+}
+
+struct SeqDeserializer {
+    items: std::vec::IntoIter<VVal>,
+    idx:   usize,
+}
+
+impl<'de> de::SeqAccess<'de> for SeqDeserializer {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T)
+        -> Result<Option<T::Value>, Error>
+    {
+        match self.items.next() {
+            Some(v) => {
+                let idx = self.idx;
+                self.idx += 1;
+                seed.deserialize(Deserializer::new(&v))
+                    .map(Some)
+                    .map_err(|e| e.at_index(idx))
+            },
+            None => Ok(None),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> { Some(self.items.len()) }
+}
+
+struct MapDeserializer {
+    entries: std::vec::IntoIter<(VVal, VVal)>,
+    value:   Option<(String, VVal)>,
+}
+
+impl<'de> de::MapAccess<'de> for MapDeserializer {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K)
+        -> Result<Option<K::Value>, Error>
+    {
+        match self.entries.next() {
+            Some((k, v)) => {
+                let key = k.s_raw();
+                let r =
+                    seed.deserialize(KeyDeserializer { key: k })
+                        .map_err(|e| e.at_key(&key))?;
+                self.value = Some((key, v));
+                Ok(Some(r))
+            },
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<T: DeserializeSeed<'de>>(&mut self, seed: T)
+        -> Result<T::Value, Error>
+    {
+        let (key, v) = self.value.take().expect("next_key_seed before next_value_seed");
+        seed.deserialize(Deserializer::new(&v)).map_err(|e| e.at_key(&key))
+    }
+
+    fn size_hint(&self) -> Option<usize> { Some(self.entries.len()) }
+}
+
+/// Deserializes map keys. Keys of WLambda maps are strings, so numbers
+/// and booleans are parsed from the key if the target type asks for them.
+struct KeyDeserializer {
+    key: VVal,
+}
+
+macro_rules! deserialize_parsed_key {
+    ($($method: ident, $visit: ident, $type: ty;)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+                match &self.key {
+                    VVal::Str(_) | VVal::Sym(_) => {
+                        match self.key.s_raw().parse::<$type>() {
+                            Ok(n)  => visitor.$visit(n),
+                            Err(_) => self.deserialize_any(visitor),
+                        }
+                    },
+                    _ => self.deserialize_any(visitor),
+                }
+            }
+        )*
+    }
+}
+
+impl<'de> de::Deserializer<'de> for KeyDeserializer {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        de::Deserializer::deserialize_any(Deserializer::new(&self.key), visitor)
+    }
+
+    deserialize_parsed_key! {
+        deserialize_bool, visit_bool, bool;
+        deserialize_i8,   visit_i8,   i8;
+        deserialize_i16,  visit_i16,  i16;
+        deserialize_i32,  visit_i32,  i32;
+        deserialize_i64,  visit_i64,  i64;
+        deserialize_u8,   visit_u8,   u8;
+        deserialize_u16,  visit_u16,  u16;
+        deserialize_u32,  visit_u32,  u32;
+        deserialize_u64,  visit_u64,  u64;
+        deserialize_f32,  visit_f32,  f32;
+        deserialize_f64,  visit_f64,  f64;
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V)
+        -> Result<V::Value, Error>
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self, name: &'static str, variants: &'static [&'static str], visitor: V)
+        -> Result<V::Value, Error>
+    {
+        de::Deserializer::deserialize_enum(
+            Deserializer::new(&self.key), name, variants, visitor)
+    }
+
+    serde::forward_to_deserialize_any! {
+        i128 u128 char str string bytes byte_buf option unit unit_struct
+        seq tuple tuple_struct map struct identifier ignored_any
+    }
+}
+
+struct EnumDeserializer {
+    variant: String,
+    payload: Option<VVal>,
+}
+
+impl<'de> de::EnumAccess<'de> for EnumDeserializer {
+    type Error   = Error;
+    type Variant = VariantDeserializer;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V)
+        -> Result<(V::Value, VariantDeserializer), Error>
+    {
+        let de : de::value::StringDeserializer<Error> =
+            self.variant.clone().into_deserializer();
+        let v = seed.deserialize(de)?;
+        Ok((v, VariantDeserializer { variant: self.variant, payload: self.payload }))
+    }
+}
+
+struct VariantDeserializer {
+    variant: String,
+    payload: Option<VVal>,
+}
+
+impl VariantDeserializer {
+    fn payload(self, expected: &str) -> Result<(String, Deserializer), Error> {
+        match self.payload {
+            Some(p) => Ok((self.variant, Deserializer::new(&p))),
+            None => {
+                Err(<Error as de::Error>::invalid_type(de::Unexpected::UnitVariant, &expected)
+                    .at_key(&self.variant))
+            },
+        }
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for VariantDeserializer {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Error> {
+        match self.payload {
+            None => Ok(()),
+            Some(_) => {
+                Err(<Error as de::Error>::invalid_type(de::Unexpected::NewtypeVariant, &"unit variant")
+                    .at_key(&self.variant))
+            },
+        }
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T)
+        -> Result<T::Value, Error>
+    {
+        // A payload that serialized to `$n`, like `None`, is stored
+        // like the missing payload of a unit variant:
+        let VariantDeserializer { variant, payload } = self;
+        let payload = payload.unwrap_or(VVal::Nul);
+        seed.deserialize(Deserializer::new(&payload))
+            .map_err(|e| e.at_key(&variant))
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V)
+        -> Result<V::Value, Error>
+    {
+        let (variant, de) = self.payload("tuple variant")?;
+        de::Deserializer::deserialize_seq(de, visitor).map_err(|e| e.at_key(&variant))
+    }
+
+    fn struct_variant<V: Visitor<'de>>(self, _fields: &'static [&'static str], visitor: V)
+        -> Result<V::Value, Error>
+    {
+        let (variant, de) = self.payload("struct variant")?;
+        de::Deserializer::deserialize_map(de, visitor).map_err(|e| e.at_key(&variant))
+    }
+}
@@ -14,7 +14,201 @@ use std::cell::RefCell;
 use std::fmt;
 use std::fmt::{Display, Debug, Formatter};
 
-use fnv::FnvHashMap;
+use fnv::{FnvHashMap, FnvBuildHasher};
+use indexmap::IndexMap;
+use std::collections::{BTreeSet, BTreeMap};
+use std::ops::Bound;
+use crate::decimal::Decimal;
+use crate::numarray::{NumArray, Num, ArrayOp, ArrayError};
+use crate::nvec::{NVec, NVecNum};
+use crate::vval_convert::TypedFn;
+
+thread_local! {
+    static SYMBOLS: RefCell<std::collections::HashSet<Rc<str>, FnvBuildHasher>> =
+        RefCell::new(std::collections::HashSet::default());
+    static PROTO_SYM: Symbol = Symbol::new("_proto");
+    static LOOKUP_EPOCH: std::cell::Cell<u64> = const { std::cell::Cell::new(0) };
+    static SER_DECIMALS_AS_NUMBERS: std::cell::Cell<bool> = const { std::cell::Cell::new(false) };
+}
+
+/// An interned symbol, used for `VVal::Sym` and the keys of `VVal::Map`.
+///
+/// All symbols with the same name share one allocation in the symbol
+/// table of the current thread. This makes comparing and hashing
+/// symbols a pointer operation. A name is removed from the symbol
+/// table when the last symbol referring to it is dropped.
+#[derive(Clone)]
+pub struct Symbol(Rc<str>);
+
+impl Symbol {
+    /// Returns the symbol for `s`, adding it to the symbol table
+    /// if it's not already in there.
+    pub fn new(s: &str) -> Self {
+        SYMBOLS.with(|syms| {
+            let mut syms = syms.borrow_mut();
+            if let Some(sym) = syms.get(s) {
+                return Symbol(sym.clone());
+            }
+            let sym : Rc<str> = Rc::from(s);
+            syms.insert(sym.clone());
+            Symbol(sym)
+        })
+    }
+
+    /// Returns the symbol for `s` only if it is already interned.
+    /// If it's not, no map can contain it as key.
+    pub fn lookup(s: &str) -> Option<Self> {
+        SYMBOLS.with(|syms| syms.borrow().get(s).map(|sym| Symbol(sym.clone())))
+    }
+
+    pub fn as_str(&self) -> &str { &self.0 }
+}
+
+impl Drop for Symbol {
+    fn drop(&mut self) {
+        // The symbol table holds the only other reference:
+        if Rc::strong_count(&self.0) == 2 {
+            let _ = SYMBOLS.try_with(|syms| {
+                if let Ok(mut syms) = syms.try_borrow_mut() {
+                    syms.remove(&*self.0);
+                }
+            });
+        }
+    }
+}
+
+impl std::ops::Deref for Symbol {
+    type Target = str;
+    fn deref(&self) -> &str { &self.0 }
+}
+
+impl PartialEq for Symbol {
+    fn eq(&self, other: &Self) -> bool { Rc::ptr_eq(&self.0, &other.0) }
+}
+
+impl Eq for Symbol { }
+
+impl std::hash::Hash for Symbol {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        (Rc::as_ptr(&self.0) as *const u8 as usize).hash(state)
+    }
+}
+
+impl PartialOrd for Symbol {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Symbol {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.as_str().cmp(other.as_str())
+    }
+}
+
+impl Display for Symbol {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl Debug for Symbol {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        write!(f, "{:?}", self.as_str())
+    }
+}
+
+type MapCell = RefCell<IndexMap<Symbol, VVal, FnvBuildHasher>>;
+
+/// Inserts `key` into the map `m`. If the map is referenced by any
+/// [LookupCache](struct.LookupCache.html) and the insert changes where
+/// a key is found (a new key or a new `_proto`), all caches are invalidated.
+fn insert_map_key(m: &Rc<MapCell>, map: &mut IndexMap<Symbol, VVal, FnvBuildHasher>,
+                  key: Symbol, val: VVal) {
+    if Rc::weak_count(m) == 0 {
+        map.insert(key, val);
+        return;
+    }
+
+    let is_proto = PROTO_SYM.with(|p| *p == key);
+    if map.insert(key, val).is_none() || is_proto {
+        LOOKUP_EPOCH.with(|e| e.set(e.get() + 1));
+    }
+}
+
+struct LookupCacheEntry {
+    epoch: u64,
+    key:   Symbol,
+    /// The looked up map first, followed by its prototypes up to
+    /// the map the key was found in.
+    chain: Vec<Weak<MapCell>>,
+    index: usize,
+}
+
+/// An inline cache for the key lookups of one call site, used by the
+/// compiled field accesses like `obj.field` and method calls `obj.method[]`.
+///
+/// It remembers the map and the position of the key in the map (or in one
+/// of its prototypes) from the last lookup. The cache is only weakly
+/// referencing the maps. Adding a key or changing the `_proto` of any map
+/// that is referenced by a cache invalidates all caches.
+#[derive(Default)]
+pub struct LookupCache {
+    entry: RefCell<Option<LookupCacheEntry>>,
+}
+
+impl LookupCache {
+    pub fn new() -> Self { Self::default() }
+
+    fn get(&self, m: &Rc<MapCell>, key: &Symbol) -> Option<VVal> {
+        let entry = self.entry.borrow();
+        let entry = entry.as_ref()?;
+        if entry.key != *key
+           || !std::ptr::eq(entry.chain[0].as_ptr(), Rc::as_ptr(m))
+           || entry.epoch != LOOKUP_EPOCH.with(|e| e.get())
+        {
+            return None;
+        }
+
+        let holder = entry.chain.last()?.upgrade()?;
+        let holder = holder.borrow();
+        match holder.get_index(entry.index) {
+            Some((k, v)) if k == key => Some(v.clone()),
+            _ => None,
+        }
+    }
+
+    fn lookup(&self, m: Rc<MapCell>, key: &Symbol, proto: bool) -> Option<VVal> {
+        if let Some(v) = self.get(&m, key) { return Some(v); }
+
+        let mut chain = vec![Rc::downgrade(&m)];
+        let mut cur   = m;
+        loop {
+            let found = cur.borrow().get_full(key).map(|(i, _, v)| (i, v.clone()));
+            if let Some((index, v)) = found {
+                self.entry.replace(Some(LookupCacheEntry {
+                    epoch: LOOKUP_EPOCH.with(|e| e.get()),
+                    key: key.clone(),
+                    chain,
+                    index,
+                }));
+                return Some(v);
+            }
+            if !proto { return None; }
+
+            // Prototypes behind references can change without touching
+            // any map, so only plain maps are cached.
+            let next = PROTO_SYM.with(|p| cur.borrow().get(p).cloned())?;
+            match next {
+                VVal::Map(next) => {
+                    chain.push(Rc::downgrade(&next));
+                    cur = next;
+                },
+                next => return next.proto_lookup_sym(key),
+            }
+        }
+    }
+}
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct FileRef {
@@ -136,6 +330,79 @@ pub enum Syntax {
     MapSplice,
     VecSplice,
     Accum,
+    Fmt,
+    Iter,
+    Match,
+    Code,
+    DefMacro,
+    IVec,
+    FVec,
+}
+
+impl Syntax {
+    /// Returns the syntax for it's name, as printed by `{:?}`.
+    /// This is used for turning ASTs that were converted to plain data
+    /// back into syntax nodes.
+    pub fn from_name(name: &str) -> Option<Syntax> {
+        Some(match name {
+            "Var"         => Syntax::Var,
+            "Key"         => Syntax::Key,
+            "SetKey"      => Syntax::SetKey,
+            "GetKey"      => Syntax::GetKey,
+            "GetKey2"     => Syntax::GetKey2,
+            "GetKey3"     => Syntax::GetKey3,
+            "GetSym"      => Syntax::GetSym,
+            "GetSym2"     => Syntax::GetSym2,
+            "GetSym3"     => Syntax::GetSym3,
+            "GetIdx"      => Syntax::GetIdx,
+            "GetIdx2"     => Syntax::GetIdx2,
+            "GetIdx3"     => Syntax::GetIdx3,
+            "BinOpAdd"    => Syntax::BinOpAdd,
+            "BinOpSub"    => Syntax::BinOpSub,
+            "BinOpMul"    => Syntax::BinOpMul,
+            "BinOpDiv"    => Syntax::BinOpDiv,
+            "BinOpMod"    => Syntax::BinOpMod,
+            "BinOpLe"     => Syntax::BinOpLe,
+            "BinOpLt"     => Syntax::BinOpLt,
+            "BinOpGe"     => Syntax::BinOpGe,
+            "BinOpGt"     => Syntax::BinOpGt,
+            "Str"         => Syntax::Str,
+            "Lst"         => Syntax::Lst,
+            "Map"         => Syntax::Map,
+            "Expr"        => Syntax::Expr,
+            "Func"        => Syntax::Func,
+            "Block"       => Syntax::Block,
+            "Err"         => Syntax::Err,
+            "Call"        => Syntax::Call,
+            "Apply"       => Syntax::Apply,
+            "And"         => Syntax::And,
+            "Or"          => Syntax::Or,
+            "Assign"      => Syntax::Assign,
+            "Def"         => Syntax::Def,
+            "Ref"         => Syntax::Ref,
+            "WRef"        => Syntax::WRef,
+            "Deref"       => Syntax::Deref,
+            "CaptureRef"  => Syntax::CaptureRef,
+            "AssignRef"   => Syntax::AssignRef,
+            "DefGlobRef"  => Syntax::DefGlobRef,
+            "SelfObj"     => Syntax::SelfObj,
+            "SelfData"    => Syntax::SelfData,
+            "Import"      => Syntax::Import,
+            "Export"      => Syntax::Export,
+            "DumpStack"   => Syntax::DumpStack,
+            "MapSplice"   => Syntax::MapSplice,
+            "VecSplice"   => Syntax::VecSplice,
+            "Accum"       => Syntax::Accum,
+            "Fmt"         => Syntax::Fmt,
+            "Iter"        => Syntax::Iter,
+            "Match"       => Syntax::Match,
+            "Code"        => Syntax::Code,
+            "DefMacro"    => Syntax::DefMacro,
+            "IVec"        => Syntax::IVec,
+            "FVec"        => Syntax::FVec,
+            _ => return None,
+        })
+    }
 }
 
 #[derive(Clone)]
@@ -174,6 +441,9 @@ impl std::fmt::Debug for Stdio {
 /// Currently hardcoded, but later the API user will be able to specify it.
 const STACK_SIZE : usize = 10240;
 
+/// Maximum nesting of vectors and maps accepted by `VVal::from_wlambda_str`.
+const MAX_WLAMBDA_STR_DEPTH : usize = 128;
+
 /// The runtime environment of the evaluator.
 #[derive(Debug, Clone)]
 pub struct Env {
@@ -619,7 +889,29 @@ impl Env {
         let old_val = std::mem::replace(&mut self.accum_val, v);
         let old_fun = std::mem::replace(&mut self.accum_fun, f);
 
-        acfun(self)?;
+        if let it @ VVal::Iter(_) = acfun(self)? {
+            loop {
+                let (v, k) =
+                    match it.iter_next(self) {
+                        Ok(Some(item))             => item,
+                        Ok(None)                   => break,
+                        Err(StackAction::Break(_)) => break,
+                        Err(StackAction::Next)     => continue,
+                        Err(e)                     => return Err(e),
+                    };
+                if let VVal::Map(_) = self.accum_val {
+                    let (k, v) =
+                        match k {
+                            Some(k) => (k, v),
+                            None    => (v.at(0).unwrap_or(VVal::Nul),
+                                        v.at(1).unwrap_or(VVal::Nul)),
+                        };
+                    self.accum_val.set_key(&k, v)?;
+                } else {
+                    self.accum_val.accum(&v);
+                }
+            }
+        }
 
         std::mem::replace(&mut self.accum_fun, old_fun);
 
@@ -776,6 +1068,33 @@ impl VValFun {
         VValFun::new_val(Rc::new(RefCell::new(fun)), Vec::new(), 0, min_args, max_args, err_arg_ok, None)
     }
 
+    /// Creates a new VVal containing the Rust function or closure `fun`
+    /// with typed parameters, see also [TypedFn](../vval_convert/trait.TypedFn.html).
+    /// The minimum and maximum number of arguments are derived from
+    /// the parameters. `fnname` is used for the error messages.
+    ///
+    ///```rust
+    /// use wlambda::compiler::EvalContext;
+    /// use wlambda::vval::VValFun;
+    ///
+    /// let mut ctx = wlambda::compiler::EvalContext::new_empty_global_env();
+    ///
+    /// ctx.set_global_var("scale",
+    ///     &VValFun::new_typed_fun("scale", |v: Vec<f64>, f: f64| {
+    ///         v.iter().map(|x| x * f).collect::<Vec<f64>>()
+    ///     }));
+    ///
+    /// assert_eq!(ctx.eval("scale $[1, 2.5] 2").unwrap().s(), "$[2,5]")
+    ///```
+    pub fn new_typed_fun<F, Args>(fnname: &str, fun: F) -> VVal
+        where F: TypedFn<Args> {
+
+        let fnname = fnname.to_string();
+        VValFun::new_fun(
+            move |env: &mut Env, _argc: usize| fun.call_typed(env, &fnname),
+            Some(F::min_args()), Some(F::max_args()), F::accepts_err())
+    }
+
     pub fn new_fun_with_pos<T>(fun: T, min_args: Option<usize>, max_args: Option<usize>, err_arg_ok: bool, spos: SynPos) -> VVal
         where T: 'static + Fn(&mut Env, usize) -> Result<VVal, StackAction> {
 
@@ -951,6 +1270,29 @@ pub trait VValUserData {
     fn get_key(&self, _key: &str) -> Option<VVal> { None }
     /// This method is called when the user data is called.
     fn call(&self, _args: &[VVal]) -> Result<VVal, StackAction> { Ok(VVal::Nul) }
+    /// This method is called for method calls like `obj.name arg1 arg2`,
+    /// with `args` being the arguments and the user data being the
+    /// `$self` object in `env`. By default the value returned by
+    /// [get_key](#method.get_key) for `name` is called with `args`.
+    fn call_method(&self, name: &str, args: &[VVal], env: &mut Env)
+        -> Result<VVal, StackAction>
+    {
+        self.get_key(name).unwrap_or(VVal::Nul).call(env, args)
+    }
+    /// Makes your user data iterable with `for` and `$iter`.
+    /// Return a value to iterate over in place of your data,
+    /// like a vector, a map or an iterator.
+    fn iter(&self) -> Option<VVal> { None }
+    /// Orders your data relative to `other`. This is used for sorting,
+    /// for the keys of sets and ordered maps and for the operators
+    /// `<`, `<=`, `>` and `>=` if [binop](#method.binop) does not
+    /// implement them. Return `None` if the values can't be compared.
+    fn compare(&self, _other: &VVal) -> Option<std::cmp::Ordering> { None }
+    /// Overloads the binary operators like `+`, `==` or `<` for your data.
+    /// `op` is the name of the operator method, for instance `"_add_"`,
+    /// `"_radd_"` or `"_lt_"`. Return `None` if the operator is not
+    /// implemented, so that the default behaviour is used.
+    fn binop(&self, _op: &str, _other: &VVal) -> Option<Result<VVal, StackAction>> { None }
     /// This should be implemented simply by returning
     /// a mutable reference to the concrete type self.
     /// It allows you to access your data structure from inside
@@ -994,6 +1336,132 @@ impl Drop for DropVVal {
     }
 }
 
+/// A value of an enum type, as returned by the variant constructors
+/// of an enum defined with `std:enum`.
+#[derive(Debug, Clone)]
+pub struct VValEnum {
+    /// The name of the enum type.
+    pub name:    String,
+    /// The name of the variant.
+    pub variant: String,
+    /// The payload of the variant, `$none` if it has none.
+    pub payload: VVal,
+}
+
+/// Looks up the component index or swizzle `key` of the vector `v`,
+/// see also [NVec::swizzle](../nvec/enum.NVec.html#method.swizzle).
+fn nvec_key<N: NVecNum>(v: &NVec<N>, key: &str,
+                        num: fn(N) -> VVal, vec: fn(NVec<N>) -> VVal)
+    -> Option<VVal>
+{
+    if let Ok(idx) = key.parse::<usize>() {
+        return v.get(idx).map(num);
+    }
+    let mut chars = key.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => v.component(c).map(num),
+        _               => v.swizzle(key).map(vec),
+    }
+}
+
+/// Compares a decimal with a float for the order of keys.
+/// NaN is ordered like `f64::total_cmp` does.
+fn dec_cmp_f64(d: &Decimal, f: f64) -> std::cmp::Ordering {
+    d.cmp_f64(f).unwrap_or_else(|| d.to_f64().total_cmp(&f))
+}
+
+/// Compares the decimal `d` with the number `v`, floats are compared
+/// exactly. Returns `None` if `v` is NaN.
+pub fn dec_partial_cmp(d: &Decimal, v: &VVal) -> Option<std::cmp::Ordering> {
+    match v.deref() {
+        VVal::Flt(f) => d.cmp_f64(f),
+        v            => Some(d.cmp(&v.d())),
+    }
+}
+
+/// Returns the key of a `VVal::OMap` for a field name. Field names
+/// that are integers, like in `m.1`, are integer keys.
+fn omap_field_key(key: &str) -> VValKey {
+    match key.parse::<i64>() {
+        Ok(i)  => VValKey(VVal::Int(i)),
+        Err(_) => VValKey(VVal::new_str(key)),
+    }
+}
+
+/// A key of a `VVal::Set` or `VVal::OMap`. The keys are ordered by
+/// [compare_key](enum.VVal.html#method.compare_key).
+#[derive(Debug, Clone)]
+pub struct VValKey(VVal);
+
+impl VValKey {
+    /// Creates a key from a value. References are dereferenced and
+    /// strings, vectors, maps and enum payloads are copied, so that
+    /// changing the original value later does not change the order
+    /// of the keys. User data is copied with
+    /// [VValUserData::clone_ud](trait.VValUserData.html#tymethod.clone_ud).
+    pub fn new(v: &VVal) -> Self { VValKey(v.key_copy()) }
+
+    /// Returns the value of this key.
+    pub fn value(&self) -> &VVal { &self.0 }
+}
+
+impl PartialEq for VValKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.compare_key(&other.0) == std::cmp::Ordering::Equal
+    }
+}
+
+impl Eq for VValKey { }
+
+impl PartialOrd for VValKey {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for VValKey {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.0.compare_key(&other.0)
+    }
+}
+
+/// The item an iterator produces: A value and an optional key,
+/// like the index of a string or the key of a map.
+pub type VValIterItem = (VVal, Option<VVal>);
+
+/// The function that produces the next item of a [VValIter](struct.VValIter.html).
+/// It returns `Ok(None)` when the iterator is exhausted.
+pub type VValIterNext =
+    Box<dyn FnMut(&mut Env) -> Result<Option<VValIterItem>, StackAction>>;
+
+/// A lazy iterator, as created by `$iter` in WLambda.
+///
+/// The iterator is evaluated on demand by `for`, the `$@vec` and `$@map`
+/// accumulators and the `std:iter:*` functions. The combinators like
+/// `std:iter:map` wrap another iterator and only call their function
+/// when the next item is requested.
+pub struct VValIter {
+    next: VValIterNext,
+}
+
+impl VValIter {
+    pub fn new(next: VValIterNext) -> Self {
+        Self { next }
+    }
+
+    /// Produces the next item, `Ok(None)` if the iterator is exhausted.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self, env: &mut Env) -> Result<Option<VValIterItem>, StackAction> {
+        (self.next)(env)
+    }
+}
+
+impl std::fmt::Debug for VValIter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "&VValIter")
+    }
+}
+
 /// VVal aka. VariantValue is a data structure to represent
 /// all kinds of WLambda data structures.
 ///
@@ -1009,11 +1477,9 @@ pub enum VVal {
     Err(Rc<RefCell<(VVal, SynPos)>>),
     /// Representation of a boolean value.
     Bol(bool),
-    /// Representation of a symbol or key.
-    ///
-    /// This one might be interned at some point, so that it only contains
-    /// an Rc<SymRef> in future.
-    Sym(Rc<RefCell<String>>),
+    /// Representation of a symbol or key. Symbols are interned,
+    /// see also [Symbol](struct.Symbol.html).
+    Sym(Symbol),
     /// Representation of a unicode/text string.
     Str(Rc<RefCell<String>>),
     /// Representation of a byte buffer.
@@ -1022,12 +1488,15 @@ pub enum VVal {
     Int(i64),
     /// Float value
     Flt(f64),
+    /// Exact decimal value, see also [Decimal](../decimal/struct.Decimal.html).
+    Dec(Rc<Decimal>),
     /// A syntax node in the AST, records the position too.
     Syn(SynPos),
     /// A list (or vector) of VVals.
     Lst(Rc<RefCell<std::vec::Vec<VVal>>>),
-    /// A mapping of strings to VVals.
-    Map(Rc<RefCell<FnvHashMap<String, VVal>>>),
+    /// A mapping of strings to VVals. The keys are interned symbols and
+    /// kept in the order in which they were inserted.
+    Map(Rc<RefCell<IndexMap<Symbol, VVal, FnvBuildHasher>>>),
     /// A function, see also [VValFun](struct.VValFun.html)
     Fun(Rc<VValFun>),
     /// A guarded VVal, that executes a given function when it is
@@ -1043,6 +1512,33 @@ pub enum VVal {
     /// A vval that can box some user data which can later be accessed
     /// from inside user supplied Rust functions via std::any::Any.
     Usr(Box<dyn VValUserData>),
+    /// A lazy iterator, see also [VValIter](struct.VValIter.html).
+    /// Copies of the value share the iterator state.
+    Iter(Rc<RefCell<VValIter>>),
+    /// A variant of an enum, see also [VValEnum](struct.VValEnum.html).
+    Enum(Rc<VValEnum>),
+    /// A set of values, ordered by [compare_key](enum.VVal.html#method.compare_key).
+    Set(Rc<RefCell<BTreeSet<VValKey>>>),
+    /// A map from arbitrary keys to values, ordered by
+    /// [compare_key](enum.VVal.html#method.compare_key).
+    OMap(Rc<RefCell<BTreeMap<VValKey, VVal>>>),
+    /// A typed array of floats or integers, see also
+    /// [NumArray](../numarray/enum.NumArray.html).
+    NArr(Rc<RefCell<NumArray>>),
+    /// A vector of 2 integers, see also [NVec](../nvec/enum.NVec.html).
+    /// Vectors with 2 or 3 components are stored directly in the value.
+    IVec2([i64; 2]),
+    /// A vector of 3 integers.
+    IVec3([i64; 3]),
+    /// A vector of 4 integers. 4 components don't fit into the value
+    /// next to the variant tag, so they are kept behind an `Rc`.
+    IVec4(Rc<[i64; 4]>),
+    /// A vector of 2 floats, see also [NVec](../nvec/enum.NVec.html).
+    FVec2([f64; 2]),
+    /// A vector of 3 floats.
+    FVec3([f64; 3]),
+    /// A vector of 4 floats, kept behind an `Rc` like `IVec4`.
+    FVec4(Rc<[f64; 4]>),
 }
 
 impl std::fmt::Debug for VValFun {
@@ -1105,12 +1601,17 @@ impl CycleCheck {
 
         match v {
             VVal::Err(e) => self.touch_walk(&(*e).borrow().0),
+            VVal::Enum(e) => self.touch_walk(&e.payload),
             VVal::Lst(l) => {
                 for v in l.borrow().iter() { self.touch_walk(v); }
             },
             VVal::Map(l) => {
                 for (_k, v) in l.borrow().iter() { self.touch_walk(&v); }
             },
+            VVal::Set(_) => {},
+            VVal::OMap(l) => {
+                for (_k, v) in l.borrow().iter() { self.touch_walk(v); }
+            },
             VVal::DropFun(f) => { self.touch_walk(&f.v); },
             VVal::Fun(f) => {
                 for v in f.upvalues.iter() {
@@ -1132,7 +1633,16 @@ impl CycleCheck {
             | VVal::Syn(_)
             | VVal::Int(_)
             | VVal::Flt(_)
-            | VVal::Usr(_) => {},
+            | VVal::Dec(_)
+            | VVal::NArr(_)
+            | VVal::IVec2(_)
+            | VVal::IVec3(_)
+            | VVal::IVec4(_)
+            | VVal::FVec2(_)
+            | VVal::FVec3(_)
+            | VVal::FVec4(_)
+            | VVal::Usr(_)
+            | VVal::Iter(_) => {},
         }
     }
 
@@ -1185,12 +1695,54 @@ impl VVal {
         VVal::Str(Rc::new(RefCell::new(s)))
     }
 
+    pub fn new_dec(d: Decimal) -> VVal {
+        VVal::Dec(Rc::new(d))
+    }
+
+    pub fn new_ivec(v: NVec<i64>) -> VVal {
+        match v {
+            NVec::Vec2(x, y)       => VVal::IVec2([x, y]),
+            NVec::Vec3(x, y, z)    => VVal::IVec3([x, y, z]),
+            NVec::Vec4(x, y, z, w) => VVal::IVec4(Rc::new([x, y, z, w])),
+        }
+    }
+
+    pub fn new_fvec(v: NVec<f64>) -> VVal {
+        match v {
+            NVec::Vec2(x, y)       => VVal::FVec2([x, y]),
+            NVec::Vec3(x, y, z)    => VVal::FVec3([x, y, z]),
+            NVec::Vec4(x, y, z, w) => VVal::FVec4(Rc::new([x, y, z, w])),
+        }
+    }
+
+    /// Returns the integer vector stored in this value,
+    /// or `None` if it is no integer vector.
+    pub fn as_ivec(&self) -> Option<NVec<i64>> {
+        match self {
+            VVal::IVec2(c) => NVec::from_slice(&c[..]),
+            VVal::IVec3(c) => NVec::from_slice(&c[..]),
+            VVal::IVec4(c) => NVec::from_slice(&c[..]),
+            _              => None,
+        }
+    }
+
+    /// Returns the float vector stored in this value,
+    /// or `None` if it is no float vector.
+    pub fn as_fvec(&self) -> Option<NVec<f64>> {
+        match self {
+            VVal::FVec2(c) => NVec::from_slice(&c[..]),
+            VVal::FVec3(c) => NVec::from_slice(&c[..]),
+            VVal::FVec4(c) => NVec::from_slice(&c[..]),
+            _              => None,
+        }
+    }
+
     pub fn new_sym(s: &str) -> VVal {
-        VVal::Sym(Rc::new(RefCell::new(String::from(s))))
+        VVal::Sym(Symbol::new(s))
     }
 
     pub fn new_sym_mv(s: String) -> VVal {
-        VVal::Sym(Rc::new(RefCell::new(s)))
+        VVal::Sym(Symbol::new(&s))
     }
 
     pub fn new_byt(v: Vec<u8>) -> VVal {
@@ -1260,6 +1812,9 @@ impl VVal {
                 }
                 out
             },
+            VVal::Set(s) => VVal::Set(Rc::new(RefCell::new(s.borrow().clone()))),
+            VVal::OMap(m) => VVal::OMap(Rc::new(RefCell::new(m.borrow().clone()))),
+            VVal::NArr(a) => VVal::new_narr(a.borrow().clone()),
             VVal::Str(s) => {
                 VVal::new_str_mv(s.borrow_mut().clone())
             },
@@ -1274,6 +1829,113 @@ impl VVal {
         }
     }
 
+    /// Compares two values as keys of sets and ordered maps. Numbers are
+    /// compared by value, strings and symbols by their characters,
+    /// vectors element wise and enums by name, variant and payload.
+    /// Values of different types are ordered: none, booleans, numbers,
+    /// strings, bytes, vectors, enums and then everything else by
+    /// its string representation. User data is ordered by
+    /// [VValUserData::compare](trait.VValUserData.html#method.compare)
+    /// if it implements it.
+    pub fn compare_key(&self, b: &VVal) -> std::cmp::Ordering {
+        fn rank(v: &VVal) -> u8 {
+            match v {
+                VVal::Nul                  => 0,
+                VVal::Bol(_)               => 1,
+                VVal::Int(_) | VVal::Flt(_)
+                | VVal::Dec(_)             => 2,
+                VVal::Str(_) | VVal::Sym(_) => 3,
+                VVal::Byt(_)               => 4,
+                VVal::Lst(_) | VVal::NArr(_) => 5,
+                VVal::Enum(_)              => 6,
+                _                          => 7,
+            }
+        }
+
+        let a = self.deref();
+        let b = b.deref();
+        let by_rank = || rank(&a).cmp(&rank(&b)).then_with(|| a.s().cmp(&b.s()));
+        match (&a, &b) {
+            (VVal::Bol(x), VVal::Bol(y)) => x.cmp(y),
+            (VVal::Int(x), VVal::Int(y)) => x.cmp(y),
+            (VVal::Flt(x), VVal::Flt(y)) =>
+                x.partial_cmp(y).unwrap_or_else(|| x.total_cmp(y)),
+            (VVal::Dec(x), VVal::Dec(y)) => x.cmp(y),
+            (VVal::Int(x), VVal::Dec(y)) => Decimal::from_i64(*x).cmp(y),
+            (VVal::Dec(x), VVal::Int(y)) => (**x).cmp(&Decimal::from_i64(*y)),
+            // Mixed with floats the numbers are compared exactly too,
+            // so that the order of the keys is consistent:
+            (VVal::Int(x), VVal::Flt(y)) => dec_cmp_f64(&Decimal::from_i64(*x), *y),
+            (VVal::Dec(x), VVal::Flt(y)) => dec_cmp_f64(x, *y),
+            (VVal::Flt(x), VVal::Int(y)) => dec_cmp_f64(&Decimal::from_i64(*y), *x).reverse(),
+            (VVal::Flt(x), VVal::Dec(y)) => dec_cmp_f64(y, *x).reverse(),
+            (VVal::Str(_), VVal::Str(_))
+            | (VVal::Str(_), VVal::Sym(_))
+            | (VVal::Sym(_), VVal::Str(_))
+            | (VVal::Sym(_), VVal::Sym(_)) => a.compare_str(&b),
+            (VVal::Byt(x), VVal::Byt(y)) => x.borrow()[..].cmp(&y.borrow()[..]),
+            (VVal::Lst(x), VVal::Lst(y)) => {
+                let x = x.borrow();
+                let y = y.borrow();
+                for (xv, yv) in x.iter().zip(y.iter()) {
+                    let o = xv.compare_key(yv);
+                    if o != std::cmp::Ordering::Equal { return o; }
+                }
+                x.len().cmp(&y.len())
+            },
+            (VVal::NArr(x), VVal::NArr(y)) => {
+                let x = x.borrow();
+                let y = y.borrow();
+                for i in 0..x.len().min(y.len()) {
+                    let (xv, yv) = (x.get(i).unwrap(), y.get(i).unwrap());
+                    let o = xv.f().total_cmp(&yv.f());
+                    if o != std::cmp::Ordering::Equal { return o; }
+                }
+                x.len().cmp(&y.len())
+            },
+            (VVal::Enum(x), VVal::Enum(y)) => {
+                x.name.cmp(&y.name)
+                 .then_with(|| x.variant.cmp(&y.variant))
+                 .then_with(|| x.payload.compare_key(&y.payload))
+            },
+            (VVal::Usr(x), _) => x.compare(&b).unwrap_or_else(by_rank),
+            (_, VVal::Usr(y)) => y.compare(&a).map(|o| o.reverse()).unwrap_or_else(by_rank),
+            _ => by_rank(),
+        }
+    }
+
+    /// Deep copies the mutable parts of a value that is used as key.
+    fn key_copy(&self) -> VVal {
+        match self.deref() {
+            VVal::Str(s) => VVal::new_str_mv(s.borrow().clone()),
+            VVal::Byt(b) => VVal::new_byt(b.borrow().clone()),
+            VVal::Lst(l) => {
+                let out = VVal::vec();
+                for v in l.borrow().iter() { out.push(v.key_copy()); }
+                out
+            },
+            VVal::Map(m) => {
+                let out = VVal::map();
+                for (k, v) in m.borrow().iter() {
+                    out.set_map_key(k.to_string(), v.key_copy());
+                }
+                out
+            },
+            // The elements of sets are copied already when inserted:
+            VVal::Set(s) => VVal::Set(Rc::new(RefCell::new(s.borrow().clone()))),
+            VVal::OMap(m) => {
+                VVal::OMap(Rc::new(RefCell::new(
+                    m.borrow().iter()
+                     .map(|(k, v)| (k.clone(), v.key_copy()))
+                     .collect())))
+            },
+            VVal::NArr(a) => VVal::new_narr(a.borrow().clone()),
+            VVal::Enum(e) => VVal::new_enum(&e.name, &e.variant, e.payload.key_copy()),
+            VVal::Usr(u) => VVal::Usr(u.clone_ud()),
+            v => v,
+        }
+    }
+
     pub fn compare_num(&self, b: &VVal) -> std::cmp::Ordering {
         if self.is_float() {
             self.f().partial_cmp(&b.f())
@@ -1308,7 +1970,9 @@ impl VVal {
 
     /// This function returns you an iterator over the VVal.
     /// It will iterate over data such as VVal::Str, VVal::Sym, VVal::Lst,
-    /// VVal::Map and VVal::Byt.
+    /// VVal::Map and VVal::Byt. A VVal::Iter is advanced with a fresh
+    /// [Env](struct.Env.html) and stops at the first error, use
+    /// [iter_next](enum.VVal.html#method.iter_next) if you need the errors.
     ///
     /// This functionality provides the `for` keyword/function in WLambda.
     ///
@@ -1343,12 +2007,69 @@ impl VVal {
                 let m = m.clone();
                 let mut idx = 0;
                 std::iter::from_fn(Box::new(move || {
-                    let r = match m.borrow().iter().nth(idx) {
-                        Some((k, v)) => {
-                            Some((v.clone(), Some(VVal::new_str(&k))))
-                        },
-                        None => None,
-                    };
+                    let r = m.borrow().get_index(idx)
+                             .map(|(k, v)| (v.clone(), Some(VVal::new_str(k))));
+                    idx += 1;
+                    r
+                }))
+            },
+            VVal::Set(s) => {
+                let s = s.clone();
+                let mut last : Option<VValKey> = None;
+                std::iter::from_fn(Box::new(move || {
+                    let next =
+                        match &last {
+                            None    => s.borrow().iter().next().cloned(),
+                            Some(k) => s.borrow()
+                                        .range((Bound::Excluded(k), Bound::Unbounded))
+                                        .next().cloned(),
+                        }?;
+                    let v = next.0.clone();
+                    last = Some(next);
+                    Some((v, None))
+                }))
+            },
+            VVal::OMap(m) => {
+                let m = m.clone();
+                let mut last : Option<VValKey> = None;
+                std::iter::from_fn(Box::new(move || {
+                    let (k, v) =
+                        match &last {
+                            None    => m.borrow().iter().next()
+                                        .map(|(k, v)| (k.clone(), v.clone())),
+                            Some(k) => m.borrow()
+                                        .range((Bound::Excluded(k), Bound::Unbounded))
+                                        .next()
+                                        .map(|(k, v)| (k.clone(), v.clone())),
+                        }?;
+                    let kv = k.0.clone();
+                    last = Some(k);
+                    Some((v, Some(kv)))
+                }))
+            },
+            VVal::IVec2(_) | VVal::IVec3(_) | VVal::IVec4(_) => {
+                let v = self.nvec::<i64>();
+                let mut idx = 0;
+                std::iter::from_fn(Box::new(move || {
+                    let r = v.get(idx).map(|i| (VVal::Int(i), None));
+                    idx += 1;
+                    r
+                }))
+            },
+            VVal::FVec2(_) | VVal::FVec3(_) | VVal::FVec4(_) => {
+                let v = self.nvec::<f64>();
+                let mut idx = 0;
+                std::iter::from_fn(Box::new(move || {
+                    let r = v.get(idx).map(|f| (VVal::Flt(f), None));
+                    idx += 1;
+                    r
+                }))
+            },
+            VVal::NArr(a) => {
+                let a = a.clone();
+                let mut idx = 0;
+                std::iter::from_fn(Box::new(move || {
+                    let r = a.borrow().get(idx).map(|n| (VVal::from_num(n), None));
                     idx += 1;
                     r
                 }))
@@ -1363,7 +2084,8 @@ impl VVal {
                     r
                 }))
             },
-            VVal::Str(s) | VVal::Sym(s) => {
+            VVal::Sym(s) => VVal::new_str(s).iter(),
+            VVal::Str(s) => {
                 let s = s.clone();
                 let mut idx = 0;
                 std::iter::from_fn(Box::new(move || {
@@ -1375,6 +2097,22 @@ impl VVal {
                     r
                 }))
             },
+            VVal::Iter(i) => {
+                let i = i.clone();
+                let mut env = Env::new();
+                std::iter::from_fn(Box::new(move || {
+                    match i.try_borrow_mut() {
+                        Ok(mut i) => i.next(&mut env).unwrap_or(None),
+                        Err(_)    => None,
+                    }
+                }))
+            },
+            VVal::Usr(u) => {
+                match u.iter() {
+                    Some(v) => v.iter(),
+                    None    => std::iter::from_fn(Box::new(|| { None })),
+                }
+            },
             VVal::DropFun(v) => v.v.iter(),
             VVal::Ref(v)     => v.borrow().iter(),
             VVal::CRef(v)    => v.borrow().iter(),
@@ -1386,6 +2124,70 @@ impl VVal {
         }
     }
 
+    /// Creates a value of the variant `variant` of the enum `name`
+    /// with the given payload.
+    pub fn new_enum(name: &str, variant: &str, payload: VVal) -> VVal {
+        VVal::Enum(Rc::new(VValEnum {
+            name:    name.to_string(),
+            variant: variant.to_string(),
+            payload,
+        }))
+    }
+
+    /// Creates a lazy iterator value from a function, that returns
+    /// the next item or `Ok(None)` if the iterator is exhausted.
+    pub fn new_iter(next: VValIterNext) -> VVal {
+        VVal::Iter(Rc::new(RefCell::new(VValIter::new(next))))
+    }
+
+    /// Turns this VVal into a lazy iterator value, this is what `$iter`
+    /// does in WLambda. Iterators are returned as is, functions are
+    /// called without arguments for each item until they return `$none`.
+    /// For all other values the iterator works like
+    /// [iter](enum.VVal.html#method.iter).
+    pub fn to_iter(&self) -> VVal {
+        let v = self.deref();
+        match v {
+            VVal::Iter(_) => v,
+            VVal::Usr(u) => {
+                match u.iter() {
+                    Some(it) => it.to_iter(),
+                    None     => VVal::new_iter(Box::new(|_env: &mut Env| Ok(None))),
+                }
+            },
+            VVal::Fun(_) => {
+                let f = v;
+                VVal::new_iter(Box::new(move |env: &mut Env| {
+                    match f.call_internal(env, 0)? {
+                        VVal::Nul => Ok(None),
+                        v         => Ok(Some((v, None))),
+                    }
+                }))
+            },
+            _ => {
+                let mut it = v.iter();
+                VVal::new_iter(Box::new(move |_env: &mut Env| Ok(it.next())))
+            },
+        }
+    }
+
+    /// Fetches the next item from an iterator value. If this VVal is not
+    /// a VVal::Iter, `Ok(None)` is returned. Any errors from functions
+    /// called by the iterator, like those passed to `std:iter:map`,
+    /// are returned.
+    pub fn iter_next(&self, env: &mut Env) -> Result<Option<VValIterItem>, StackAction> {
+        match self.deref() {
+            VVal::Iter(i) => {
+                match i.try_borrow_mut() {
+                    Ok(mut i) => i.next(env),
+                    Err(_) => Err(StackAction::panic_msg(
+                        "Can't get the next item of an iterator that is already being iterated".to_string())),
+                }
+            },
+            _ => Ok(None),
+        }
+    }
+
     /// This method will disable all arity checks of the function in the VVal.
     /// Does nothing if the VVal is not a function.
     ///
@@ -1482,7 +2284,7 @@ impl VVal {
                 env.with_local_call_info(argc, |e: &mut Env| {
                     if argc > 0 {
                         let v = e.arg(0);
-                        Ok(v.get_key(&*sym.borrow()).unwrap_or(VVal::Nul))
+                        Ok(v.get_key_sym(sym).unwrap_or(VVal::Nul))
                     } else { Ok(self.clone()) }
                 })
             },
@@ -1767,11 +2569,89 @@ impl VVal {
     }
 
     pub fn map() -> VVal {
-        VVal::Map(Rc::new(RefCell::new(FnvHashMap::with_capacity_and_hasher(2, Default::default()))))
+        VVal::Map(Rc::new(RefCell::new(IndexMap::with_capacity_and_hasher(2, Default::default()))))
+    }
+
+    /// Creates an empty set, see also [VValKey](struct.VValKey.html).
+    pub fn new_set() -> VVal {
+        VVal::Set(Rc::new(RefCell::new(BTreeSet::new())))
+    }
+
+    /// Creates an empty ordered map, see also [VValKey](struct.VValKey.html).
+    pub fn new_omap() -> VVal {
+        VVal::OMap(Rc::new(RefCell::new(BTreeMap::new())))
+    }
+
+    /// Creates a numeric array value, see also
+    /// [NumArray](../numarray/enum.NumArray.html).
+    pub fn new_narr(a: NumArray) -> VVal {
+        VVal::NArr(Rc::new(RefCell::new(a)))
+    }
+
+    /// Converts this value to a vector with components of type `N`.
+    /// Vectors are converted, vectors and numeric arrays use their first
+    /// 2 to 4 elements and maps their keys `x`, `y`, `z` and `w`. Missing
+    /// components are 0. Other values are used for both components of
+    /// a 2 component vector.
+    pub fn nvec<N: NVecNum>(&self) -> NVec<N> {
+        let v = self.deref();
+        if let Some(v) = v.as_ivec() { return v.convert(); }
+        if let Some(v) = v.as_fvec() { return v.convert(); }
+
+        match v {
+            VVal::Lst(_) | VVal::NArr(_) => {
+                let mut c = [N::default(); 4];
+                let mut dims = 2;
+                for (i, (v, _)) in self.iter().take(4).enumerate() {
+                    c[i] = v.nvec_num();
+                    dims = dims.max(i + 1);
+                }
+                NVec::from_slice(&c[..dims]).unwrap()
+            },
+            VVal::Map(_) => {
+                let mut c = [N::default(); 4];
+                let mut dims = 2;
+                for (i, k) in ["x", "y", "z", "w"].iter().enumerate() {
+                    if let Some(v) = self.get_key(k) {
+                        c[i] = v.nvec_num();
+                        dims = dims.max(i + 1);
+                    }
+                }
+                NVec::from_slice(&c[..dims]).unwrap()
+            },
+            v => NVec::splat(v.nvec_num(), 2),
+        }
+    }
+
+    fn nvec_num<N: NVecNum>(&self) -> N {
+        match self {
+            VVal::Flt(_) | VVal::Dec(_) => N::from_f64(self.f()),
+            _ => N::from_i64(self.i()),
+        }
+    }
+
+    /// Converts the right operand of a vector operation,
+    /// numbers are used for all `dims` components.
+    fn nvec_operand<N: NVecNum>(&self, dims: usize) -> NVec<N> {
+        match self.deref() {
+            VVal::IVec2(_) | VVal::IVec3(_) | VVal::IVec4(_)
+            | VVal::FVec2(_) | VVal::FVec3(_) | VVal::FVec4(_)
+            | VVal::Lst(_) | VVal::NArr(_) | VVal::Map(_) => self.nvec(),
+            v => NVec::splat(v.nvec_num(), dims),
+        }
+    }
+
+    /// Creates an integer or float value from an element
+    /// of a numeric array.
+    pub fn from_num(n: Num) -> VVal {
+        match n {
+            Num::Flt(f) => VVal::Flt(f),
+            Num::Int(i) => VVal::Int(i),
+        }
     }
 
     pub fn sym(s: &str) -> VVal {
-        VVal::Sym(std::rc::Rc::new(std::cell::RefCell::new(String::from(s))))
+        VVal::Sym(Symbol::new(s))
     }
 
     #[allow(clippy::cast_ptr_alignment)]
@@ -1781,9 +2661,13 @@ impl VVal {
             VVal::Str(s)     => { &*s.borrow() as *const String as i64 },
             VVal::Byt(s)     => { &*s.borrow() as *const Vec<u8> as i64 },
             VVal::Lst(v)     => { &*v.borrow() as *const Vec<VVal> as i64 },
-            VVal::Map(v)     => { &*v.borrow() as *const FnvHashMap<String, VVal> as i64 },
+            VVal::Map(v)     => { &*v.borrow() as *const IndexMap<Symbol, VVal, FnvBuildHasher> as i64 },
+            VVal::Set(v)     => { &*v.borrow() as *const BTreeSet<VValKey> as i64 },
+            VVal::OMap(v)    => { &*v.borrow() as *const BTreeMap<VValKey, VVal> as i64 },
+            VVal::NArr(v)    => { &*v.borrow() as *const NumArray as i64 },
             VVal::Fun(f)     => { &**f as *const VValFun as i64 },
             VVal::DropFun(f) => { &**f as *const DropVVal as i64 },
+            VVal::Iter(i)    => { i.as_ptr() as *const VValIter as i64 },
             VVal::Ref(v)     => { &*v.borrow() as *const VVal as i64 },
             VVal::CRef(v)    => { &*v.borrow() as *const VVal as i64 },
             VVal::Usr(b)     => { &**b as *const dyn VValUserData as *const usize as i64 },
@@ -1804,6 +2688,7 @@ impl VVal {
             VVal::Bol(ia) => { if let VVal::Bol(ib) = v { ia == ib } else { false } },
             VVal::Int(ia) => { if let VVal::Int(ib) = v { ia == ib } else { false } },
             VVal::Flt(ia) => { if let VVal::Flt(ib) = v { (ia - ib).abs() < std::f64::EPSILON } else { false } },
+            VVal::Dec(ia) => { if let VVal::Dec(ib) = v { ia == ib } else { false } },
             VVal::Sym(s)  => { if let VVal::Sym(ib) = v { *s == *ib } else { false } },
             VVal::Syn(s)  => { if let VVal::Syn(ib) = v { *s == *ib } else { false } },
             VVal::Str(s)  => { if let VVal::Str(ib) = v { *s == *ib } else { false } },
@@ -1814,12 +2699,35 @@ impl VVal {
             VVal::Map(l)  => {
                 if let VVal::Map(l2) = v { Rc::ptr_eq(l, l2) } else { false }
             },
+            VVal::Set(l)  => {
+                if let VVal::Set(l2) = v { Rc::ptr_eq(l, l2) } else { false }
+            },
+            VVal::OMap(l)  => {
+                if let VVal::OMap(l2) = v { Rc::ptr_eq(l, l2) } else { false }
+            },
+            VVal::NArr(a)  => {
+                if let VVal::NArr(a2) = v { *a.borrow() == *a2.borrow() } else { false }
+            },
+            VVal::IVec2(_) | VVal::IVec3(_) | VVal::IVec4(_)
+                => self.as_ivec() == v.as_ivec(),
+            VVal::FVec2(_) | VVal::FVec3(_) | VVal::FVec4(_)
+                => self.as_fvec() == v.as_fvec(),
             VVal::Fun(l)  => {
                 if let VVal::Fun(l2) = v { Rc::ptr_eq(l, l2) } else { false }
             },
             VVal::DropFun(l)  => {
                 if let VVal::DropFun(l2) = v { Rc::ptr_eq(l, l2) } else { false }
             },
+            VVal::Iter(l)  => {
+                if let VVal::Iter(l2) = v { Rc::ptr_eq(l, l2) } else { false }
+            },
+            VVal::Enum(e)  => {
+                if let VVal::Enum(e2) = v {
+                       e.name    == e2.name
+                    && e.variant == e2.variant
+                    && e.payload.eqv(&e2.payload)
+                } else { false }
+            },
             VVal::Err(l)  => {
                 if let VVal::Err(l2) = v { Rc::ptr_eq(l, l2) } else { false }
             },
@@ -1886,13 +2794,13 @@ impl VVal {
         out.concat()
     }
 
-    fn dump_map_as_str(m: &Rc<RefCell<FnvHashMap<String,VVal>>>, c: &mut CycleCheck) -> String {
+    fn dump_map_as_str(m: &Rc<RefCell<IndexMap<Symbol, VVal, FnvBuildHasher>>>, c: &mut CycleCheck) -> String {
         let mut out : Vec<String> = Vec::new();
         let mut first = true;
         out.push(String::from("${"));
         let hm = m.borrow();
 
-        let mut keys : Vec<&String> = hm.keys().collect();
+        let mut keys : Vec<&Symbol> = hm.keys().collect();
         keys.sort();
         for k in keys {
             let v = hm.get(k).unwrap();
@@ -1993,6 +2901,12 @@ impl VVal {
                     None
                 }
             },
+            VVal::OMap(m) => m.borrow().get(&VValKey(VVal::Int(index as i64))).cloned(),
+            VVal::NArr(a) => a.borrow().get(index).map(VVal::from_num),
+            VVal::IVec2(_) | VVal::IVec3(_) | VVal::IVec4(_)
+                => self.nvec::<i64>().get(index).map(VVal::Int),
+            VVal::FVec2(_) | VVal::FVec3(_) | VVal::FVec4(_)
+                => self.nvec::<f64>().get(index).map(VVal::Flt),
             _ => self.get_key(&format!("{}", index)),
         }
     }
@@ -2002,7 +2916,7 @@ impl VVal {
             VVal::Ref(_)   => self.deref().proto_data(),
             VVal::CRef(_)  => self.deref().proto_data(),
             VVal::WWRef(_) => self.deref().proto_data(),
-            VVal::Map(m) => m.borrow().get("_data").cloned().unwrap_or(VVal::Nul),
+            VVal::Map(_) => self.get_key("_data").unwrap_or(VVal::Nul),
             VVal::Lst(l) => {
                 if l.borrow().len() > 1 {
                     l.borrow()[1].clone()
@@ -2014,16 +2928,118 @@ impl VVal {
         }
     }
 
+    /// Applies the element wise arithmetic operation `op` to the numeric
+    /// array or vector `self` and `b`. For numeric arrays `b` is either
+    /// a numeric array or a number that is used for all elements.
+    /// For vectors `b` is converted to a vector like by
+    /// [nvec](#method.nvec), numbers are used for all components.
+    /// Returns `None` if `self` is neither a numeric array nor a vector.
+    #[inline]
+    pub fn elem_binop(&self, op: ArrayOp, b: &VVal)
+        -> Option<Result<VVal, ArrayError>>
+    {
+        let a =
+            match self {
+                VVal::NArr(a) => a.borrow(),
+                VVal::IVec2(_) | VVal::IVec3(_) | VVal::IVec4(_) => {
+                    let a = self.nvec::<i64>();
+                    return Some(a.binop(op, &b.nvec_operand(a.dims())).map(VVal::new_ivec));
+                },
+                VVal::FVec2(_) | VVal::FVec3(_) | VVal::FVec4(_) => {
+                    let a = self.nvec::<f64>();
+                    return Some(a.binop(op, &b.nvec_operand(a.dims())).map(VVal::new_fvec));
+                },
+                _ => return None,
+            };
+        let r =
+            match b.deref() {
+                VVal::NArr(b) => a.binop(op, &b.borrow()),
+                VVal::Flt(f)  => a.binop(op, &NumArray::Flt(vec![f])),
+                VVal::Dec(d)  => a.binop(op, &NumArray::Flt(vec![d.to_f64()])),
+                b             => a.binop(op, &NumArray::Int(vec![b.i()])),
+            };
+        Some(r.map(VVal::new_narr))
+    }
+
+    /// Calls the method `method`, that overloads a binary operator, on
+    /// the prototype object or user data `a` with `b` as argument.
+    /// If `a` does not overload the operator, the reflected method
+    /// `rmethod` (like `_radd_` for `_add_`) is called on `b` with `a`
+    /// as argument. Returns `None` if none of both overloads the operator.
+    pub fn call_binop(env: &mut Env, method: &str, rmethod: Option<&str>,
+                      a: &VVal, b: &VVal)
+        -> Option<Result<VVal, StackAction>>
+    {
+        if let Some(ret) = a.call_op_method(env, method, b) {
+            return Some(ret);
+        }
+        match rmethod {
+            Some(rmethod) => b.call_op_method(env, rmethod, a),
+            None          => None,
+        }
+    }
+
+    fn call_op_method(&self, env: &mut Env, method: &str, other: &VVal)
+        -> Option<Result<VVal, StackAction>>
+    {
+        match self {
+            VVal::Usr(u) => {
+                u.binop(method, other).or_else(|| {
+                    let o = u.compare(other)?;
+                    match method {
+                        "_lt_" => Some(Ok(VVal::Bol(o == std::cmp::Ordering::Less))),
+                        "_le_" => Some(Ok(VVal::Bol(o != std::cmp::Ordering::Greater))),
+                        "_gt_" => Some(Ok(VVal::Bol(o == std::cmp::Ordering::Greater))),
+                        "_ge_" => Some(Ok(VVal::Bol(o != std::cmp::Ordering::Less))),
+                        _      => None,
+                    }
+                })
+            },
+            VVal::Lst(l) => {
+                // Only `$[class, data]` objects overload operators, where the
+                // class map defines the method. Not vectors that merely
+                // start with an object:
+                let fun = {
+                    let l = l.borrow();
+                    if l.len() != 2 { return None; }
+                    match l[0].deref() {
+                        VVal::Map(class) =>
+                            class.borrow().get(&Symbol::lookup(method)?)?.clone(),
+                        _ => return None,
+                    }
+                };
+                Some(env.with_object(self.clone(), |e: &mut Env|
+                    fun.call(e, std::slice::from_ref(other))))
+            },
+            VVal::Map(_) => {
+                let fun = self.proto_lookup(method)?;
+                Some(env.with_object(self.clone(), |e: &mut Env|
+                    fun.call(e, std::slice::from_ref(other))))
+            },
+            VVal::Ref(_) | VVal::CRef(_) | VVal::WWRef(_) =>
+                self.deref().call_op_method(env, method, other),
+            _ => None,
+        }
+    }
+
     pub fn proto_lookup(&self, key: &str) -> Option<VVal> {
+        self.proto_lookup_sym(&Symbol::lookup(key)?)
+    }
+
+    /// Like [proto_lookup](#method.proto_lookup), but with an
+    /// already interned key.
+    pub fn proto_lookup_sym(&self, key: &Symbol) -> Option<VVal> {
         match self {
-            VVal::Ref(_)   => self.deref().proto_lookup(key),
-            VVal::CRef(_)  => self.deref().proto_lookup(key),
-            VVal::WWRef(_) => self.deref().proto_lookup(key),
+            VVal::Ref(_)   => self.deref().proto_lookup_sym(key),
+            VVal::CRef(_)  => self.deref().proto_lookup_sym(key),
+            VVal::WWRef(_) => self.deref().proto_lookup_sym(key),
             VVal::Map(m) => {
                 if let Some(func) = m.borrow().get(key) {
                     Some(func.clone())
-                } else if let Some(proto) = m.borrow().get("_proto") {
-                    proto.proto_lookup(key)
+                } else if let Some(proto) =
+                    PROTO_SYM.with(|p| m.borrow().get(p).cloned())
+                {
+                    proto.proto_lookup_sym(key)
                 } else {
                     None
                 }
@@ -2033,19 +3049,65 @@ impl VVal {
                 if l.is_empty() {
                     None
                 } else {
-                    l[0].proto_lookup(key)
+                    l[0].proto_lookup_sym(key)
                 }
             },
+            VVal::Usr(u) => u.get_key(key),
             _ => None
         }
     }
 
+    /// Like [get_key](#method.get_key), but with an already interned key.
+    /// Looking up a map key is then only a pointer comparison.
+    pub fn get_key_sym(&self, key: &Symbol) -> Option<VVal> {
+        match self {
+            VVal::Ref(_)   => self.deref().get_key_sym(key),
+            VVal::CRef(_)  => self.deref().get_key_sym(key),
+            VVal::WWRef(_) => self.deref().get_key_sym(key),
+            VVal::Map(m)   => m.borrow().get(key).cloned(),
+            _              => self.get_key(key),
+        }
+    }
+
+    /// Like [get_key_sym](#method.get_key_sym), but remembers the
+    /// position of the key in `cache` for the next lookup.
+    pub fn get_key_cached(&self, key: &Symbol, cache: &LookupCache) -> Option<VVal> {
+        match self {
+            VVal::Ref(_)   => self.deref().get_key_cached(key, cache),
+            VVal::CRef(_)  => self.deref().get_key_cached(key, cache),
+            VVal::WWRef(_) => self.deref().get_key_cached(key, cache),
+            VVal::Map(m)   => cache.lookup(m.clone(), key, false),
+            _              => self.get_key(key),
+        }
+    }
+
+    /// Like [proto_lookup_sym](#method.proto_lookup_sym), but remembers
+    /// the map and position of the key in `cache` for the next lookup.
+    pub fn proto_lookup_cached(&self, key: &Symbol, cache: &LookupCache) -> Option<VVal> {
+        match self {
+            VVal::Ref(_)   => self.deref().proto_lookup_cached(key, cache),
+            VVal::CRef(_)  => self.deref().proto_lookup_cached(key, cache),
+            VVal::WWRef(_) => self.deref().proto_lookup_cached(key, cache),
+            VVal::Map(m)   => cache.lookup(m.clone(), key, true),
+            _              => self.proto_lookup_sym(key),
+        }
+    }
+
     pub fn get_key(&self, key: &str) -> Option<VVal> {
         match self {
             VVal::Ref(_)   => self.deref().get_key(key),
             VVal::CRef(_)  => self.deref().get_key(key),
             VVal::WWRef(_) => self.deref().get_key(key),
-            VVal::Map(m) => m.borrow().get(key).cloned(),
+            VVal::Map(m) => m.borrow().get(&Symbol::lookup(key)?).cloned(),
+            VVal::OMap(m) => m.borrow().get(&omap_field_key(key)).cloned(),
+            VVal::NArr(a) => {
+                let idx = key.parse::<usize>().ok()?;
+                a.borrow().get(idx).map(VVal::from_num)
+            },
+            VVal::IVec2(_) | VVal::IVec3(_) | VVal::IVec4(_)
+                => nvec_key(&self.nvec(), key, VVal::Int, VVal::new_ivec),
+            VVal::FVec2(_) | VVal::FVec3(_) | VVal::FVec4(_)
+                => nvec_key(&self.nvec(), key, VVal::Flt, VVal::new_fvec),
             VVal::Lst(l) => {
                 let idx = usize::from_str_radix(key, 10).unwrap_or(0);
                 if idx < l.borrow().len() {
@@ -2069,7 +3131,7 @@ impl VVal {
             VVal::Ref(_)   => self.deref().set_map_key(key, val),
             VVal::CRef(_)  => self.deref().set_map_key(key, val),
             VVal::WWRef(_) => self.deref().set_map_key(key, val),
-            VVal::Map(m)   => { m.borrow_mut().insert(key, val); },
+            VVal::Map(m)   => { insert_map_key(m, &mut m.borrow_mut(), Symbol::new(&key), val); },
             _ => (),
         }
     }
@@ -2081,7 +3143,10 @@ impl VVal {
             VVal::WWRef(_) => self.deref().set_key_mv(key, val),
             VVal::DropFun(f) => f.v.set_key_mv(key, val),
             VVal::Map(m) => {
-                m.borrow_mut().insert(key, val);
+                insert_map_key(m, &mut m.borrow_mut(), Symbol::new(&key), val);
+            },
+            VVal::OMap(m) => {
+                m.borrow_mut().insert(omap_field_key(&key), val);
             },
             VVal::Lst(l) => {
                 let idx = key.parse::<usize>().unwrap_or(0);
@@ -2125,9 +3190,32 @@ impl VVal {
             VVal::WWRef(_) => self.deref().set_key(key, val),
             VVal::DropFun(f) => f.v.set_key(key, val),
             VVal::Map(m) => {
-                let ks = key.s_raw();
+                let ks =
+                    match key {
+                        VVal::Sym(s) => s.clone(),
+                        _            => Symbol::new(&key.s_raw()),
+                    };
                 match m.try_borrow_mut() {
-                    Ok(mut r)  => { r.insert(ks, val); Ok(()) },
+                    Ok(mut r)  => { insert_map_key(m, &mut r, ks, val); Ok(()) },
+                    Err(_)     => Err(StackAction::panic_borrow(self)),
+                }
+            },
+            VVal::OMap(m) => {
+                let key =
+                    match key.deref() {
+                        VVal::Str(s) => omap_field_key(&s.borrow()),
+                        key          => VValKey::new(&key),
+                    };
+                match m.try_borrow_mut() {
+                    Ok(mut r)  => { r.insert(key, val); Ok(()) },
+                    Err(_)     => Err(StackAction::panic_borrow(self)),
+                }
+            },
+            VVal::NArr(a) => {
+                if key.i() < 0 { return Ok(()); }
+                let n = if val.is_float() { Num::Flt(val.f()) } else { Num::Int(val.i()) };
+                match a.try_borrow_mut() {
+                    Ok(mut a)  => { a.set(key.i() as usize, n); Ok(()) },
                     Err(_)     => Err(StackAction::panic_borrow(self)),
                 }
             },
@@ -2165,7 +3253,7 @@ impl VVal {
                     VVal::Int(i) => { acc.push(*i as u8); },
                     VVal::Flt(f) => { acc.push(*f as u8); },
                     VVal::Str(s) => { acc.extend_from_slice(s.borrow().as_bytes()); },
-                    VVal::Sym(s) => { acc.extend_from_slice(s.borrow().as_bytes()); },
+                    VVal::Sym(s) => { acc.extend_from_slice(s.as_bytes()); },
                     VVal::Byt(s) => { acc.extend_from_slice(&s.borrow()); },
                     VVal::Bol(b) => { acc.push(*b as u8); },
                     _ => { acc.extend_from_slice(val.s_raw().as_bytes()); }
@@ -2175,7 +3263,7 @@ impl VVal {
                 let mut acc = a.borrow_mut();
                 match val {
                     VVal::Str(s) => { acc.push_str(&s.borrow()); },
-                    VVal::Sym(s) => { acc.push_str(&s.borrow()); },
+                    VVal::Sym(s) => { acc.push_str(s); },
                     VVal::Byt(s) => {
                         for b in s.borrow().iter() {
                             let b = *b as char;
@@ -2187,6 +3275,9 @@ impl VVal {
             },
             VVal::Int(i) => { *i += val.i(); },
             VVal::Flt(f) => { *f += val.f(); },
+            VVal::Dec(d) => {
+                if let Some(sum) = d.checked_add(val.d()) { *d = Rc::new(sum); }
+            },
             VVal::Lst(v) => { v.borrow_mut().push(val.clone()); },
             _ => (),
         }
@@ -2207,9 +3298,15 @@ impl VVal {
         match self {
             VVal::Lst(l) => l.borrow().len(),
             VVal::Map(l) => l.borrow().len(),
+            VVal::Set(l) => l.borrow().len(),
+            VVal::OMap(l) => l.borrow().len(),
+            VVal::NArr(l) => l.borrow().len(),
+            VVal::IVec2(_) | VVal::FVec2(_) => 2,
+            VVal::IVec3(_) | VVal::FVec3(_) => 3,
+            VVal::IVec4(_) | VVal::FVec4(_) => 4,
             VVal::Byt(l) => l.borrow().len(),
             VVal::Str(l) => l.borrow().len(),
-            VVal::Sym(l) => l.borrow().len(),
+            VVal::Sym(l) => l.len(),
             _ => 0,
         }
     }
@@ -2217,7 +3314,7 @@ impl VVal {
     pub fn s_len(&self) -> usize {
         match self {
             VVal::Str(s)  => s.borrow().chars().count(),
-            VVal::Sym(s)  => s.borrow().chars().count(),
+            VVal::Sym(s)  => s.chars().count(),
             VVal::Usr(s)  => s.s_raw().chars().count(),
             VVal::Byt(b)  => b.borrow().len(),
             VVal::Nul     => 0,
@@ -2242,10 +3339,11 @@ impl VVal {
     pub fn s_raw(&self) -> String {
         match self {
             VVal::Str(s)  => s.borrow().clone(),
-            VVal::Sym(s)  => s.borrow().clone(),
+            VVal::Sym(s)  => s.to_string(),
             VVal::Usr(s)  => s.s_raw(),
             VVal::Byt(s)  => s.borrow().iter().map(|b| *b as char).collect(),
             VVal::Nul     => String::from(""),
+            VVal::Dec(d)  => d.to_string(),
             _             => self.s(),
         }
     }
@@ -2254,6 +3352,10 @@ impl VVal {
         match self { VVal::Flt(_) => true, _ => false }
     }
 
+    pub fn is_decimal(&self) -> bool {
+        matches!(self, VVal::Dec(_))
+    }
+
     pub fn is_int(&self) -> bool {
         match self { VVal::Int(_) => true, _ => false }
     }
@@ -2357,9 +3459,19 @@ impl VVal {
             VVal::Syn(_)     => String::from("syn"),
             VVal::Int(_)     => String::from("int"),
             VVal::Flt(_)     => String::from("float"),
+            VVal::Dec(_)     => String::from("decimal"),
             VVal::Lst(_)     => String::from("vector"),
             VVal::Map(_)     => String::from("map"),
+            VVal::Set(_)     => String::from("set"),
+            VVal::OMap(_)    => String::from("omap"),
+            VVal::NArr(a)    => format!("{}array", a.borrow().elem_type()),
+            VVal::IVec2(_) | VVal::IVec3(_) | VVal::IVec4(_)
+                             => String::from("ivec"),
+            VVal::FVec2(_) | VVal::FVec3(_) | VVal::FVec4(_)
+                             => String::from("fvec"),
             VVal::Usr(_)     => String::from("userdata"),
+            VVal::Iter(_)    => String::from("iter"),
+            VVal::Enum(_)    => String::from("enum"),
             VVal::Fun(_)     => String::from("function"),
             VVal::DropFun(_) => String::from("drop_function"),
             VVal::Ref(_)     => String::from("ref"),
@@ -2490,7 +3602,7 @@ impl VVal {
     pub fn f(&self) -> f64 {
         match self {
             VVal::Str(s)     => (*s).borrow().parse::<f64>().unwrap_or(0.0),
-            VVal::Sym(s)     => (*s).borrow().parse::<f64>().unwrap_or(0.0),
+            VVal::Sym(s)     => s.parse::<f64>().unwrap_or(0.0),
             VVal::Byt(s)     => if (*s).borrow().len() > 0 { (*s).borrow()[0] as f64 } else { 0.0 },
             VVal::Nul        => 0.0,
             VVal::Err(_)     => 0.0,
@@ -2498,9 +3610,18 @@ impl VVal {
             VVal::Syn(s)     => (s.syn.clone() as i64) as f64,
             VVal::Int(i)     => *i as f64,
             VVal::Flt(f)     => *f,
+            VVal::Dec(d)     => d.to_f64(),
             VVal::Lst(l)     => l.borrow().len() as f64,
             VVal::Map(l)     => l.borrow().len() as f64,
+            VVal::Set(l)     => l.borrow().len() as f64,
+            VVal::OMap(l)    => l.borrow().len() as f64,
+            VVal::NArr(l)    => l.borrow().len() as f64,
+            VVal::IVec2(_) | VVal::IVec3(_) | VVal::IVec4(_)
+            | VVal::FVec2(_) | VVal::FVec3(_) | VVal::FVec4(_)
+                             => self.nvec::<f64>().x(),
             VVal::Usr(u)     => u.f(),
+            VVal::Iter(_)    => 1.0,
+            VVal::Enum(e)    => e.payload.f(),
             VVal::Fun(_)     => 1.0,
             VVal::DropFun(f) => f.v.f(),
             VVal::Ref(l)     => (*l).borrow().f(),
@@ -2518,7 +3639,7 @@ impl VVal {
     pub fn i(&self) -> i64 {
         match self {
             VVal::Str(s)     => (*s).borrow().parse::<i64>().unwrap_or(0),
-            VVal::Sym(s)     => (*s).borrow().parse::<i64>().unwrap_or(0),
+            VVal::Sym(s)     => s.parse::<i64>().unwrap_or(0),
             VVal::Byt(s)     => if (*s).borrow().len() > 0 { (*s).borrow()[0] as i64 } else { 0 as i64 },
             VVal::Nul        => 0,
             VVal::Err(_)     => 0,
@@ -2526,9 +3647,18 @@ impl VVal {
             VVal::Syn(s)     => s.syn.clone() as i64,
             VVal::Int(i)     => *i,
             VVal::Flt(f)     => (*f as i64),
+            VVal::Dec(d)     => d.to_i64(),
             VVal::Lst(l)     => l.borrow().len() as i64,
             VVal::Map(l)     => l.borrow().len() as i64,
+            VVal::Set(l)     => l.borrow().len() as i64,
+            VVal::OMap(l)    => l.borrow().len() as i64,
+            VVal::NArr(l)    => l.borrow().len() as i64,
+            VVal::IVec2(_) | VVal::IVec3(_) | VVal::IVec4(_)
+            | VVal::FVec2(_) | VVal::FVec3(_) | VVal::FVec4(_)
+                             => self.nvec::<i64>().x(),
             VVal::Usr(u)     => u.i(),
+            VVal::Iter(_)    => 1,
+            VVal::Enum(e)    => e.payload.i(),
             VVal::Fun(_)     => 1,
             VVal::DropFun(f) => f.v.i(),
             VVal::Ref(l)     => (*l).borrow().i(),
@@ -2542,11 +3672,41 @@ impl VVal {
         }
     }
 
+    /// Like [d](#method.d), but returns `None` for floats that
+    /// can't be represented as decimal, like NaN, infinity or floats
+    /// beyond the range of decimals.
+    pub fn try_d(&self) -> Option<Decimal> {
+        match self.deref() {
+            VVal::Flt(f) => Decimal::from_f64(f),
+            v            => Some(v.d()),
+        }
+    }
+
+    /// Returns the decimal representation of the value. Floats are
+    /// converted to their shortest decimal representation and strings
+    /// are parsed. Everything else is converted like [i](#method.i).
+    /// Floats that can't be represented are converted to 0, see
+    /// also [try_d](#method.try_d).
+    pub fn d(&self) -> Decimal {
+        match self {
+            VVal::Dec(d)     => **d,
+            VVal::Flt(f)     => Decimal::from_f64(*f).unwrap_or_default(),
+            VVal::Str(s)     => Decimal::parse(&s.borrow()).unwrap_or_default(),
+            VVal::Sym(s)     => Decimal::parse(s).unwrap_or_default(),
+            VVal::Enum(e)    => e.payload.d(),
+            VVal::DropFun(f) => f.v.d(),
+            VVal::Ref(_)
+            | VVal::CRef(_)
+            | VVal::WWRef(_) => self.deref().d(),
+            _                => Decimal::from_i64(self.i()),
+        }
+    }
+
     #[allow(clippy::cast_lossless)]
     pub fn b(&self) -> bool {
         match self {
             VVal::Str(s)     => (*s).borrow().parse::<i64>().unwrap_or(0) != 0,
-            VVal::Sym(s)     => (*s).borrow().parse::<i64>().unwrap_or(0) != 0,
+            VVal::Sym(s)     => s.parse::<i64>().unwrap_or(0) != 0,
             VVal::Byt(s)     => (if (*s).borrow().len() > 0 { (*s).borrow()[0] as i64 } else { 0 as i64 }) != 0,
             VVal::Nul        => false,
             VVal::Err(_)     => false,
@@ -2554,9 +3714,19 @@ impl VVal {
             VVal::Syn(s)     => (s.syn.clone() as i64) != 0,
             VVal::Int(i)     => (*i) != 0,
             VVal::Flt(f)     => (*f as i64) != 0,
+            VVal::Dec(d)     => !d.is_zero(),
             VVal::Lst(l)     => (l.borrow().len() as i64) != 0,
             VVal::Map(l)     => (l.borrow().len() as i64) != 0,
+            VVal::Set(l)     => !l.borrow().is_empty(),
+            VVal::OMap(l)    => !l.borrow().is_empty(),
+            VVal::NArr(l)    => !l.borrow().is_empty(),
+            VVal::IVec2(_) | VVal::IVec3(_) | VVal::IVec4(_)
+                             => self.nvec::<i64>().components().iter().any(|c| *c != 0),
+            VVal::FVec2(_) | VVal::FVec3(_) | VVal::FVec4(_)
+                             => self.nvec::<f64>().components().iter().any(|c| *c != 0.0),
             VVal::Usr(u)     => u.b(),
+            VVal::Iter(_)    => true,
+            VVal::Enum(_)    => true,
             VVal::Fun(_)     => true,
             VVal::DropFun(f) => f.v.b(),
             VVal::Ref(l)     => (*l).borrow().i() != 0,
@@ -2579,7 +3749,7 @@ impl VVal {
         };
         let s = match self {
             VVal::Str(s)     => format_vval_str(&s.borrow(), false),
-            VVal::Sym(s)     => format!(":\"{}\"", &s.borrow()),
+            VVal::Sym(s)     => format!(":\"{}\"", s),
             VVal::Byt(s)     => format!("$b{}", format_vval_byt(&s.borrow())),
             VVal::Nul        => "$n".to_string(),
             VVal::Err(e)     => format!("$e{} {}", (*e).borrow().1, (*e).borrow().0.s_cy(c)),
@@ -2587,9 +3757,35 @@ impl VVal {
             VVal::Syn(s)     => format!("&{:?}", s.syn),
             VVal::Int(i)     => i.to_string(),
             VVal::Flt(f)     => f.to_string(),
+            VVal::Dec(d)     => format!("{}d", d),
             VVal::Lst(l)     => VVal::dump_vec_as_str(l, c),
             VVal::Map(l)     => VVal::dump_map_as_str(l, c), // VVal::dump_map_as_str(l),
+            VVal::Set(l)     => {
+                let elems : Vec<String> =
+                    l.borrow().iter().map(|k| k.0.s_cy(c)).collect();
+                format!("$<set[{}]>", elems.join(","))
+            },
+            VVal::OMap(l)    => {
+                let elems : Vec<String> =
+                    l.borrow().iter()
+                     .map(|(k, v)| format!("{}={}", k.0.s_cy(c), v.s_cy(c)))
+                     .collect();
+                format!("$<omap[{}]>", elems.join(","))
+            },
+            VVal::NArr(a)    => a.borrow().to_string(),
+            VVal::IVec2(_) | VVal::IVec3(_) | VVal::IVec4(_)
+                             => self.nvec::<i64>().to_string(),
+            VVal::FVec2(_) | VVal::FVec3(_) | VVal::FVec4(_)
+                             => self.nvec::<f64>().to_string(),
             VVal::Usr(u)     => u.s(),
+            VVal::Iter(_)    => "$iter &VValIter".to_string(),
+            VVal::Enum(e)    => {
+                if e.payload.is_none() {
+                    format!("$<{}.{}>", e.name, e.variant)
+                } else {
+                    format!("$<{}.{} {}>", e.name, e.variant, e.payload.s_cy(c))
+                }
+            },
             VVal::Fun(f)     => {
                 let min = if f.min_args.is_none() { "any".to_string() }
                           else { format!("{}", f.min_args.unwrap()) };
@@ -2679,18 +3875,28 @@ impl VVal {
     }
 
     /// Serializes the VVal (non cyclic) structure to a JSON string.
+    /// Decimals are written as strings to keep them exact.
     #[cfg(feature="serde_json")]
     pub fn to_json(&self, not_pretty: bool) -> Result<String, String> {
-        if not_pretty {
-            match serde_json::to_string(self) {
-                Ok(s) => Ok(s),
-                Err(e) => Err(format!("to_json failed: {}", e))
-            }
-        } else {
-            match serde_json::to_string_pretty(self) {
-                Ok(s) => Ok(s),
-                Err(e) => Err(format!("to_json failed: {}", e))
-            }
+        self.to_json_opts(not_pretty, false)
+    }
+
+    /// Like [to_json](#method.to_json), but writes decimals as JSON
+    /// numbers if `decimals_as_numbers` is true. These are converted
+    /// to floats and might lose precision.
+    #[cfg(feature="serde_json")]
+    pub fn to_json_opts(&self, not_pretty: bool, decimals_as_numbers: bool)
+        -> Result<String, String>
+    {
+        let prev = SER_DECIMALS_AS_NUMBERS.with(|f| f.replace(decimals_as_numbers));
+        let ret =
+            if not_pretty { serde_json::to_string(self) }
+            else          { serde_json::to_string_pretty(self) };
+        SER_DECIMALS_AS_NUMBERS.with(|f| f.set(prev));
+
+        match ret {
+            Ok(s) => Ok(s),
+            Err(e) => Err(format!("to_json failed: {}", e))
         }
     }
 
@@ -2702,6 +3908,78 @@ impl VVal {
             Err(e) => Err(format!("from_json failed: {}", e)),
         }
     }
+
+    /// Creates a VVal structure from WLambda literal syntax, as written
+    /// by [s](#method.s). Only numbers, strings, byte vectors, symbols,
+    /// vectors, maps, booleans and `$none` are accepted. Anything that
+    /// would be executed, like variables, calls or functions, is rejected,
+    /// so untrusted data can be read with this. Values nested deeper
+    /// than 128 levels are rejected, instead of overflowing the stack.
+    ///
+    /// ```
+    /// use wlambda::VVal;
+    ///
+    /// let v = VVal::from_wlambda_str("${a=$[1,2.5,:x],b=$b\"\\x01\"}").unwrap();
+    /// assert_eq!(v.v_k("a").s(), "$[1,2.5,:\"x\"]");
+    /// assert_eq!(v.v_k("b").s(), "$b\"\\x01\"");
+    ///
+    /// assert!(VVal::from_wlambda_str("$[std:displayln 10]").is_err());
+    /// ```
+    pub fn from_wlambda_str(s: &str) -> Result<VVal, String> {
+        let ast =
+            match crate::parser::parse_with_max_depth(
+                    s, "<wlambda:deser>", MAX_WLAMBDA_STR_DEPTH)
+            {
+                Ok(ast) => ast,
+                Err(e)  => return Err(format!("from_wlambda_str failed: {}", e)),
+            };
+        if ast.len() != 2 {
+            return Err("from_wlambda_str failed: expected exactly one value".to_string());
+        }
+        VVal::from_literal_ast(&ast.v_(1))
+            .map_err(|e| format!("from_wlambda_str failed: {}", e))
+    }
+
+    fn from_literal_ast(ast: &VVal) -> Result<VVal, String> {
+        let spos =
+            match ast {
+                VVal::Nul | VVal::Bol(_) | VVal::Int(_)
+                | VVal::Flt(_) | VVal::Dec(_) => { return Ok(ast.clone()); },
+                VVal::Lst(_) => {
+                    match ast.v_(0) {
+                        VVal::Syn(spos) => spos,
+                        _ => return Err(format!("unexpected value: {}", ast.s())),
+                    }
+                },
+                _ => return Err(format!("unexpected value: {}", ast.s())),
+            };
+
+        match spos.syn {
+            Syntax::Str | Syntax::Key => Ok(ast.v_(1)),
+            Syntax::Lst => {
+                let v = VVal::vec();
+                for i in 1..ast.len() {
+                    v.push(VVal::from_literal_ast(&ast.v_(i))?);
+                }
+                Ok(v)
+            },
+            Syntax::Map => {
+                let m = VVal::map();
+                for i in 1..ast.len() {
+                    let pair = ast.v_(i);
+                    let key =
+                        match pair.v_(0) {
+                            k @ VVal::Sym(_) => k,
+                            k                => VVal::from_literal_ast(&k)?,
+                        };
+                    let val = VVal::from_literal_ast(&pair.v_(1))?;
+                    m.set_key(&key, val).expect("unshared map");
+                }
+                Ok(m)
+            },
+            _ => Err(format!("{} only literal values are allowed", spos)),
+        }
+    }
 }
 
 #[cfg(feature="serde")]
@@ -2720,6 +3998,13 @@ impl serde::ser::Serialize for VVal {
             VVal::Syn(_)     => serializer.serialize_str(&self.s()),
             VVal::Int(i)     => serializer.serialize_i64(*i),
             VVal::Flt(f)     => serializer.serialize_f64(*f),
+            VVal::Dec(d)     => {
+                if SER_DECIMALS_AS_NUMBERS.with(|f| f.get()) {
+                    serializer.serialize_f64(d.to_f64())
+                } else {
+                    serializer.serialize_str(&d.to_string())
+                }
+            },
             VVal::Lst(l)     => {
                 let mut seq = serializer.serialize_seq(Some(l.borrow().len()))?;
                 for v in l.borrow().iter() {
@@ -2732,11 +4017,60 @@ impl serde::ser::Serialize for VVal {
 
                 let mut map = serializer.serialize_map(Some(l.borrow().len()))?;
                 for (k, v) in hm.iter() {
-                    map.serialize_entry(k, v)?;
+                    map.serialize_entry(k.as_str(), v)?;
+                }
+                map.end()
+            },
+            VVal::Set(l) => {
+                let mut seq = serializer.serialize_seq(Some(l.borrow().len()))?;
+                for k in l.borrow().iter() {
+                    seq.serialize_element(&k.0)?;
+                }
+                seq.end()
+            },
+            VVal::OMap(l) => {
+                let mut map = serializer.serialize_map(Some(l.borrow().len()))?;
+                for (k, v) in l.borrow().iter() {
+                    map.serialize_entry(&k.0, v)?;
                 }
                 map.end()
             },
+            VVal::NArr(a) => {
+                let a = a.borrow();
+                let mut seq = serializer.serialize_seq(Some(a.len()))?;
+                match &*a {
+                    NumArray::Flt(v) => for f in v.iter() { seq.serialize_element(f)?; },
+                    NumArray::Int(v) => for i in v.iter() { seq.serialize_element(i)?; },
+                }
+                seq.end()
+            },
+            VVal::IVec2(_) | VVal::IVec3(_) | VVal::IVec4(_) => {
+                let v = self.nvec::<i64>();
+                let mut seq = serializer.serialize_seq(Some(v.dims()))?;
+                for c in v.components()[..v.dims()].iter() {
+                    seq.serialize_element(c)?;
+                }
+                seq.end()
+            },
+            VVal::FVec2(_) | VVal::FVec3(_) | VVal::FVec4(_) => {
+                let v = self.nvec::<f64>();
+                let mut seq = serializer.serialize_seq(Some(v.dims()))?;
+                for c in v.components()[..v.dims()].iter() {
+                    seq.serialize_element(c)?;
+                }
+                seq.end()
+            },
             VVal::Usr(_)     => serializer.serialize_str(&self.s()),
+            VVal::Iter(_)    => serializer.serialize_str(&self.s()),
+            VVal::Enum(e)    => {
+                if e.payload.is_none() {
+                    serializer.serialize_str(&e.variant)
+                } else {
+                    let mut map = serializer.serialize_map(Some(1))?;
+                    map.serialize_entry(&e.variant, &e.payload)?;
+                    map.end()
+                }
+            },
             VVal::Fun(_)     => serializer.serialize_str(&self.s()),
             VVal::DropFun(_) => serializer.serialize_str(&self.s()),
             VVal::Ref(_)     => self.deref().serialize(serializer),
@@ -0,0 +1,362 @@
+// Copyright (c) 2020 Weird Constructor <weirdconstructor@gmail.com>
+// This is a part of WLambda. See README.md and COPYING for details.
+
+/*!
+Conversion of Rust values to and from [VVal](../vval/enum.VVal.html) with
+the [FromVVal](trait.FromVVal.html) and [IntoVVal](trait.IntoVVal.html)
+traits, and functions with typed parameters built on top of them.
+
+Ordinary Rust functions and closures, whose parameters implement `FromVVal`
+and whose return value implements `IntoVVal`, can be registered with
+[GlobalEnv::add_typed_func](../compiler/struct.GlobalEnv.html#method.add_typed_func)
+or [SymbolTable::typed_fun](../compiler/struct.SymbolTable.html#method.typed_fun).
+The number of arguments is derived from the parameters, trailing `Option`
+parameters are optional. Mistyped arguments return an error value:
+
+```
+use wlambda::*;
+
+let mut ctx = EvalContext::new_default();
+ctx.global.borrow_mut().add_typed_func("repeat", |s: String, n: Option<usize>| {
+    s.repeat(n.unwrap_or(2))
+});
+
+assert_eq!(ctx.eval("repeat \"ab\" 3").unwrap().s_raw(), "ababab");
+assert_eq!(ctx.eval("repeat \"ab\"").unwrap().s_raw(),   "abab");
+let err = ctx.eval("repeat 10").unwrap();
+assert!(err.is_err());
+assert_eq!(err.s(), "$e \"repeat: argument 1: expected string, got: 10\"");
+```
+*/
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::hash::BuildHasher;
+use std::fmt;
+use crate::vval::{VVal, Env, StackAction};
+
+/// Converts a VVal to a Rust value. The error is a message
+/// like `expected integer, got: "abc"`.
+pub trait FromVVal: Sized {
+    /// Whether a missing argument may be left out, which is the
+    /// case for `Option`.
+    const OPTIONAL : bool = false;
+    /// Whether error values are accepted, which is the case
+    /// for `Result`.
+    const ACCEPTS_ERR : bool = false;
+
+    fn from_vval(v: &VVal) -> Result<Self, String>;
+}
+
+/// Converts a Rust value to a VVal.
+pub trait IntoVVal {
+    fn into_vval(self) -> VVal;
+
+    /// Converts the return value of the typed function `fname`.
+    /// Errors become error values at the position of the call.
+    fn into_return(self, _env: &Env, _fname: &str) -> VVal where Self: Sized {
+        self.into_vval()
+    }
+}
+
+fn expected(what: &str, v: &VVal) -> String {
+    format!("expected {}, got: {}", what, v.s())
+}
+
+impl FromVVal for VVal {
+    fn from_vval(v: &VVal) -> Result<Self, String> { Ok(v.clone()) }
+}
+
+impl IntoVVal for VVal {
+    fn into_vval(self) -> VVal { self }
+}
+
+impl FromVVal for bool {
+    fn from_vval(v: &VVal) -> Result<Self, String> {
+        match v {
+            VVal::Bol(b) => Ok(*b),
+            _            => Err(expected("boolean", v)),
+        }
+    }
+}
+
+impl IntoVVal for bool {
+    fn into_vval(self) -> VVal { VVal::Bol(self) }
+}
+
+macro_rules! int_conversion {
+    ($($type: ty),*) => {
+        $(
+            impl FromVVal for $type {
+                fn from_vval(v: &VVal) -> Result<Self, String> {
+                    match v {
+                        VVal::Int(i) => {
+                            <$type>::try_from(*i).map_err(|_| {
+                                format!("integer out of range for {}: {}",
+                                        stringify!($type), i)
+                            })
+                        },
+                        _ => Err(expected("integer", v)),
+                    }
+                }
+            }
+
+            /// Integers beyond the range of an `i64` are converted
+            /// to an error value.
+            impl IntoVVal for $type {
+                fn into_vval(self) -> VVal {
+                    match i64::try_from(self) {
+                        Ok(i)  => VVal::Int(i),
+                        Err(_) => VVal::err_msg(&format!("integer out of range: {}", self)),
+                    }
+                }
+
+                fn into_return(self, env: &Env, fname: &str) -> VVal {
+                    match i64::try_from(self) {
+                        Ok(i)  => VVal::Int(i),
+                        Err(_) => env.new_err(format!("{}: integer out of range: {}", fname, self)),
+                    }
+                }
+            }
+        )*
+    }
+}
+
+int_conversion!{i8, i16, i32, i64, isize, u8, u16, u32, u64, usize}
+
+impl FromVVal for f64 {
+    fn from_vval(v: &VVal) -> Result<Self, String> {
+        match v {
+            VVal::Flt(f) => Ok(*f),
+            VVal::Int(i) => Ok(*i as f64),
+            _            => Err(expected("float", v)),
+        }
+    }
+}
+
+impl IntoVVal for f64 {
+    fn into_vval(self) -> VVal { VVal::Flt(self) }
+}
+
+impl FromVVal for f32 {
+    fn from_vval(v: &VVal) -> Result<Self, String> {
+        f64::from_vval(v).map(|f| f as f32)
+    }
+}
+
+impl IntoVVal for f32 {
+    fn into_vval(self) -> VVal { VVal::Flt(f64::from(self)) }
+}
+
+impl FromVVal for String {
+    fn from_vval(v: &VVal) -> Result<Self, String> {
+        match v {
+            VVal::Str(_) | VVal::Sym(_) => Ok(v.s_raw()),
+            _                           => Err(expected("string", v)),
+        }
+    }
+}
+
+impl IntoVVal for String {
+    fn into_vval(self) -> VVal { VVal::new_str_mv(self) }
+}
+
+impl IntoVVal for &str {
+    fn into_vval(self) -> VVal { VVal::new_str(self) }
+}
+
+impl IntoVVal for () {
+    fn into_vval(self) -> VVal { VVal::Nul }
+}
+
+impl<T: FromVVal> FromVVal for Vec<T> {
+    fn from_vval(v: &VVal) -> Result<Self, String> {
+        match v {
+            VVal::Lst(l) => {
+                l.borrow().iter().enumerate()
+                 .map(|(i, e)| {
+                     T::from_vval(e).map_err(|err| format!("element {}: {}", i, err))
+                 })
+                 .collect()
+            },
+            _ => Err(expected("vector", v)),
+        }
+    }
+}
+
+impl<T: IntoVVal> IntoVVal for Vec<T> {
+    fn into_vval(self) -> VVal {
+        let v = VVal::vec();
+        for e in self.into_iter() {
+            v.push(e.into_vval());
+        }
+        v
+    }
+}
+
+impl<T: FromVVal, S: BuildHasher + Default> FromVVal for HashMap<String, T, S> {
+    fn from_vval(v: &VVal) -> Result<Self, String> {
+        match v {
+            VVal::Map(m) => {
+                m.borrow().iter()
+                 .map(|(k, e)| {
+                     T::from_vval(e)
+                        .map(|e| (k.to_string(), e))
+                        .map_err(|err| format!("key {}: {}", k, err))
+                 })
+                 .collect()
+            },
+            _ => Err(expected("map", v)),
+        }
+    }
+}
+
+impl<T: IntoVVal, S: BuildHasher> IntoVVal for HashMap<String, T, S> {
+    fn into_vval(self) -> VVal {
+        let m = VVal::map();
+        for (k, e) in self.into_iter() {
+            m.set_key(&VVal::new_sym(&k), e.into_vval()).expect("unshared map");
+        }
+        m
+    }
+}
+
+impl<T: FromVVal> FromVVal for Option<T> {
+    const OPTIONAL : bool = true;
+
+    fn from_vval(v: &VVal) -> Result<Self, String> {
+        if v.is_none() { Ok(None) }
+        else { T::from_vval(v).map(Some) }
+    }
+}
+
+impl<T: IntoVVal> IntoVVal for Option<T> {
+    fn into_vval(self) -> VVal {
+        match self {
+            Some(v) => v.into_vval(),
+            None    => VVal::Nul,
+        }
+    }
+}
+
+/// Error values are converted to `Err` with the value they wrap,
+/// all other values to `Ok`.
+impl<T: FromVVal, E: FromVVal> FromVVal for Result<T, E> {
+    const ACCEPTS_ERR : bool = true;
+
+    fn from_vval(v: &VVal) -> Result<Self, String> {
+        match v {
+            VVal::Err(e) => E::from_vval(&e.borrow().0).map(Err),
+            _            => T::from_vval(v).map(Ok),
+        }
+    }
+}
+
+/// `Err` is converted to an error value with the message of the error.
+impl<T: IntoVVal, E: fmt::Display> IntoVVal for Result<T, E> {
+    fn into_vval(self) -> VVal {
+        match self {
+            Ok(v)  => v.into_vval(),
+            Err(e) => VVal::err_msg(&e.to_string()),
+        }
+    }
+
+    fn into_return(self, env: &Env, fname: &str) -> VVal {
+        match self {
+            Ok(v)  => v.into_return(env, fname),
+            Err(e) => env.new_err(format!("{}: {}", fname, e)),
+        }
+    }
+}
+
+macro_rules! tuple_conversion {
+    ($len: expr; $($type: ident $var: ident $idx: tt),*) => {
+        impl<$($type: FromVVal),*> FromVVal for ($($type,)*) {
+            fn from_vval(v: &VVal) -> Result<Self, String> {
+                match v {
+                    VVal::Lst(l) if l.borrow().len() == $len => {
+                        let l = l.borrow();
+                        Ok(($(
+                            $type::from_vval(&l[$idx]).map_err(|err| {
+                                format!("element {}: {}", $idx, err)
+                            })?,
+                        )*))
+                    },
+                    _ => Err(expected(concat!("vector of length ", $len), v)),
+                }
+            }
+        }
+
+        impl<$($type: IntoVVal),*> IntoVVal for ($($type,)*) {
+            fn into_vval(self) -> VVal {
+                let ($($var,)*) = self;
+                let v = VVal::vec();
+                $( v.push($var.into_vval()); )*
+                v
+            }
+        }
+    }
+}
+
+tuple_conversion!{2; A a 0, B b 1}
+tuple_conversion!{3; A a 0, B b 1, C c 2}
+tuple_conversion!{4; A a 0, B b 1, C c 2, D d 3}
+
+/// A Rust function or closure with parameters implementing
+/// [FromVVal](trait.FromVVal.html) and a return value implementing
+/// [IntoVVal](trait.IntoVVal.html). `Args` is the tuple of the
+/// parameter types. Implemented for up to 6 parameters.
+pub trait TypedFn<Args>: 'static {
+    /// The number of arguments without the trailing optional ones.
+    fn min_args() -> usize;
+    /// The number of parameters.
+    fn max_args() -> usize;
+    /// Whether any parameter accepts error values.
+    fn accepts_err() -> bool;
+
+    /// Converts the arguments in `env`, calls the function and converts
+    /// the return value. `fname` is used for the error messages.
+    fn call_typed(&self, env: &mut Env, fname: &str) -> Result<VVal, StackAction>;
+}
+
+macro_rules! typed_fn {
+    ($len: expr; $($type: ident $var: ident $idx: tt),*) => {
+        impl<F, R, $($type),*> TypedFn<($($type,)*)> for F
+            where F: 'static + Fn($($type),*) -> R,
+                  R: IntoVVal,
+                  $($type: FromVVal),*
+        {
+            fn min_args() -> usize {
+                let optional : &[bool] = &[$($type::OPTIONAL),*];
+                optional.iter().rposition(|o| !o).map_or(0, |i| i + 1)
+            }
+
+            fn max_args() -> usize { $len }
+
+            fn accepts_err() -> bool { false $(|| $type::ACCEPTS_ERR)* }
+
+            #[allow(unused_variables)]
+            fn call_typed(&self, env: &mut Env, fname: &str) -> Result<VVal, StackAction> {
+                $(
+                    let $var =
+                        match $type::from_vval(&env.arg($idx)) {
+                            Ok(v)  => v,
+                            Err(e) => {
+                                return Ok(env.new_err(
+                                    format!("{}: argument {}: {}", fname, $idx + 1, e)));
+                            },
+                        };
+                )*
+                Ok(self($($var),*).into_return(env, fname))
+            }
+        }
+    }
+}
+
+typed_fn!{0; }
+typed_fn!{1; A a 0}
+typed_fn!{2; A a 0, B b 1}
+typed_fn!{3; A a 0, B b 1, C c 2}
+typed_fn!{4; A a 0, B b 1, C c 2, D d 3}
+typed_fn!{5; A a 0, B b 1, C c 2, D d 3, E e 4}
+typed_fn!{6; A a 0, B b 1, C c 2, D d 3, E e 4, G g 5}
@@ -1,6 +1,11 @@
 // Copyright (c) 2020 Weird Constructor <weirdconstructor@gmail.com>
 // This is a part of WLambda. See README.md and COPYING for details.
 
+use std::rc::Rc;
+use std::cell::{RefCell, Ref, RefMut, BorrowError, BorrowMutError};
+use crate::vval::{VVal, VValUserData, StackAction};
+use crate::vval_convert::FromVVal;
+
 /// This macro helps with exporting something with a more OO interface
 /// instead of using a VValUserData trait implementation.
 ///
@@ -33,3 +38,138 @@ macro_rules! set_vval_method {
         }
     }
 }
+
+/// Exposes the fields and methods of a Rust struct to WLambda, when it is
+/// wrapped in a [UserDataRef](struct.UserDataRef.html). This is usually
+/// implemented with `#[derive(WLambdaUserData)]` from the `wlambda_derive`
+/// crate:
+///
+///```
+/// use wlambda::*;
+/// use wlambda::vval_user_obj::{UserDataStruct, UserDataRef};
+///
+/// struct Counter { count: i64 }
+///
+/// impl UserDataStruct for Counter {
+///     const NAME : &'static str = "Counter";
+///
+///     fn field_names() -> &'static [&'static str] { &["count"] }
+///
+///     fn get_field(&self, key: &str) -> Option<VVal> {
+///         match key {
+///             "count" => Some(self.count.into_vval()),
+///             _       => None,
+///         }
+///     }
+///
+///     fn set_field(&mut self, key: &str, v: &VVal) -> Result<(), String> {
+///         match key {
+///             "count" => { self.count = i64::from_vval(v)?; Ok(()) },
+///             _       => Err(format!("no such field: {}", key)),
+///         }
+///     }
+/// }
+///
+/// let mut ctx = EvalContext::new_default();
+/// let c = UserDataRef::new(Counter { count: 10 });
+/// ctx.set_global_var("c", &c.clone().into());
+///
+/// assert_eq!(ctx.eval("c.count = c.count + 1; c").unwrap().s(), "$<Counter{count=11}>");
+/// assert_eq!(c.borrow().count, 11);
+///```
+pub trait UserDataStruct: 'static {
+    /// The type name shown in the string representation.
+    const NAME : &'static str;
+
+    /// Returns the names of the exposed fields in declaration order.
+    fn field_names() -> &'static [&'static str];
+
+    /// Returns the value of the field `key`.
+    fn get_field(&self, key: &str) -> Option<VVal>;
+
+    /// Sets the field `key`. Returns an error message if there
+    /// is no such field, it is read only or the value has the wrong type.
+    fn set_field(&mut self, key: &str, v: &VVal) -> Result<(), String>;
+
+    /// Returns the method `name` bound to `this` as function.
+    fn method(_this: &UserDataRef<Self>, _name: &str) -> Option<VVal>
+        where Self: Sized { None }
+}
+
+/// A shared reference to a [UserDataStruct](trait.UserDataStruct.html),
+/// which implements [VValUserData](../vval/trait.VValUserData.html).
+/// Clones refer to the same struct, so changes made by WLambda code
+/// are visible to Rust and the other way around.
+pub struct UserDataRef<T>(Rc<RefCell<T>>);
+
+impl<T> Clone for UserDataRef<T> {
+    fn clone(&self) -> Self { UserDataRef(self.0.clone()) }
+}
+
+impl<T: UserDataStruct> UserDataRef<T> {
+    pub fn new(v: T) -> Self { UserDataRef(Rc::new(RefCell::new(v))) }
+
+    pub fn borrow(&self) -> Ref<'_, T> { self.0.borrow() }
+
+    pub fn borrow_mut(&self) -> RefMut<'_, T> { self.0.borrow_mut() }
+
+    pub fn try_borrow(&self) -> Result<Ref<'_, T>, BorrowError> { self.0.try_borrow() }
+
+    pub fn try_borrow_mut(&self) -> Result<RefMut<'_, T>, BorrowMutError> {
+        self.0.try_borrow_mut()
+    }
+}
+
+impl<T: UserDataStruct> VValUserData for UserDataRef<T> {
+    fn s(&self) -> String {
+        let this =
+            match self.0.try_borrow() {
+                Ok(this) => this,
+                Err(_)   => return format!("$<{}{{...}}>", T::NAME),
+            };
+        let fields : Vec<String> =
+            T::field_names().iter()
+             .map(|k| format!("{}={}", k, this.get_field(k).unwrap_or(VVal::Nul).s()))
+             .collect();
+        format!("$<{}{{{}}}>", T::NAME, fields.join(","))
+    }
+
+    fn get_key(&self, key: &str) -> Option<VVal> {
+        match self.0.try_borrow() {
+            Ok(this) => this.get_field(key).or_else(|| T::method(self, key)),
+            Err(_)   => T::method(self, key).or_else(|| {
+                Some(VVal::err_msg(&format!("{}.{}: already borrowed", T::NAME, key)))
+            }),
+        }
+    }
+
+    fn set_key(&self, key: &VVal, val: VVal) -> Result<(), StackAction> {
+        let key = key.s_raw();
+        self.0.try_borrow_mut()
+            .map_err(|_| String::from("already borrowed"))
+            .and_then(|mut this| this.set_field(&key, &val))
+            .map_err(|e| {
+                StackAction::panic_msg(format!("{}.{}: {}", T::NAME, key, e))
+            })
+    }
+
+    fn as_any(&mut self) -> &mut dyn std::any::Any { self }
+
+    fn clone_ud(&self) -> Box<dyn VValUserData> { Box::new(self.clone()) }
+}
+
+impl<T: UserDataStruct> From<UserDataRef<T>> for VVal {
+    fn from(r: UserDataRef<T>) -> VVal { VVal::Usr(Box::new(r)) }
+}
+
+impl<T: UserDataStruct> FromVVal for UserDataRef<T> {
+    fn from_vval(v: &VVal) -> Result<Self, String> {
+        if let VVal::Usr(u) = v {
+            let mut u = u.clone();
+            if let Some(r) = u.as_any().downcast_mut::<UserDataRef<T>>() {
+                return Ok(r.clone());
+            }
+        }
+        Err(format!("expected {}, got: {}", T::NAME, v.s()))
+    }
+}
@@ -0,0 +1,81 @@
+#[cfg(feature="serde")]
+use std::collections::{HashMap, BTreeMap};
+
+#[cfg(feature="serde")]
+use wlambda::{GlobalEnv, EvalContext, to_vval, from_vval};
+
+#[cfg(feature="serde")]
+#[test]
+fn serde_vval() {
+    let global_env = GlobalEnv::new_default();
+    let mut ctx = EvalContext::new(global_env);
+
+    let l = vec![(String::from("a"), Some(1u16)), (String::from("b"), None)];
+    let v = to_vval(&l).unwrap();
+    assert_eq!(v.s(), "$[$[\"a\",1],$[\"b\",$n]]");
+    assert_eq!(from_vval::<Vec<(String, Option<u16>)>>(&v).unwrap(), l);
+
+    let mut m = BTreeMap::new();
+    m.insert(3i64, true);
+    m.insert(10i64, false);
+    let v = to_vval(&m).unwrap();
+    assert_eq!(v.s(), "${10=$false,3=$true}");
+    assert_eq!(from_vval::<BTreeMap<i64, bool>>(&v).unwrap(), m);
+
+    let r : Result<i64, String> = Ok(10);
+    let v = to_vval(&r).unwrap();
+    assert_eq!(v.s(), "$<Result.Ok 10>");
+    ctx.set_global_var("r", &v);
+    assert_eq!(ctx.eval("std:enum:tag r").unwrap().s(), ":\"Ok\"");
+    assert_eq!(ctx.eval("std:enum:payload r").unwrap().s(), "10");
+    assert_eq!(from_vval::<Result<i64, String>>(&v).unwrap(), r);
+    assert_eq!(from_vval::<Result<i64, String>>(
+        &ctx.eval("${Err=\"fail\"}").unwrap()).unwrap(),
+        Err(String::from("fail")));
+
+    // A newtype variant with a `$n` payload is no unit variant:
+    let r : Vec<Result<Option<u32>, ()>> = vec![Ok(None), Ok(Some(3)), Err(())];
+    let v = to_vval(&r).unwrap();
+    assert_eq!(v.s(), "$[$<Result.Ok>,$<Result.Ok 3>,$<Result.Err>]");
+    assert_eq!(from_vval::<Vec<Result<Option<u32>, ()>>>(&v).unwrap(), r);
+
+    assert_eq!(
+        from_vval::<HashMap<String, Vec<HashMap<String, u16>>>>(
+            &ctx.eval("${servers=$[${port=80},${port=\"x\"}]}").unwrap())
+        .unwrap_err().to_string(),
+        "servers[1].port: invalid type: string \"x\", expected u16");
+    assert_eq!(
+        from_vval::<(i64, i64)>(&ctx.eval("$[1,2,3]").unwrap())
+        .unwrap_err().to_string(),
+        "invalid length 3, expected fewer elements in sequence");
+    assert_eq!(
+        from_vval::<Result<i64, String>>(&ctx.eval("$[1]").unwrap())
+        .unwrap_err().to_string(),
+        "invalid type: vector, expected enum");
+    assert_eq!(to_vval(&u64::MAX).unwrap_err().to_string(),
+               "integer out of range: 18446744073709551615");
+
+    let v = ctx.eval("${a=$[1.5, $[1,2,300]]}").unwrap();
+    let e = from_vval::<HashMap<String, (f32, Vec<u8>)>>(&v).unwrap_err();
+    assert_eq!(e.path(),    "a[1][2]");
+    assert_eq!(e.message(), "invalid value: integer `300`, expected u8");
+}
+
+#[cfg(feature="serde")]
+#[test]
+fn serde_vval_ignored_and_decimal() {
+    use serde::de::IgnoredAny;
+
+    let mut ctx = EvalContext::new_default();
+
+    // Fields that are skipped can hold values that can't be deserialized:
+    let v = ctx.eval("${port=80, on_start={1}}").unwrap();
+    assert!(from_vval::<HashMap<String, IgnoredAny>>(&v).is_ok());
+    assert!(from_vval::<HashMap<String, u16>>(&v).unwrap_err().to_string()
+            .starts_with("on_start: unsupported value: &F{"));
+
+    // Decimals keep their digits as string, but can be read as float too:
+    let v = ctx.eval("$[1.50d, 0.1d]").unwrap();
+    assert_eq!(from_vval::<Vec<String>>(&v).unwrap(), vec!["1.50", "0.1"]);
+    assert_eq!(from_vval::<Vec<f64>>(&v).unwrap(),    vec![1.5, 0.1]);
+}
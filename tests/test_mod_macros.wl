@@ -0,0 +1,3 @@
+!@wlambda;
+!@import std std;
+!@macro square = { std:wlambda:subst ${x = _} ~ $code (x * x) };
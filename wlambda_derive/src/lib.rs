@@ -0,0 +1,345 @@
+// Copyright (c) 2020 Weird Constructor <weirdconstructor@gmail.com>
+// This is a part of WLambda. See README.md and COPYING for details.
+
+/*!
+Derive macros for exposing Rust structs to WLambda as user data.
+
+`#[derive(WLambdaUserData)]` implements
+`wlambda::vval_user_obj::UserDataStruct` for a struct with named fields.
+Wrapped in a `UserDataRef` the fields can be read and written from WLambda
+with `obj.field` and `obj.field = value`, and the string representation
+looks like `$<Point{x=1,y=2}>`. The field types need to implement `Clone`,
+`wlambda::FromVVal` and `wlambda::IntoVVal`.
+
+`#[wlambda_methods]` on an impl block exposes the methods taking `&self`
+or `&mut self`, which are called like `obj.method[args]`. The struct needs
+the `#[wlambda(methods)]` attribute then.
+
+```
+use wlambda::*;
+use wlambda_derive::{WLambdaUserData, wlambda_methods};
+
+#[derive(WLambdaUserData)]
+#[wlambda(methods)]
+struct Point {
+    x: i64,
+    y: i64,
+    #[wlambda(readonly)]
+    id: String,
+    #[wlambda(skip)]
+    moves: usize,
+}
+
+#[wlambda_methods]
+impl Point {
+    fn len2(&self) -> i64 { self.x * self.x + self.y * self.y }
+
+    fn move_by(&mut self, dx: i64, dy: i64) {
+        self.x += dx;
+        self.y += dy;
+        self.moves += 1;
+    }
+}
+
+let mut ctx = EvalContext::new_default();
+let p = vval_user_obj::UserDataRef::new(
+    Point { x: 1, y: 2, id: "p1".to_string(), moves: 0 });
+ctx.set_global_var("p", &p.clone().into());
+
+assert_eq!(ctx.eval("p.move_by 2 2; p.x = p.x * 2; p").unwrap().s(),
+           "$<Point{x=6,y=4,id=\"p1\"}>");
+assert_eq!(ctx.eval("p.len2[]").unwrap().i(), 52);
+assert_eq!(p.borrow().moves, 1);
+```
+
+Field attributes:
+
+- `#[wlambda(skip)]` hides the field from WLambda.
+- `#[wlambda(readonly)]` makes assigning to the field an error.
+
+Struct attributes:
+
+- `#[wlambda(name = "...")]` sets the type name of the string representation.
+- `#[wlambda(methods)]` looks up methods generated by `#[wlambda_methods]`.
+
+Method attributes inside `#[wlambda_methods]`:
+
+- `#[wlambda(skip)]` hides the method from WLambda.
+- `#[wlambda(name = "...")]` exposes the method under a different name.
+*/
+
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{quote, format_ident};
+use syn::ext::IdentExt;
+use syn::{
+    parse_macro_input, Attribute, Data, DeriveInput, Error, Fields, FnArg,
+    ImplItem, ItemImpl, LitStr,
+};
+
+/// The options set with `#[wlambda(...)]` attributes.
+#[derive(Default)]
+struct Options {
+    skip:     bool,
+    readonly: bool,
+    methods:  bool,
+    name:     Option<String>,
+}
+
+/// Parses the `#[wlambda(...)]` attributes in `attrs`, only the
+/// options listed in `allowed` are accepted.
+fn parse_options(attrs: &[Attribute], allowed: &[&str]) -> syn::Result<Options> {
+    let mut opts = Options::default();
+
+    for attr in attrs.iter().filter(|a| a.path().is_ident("wlambda")) {
+        attr.parse_nested_meta(|meta| {
+            let opt = meta.path.get_ident().map(|i| i.to_string()).unwrap_or_default();
+            if !allowed.contains(&&opt[..]) {
+                return Err(meta.error(
+                    format!("unknown wlambda attribute, expected one of: {}",
+                            allowed.join(", "))));
+            }
+
+            match &opt[..] {
+                "skip"     => opts.skip     = true,
+                "readonly" => opts.readonly = true,
+                "methods"  => opts.methods  = true,
+                "name"     => opts.name = Some(meta.value()?.parse::<LitStr>()?.value()),
+                _          => unreachable!(),
+            }
+            Ok(())
+        })?;
+    }
+
+    Ok(opts)
+}
+
+/// Derives `wlambda::vval_user_obj::UserDataStruct` and
+/// `From<T> for wlambda::VVal`, see the [crate documentation](index.html).
+#[proc_macro_derive(WLambdaUserData, attributes(wlambda))]
+pub fn derive_user_data(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match user_data_impl(&input) {
+        Ok(ts) => ts.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+fn user_data_impl(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let ident = &input.ident;
+
+    if !input.generics.params.is_empty() {
+        return Err(Error::new_spanned(
+            &input.generics,
+            "WLambdaUserData can't be derived for generic structs"));
+    }
+
+    let fields =
+        match &input.data {
+            Data::Struct(s) => {
+                match &s.fields {
+                    Fields::Named(f) => &f.named,
+                    _ => {
+                        return Err(Error::new_spanned(
+                            ident,
+                            "WLambdaUserData can only be derived for structs with named fields"));
+                    },
+                }
+            },
+            _ => {
+                return Err(Error::new_spanned(
+                    ident,
+                    "WLambdaUserData can only be derived for structs with named fields"));
+            },
+        };
+
+    let opts = parse_options(&input.attrs, &["name", "methods"])?;
+    let name = opts.name.unwrap_or_else(|| ident.unraw().to_string());
+
+    let mut names   = vec![];
+    let mut getters = vec![];
+    let mut setters = vec![];
+
+    for field in fields.iter() {
+        let fopts = parse_options(&field.attrs, &["skip", "readonly"])?;
+        if fopts.skip { continue; }
+
+        let fident = field.ident.as_ref().unwrap();
+        let fname  = fident.unraw().to_string();
+        let ty     = &field.ty;
+
+        getters.push(quote! {
+            #fname => ::std::option::Option::Some(
+                ::wlambda::IntoVVal::into_vval(
+                    ::std::clone::Clone::clone(&self.#fident))),
+        });
+
+        if fopts.readonly {
+            setters.push(quote! {
+                #fname => ::std::result::Result::Err(
+                    ::std::string::String::from("field is read only")),
+            });
+        } else {
+            setters.push(quote! {
+                #fname => {
+                    self.#fident = <#ty as ::wlambda::FromVVal>::from_vval(v)?;
+                    ::std::result::Result::Ok(())
+                },
+            });
+        }
+
+        names.push(fname);
+    }
+
+    let method =
+        if opts.methods {
+            quote! {
+                fn method(this: &::wlambda::vval_user_obj::UserDataRef<Self>, name: &str)
+                    -> ::std::option::Option<::wlambda::VVal>
+                {
+                    Self::__wlambda_method(this, name)
+                }
+            }
+        } else {
+            quote! { }
+        };
+
+    Ok(quote! {
+        impl ::wlambda::vval_user_obj::UserDataStruct for #ident {
+            const NAME : &'static str = #name;
+
+            fn field_names() -> &'static [&'static str] { &[#(#names),*] }
+
+            fn get_field(&self, key: &str) -> ::std::option::Option<::wlambda::VVal> {
+                match key {
+                    #(#getters)*
+                    _ => ::std::option::Option::None,
+                }
+            }
+
+            #[allow(unused_variables)]
+            fn set_field(&mut self, key: &str, v: &::wlambda::VVal)
+                -> ::std::result::Result<(), ::std::string::String>
+            {
+                match key {
+                    #(#setters)*
+                    _ => ::std::result::Result::Err(
+                        ::std::string::String::from("no such field")),
+                }
+            }
+
+            #method
+        }
+
+        impl ::std::convert::From<#ident> for ::wlambda::VVal {
+            fn from(v: #ident) -> Self {
+                ::wlambda::vval_user_obj::UserDataRef::new(v).into()
+            }
+        }
+    })
+}
+
+/// Exposes the methods of an impl block to WLambda,
+/// see the [crate documentation](index.html).
+#[proc_macro_attribute]
+pub fn wlambda_methods(_attr: TokenStream, item: TokenStream) -> TokenStream {
+    let mut imp = parse_macro_input!(item as ItemImpl);
+    match methods_impl(&mut imp) {
+        Ok(ts) => quote! { #imp #ts }.into(),
+        Err(e) => {
+            let e = e.to_compile_error();
+            quote! { #imp #e }.into()
+        },
+    }
+}
+
+fn methods_impl(imp: &mut ItemImpl) -> syn::Result<TokenStream2> {
+    if imp.trait_.is_some() || !imp.generics.params.is_empty() {
+        return Err(Error::new_spanned(
+            &imp.self_ty,
+            "#[wlambda_methods] can only be used on inherent impl blocks without generics"));
+    }
+
+    let mut arms = vec![];
+
+    for item in imp.items.iter_mut() {
+        let f = match item { ImplItem::Fn(f) => f, _ => continue };
+
+        let opts = parse_options(&f.attrs, &["skip", "name"])?;
+        f.attrs.retain(|a| !a.path().is_ident("wlambda"));
+        if opts.skip { continue; }
+
+        let mutable =
+            match f.sig.inputs.first() {
+                Some(FnArg::Receiver(r)) if r.reference.is_some() => r.mutability.is_some(),
+                Some(FnArg::Receiver(r)) => {
+                    return Err(Error::new_spanned(
+                        r,
+                        "methods exposed to WLambda must take &self or &mut self, \
+                         use #[wlambda(skip)] to hide this method"));
+                },
+                _ => continue,
+            };
+
+        if !f.sig.generics.params.is_empty() {
+            return Err(Error::new_spanned(
+                &f.sig.generics,
+                "methods exposed to WLambda can't have generic parameters, \
+                 use #[wlambda(skip)] to hide this method"));
+        }
+
+        let fident = &f.sig.ident;
+        let mname  = opts.name.unwrap_or_else(|| fident.unraw().to_string());
+
+        let mut args = vec![];
+        let mut tys  = vec![];
+        for (i, arg) in f.sig.inputs.iter().skip(1).enumerate() {
+            if let FnArg::Typed(pt) = arg {
+                args.push(format_ident!("a{}", i));
+                tys.push(&pt.ty);
+            }
+        }
+
+        // The struct might already be borrowed, if the method is called
+        // while another one is running, so this results in an error value:
+        let borrow =
+            if mutable { quote! { let mut obj = this.try_borrow_mut() } }
+            else       { quote! { let obj = this.try_borrow() } };
+
+        arms.push(quote! {
+            #mname => {
+                let this = ::std::clone::Clone::clone(this);
+                ::std::option::Option::Some(
+                    ::wlambda::vval::VValFun::new_typed_fun(
+                        #mname,
+                        move |#(#args: #tys),*|
+                            -> ::std::result::Result<_, ::std::string::String>
+                        {
+                            #borrow.map_err(|_| ::std::format!(
+                                "{} is already borrowed",
+                                <Self as ::wlambda::vval_user_obj::UserDataStruct>::NAME))?;
+                            ::std::result::Result::Ok(obj.#fident(#(#args),*))
+                        }))
+            },
+        });
+    }
+
+    let self_ty = &imp.self_ty;
+
+    Ok(quote! {
+        impl #self_ty {
+            #[doc(hidden)]
+            pub fn __wlambda_method(
+                this: &::wlambda::vval_user_obj::UserDataRef<Self>, name: &str)
+                -> ::std::option::Option<::wlambda::VVal>
+            {
+                match name {
+                    #(#arms)*
+                    _ => ::std::option::Option::None,
+                }
+            }
+        }
+    })
+}
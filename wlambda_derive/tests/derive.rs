@@ -0,0 +1,144 @@
+use wlambda::*;
+use wlambda::vval_user_obj::UserDataRef;
+use wlambda_derive::{WLambdaUserData, wlambda_methods};
+
+#[derive(WLambdaUserData)]
+#[wlambda(name = "Acc", methods)]
+struct Account {
+    owner:   String,
+    #[wlambda(readonly)]
+    balance: i64,
+    tags:    Vec<String>,
+    #[wlambda(skip)]
+    log:     Vec<String>,
+}
+
+#[wlambda_methods]
+impl Account {
+    fn deposit(&mut self, amount: i64, note: Option<String>) -> i64 {
+        self.balance += amount;
+        self.log.push(note.unwrap_or_else(|| format!("deposit {}", amount)));
+        self.balance
+    }
+
+    #[wlambda(name = "withdraw")]
+    fn try_withdraw(&mut self, amount: i64) -> Result<i64, String> {
+        if amount > self.balance {
+            return Err(format!("insufficient funds: {} > {}", amount, self.balance));
+        }
+        self.balance -= amount;
+        Ok(self.balance)
+    }
+
+    fn merge(&mut self, other: UserDataRef<Account>) -> Result<i64, String> {
+        let mut other = other.try_borrow_mut()
+            .map_err(|_| String::from("can't merge an account into itself"))?;
+        self.balance += other.balance;
+        other.balance = 0;
+        Ok(self.balance)
+    }
+
+    #[wlambda(skip)]
+    #[allow(dead_code)]
+    fn reset(&mut self) { self.balance = 0; }
+
+    fn new(owner: &str) -> Self {
+        Account { owner: owner.to_string(), balance: 0, tags: vec![], log: vec![] }
+    }
+}
+
+#[derive(WLambdaUserData)]
+struct Empty { }
+
+fn eval(ctx: &mut EvalContext, s: &str) -> String {
+    match ctx.eval(s) {
+        Ok(VVal::Err(e)) => format!("ERR: {}", e.borrow().0.s_raw()),
+        Ok(v)            => v.s(),
+        Err(e)           => format!("{}", e),
+    }
+}
+
+#[test]
+fn check_derive_fields() {
+    let mut ctx = EvalContext::new_default();
+    let acc = UserDataRef::new(Account::new("bob"));
+    ctx.set_global_var("a", &acc.clone().into());
+
+    assert_eq!(eval(&mut ctx, "a"), "$<Acc{owner=\"bob\",balance=0,tags=$[]}>");
+    assert_eq!(eval(&mut ctx, "a.owner"), "\"bob\"");
+    assert_eq!(eval(&mut ctx, "a.log"), "$n");
+
+    eval(&mut ctx, "a.owner = \"alice\"; a.tags = $[\"x\", \"y\"]");
+    assert_eq!(acc.borrow().owner, "alice");
+    assert_eq!(acc.borrow().tags, vec!["x".to_string(), "y".to_string()]);
+
+    acc.borrow_mut().balance = 5;
+    assert_eq!(eval(&mut ctx, "a.balance"), "5");
+
+    assert!(eval(&mut ctx, "a.balance = 100")
+            .contains("Acc.balance: field is read only"));
+    assert!(eval(&mut ctx, "a.owner = 10")
+            .contains("Acc.owner: expected string, got: 10"));
+    assert!(eval(&mut ctx, "a.log = $[]")
+            .contains("Acc.log: no such field"));
+
+    let e : VVal = Empty { }.into();
+    assert_eq!(e.s(), "$<Empty{}>");
+}
+
+#[test]
+fn check_derive_methods() {
+    let mut ctx = EvalContext::new_default();
+    let acc = UserDataRef::new(Account::new("bob"));
+    ctx.set_global_var("a", &acc.clone().into());
+
+    assert_eq!(eval(&mut ctx, "a.deposit 10"), "10");
+    assert_eq!(eval(&mut ctx, "a.deposit 5 \"gift\""), "15");
+    assert_eq!(acc.borrow().log, vec!["deposit 10".to_string(), "gift".to_string()]);
+
+    assert_eq!(eval(&mut ctx, "a.withdraw 7"), "8");
+    assert_eq!(eval(&mut ctx, "a.withdraw 9"),
+               "ERR: withdraw: insufficient funds: 9 > 8");
+    assert_eq!(eval(&mut ctx, "a.deposit :x"),
+               "ERR: deposit: argument 1: expected integer, got: :\"x\"");
+    assert_eq!(eval(&mut ctx, "a.try_withdraw"), "$n");
+    assert_eq!(eval(&mut ctx, "a.reset"), "$n");
+    assert_eq!(eval(&mut ctx, "a.new"), "$n");
+}
+
+#[test]
+fn check_derive_already_borrowed() {
+    let mut ctx = EvalContext::new_default();
+    let acc = UserDataRef::new(Account::new("bob"));
+    ctx.set_global_var("a", &acc.clone().into());
+    ctx.set_global_var("b", &UserDataRef::new(Account::new("eve")).into());
+
+    eval(&mut ctx, "a.deposit 10; b.deposit 5");
+    assert_eq!(eval(&mut ctx, "a.merge b"), "15");
+    assert_eq!(eval(&mut ctx, "a.merge a"), "ERR: merge: can't merge an account into itself");
+
+    {
+        let _guard = acc.borrow_mut();
+        assert_eq!(eval(&mut ctx, "a.deposit 1"), "ERR: deposit: Acc is already borrowed");
+        assert_eq!(eval(&mut ctx, "a.owner"), "ERR: Acc.owner: already borrowed");
+        assert!(eval(&mut ctx, "a.owner = \"x\"")
+                .contains("Acc.owner: already borrowed"));
+        assert_eq!(eval(&mut ctx, "a"), "$<Acc{...}>");
+    }
+    assert_eq!(eval(&mut ctx, "a.deposit 1"), "16");
+}
+
+#[test]
+fn check_derive_typed_func_arg() {
+    let mut ctx = EvalContext::new_default();
+    ctx.global.borrow_mut().add_typed_func("new_account", |owner: String| -> VVal {
+        Account::new(&owner).into()
+    });
+    ctx.global.borrow_mut().add_typed_func("owner_of", |a: UserDataRef<Account>| {
+        a.borrow().owner.clone()
+    });
+
+    assert_eq!(eval(&mut ctx, "owner_of ~ new_account \"eve\""), "\"eve\"");
+    assert_eq!(eval(&mut ctx, "owner_of $[]"),
+               "ERR: owner_of: argument 1: expected Acc, got: $[]");
+}